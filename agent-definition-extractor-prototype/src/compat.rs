@@ -0,0 +1,863 @@
+use crate::literal::{format_text_type, format_type};
+use crate::model::{AgentType, DataSchema, ParameterType, TextFormat, TextType};
+use golem_wasm_ast::analysis::{
+    AnalysedType, NameOptionTypePair, NameTypePair, TypeEnum, TypeOption, TypeTuple,
+};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+// Classifies the changes between two versions of a set of agent types as compatible or breaking.
+//
+// Values are matched by position, the way they are encoded as `wit-value`s: parameters, record fields,
+// tuple items and variant cases. So any change of the number or order of fields or parameters is breaking,
+// and the only cases and flags that can be added are those appended at the end.
+//
+// Whether a change is breaking depends on the direction values flow in:
+// callers keep sending values of the old parameter types, so parameter types may only accept more,
+// and callers keep expecting values of the old result types, so result types may only produce less.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaChange {
+    pub agent_type: String,
+    // None for changes of the agent type itself, or of its constructor
+    pub method: Option<String>,
+    // Where the change is within the parameters or result, e.g. `parameter 0 > field city`
+    pub location: Vec<String>,
+    pub kind: ChangeKind,
+    pub compatibility: Compatibility,
+}
+
+impl SchemaChange {
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility == Compatibility::Breaking
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ChangeKind {
+    AgentTypeAdded,
+    AgentTypeRemoved,
+    MethodAdded,
+    MethodRemoved,
+    ParameterAdded { optional: bool },
+    ParameterRemoved,
+    FieldAdded { name: String, optional: bool },
+    FieldRemoved { name: String, optional: bool },
+    FieldMoved { name: String, from: usize, to: usize },
+    CaseAdded { name: String },
+    CaseRemoved { name: String },
+    CaseRenamed { from: String, to: String },
+    CaseMoved { name: String, from: usize, to: usize },
+    FlagAdded { name: String },
+    FlagRemoved { name: String },
+    MadeOptional,
+    MadeRequired,
+    TypeWidened { from: String, to: String },
+    TypeNarrowed { from: String, to: String },
+    TypeChanged { from: String, to: String },
+    TextConstraintsChanged { from: String, to: String },
+    VersionChanged { from: u32, to: u32 },
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::AgentTypeAdded => write!(f, "agent type added"),
+            ChangeKind::AgentTypeRemoved => write!(f, "agent type removed"),
+            ChangeKind::MethodAdded => write!(f, "method added"),
+            ChangeKind::MethodRemoved => write!(f, "method removed"),
+            ChangeKind::ParameterAdded { optional: true } => write!(f, "optional parameter added"),
+            ChangeKind::ParameterAdded { optional: false } => write!(f, "parameter added"),
+            ChangeKind::ParameterRemoved => write!(f, "parameter removed"),
+            ChangeKind::FieldAdded { name, optional: true } => {
+                write!(f, "optional field {name} added")
+            }
+            ChangeKind::FieldAdded { name, optional: false } => write!(f, "field {name} added"),
+            ChangeKind::FieldRemoved { name, optional: true } => {
+                write!(f, "optional field {name} removed")
+            }
+            ChangeKind::FieldRemoved { name, optional: false } => {
+                write!(f, "field {name} removed")
+            }
+            ChangeKind::FieldMoved { name, from, to } => {
+                write!(f, "field {name} moved from position {from} to {to}")
+            }
+            ChangeKind::CaseAdded { name } => write!(f, "case {name} added"),
+            ChangeKind::CaseRemoved { name } => write!(f, "case {name} removed"),
+            ChangeKind::CaseRenamed { from, to } => write!(f, "case {from} renamed to {to}"),
+            ChangeKind::CaseMoved { name, from, to } => {
+                write!(f, "case {name} moved from position {from} to {to}")
+            }
+            ChangeKind::FlagAdded { name } => write!(f, "flag {name} added"),
+            ChangeKind::FlagRemoved { name } => write!(f, "flag {name} removed"),
+            ChangeKind::MadeOptional => write!(f, "made optional"),
+            ChangeKind::MadeRequired => write!(f, "made required"),
+            ChangeKind::TypeWidened { from, to } => write!(f, "type widened from {from} to {to}"),
+            ChangeKind::TypeNarrowed { from, to } => {
+                write!(f, "type narrowed from {from} to {to}")
+            }
+            ChangeKind::TypeChanged { from, to } => write!(f, "type changed from {from} to {to}"),
+            ChangeKind::TextConstraintsChanged { from, to } => {
+                write!(f, "text constraints changed from {from} to {to}")
+            }
+            ChangeKind::VersionChanged { from, to } => {
+                write!(f, "version changed from {from} to {to}")
+            }
+        }
+    }
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let compatibility = match self.compatibility {
+            Compatibility::Compatible => "compatible",
+            Compatibility::Breaking => "breaking",
+        };
+        write!(f, "[{compatibility}] {}", self.agent_type)?;
+        if let Some(method) = &self.method {
+            write!(f, ".{method}")?;
+        }
+        if !self.location.is_empty() {
+            write!(f, " {}", self.location.join(" > "))?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Input,
+    Output,
+}
+
+impl Direction {
+    // Accepting more values is compatible for inputs, producing more values is breaking for outputs
+    fn widened(self) -> Compatibility {
+        match self {
+            Direction::Input => Compatibility::Compatible,
+            Direction::Output => Compatibility::Breaking,
+        }
+    }
+
+    fn narrowed(self) -> Compatibility {
+        match self {
+            Direction::Input => Compatibility::Breaking,
+            Direction::Output => Compatibility::Compatible,
+        }
+    }
+}
+
+pub fn compare_agent_types(old: &[AgentType], new: &[AgentType]) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+
+    for old_type in old {
+        match new.iter().find(|t| t.type_name == old_type.type_name) {
+            Some(new_type) => compare_agent_type(old_type, new_type, &mut changes),
+            None => changes.push(SchemaChange {
+                agent_type: old_type.type_name.clone(),
+                method: None,
+                location: Vec::new(),
+                kind: ChangeKind::AgentTypeRemoved,
+                compatibility: Compatibility::Breaking,
+            }),
+        }
+    }
+
+    for new_type in new {
+        if !old.iter().any(|t| t.type_name == new_type.type_name) {
+            changes.push(SchemaChange {
+                agent_type: new_type.type_name.clone(),
+                method: None,
+                location: Vec::new(),
+                kind: ChangeKind::AgentTypeAdded,
+                compatibility: Compatibility::Compatible,
+            });
+        }
+    }
+
+    changes
+}
+
+fn compare_agent_type(old: &AgentType, new: &AgentType, changes: &mut Vec<SchemaChange>) {
+    let mut comparison = Comparison {
+        agent_type: &old.type_name,
+        method: None,
+        location: Vec::new(),
+        changes,
+    };
+    comparison.compare_schemas(
+        &old.agent_constructor.input_schema,
+        &new.agent_constructor.input_schema,
+        Direction::Input,
+        "parameter",
+    );
+
+    // Agents saved by a newer version can't be restored by an older one, see `golem_agentic::versioning`
+    if old.version != new.version {
+        let compatibility = if new.version > old.version {
+            Compatibility::Compatible
+        } else {
+            Compatibility::Breaking
+        };
+        comparison.push(
+            ChangeKind::VersionChanged {
+                from: old.version,
+                to: new.version,
+            },
+            compatibility,
+        );
+    }
+
+    for old_method in &old.methods {
+        let mut comparison = Comparison {
+            agent_type: &old.type_name,
+            method: Some(&old_method.name),
+            location: Vec::new(),
+            changes,
+        };
+        match new.method(&old_method.name) {
+            Some(new_method) => {
+                comparison.compare_schemas(
+                    &old_method.input_schema,
+                    &new_method.input_schema,
+                    Direction::Input,
+                    "parameter",
+                );
+                comparison.compare_schemas(
+                    &old_method.output_schema,
+                    &new_method.output_schema,
+                    Direction::Output,
+                    "result",
+                );
+            }
+            None => comparison.push(ChangeKind::MethodRemoved, Compatibility::Breaking),
+        }
+    }
+
+    for new_method in &new.methods {
+        if old.method(&new_method.name).is_none() {
+            Comparison {
+                agent_type: &old.type_name,
+                method: Some(&new_method.name),
+                location: Vec::new(),
+                changes,
+            }
+            .push(ChangeKind::MethodAdded, Compatibility::Compatible);
+        }
+    }
+}
+
+struct Comparison<'a> {
+    agent_type: &'a str,
+    method: Option<&'a str>,
+    location: Vec<String>,
+    changes: &'a mut Vec<SchemaChange>,
+}
+
+impl Comparison<'_> {
+    fn push(&mut self, kind: ChangeKind, compatibility: Compatibility) {
+        self.changes.push(SchemaChange {
+            agent_type: self.agent_type.to_string(),
+            method: self.method.map(|method| method.to_string()),
+            location: self.location.clone(),
+            kind,
+            compatibility,
+        });
+    }
+
+    fn within(&mut self, segment: String, compare: impl FnOnce(&mut Self)) {
+        self.location.push(segment);
+        compare(self);
+        self.location.pop();
+    }
+
+    fn compare_schemas(
+        &mut self,
+        old: &DataSchema,
+        new: &DataSchema,
+        direction: Direction,
+        item_name: &str,
+    ) {
+        match (old, new) {
+            (
+                DataSchema::Structured { parameters: old },
+                DataSchema::Structured { parameters: new },
+            ) => {
+                for (index, (old, new)) in old.iter().zip(new).enumerate() {
+                    self.within(format!("{item_name} {index}"), |c| {
+                        c.compare_parameters(old, new, direction)
+                    });
+                }
+                // Invocations are rejected unless they have exactly as many parameters as the method
+                for (index, parameter) in new.iter().enumerate().skip(old.len()) {
+                    let optional = is_optional(&parameter.analysed_type());
+                    self.within(format!("{item_name} {index}"), |c| {
+                        c.push(ChangeKind::ParameterAdded { optional }, Compatibility::Breaking)
+                    });
+                }
+                for index in new.len()..old.len() {
+                    self.within(format!("{item_name} {index}"), |c| {
+                        c.push(ChangeKind::ParameterRemoved, Compatibility::Breaking)
+                    });
+                }
+            }
+            (DataSchema::Multimodal { text: old }, DataSchema::Multimodal { text: new }) => {
+                if old != new {
+                    self.push(
+                        ChangeKind::TypeChanged {
+                            from: "multimodal".to_string(),
+                            to: "multimodal".to_string(),
+                        },
+                        Compatibility::Breaking,
+                    );
+                }
+            }
+            _ => self.push(
+                ChangeKind::TypeChanged {
+                    from: schema_kind(old).to_string(),
+                    to: schema_kind(new).to_string(),
+                },
+                Compatibility::Breaking,
+            ),
+        }
+    }
+
+    fn compare_parameters(&mut self, old: &ParameterType, new: &ParameterType, direction: Direction) {
+        match (old, new) {
+            (ParameterType::Wit(old), ParameterType::Wit(new)) => {
+                self.compare_types(old, new, direction)
+            }
+            (ParameterType::Text(old), ParameterType::Text(new)) => {
+                self.compare_text_types(old, new, direction)
+            }
+            // Text is passed as a string, but its constraints are only checked as a text parameter
+            (ParameterType::Wit(AnalysedType::Str(_)), ParameterType::Text(_)) => self.push(
+                ChangeKind::TextConstraintsChanged {
+                    from: "string".to_string(),
+                    to: format_parameter_type(new),
+                },
+                direction.narrowed(),
+            ),
+            (ParameterType::Text(_), ParameterType::Wit(AnalysedType::Str(_))) => self.push(
+                ChangeKind::TextConstraintsChanged {
+                    from: format_parameter_type(old),
+                    to: "string".to_string(),
+                },
+                direction.widened(),
+            ),
+            _ => self.push(
+                ChangeKind::TypeChanged {
+                    from: format_parameter_type(old),
+                    to: format_parameter_type(new),
+                },
+                Compatibility::Breaking,
+            ),
+        }
+    }
+
+    fn compare_text_types(&mut self, old: &TextType, new: &TextType, direction: Direction) {
+        if old == new {
+            return;
+        }
+
+        let accepts_more = |wider: &TextType, narrower: &TextType| {
+            let length = match (wider.max_length, narrower.max_length) {
+                (None, _) => true,
+                (Some(_), None) => false,
+                (Some(wider), Some(narrower)) => wider >= narrower,
+            };
+            let language =
+                wider.language_code.is_empty() || wider.language_code == narrower.language_code;
+            let format = wider.format == TextFormat::Plain || wider.format == narrower.format;
+            length && language && format
+        };
+
+        let compatibility = if accepts_more(new, old) {
+            direction.widened()
+        } else if accepts_more(old, new) {
+            direction.narrowed()
+        } else {
+            Compatibility::Breaking
+        };
+
+        self.push(
+            ChangeKind::TextConstraintsChanged {
+                from: format_text_type(old),
+                to: format_text_type(new),
+            },
+            compatibility,
+        );
+    }
+
+    fn compare_types(&mut self, old: &AnalysedType, new: &AnalysedType, direction: Direction) {
+        match (old, new) {
+            (AnalysedType::Option(old), AnalysedType::Option(new)) => {
+                self.compare_types(&old.inner, &new.inner, direction)
+            }
+            (AnalysedType::List(old), AnalysedType::List(new)) => {
+                self.compare_types(&old.inner, &new.inner, direction)
+            }
+            (AnalysedType::Option(TypeOption { inner, .. }), new) if same_kind(inner, new) => {
+                self.push(ChangeKind::MadeRequired, direction.narrowed());
+                self.compare_types(inner, new, direction);
+            }
+            (old, AnalysedType::Option(TypeOption { inner, .. })) if same_kind(old, inner) => {
+                self.push(ChangeKind::MadeOptional, direction.widened());
+                self.compare_types(old, inner, direction);
+            }
+            (
+                AnalysedType::Tuple(TypeTuple { items: old_items, .. }),
+                AnalysedType::Tuple(TypeTuple { items: new_items, .. }),
+            ) => {
+                if old_items.len() != new_items.len() {
+                    self.push(type_changed(old, new), Compatibility::Breaking);
+                    return;
+                }
+                for (index, (old, new)) in old_items.iter().zip(new_items).enumerate() {
+                    self.within(format!("tuple item {index}"), |c| {
+                        c.compare_types(old, new, direction)
+                    });
+                }
+            }
+            (AnalysedType::Record(old), AnalysedType::Record(new)) => {
+                self.compare_records(&old.fields, &new.fields, direction)
+            }
+            (AnalysedType::Variant(old), AnalysedType::Variant(new)) => {
+                self.compare_variants(&old.cases, &new.cases, direction)
+            }
+            (AnalysedType::Enum(old), AnalysedType::Enum(new)) => {
+                self.compare_variants(&unit_cases(old), &unit_cases(new), direction)
+            }
+            (AnalysedType::Flags(old), AnalysedType::Flags(new)) => {
+                // Flags are bits by position, so only flags appended to the unchanged ones keep their meaning
+                let appended = new.names.starts_with(&old.names);
+                for name in new.names.iter().filter(|name| !old.names.contains(name)) {
+                    let compatibility = if appended {
+                        direction.widened()
+                    } else {
+                        Compatibility::Breaking
+                    };
+                    self.push(ChangeKind::FlagAdded { name: name.clone() }, compatibility);
+                }
+                for name in old.names.iter().filter(|name| !new.names.contains(name)) {
+                    self.push(ChangeKind::FlagRemoved { name: name.clone() }, Compatibility::Breaking);
+                }
+                let old_names = old.names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+                let new_names = new.names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+                if !moved(&old_names, &new_names).is_empty() {
+                    self.push(type_changed_flags(&old.names, &new.names), Compatibility::Breaking);
+                }
+            }
+            (AnalysedType::Result(old), AnalysedType::Result(new)) => {
+                self.within("ok".to_string(), |c| {
+                    c.compare_payloads(old.ok.as_deref(), new.ok.as_deref(), direction)
+                });
+                self.within("err".to_string(), |c| {
+                    c.compare_payloads(old.err.as_deref(), new.err.as_deref(), direction)
+                });
+            }
+            (old, new) if same_kind(old, new) => {}
+            (old, new) => match widens(old, new) {
+                Some(true) => self.push(
+                    ChangeKind::TypeWidened {
+                        from: format_type(old),
+                        to: format_type(new),
+                    },
+                    direction.widened(),
+                ),
+                Some(false) => self.push(
+                    ChangeKind::TypeNarrowed {
+                        from: format_type(old),
+                        to: format_type(new),
+                    },
+                    direction.narrowed(),
+                ),
+                None => self.push(type_changed(old, new), Compatibility::Breaking),
+            },
+        }
+    }
+
+    fn compare_records(&mut self, old: &[NameTypePair], new: &[NameTypePair], direction: Direction) {
+        let old_names = old.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+        let new_names = new.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+
+        for old_field in old {
+            match new.iter().find(|field| field.name == old_field.name) {
+                Some(new_field) => self.within(format!("field {}", old_field.name), |c| {
+                    c.compare_types(&old_field.typ, &new_field.typ, direction)
+                }),
+                None => self.push(
+                    ChangeKind::FieldRemoved {
+                        name: old_field.name.clone(),
+                        optional: is_optional(&old_field.typ),
+                    },
+                    Compatibility::Breaking,
+                ),
+            }
+        }
+
+        for new_field in new.iter().filter(|field| !old_names.contains(&field.name.as_str())) {
+            self.push(
+                ChangeKind::FieldAdded {
+                    name: new_field.name.clone(),
+                    optional: is_optional(&new_field.typ),
+                },
+                Compatibility::Breaking,
+            );
+        }
+
+        for (name, from, to) in moved(&old_names, &new_names) {
+            self.push(ChangeKind::FieldMoved { name, from, to }, Compatibility::Breaking);
+        }
+    }
+
+    fn compare_variants(
+        &mut self,
+        old: &[NameOptionTypePair],
+        new: &[NameOptionTypePair],
+        direction: Direction,
+    ) {
+        let old_names = old.iter().map(|case| case.name.as_str()).collect::<Vec<_>>();
+        let new_names = new.iter().map(|case| case.name.as_str()).collect::<Vec<_>>();
+
+        for old_case in old {
+            if let Some(new_case) = new.iter().find(|case| case.name == old_case.name) {
+                self.within(format!("case {}", old_case.name), |c| {
+                    c.compare_payloads(old_case.typ.as_ref(), new_case.typ.as_ref(), direction)
+                });
+            }
+        }
+
+        // A case removed and another one added in its place, with the same payload, is a rename
+        let mut renamed = Vec::new();
+        for (index, (old_case, new_case)) in old.iter().zip(new).enumerate() {
+            if !new_names.contains(&old_case.name.as_str())
+                && !old_names.contains(&new_case.name.as_str())
+                && old_case.typ == new_case.typ
+            {
+                self.push(
+                    ChangeKind::CaseRenamed { from: old_case.name.clone(), to: new_case.name.clone() },
+                    Compatibility::Breaking,
+                );
+                renamed.push(index);
+            }
+        }
+
+        for (index, case) in old.iter().enumerate() {
+            if !renamed.contains(&index) && !new_names.contains(&case.name.as_str()) {
+                self.push(ChangeKind::CaseRemoved { name: case.name.clone() }, Compatibility::Breaking);
+            }
+        }
+
+        // Cases appended after the existing ones leave the positions of these unchanged
+        let appended = new_names.starts_with(&old_names);
+        for (index, case) in new.iter().enumerate() {
+            if !renamed.contains(&index) && !old_names.contains(&case.name.as_str()) {
+                let compatibility = if appended {
+                    direction.widened()
+                } else {
+                    Compatibility::Breaking
+                };
+                self.push(ChangeKind::CaseAdded { name: case.name.clone() }, compatibility);
+            }
+        }
+
+        for (name, from, to) in moved(&old_names, &new_names) {
+            self.push(ChangeKind::CaseMoved { name, from, to }, Compatibility::Breaking);
+        }
+    }
+
+    fn compare_payloads(&mut self, old: Option<&AnalysedType>, new: Option<&AnalysedType>, direction: Direction) {
+        match (old, new) {
+            (Some(old), Some(new)) => self.compare_types(old, new, direction),
+            (None, None) => {}
+            (old, new) => self.push(
+                ChangeKind::TypeChanged {
+                    from: old.map(format_type).unwrap_or_else(|| "no payload".to_string()),
+                    to: new.map(format_type).unwrap_or_else(|| "no payload".to_string()),
+                },
+                Compatibility::Breaking,
+            ),
+        }
+    }
+}
+
+// The names kept in both versions whose order changed, with their old and new positions
+fn moved(old: &[&str], new: &[&str]) -> Vec<(String, usize, usize)> {
+    let old_kept = old.iter().filter(|name| new.contains(name)).collect::<Vec<_>>();
+    let new_kept = new.iter().filter(|name| old.contains(name)).collect::<Vec<_>>();
+
+    old_kept
+        .iter()
+        .zip(&new_kept)
+        .filter(|(old_name, new_name)| old_name != new_name)
+        .map(|(name, _)| {
+            let from = old.iter().position(|old_name| old_name == *name).unwrap_or_default();
+            let to = new.iter().position(|new_name| new_name == *name).unwrap_or_default();
+            (name.to_string(), from, to)
+        })
+        .collect()
+}
+
+fn type_changed_flags(old: &[String], new: &[String]) -> ChangeKind {
+    ChangeKind::TypeChanged {
+        from: format!("flags {{ {} }}", old.join(", ")),
+        to: format!("flags {{ {} }}", new.join(", ")),
+    }
+}
+
+fn type_changed(old: &AnalysedType, new: &AnalysedType) -> ChangeKind {
+    ChangeKind::TypeChanged {
+        from: format_type(old),
+        to: format_type(new),
+    }
+}
+
+fn is_optional(typ: &AnalysedType) -> bool {
+    matches!(typ, AnalysedType::Option(_))
+}
+
+fn same_kind(a: &AnalysedType, b: &AnalysedType) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn unit_cases(type_enum: &TypeEnum) -> Vec<NameOptionTypePair> {
+    type_enum
+        .cases
+        .iter()
+        .map(|name| NameOptionTypePair {
+            name: name.clone(),
+            typ: None,
+        })
+        .collect()
+}
+
+// Whether every value of the old numeric type is a value of the new one (widening),
+// or the other way around (narrowing). Integers and floats are not mixed,
+// as floats can't represent every integer exactly
+fn widens(old: &AnalysedType, new: &AnalysedType) -> Option<bool> {
+    match (old, new) {
+        (AnalysedType::F32(_), AnalysedType::F64(_)) => Some(true),
+        (AnalysedType::F64(_), AnalysedType::F32(_)) => Some(false),
+        _ => {
+            let (old_min, old_max) = integer_range(old)?;
+            let (new_min, new_max) = integer_range(new)?;
+            if new_min <= old_min && old_max <= new_max {
+                Some(true)
+            } else if old_min <= new_min && new_max <= old_max {
+                Some(false)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn integer_range(typ: &AnalysedType) -> Option<(i128, i128)> {
+    match typ {
+        AnalysedType::U8(_) => Some((0, u8::MAX as i128)),
+        AnalysedType::U16(_) => Some((0, u16::MAX as i128)),
+        AnalysedType::U32(_) => Some((0, u32::MAX as i128)),
+        AnalysedType::U64(_) => Some((0, u64::MAX as i128)),
+        AnalysedType::S8(_) => Some((i8::MIN as i128, i8::MAX as i128)),
+        AnalysedType::S16(_) => Some((i16::MIN as i128, i16::MAX as i128)),
+        AnalysedType::S32(_) => Some((i32::MIN as i128, i32::MAX as i128)),
+        AnalysedType::S64(_) => Some((i64::MIN as i128, i64::MAX as i128)),
+        _ => None,
+    }
+}
+
+fn schema_kind(schema: &DataSchema) -> &'static str {
+    match schema {
+        DataSchema::Structured { .. } => "structured",
+        DataSchema::Multimodal { .. } => "multimodal",
+    }
+}
+
+fn format_parameter_type(parameter: &ParameterType) -> String {
+    match parameter {
+        ParameterType::Wit(typ) => format_type(typ),
+        ParameterType::Text(text_type) => format_text_type(text_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AgentConstructor, AgentMethod};
+    use golem_wasm_ast::analysis::analysed_type::{
+        case, field, flags, option, r#enum, record, str, u32, unit_case, variant,
+    };
+
+    fn agent_type(parameter: AnalysedType, result: AnalysedType) -> AgentType {
+        AgentType {
+            type_name: "weather-agent".to_string(),
+            description: String::new(),
+            version: 1,
+            agent_constructor: AgentConstructor {
+                name: None,
+                description: String::new(),
+                prompt_hint: None,
+                input_schema: DataSchema::Structured { parameters: vec![] },
+            },
+            methods: vec![AgentMethod {
+                name: "get-forecast".to_string(),
+                description: String::new(),
+                prompt_hint: None,
+                input_schema: DataSchema::Structured {
+                    parameters: vec![ParameterType::Wit(parameter)],
+                },
+                output_schema: DataSchema::Structured {
+                    parameters: vec![ParameterType::Wit(result)],
+                },
+            }],
+            requires: vec![],
+        }
+    }
+
+    fn changes(old: AgentType, new: AgentType) -> Vec<(Vec<String>, ChangeKind, Compatibility)> {
+        compare_agent_types(&[old], &[new])
+            .into_iter()
+            .map(|change| (change.location, change.kind, change.compatibility))
+            .collect()
+    }
+
+    #[test]
+    fn optional_record_fields_can_not_be_added() {
+        let old = record(vec![field("city", str())]);
+        let new = record(vec![field("city", str()), field("days", option(u32()))]);
+
+        assert_eq!(
+            changes(agent_type(old, str()), agent_type(new, str())),
+            vec![(
+                vec!["parameter 0".to_string()],
+                ChangeKind::FieldAdded { name: "days".to_string(), optional: true },
+                Compatibility::Breaking
+            )]
+        );
+    }
+
+    #[test]
+    fn reordered_record_fields_are_breaking() {
+        let old = record(vec![field("city", str()), field("country", str())]);
+        let new = record(vec![field("country", str()), field("city", str())]);
+
+        assert_eq!(
+            changes(agent_type(str(), old), agent_type(str(), new)),
+            vec![
+                (
+                    vec!["result 0".to_string()],
+                    ChangeKind::FieldMoved { name: "city".to_string(), from: 0, to: 1 },
+                    Compatibility::Breaking
+                ),
+                (
+                    vec!["result 0".to_string()],
+                    ChangeKind::FieldMoved { name: "country".to_string(), from: 1, to: 0 },
+                    Compatibility::Breaking
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn variant_cases_can_only_be_appended() {
+        let old = variant(vec![case("city", str()), unit_case("here")]);
+        let appended = variant(vec![case("city", str()), unit_case("here"), case("zip", u32())]);
+        let inserted = variant(vec![case("zip", u32()), case("city", str()), unit_case("here")]);
+
+        assert_eq!(
+            changes(agent_type(old.clone(), str()), agent_type(appended, str())),
+            vec![(
+                vec!["parameter 0".to_string()],
+                ChangeKind::CaseAdded { name: "zip".to_string() },
+                Compatibility::Compatible
+            )]
+        );
+        assert_eq!(
+            changes(agent_type(old, str()), agent_type(inserted, str())),
+            vec![(
+                vec!["parameter 0".to_string()],
+                ChangeKind::CaseAdded { name: "zip".to_string() },
+                Compatibility::Breaking
+            )]
+        );
+    }
+
+    #[test]
+    fn appended_result_cases_are_breaking() {
+        let old = r#enum(&["sunny", "rainy"]);
+        let new = r#enum(&["sunny", "rainy", "snowy"]);
+
+        assert_eq!(
+            changes(agent_type(str(), old), agent_type(str(), new)),
+            vec![(
+                vec!["result 0".to_string()],
+                ChangeKind::CaseAdded { name: "snowy".to_string() },
+                Compatibility::Breaking
+            )]
+        );
+    }
+
+    #[test]
+    fn reordered_and_removed_cases_are_breaking() {
+        let old = r#enum(&["sunny", "rainy", "snowy"]);
+        let reordered = r#enum(&["rainy", "sunny", "snowy"]);
+        let removed = r#enum(&["sunny", "rainy"]);
+
+        assert_eq!(
+            changes(agent_type(old.clone(), str()), agent_type(reordered, str())),
+            vec![
+                (
+                    vec!["parameter 0".to_string()],
+                    ChangeKind::CaseMoved { name: "sunny".to_string(), from: 0, to: 1 },
+                    Compatibility::Breaking
+                ),
+                (
+                    vec!["parameter 0".to_string()],
+                    ChangeKind::CaseMoved { name: "rainy".to_string(), from: 1, to: 0 },
+                    Compatibility::Breaking
+                ),
+            ]
+        );
+        assert_eq!(
+            changes(agent_type(str(), old), agent_type(str(), removed)),
+            vec![(
+                vec!["result 0".to_string()],
+                ChangeKind::CaseRemoved { name: "snowy".to_string() },
+                Compatibility::Breaking
+            )]
+        );
+    }
+
+    #[test]
+    fn flags_can_only_be_appended() {
+        let old = flags(&["radar"]);
+        let appended = flags(&["radar", "satellite"]);
+        let inserted = flags(&["satellite", "radar"]);
+
+        assert_eq!(
+            changes(agent_type(old.clone(), str()), agent_type(appended, str())),
+            vec![(
+                vec!["parameter 0".to_string()],
+                ChangeKind::FlagAdded { name: "satellite".to_string() },
+                Compatibility::Compatible
+            )]
+        );
+        assert_eq!(
+            changes(agent_type(old, str()), agent_type(inserted, str()))
+                .into_iter()
+                .map(|(_, _, compatibility)| compatibility)
+                .collect::<Vec<_>>(),
+            vec![Compatibility::Breaking]
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use crate::rpc::{LocalRpc, GOLEM_RPC_INTERFACE_PREFIX};
+use crate::stubs::{HostStubs, GOLEM_HOST_INTERFACE_PREFIX};
+use anyhow::anyhow;
+use rib::ParsedFunctionName;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::{Arc, Weak};
+use wasmtime::component::types::{ComponentInstance, ComponentItem};
+use wasmtime::component::{
+    Component, Instance, Linker, LinkerInstance, ResourceTable, ResourceType, Type, Val,
+};
+use wasmtime::{Engine, Store};
+use wasmtime_wasi::p2::{bindings, IoView, WasiCtx, WasiView};
+
+pub const AGENT_GUEST_INTERFACE: &str = "golem:agent/guest";
+
+// Returned when the component does not export the agent guest interface,
+// i.e. it was not built with golem-agentic
+#[derive(Debug)]
+pub struct NotAnAgentComponent(pub String);
+
+impl Display for NotAnAgentComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not an agent component: it does not export {}",
+            self.0, AGENT_GUEST_INTERFACE
+        )
+    }
+}
+
+impl std::error::Error for NotAnAgentComponent {}
+
+#[derive(Clone, Debug, Default)]
+pub struct HostOptions {
+    // Logs the imports that are linked dynamically to stderr
+    pub verbose: bool,
+    // Fake values returned by the stubbed `golem:api/host` functions
+    pub stubs: HostStubs,
+}
+
+// An instantiated agent component, with every non-WASI import linked dynamically
+pub struct AgentComponent {
+    pub store: Store<Host>,
+    pub instance: Instance,
+}
+
+impl AgentComponent {
+    pub async fn instantiate(path: &Path, options: &HostOptions) -> anyhow::Result<Self> {
+        Self::instantiate_with_rpc(path, options, Weak::new()).await
+    }
+
+    // Instantiates the component as one of the workers of a `LocalRpc`,
+    // so its `wasm-rpc` calls are routed to the other workers
+    pub async fn instantiate_worker(
+        path: &Path,
+        options: &HostOptions,
+        rpc: &Arc<LocalRpc>,
+    ) -> anyhow::Result<Self> {
+        Self::instantiate_with_rpc(path, options, Arc::downgrade(rpc)).await
+    }
+
+    async fn instantiate_with_rpc(
+        path: &Path,
+        options: &HostOptions,
+        rpc: Weak<LocalRpc>,
+    ) -> anyhow::Result<Self> {
+        let mut config = wasmtime::Config::default();
+        config.async_support(true);
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config)?;
+        let mut linker: Linker<Host> = Linker::new(&engine);
+
+        wasmtime_wasi::p2::add_to_linker_with_options_async(
+            &mut linker,
+            &bindings::LinkOptions::default(),
+        )?;
+
+        let ctx = WasiCtx::builder().inherit_stderr().build();
+        let host = Host {
+            table: Arc::new(Mutex::new(ResourceTable::new())),
+            wasi: Arc::new(Mutex::new(ctx)),
+            rpc,
+        };
+
+        let component = Component::from_file(&engine, path)
+            .map_err(|err| anyhow!("Failed to load component {}: {err}", path.display()))?;
+
+        let component_type = component.component_type();
+
+        let exports_agent_guest = component_type
+            .exports(&engine)
+            .any(|(name, _)| name == AGENT_GUEST_INTERFACE);
+
+        if !exports_agent_guest {
+            return Err(NotAnAgentComponent(path.display().to_string()).into());
+        }
+
+        let mut store = Store::new(&engine, host);
+
+        let mut linker_instance = linker.root();
+        for (name, item) in component_type.imports(&engine) {
+            let name = name.to_string();
+            match item {
+                ComponentItem::ComponentFunc(_) => {}
+                ComponentItem::CoreFunc(_) => {}
+                ComponentItem::Module(_) => {}
+                ComponentItem::Component(_) => {}
+                ComponentItem::ComponentInstance(ref inst) => {
+                    dynamic_import(&name, &engine, &mut linker_instance, inst, options)?;
+                }
+                ComponentItem::Type(_) => {}
+                ComponentItem::Resource(_) => {}
+            }
+        }
+
+        let instance = linker.instantiate_async(&mut store, &component).await?;
+
+        Ok(AgentComponent { store, instance })
+    }
+
+    // Calls a function of the agent guest interface, e.g. `discover-agent-types`
+    // or `[method]agent.invoke`
+    pub async fn call_guest_function(
+        &mut self,
+        function_name: &str,
+        params: &[Val],
+    ) -> anyhow::Result<Vec<Val>> {
+        let (_, exported_instance_id) = self
+            .instance
+            .get_export(&mut self.store, None, AGENT_GUEST_INTERFACE)
+            .ok_or_else(|| anyhow!("Interface {AGENT_GUEST_INTERFACE} not found"))?;
+        let (_, func_id) = self
+            .instance
+            .get_export(&mut self.store, Some(&exported_instance_id), function_name)
+            .ok_or_else(|| {
+                anyhow!("Function {function_name} not found in interface {AGENT_GUEST_INTERFACE}")
+            })?;
+        let func = self
+            .instance
+            .get_func(&mut self.store, func_id)
+            .ok_or_else(|| anyhow!("Function {function_name} not found"))?;
+
+        let mut results = (0..func.results(&self.store).len())
+            .map(|_| Val::Bool(false))
+            .collect::<Vec<_>>();
+        func.call_async(&mut self.store, params, &mut results)
+            .await
+            .map_err(|err| anyhow!("Calling {function_name} failed: {err:?}"))?;
+        func.post_return_async(&mut self.store).await?;
+
+        Ok(results)
+    }
+}
+
+#[derive(Clone)]
+pub struct Host {
+    pub table: Arc<Mutex<ResourceTable>>,
+    pub wasi: Arc<Mutex<WasiCtx>>,
+    // Not set when the component is instantiated on its own, e.g. for extracting agent types
+    pub rpc: Weak<LocalRpc>,
+}
+
+impl IoView for Host {
+    fn table(&mut self) -> &mut ResourceTable {
+        Arc::get_mut(&mut self.table)
+            .expect("ResourceTable is shared and cannot be borrowed mutably")
+            .get_mut()
+            .expect("ResourceTable mutex must never fail")
+    }
+}
+
+impl WasiView for Host {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        Arc::get_mut(&mut self.wasi)
+            .expect("WasiCtx is shared and cannot be borrowed mutably")
+            .get_mut()
+            .expect("WasiCtx mutex must never fail")
+    }
+}
+
+pub fn dynamic_import(
+    name: &str,
+    engine: &Engine,
+    root: &mut LinkerInstance<Host>,
+    inst: &ComponentInstance,
+    options: &HostOptions,
+) -> anyhow::Result<()> {
+    if name.starts_with("wasi:cli")
+        || name.starts_with("wasi:clocks")
+        || name.starts_with("wasi:filesystem")
+        || name.starts_with("wasi:io")
+        || name.starts_with("wasi:random")
+        || name.starts_with("wasi:sockets")
+    {
+        // These does not have to be mocked, we allow them through wasmtime-wasi
+        Ok(())
+    } else {
+        if options.verbose {
+            eprintln!("dynamic_import: {}", name);
+        }
+
+        let mut instance = root.instance(name)?;
+        let mut resources: HashMap<(String, String), Vec<MethodInfo>> = HashMap::new();
+        let mut functions = Vec::new();
+
+        for (inner_name, inner_item) in inst.exports(engine) {
+            let name = name.to_owned();
+            let inner_name = inner_name.to_owned();
+
+            match inner_item {
+                ComponentItem::ComponentFunc(fun) => {
+                    let param_types: Vec<Type> = fun.params().map(|(_, t)| t).collect();
+                    let result_types: Vec<Type> = fun.results().collect();
+
+                    let function_name = ParsedFunctionName::parse(format!(
+                        "{name}.{{{inner_name}}}"
+                    ))
+                        .map_err(|err| anyhow!(format!("Unexpected linking error: {name}.{{{inner_name}}} is not a valid function name: {err}")))?;
+
+                    if let Some(resource_name) = function_name.function.resource_name() {
+                        let methods = resources
+                            .entry((name.clone(), resource_name.clone()))
+                            .or_default();
+                        methods.push(MethodInfo {
+                            method_name: inner_name.clone(),
+                            params: param_types.clone(),
+                            results: result_types.clone(),
+                        });
+                    }
+
+                    functions.push(FunctionInfo {
+                        name: function_name,
+                        params: param_types,
+                        results: result_types,
+                    });
+                }
+                ComponentItem::CoreFunc(_) => {}
+                ComponentItem::Module(_) => {}
+                ComponentItem::Component(_) => {}
+                ComponentItem::ComponentInstance(_) => {}
+                ComponentItem::Type(_) => {}
+                ComponentItem::Resource(_resource) => {
+                    resources.entry((name, inner_name)).or_default();
+                }
+            }
+        }
+
+        for ((interface_name, resource_name), _methods) in resources {
+            if options.verbose {
+                eprintln!("Defining resource: {interface_name}.{resource_name}");
+            }
+            instance.resource(
+                &resource_name,
+                ResourceType::host::<ResourceEntry>(),
+                |_store, _rep| Ok(()),
+            )?;
+        }
+
+        let stubs = name
+            .starts_with(GOLEM_HOST_INTERFACE_PREFIX)
+            .then(|| options.stubs.clone());
+
+        if name.starts_with(GOLEM_RPC_INTERFACE_PREFIX) {
+            for function in functions {
+                if options.verbose {
+                    eprintln!("Defining function: {}", function.name);
+                }
+                instance.func_new_async(
+                    &function.name.function.function_name(),
+                    move |store, params, results| {
+                        let function_name = function.name.function.function_name();
+                        Box::new(async move {
+                            let rpc = store.data().rpc.upgrade().ok_or_else(|| {
+                                anyhow!("External function called in get-agent-definitions: {function_name}")
+                            })?;
+                            let values = rpc.call(store, &function_name, params).await?;
+                            for (result, value) in results.iter_mut().zip(values) {
+                                *result = value;
+                            }
+                            Ok(())
+                        })
+                    },
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        for function in functions {
+            if options.verbose {
+                eprintln!("Defining function: {}", function.name);
+            }
+            let stubs = stubs.clone();
+            instance.func_new_async(
+                &function.name.function.function_name(),
+                move |_store, params, results| {
+                    let function_name = function.name.clone();
+                    let stubbed = stubs.as_ref().and_then(|stubs| {
+                        stubs.call(
+                            &function_name.function.function_name(),
+                            params,
+                            &function.results,
+                        )
+                    });
+
+                    let outcome = match stubbed {
+                        Some(Ok(values)) => {
+                            for (result, value) in results.iter_mut().zip(values) {
+                                *result = value;
+                            }
+                            Ok(())
+                        }
+                        Some(Err(err)) => Err(anyhow!("Stubbed {function_name} failed: {err}")),
+                        None => Err(anyhow!(
+                            "External function called in get-agent-definitions: {function_name}"
+                        )),
+                    };
+
+                    Box::new(async move { outcome })
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+struct MethodInfo {
+    method_name: String,
+    params: Vec<Type>,
+    results: Vec<Type>,
+}
+
+#[allow(dead_code)]
+struct FunctionInfo {
+    name: ParsedFunctionName,
+    params: Vec<Type>,
+    results: Vec<Type>,
+}
+
+pub struct ResourceEntry;
@@ -0,0 +1,54 @@
+pub mod compat;
+pub mod host;
+pub mod literal;
+pub mod manifest;
+pub mod model;
+pub mod rpc;
+pub mod runner;
+pub mod stubs;
+pub mod values;
+pub mod worker;
+
+use crate::host::{AgentComponent, HostOptions};
+use crate::manifest::read_manifest;
+use crate::model::{list_of, AgentType};
+use std::path::Path;
+
+// Instantiates the component and calls `discover-agent-types` on it
+pub async fn extract_agent_types(
+    component_path: &Path,
+    options: &HostOptions,
+) -> anyhow::Result<Vec<AgentType>> {
+    let mut component = AgentComponent::instantiate(component_path, options).await?;
+    discover_agent_types(&mut component).await
+}
+
+// Reads the agent types embedded in the component by `embed`, without instantiating it
+pub fn read_embedded_agent_types(component_path: &Path) -> anyhow::Result<Option<Vec<AgentType>>> {
+    let wasm = std::fs::read(component_path)
+        .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", component_path.display()))?;
+    Ok(read_manifest(&wasm)?.map(|manifest| manifest.agent_types))
+}
+
+// Same as `read_embedded_agent_types`, but components that didn't go through `embed` are rejected
+pub fn read_required_embedded_agent_types(component_path: &Path) -> anyhow::Result<Vec<AgentType>> {
+    read_embedded_agent_types(component_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} has no embedded agent types, use `embed` to add them",
+            component_path.display()
+        )
+    })
+}
+
+pub async fn discover_agent_types(component: &mut AgentComponent) -> anyhow::Result<Vec<AgentType>> {
+    let results = component
+        .call_guest_function("discover-agent-types", &[])
+        .await?;
+
+    let agent_types = results
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("discover-agent-types returned no result"))?;
+
+    list_of(agent_types, AgentType::from_val)
+        .map_err(|err| anyhow::anyhow!("Unexpected result of discover-agent-types: {err}"))
+}
@@ -0,0 +1,758 @@
+use crate::model::TextType;
+use anyhow::{anyhow, bail};
+use golem_wasm_ast::analysis::{
+    AnalysedType, NameOptionTypePair, NameTypePair, TypeEnum, TypeFlags, TypeList, TypeOption,
+    TypeRecord, TypeResult, TypeTuple, TypeVariant,
+};
+use wasmtime::component::Val;
+
+// A typed literal syntax for values, close to how values are written in WIT related tooling:
+//
+// bool    -> true, false
+// numbers -> 42, -1, 3.14
+// char    -> 'a'
+// string  -> "hello"
+// list    -> [1, 2, 3]
+// tuple   -> (1, "a")
+// record  -> {name: "x", age: 3} (option fields can be left out)
+// variant -> case or case(value)
+// enum    -> case
+// flags   -> {read, write}
+// option  -> none, some(value), or just the value
+// result  -> ok, ok(value), err, err(value)
+//
+// Literals are parsed according to the expected type, so the same text may be read differently
+// depending on the parameter it is given for.
+
+pub fn parse_literal(text: &str, typ: &AnalysedType) -> anyhow::Result<Val> {
+    let mut parser = Parser::new(text);
+    let val = parser.value(typ)?;
+    parser.end()?;
+    Ok(val)
+}
+
+// Parses comma separated literals, one for each of the given types
+pub fn parse_literals(text: &str, types: &[AnalysedType]) -> anyhow::Result<Vec<Val>> {
+    let mut parser = Parser::new(text);
+    let mut vals = Vec::new();
+
+    for (index, typ) in types.iter().enumerate() {
+        if index > 0 {
+            parser.expect(',')?;
+        }
+        vals.push(
+            parser
+                .value(typ)
+                .map_err(|err| anyhow!("Parameter {index}: {err}"))?,
+        );
+    }
+
+    parser.end()?;
+    Ok(vals)
+}
+
+pub fn format_val(val: &Val) -> String {
+    match val {
+        Val::Bool(b) => b.to_string(),
+        Val::S8(n) => n.to_string(),
+        Val::U8(n) => n.to_string(),
+        Val::S16(n) => n.to_string(),
+        Val::U16(n) => n.to_string(),
+        Val::S32(n) => n.to_string(),
+        Val::U32(n) => n.to_string(),
+        Val::S64(n) => n.to_string(),
+        Val::U64(n) => n.to_string(),
+        Val::Float32(n) => n.to_string(),
+        Val::Float64(n) => n.to_string(),
+        Val::Char(c) => format!("{c:?}"),
+        Val::String(s) => format!("{s:?}"),
+        Val::List(items) => format!("[{}]", join(items.iter().map(format_val))),
+        Val::Tuple(items) => format!("({})", join(items.iter().map(format_val))),
+        Val::Record(fields) => format!(
+            "{{{}}}",
+            join(
+                fields
+                    .iter()
+                    .map(|(name, val)| format!("{name}: {}", format_val(val)))
+            )
+        ),
+        Val::Variant(case_name, None) | Val::Enum(case_name) => case_name.clone(),
+        Val::Variant(case_name, Some(payload)) => format!("{case_name}({})", format_val(payload)),
+        Val::Option(None) => "none".to_string(),
+        Val::Option(Some(inner)) => format!("some({})", format_val(inner)),
+        Val::Result(Ok(None)) => "ok".to_string(),
+        Val::Result(Ok(Some(inner))) => format!("ok({})", format_val(inner)),
+        Val::Result(Err(None)) => "err".to_string(),
+        Val::Result(Err(Some(inner))) => format!("err({})", format_val(inner)),
+        Val::Flags(names) => format!("{{{}}}", names.join(", ")),
+        other => format!("{other:?}"),
+    }
+}
+
+// Renders a type in WIT syntax
+pub fn format_type(typ: &AnalysedType) -> String {
+    match typ {
+        AnalysedType::Bool(_) => "bool".to_string(),
+        AnalysedType::S8(_) => "s8".to_string(),
+        AnalysedType::U8(_) => "u8".to_string(),
+        AnalysedType::S16(_) => "s16".to_string(),
+        AnalysedType::U16(_) => "u16".to_string(),
+        AnalysedType::S32(_) => "s32".to_string(),
+        AnalysedType::U32(_) => "u32".to_string(),
+        AnalysedType::S64(_) => "s64".to_string(),
+        AnalysedType::U64(_) => "u64".to_string(),
+        AnalysedType::F32(_) => "f32".to_string(),
+        AnalysedType::F64(_) => "f64".to_string(),
+        AnalysedType::Chr(_) => "char".to_string(),
+        AnalysedType::Str(_) => "string".to_string(),
+        AnalysedType::List(TypeList { inner, .. }) => format!("list<{}>", format_type(inner)),
+        AnalysedType::Option(TypeOption { inner, .. }) => format!("option<{}>", format_type(inner)),
+        AnalysedType::Tuple(TypeTuple { items, .. }) => {
+            format!("tuple<{}>", join(items.iter().map(format_type)))
+        }
+        AnalysedType::Record(TypeRecord { fields, .. }) => format!(
+            "record {{ {} }}",
+            join(
+                fields
+                    .iter()
+                    .map(|NameTypePair { name, typ }| format!("{name}: {}", format_type(typ)))
+            )
+        ),
+        AnalysedType::Variant(TypeVariant { cases, .. }) => format!(
+            "variant {{ {} }}",
+            join(cases.iter().map(|NameOptionTypePair { name, typ }| match typ {
+                Some(typ) => format!("{name}({})", format_type(typ)),
+                None => name.clone(),
+            }))
+        ),
+        AnalysedType::Enum(TypeEnum { cases, .. }) => format!("enum {{ {} }}", cases.join(", ")),
+        AnalysedType::Flags(TypeFlags { names, .. }) => format!("flags {{ {} }}", names.join(", ")),
+        AnalysedType::Result(TypeResult { ok, err, .. }) => match (ok, err) {
+            (Some(ok), Some(err)) => format!("result<{}, {}>", format_type(ok), format_type(err)),
+            (Some(ok), None) => format!("result<{}>", format_type(ok)),
+            (None, Some(err)) => format!("result<_, {}>", format_type(err)),
+            (None, None) => "result".to_string(),
+        },
+        AnalysedType::Handle(_) => "handle".to_string(),
+    }
+}
+
+// Text parameters are shown with their constraints, e.g. `text<en, max 4000, markdown>`
+pub fn format_text_type(text_type: &TextType) -> String {
+    let mut constraints = Vec::new();
+    if !text_type.language_code.is_empty() {
+        constraints.push(text_type.language_code.clone());
+    }
+    if let Some(max_length) = text_type.max_length {
+        constraints.push(format!("max {max_length}"));
+    }
+    constraints.push(format!("{:?}", text_type.format).to_lowercase());
+    format!("text<{}>", constraints.join(", "))
+}
+
+// A literal of the type, used as a starting point when completing parameters
+pub fn example_literal(typ: &AnalysedType) -> String {
+    match typ {
+        AnalysedType::Bool(_) => "false".to_string(),
+        AnalysedType::F32(_) | AnalysedType::F64(_) => "0.0".to_string(),
+        AnalysedType::Chr(_) => "'a'".to_string(),
+        AnalysedType::Str(_) => "\"\"".to_string(),
+        AnalysedType::List(_) => "[]".to_string(),
+        AnalysedType::Option(_) => "none".to_string(),
+        AnalysedType::Tuple(TypeTuple { items, .. }) => {
+            format!("({})", join(items.iter().map(example_literal)))
+        }
+        AnalysedType::Record(TypeRecord { fields, .. }) => format!(
+            "{{{}}}",
+            join(
+                fields
+                    .iter()
+                    .map(|NameTypePair { name, typ }| format!("{name}: {}", example_literal(typ)))
+            )
+        ),
+        AnalysedType::Variant(TypeVariant { cases, .. }) => match cases.first() {
+            Some(NameOptionTypePair {
+                name,
+                typ: Some(typ),
+            }) => format!("{name}({})", example_literal(typ)),
+            Some(NameOptionTypePair { name, typ: None }) => name.clone(),
+            None => String::new(),
+        },
+        AnalysedType::Enum(TypeEnum { cases, .. }) => cases.first().cloned().unwrap_or_default(),
+        AnalysedType::Flags(_) => "{}".to_string(),
+        AnalysedType::Result(TypeResult { ok, .. }) => match ok {
+            Some(ok) => format!("ok({})", example_literal(ok)),
+            None => "ok".to_string(),
+        },
+        AnalysedType::Handle(_) => String::new(),
+        _ => "0".to_string(),
+    }
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn value(&mut self, typ: &AnalysedType) -> anyhow::Result<Val> {
+        self.skip_whitespace();
+
+        Ok(match typ {
+            AnalysedType::Bool(_) => match self.ident()?.as_str() {
+                "true" => Val::Bool(true),
+                "false" => Val::Bool(false),
+                other => bail!("Expected true or false, but got {other}"),
+            },
+            AnalysedType::S8(_) => Val::S8(self.number("s8")?),
+            AnalysedType::U8(_) => Val::U8(self.number("u8")?),
+            AnalysedType::S16(_) => Val::S16(self.number("s16")?),
+            AnalysedType::U16(_) => Val::U16(self.number("u16")?),
+            AnalysedType::S32(_) => Val::S32(self.number("s32")?),
+            AnalysedType::U32(_) => Val::U32(self.number("u32")?),
+            AnalysedType::S64(_) => Val::S64(self.number("s64")?),
+            AnalysedType::U64(_) => Val::U64(self.number("u64")?),
+            AnalysedType::F32(_) => Val::Float32(self.number("f32")?),
+            AnalysedType::F64(_) => Val::Float64(self.number("f64")?),
+            AnalysedType::Chr(_) => {
+                let chars = self.quoted('\'')?;
+                match chars.as_slice() {
+                    [c] => Val::Char(*c),
+                    _ => bail!("Expected a single character"),
+                }
+            }
+            AnalysedType::Str(_) => Val::String(self.quoted('"')?.into_iter().collect()),
+            AnalysedType::List(TypeList { inner, .. }) => {
+                Val::List(self.sequence('[', ']', |parser| parser.value(inner))?)
+            }
+            AnalysedType::Tuple(TypeTuple { items, .. }) => {
+                let mut types = items.iter();
+                let vals = self.sequence('(', ')', |parser| match types.next() {
+                    Some(typ) => parser.value(typ),
+                    None => bail!("Too many items, expected a tuple of {}", items.len()),
+                })?;
+                if vals.len() != items.len() {
+                    bail!("Expected a tuple of {} items, but got {}", items.len(), vals.len());
+                }
+                Val::Tuple(vals)
+            }
+            AnalysedType::Record(TypeRecord { fields, .. }) => {
+                let mut vals = self.sequence('{', '}', |parser| {
+                    let name = parser.ident()?;
+                    parser.expect(':')?;
+                    let NameTypePair { typ, .. } = fields
+                        .iter()
+                        .find(|field| field.name == name)
+                        .ok_or_else(|| anyhow!("Unknown field {name}"))?;
+                    Ok((name, parser.value(typ)?))
+                })?;
+
+                Val::Record(
+                    fields
+                        .iter()
+                        .map(|NameTypePair { name, typ }| {
+                            match vals.iter().position(|(field_name, _)| field_name == name) {
+                                Some(index) => Ok((name.clone(), vals.remove(index).1)),
+                                None if matches!(typ, AnalysedType::Option(_)) => {
+                                    Ok((name.clone(), Val::Option(None)))
+                                }
+                                None => bail!("Missing field {name}"),
+                            }
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                )
+            }
+            AnalysedType::Flags(TypeFlags { names, .. }) => {
+                let set = self.sequence('{', '}', |parser| parser.ident())?;
+                if let Some(unknown) = set.iter().find(|flag| !names.contains(flag)) {
+                    bail!("Unknown flag {unknown}, expected any of {}", names.join(", "));
+                }
+                Val::Flags(set)
+            }
+            AnalysedType::Enum(TypeEnum { cases, .. }) => {
+                let case_name = self.ident()?;
+                if !cases.contains(&case_name) {
+                    bail!("Unknown case {case_name}, expected one of {}", cases.join(", "));
+                }
+                Val::Enum(case_name)
+            }
+            AnalysedType::Variant(TypeVariant { cases, .. }) => {
+                let case_name = self.ident()?;
+                let NameOptionTypePair { typ, .. } = cases
+                    .iter()
+                    .find(|case| case.name == case_name)
+                    .ok_or_else(|| anyhow!("Unknown case {case_name}"))?;
+                let payload = self.payload(typ.as_ref(), &case_name)?;
+                Val::Variant(case_name, payload)
+            }
+            AnalysedType::Option(TypeOption { inner, .. }) => {
+                if self.peek_ident("none") {
+                    self.ident()?;
+                    Val::Option(None)
+                } else if self.peek_ident("some") {
+                    self.ident()?;
+                    Val::Option(self.payload(Some(&**inner), "some")?)
+                } else {
+                    Val::Option(Some(Box::new(self.value(inner)?)))
+                }
+            }
+            AnalysedType::Result(TypeResult { ok, err, .. }) => match self.ident()?.as_str() {
+                "ok" => Val::Result(Ok(self.payload(ok.as_deref(), "ok")?)),
+                "err" => Val::Result(Err(self.payload(err.as_deref(), "err")?)),
+                other => bail!("Expected ok or err, but got {other}"),
+            },
+            AnalysedType::Handle(_) => bail!("Resource handles cannot be written as literals"),
+        })
+    }
+
+    // The `(value)` after a case name, required if and only if the case has a type
+    fn payload(
+        &mut self,
+        typ: Option<&AnalysedType>,
+        case_name: &str,
+    ) -> anyhow::Result<Option<Box<Val>>> {
+        match typ {
+            Some(typ) => {
+                self.expect('(')
+                    .map_err(|_| anyhow!("{case_name} requires a value"))?;
+                let val = self.value(typ)?;
+                self.expect(')')?;
+                Ok(Some(Box::new(val)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn sequence<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        self.expect(open)?;
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                return Ok(items);
+            }
+            if !items.is_empty() {
+                self.expect(',')?;
+                self.skip_whitespace();
+                // Trailing comma
+                if self.peek() == Some(close) {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+            }
+            items.push(item(self)?);
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, name: &str) -> anyhow::Result<T> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.' | '_') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let token: String = self.chars[start..self.pos].iter().collect();
+        token
+            .replace('_', "")
+            .parse()
+            .map_err(|_| anyhow!("Expected {name}, but got '{token}'"))
+    }
+
+    fn ident(&mut self) -> anyhow::Result<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '%' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            bail!("Expected a name at position {}", self.pos);
+        }
+        Ok(self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .trim_start_matches('%')
+            .to_string())
+    }
+
+    fn peek_ident(&mut self, ident: &str) -> bool {
+        let start = self.pos;
+        let matches = self.ident().map(|i| i == ident).unwrap_or(false);
+        self.pos = start;
+        matches
+    }
+
+    fn quoted(&mut self, quote: char) -> anyhow::Result<Vec<char>> {
+        self.expect(quote)?;
+        let mut chars = Vec::new();
+
+        loop {
+            match self.next() {
+                Some('\\') => chars.push(match self.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some(c @ ('\\' | '"' | '\'')) => c,
+                    other => bail!("Invalid escape sequence \\{}", other.unwrap_or(' ')),
+                }),
+                Some(c) if c == quote => return Ok(chars),
+                Some(c) => chars.push(c),
+                None => bail!("Missing closing {quote}"),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("Expected '{expected}', but got '{c}' at position {}", self.pos - 1),
+            None => bail!("Expected '{expected}', but the input ended"),
+        }
+    }
+
+    fn end(&mut self) -> anyhow::Result<()> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(c) => bail!("Unexpected '{c}' at position {}", self.pos),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += usize::from(c.is_some());
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use golem_wasm_ast::analysis::analysed_type::{
+        bool, case, chr, f64, field, flags, list, option, r#enum, record, result, result_err, s32,
+        str, tuple, u32, u64, u8, unit_case, variant,
+    };
+
+    fn parse(text: &str, typ: AnalysedType) -> Val {
+        parse_literal(text, &typ).unwrap()
+    }
+
+    fn parse_error(text: &str, typ: AnalysedType) -> String {
+        parse_literal(text, &typ).unwrap_err().to_string()
+    }
+
+    fn some(val: Val) -> Val {
+        Val::Option(Some(Box::new(val)))
+    }
+
+    fn forecast_type() -> AnalysedType {
+        record(vec![
+            field("city", str()),
+            field("days", list(u32())),
+            field(
+                "alert",
+                variant(vec![case("storm", str()), unit_case("heat")]),
+            ),
+            field("unit", r#enum(&["celsius", "fahrenheit"])),
+            field("sources", flags(&["radar", "satellite"])),
+            field("temperature", result(f64(), str())),
+            field("note", option(str())),
+        ])
+    }
+
+    #[test]
+    fn parses_primitives() {
+        assert_eq!(parse(" true ", bool()), Val::Bool(true));
+        assert_eq!(parse("200", u8()), Val::U8(200));
+        assert_eq!(parse("-17", s32()), Val::S32(-17));
+        assert_eq!(parse("1_000_000", u64()), Val::U64(1_000_000));
+        assert_eq!(parse("3.25", f64()), Val::Float64(3.25));
+        assert_eq!(parse("'x'", chr()), Val::Char('x'));
+        assert_eq!(
+            parse("\"Berlin\"", str()),
+            Val::String("Berlin".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_numbers_out_of_range() {
+        assert_eq!(parse_error("300", u8()), "Expected u8, but got '300'");
+        assert_eq!(parse_error("-1", u32()), "Expected u32, but got '-1'");
+        assert_eq!(
+            parse_error("maybe", bool()),
+            "Expected true or false, but got maybe"
+        );
+    }
+
+    #[test]
+    fn parses_escaped_strings() {
+        assert_eq!(
+            parse(r#""line\n\t\"quoted\" \\ 'single' \0""#, str()),
+            Val::String("line\n\t\"quoted\" \\ 'single' \0".to_string())
+        );
+        assert_eq!(parse(r"'\''", chr()), Val::Char('\''));
+
+        assert_eq!(
+            parse_error(r#""\x41""#, str()),
+            r"Invalid escape sequence \x"
+        );
+        assert_eq!(parse_error("\"open", str()), "Missing closing \"");
+        assert_eq!(parse_error("'ab'", chr()), "Expected a single character");
+    }
+
+    #[test]
+    fn parses_records_in_any_field_order() {
+        let typ = record(vec![
+            field("city", str()),
+            field("days", u32()),
+            field("note", option(str())),
+        ]);
+
+        assert_eq!(
+            parse("{days: 3, city: \"Berlin\",}", typ.clone()),
+            Val::Record(vec![
+                ("city".to_string(), Val::String("Berlin".to_string())),
+                ("days".to_string(), Val::U32(3)),
+                ("note".to_string(), Val::Option(None)),
+            ])
+        );
+        assert_eq!(
+            parse_error("{city: \"Berlin\"}", typ.clone()),
+            "Missing field days"
+        );
+        assert_eq!(
+            parse_error("{city: \"Berlin\", days: 3, zip: 1}", typ),
+            "Unknown field zip"
+        );
+    }
+
+    #[test]
+    fn parses_variants_with_and_without_payloads() {
+        let typ = variant(vec![case("storm", str()), unit_case("heat")]);
+
+        assert_eq!(
+            parse("storm(\"north\")", typ.clone()),
+            Val::Variant(
+                "storm".to_string(),
+                Some(Box::new(Val::String("north".to_string())))
+            )
+        );
+        assert_eq!(
+            parse("heat", typ.clone()),
+            Val::Variant("heat".to_string(), None)
+        );
+        assert_eq!(parse_error("storm", typ.clone()), "storm requires a value");
+        assert_eq!(parse_error("frost", typ), "Unknown case frost");
+    }
+
+    #[test]
+    fn parses_enums_and_flags_by_name() {
+        let unit = r#enum(&["celsius", "fahrenheit"]);
+        let sources = flags(&["radar", "satellite"]);
+
+        assert_eq!(
+            parse("fahrenheit", unit.clone()),
+            Val::Enum("fahrenheit".to_string())
+        );
+        assert_eq!(
+            parse_error("kelvin", unit),
+            "Unknown case kelvin, expected one of celsius, fahrenheit"
+        );
+
+        assert_eq!(
+            parse("{satellite, radar}", sources.clone()),
+            Val::Flags(vec!["satellite".to_string(), "radar".to_string()])
+        );
+        assert_eq!(parse("{}", sources.clone()), Val::Flags(vec![]));
+        assert_eq!(
+            parse_error("{sonar}", sources),
+            "Unknown flag sonar, expected any of radar, satellite"
+        );
+    }
+
+    #[test]
+    fn parses_options_with_or_without_some() {
+        assert_eq!(parse("none", option(u32())), Val::Option(None));
+        assert_eq!(parse("some(3)", option(u32())), some(Val::U32(3)));
+        assert_eq!(parse("3", option(u32())), some(Val::U32(3)));
+        assert_eq!(
+            parse("some(none)", option(option(u32()))),
+            some(Val::Option(None))
+        );
+        assert_eq!(parse_error("some", option(u32())), "some requires a value");
+    }
+
+    #[test]
+    fn parses_lists_and_tuples() {
+        assert_eq!(
+            parse("[1, 2, 3,]", list(u32())),
+            Val::List(vec![Val::U32(1), Val::U32(2), Val::U32(3)])
+        );
+        assert_eq!(parse("[ ]", list(u32())), Val::List(vec![]));
+        assert_eq!(
+            parse("(1, \"a\")", tuple(vec![u32(), str()])),
+            Val::Tuple(vec![Val::U32(1), Val::String("a".to_string())])
+        );
+        assert_eq!(
+            parse_error("(1)", tuple(vec![u32(), str()])),
+            "Expected a tuple of 2 items, but got 1"
+        );
+        assert_eq!(
+            parse_error("(1, \"a\", 2)", tuple(vec![u32(), str()])),
+            "Too many items, expected a tuple of 2"
+        );
+    }
+
+    #[test]
+    fn parses_results() {
+        assert_eq!(
+            parse("ok(3.5)", result(f64(), str())),
+            Val::Result(Ok(Some(Box::new(Val::Float64(3.5)))))
+        );
+        assert_eq!(
+            parse("err(\"offline\")", result(f64(), str())),
+            Val::Result(Err(Some(Box::new(Val::String("offline".to_string())))))
+        );
+        assert_eq!(parse("ok", result_err(str())), Val::Result(Ok(None)));
+        assert_eq!(
+            parse_error("fine", result(f64(), str())),
+            "Expected ok or err, but got fine"
+        );
+    }
+
+    #[test]
+    fn parses_a_literal_for_each_parameter() {
+        assert_eq!(
+            parse_literals("\"Berlin\", 3", &[str(), u32()]).unwrap(),
+            vec![Val::String("Berlin".to_string()), Val::U32(3)]
+        );
+        assert_eq!(
+            parse_literals("\"Berlin\", three", &[str(), u32()])
+                .unwrap_err()
+                .to_string(),
+            "Parameter 1: Expected u32, but got 'three'"
+        );
+        assert_eq!(
+            parse_literals("\"Berlin\"", &[str(), u32()])
+                .unwrap_err()
+                .to_string(),
+            "Expected ',', but the input ended"
+        );
+    }
+
+    #[test]
+    fn reports_the_position_of_errors() {
+        assert_eq!(
+            parse_error("{city \"Berlin\"}", record(vec![field("city", str())])),
+            "Expected ':', but got '\"' at position 6"
+        );
+        assert_eq!(parse_error("3 4", u32()), "Unexpected '4' at position 2");
+        assert_eq!(
+            parse_error("{radar, , satellite}", flags(&["radar", "satellite"])),
+            "Expected a name at position 8"
+        );
+        assert_eq!(
+            parse_error("[1 2]", list(u32())),
+            "Expected ',', but got '2' at position 3"
+        );
+        assert_eq!(
+            parse_error("[1, 2", list(u32())),
+            "Expected ',', but the input ended"
+        );
+    }
+
+    #[test]
+    fn prints_literals_as_they_are_parsed() {
+        let val = Val::Record(vec![
+            (
+                "city".to_string(),
+                Val::String("Saint \"Paul\"\n".to_string()),
+            ),
+            (
+                "days".to_string(),
+                Val::List(vec![Val::U32(1), Val::U32(2)]),
+            ),
+            (
+                "alert".to_string(),
+                Val::Variant(
+                    "storm".to_string(),
+                    Some(Box::new(Val::String("north".to_string()))),
+                ),
+            ),
+            ("unit".to_string(), Val::Enum("celsius".to_string())),
+            ("sources".to_string(), Val::Flags(vec!["radar".to_string()])),
+            (
+                "temperature".to_string(),
+                Val::Result(Ok(Some(Box::new(Val::Float64(-3.5))))),
+            ),
+            ("note".to_string(), Val::Option(None)),
+        ]);
+
+        let text = format_val(&val);
+
+        assert_eq!(
+            text,
+            r#"{city: "Saint \"Paul\"\n", days: [1, 2], alert: storm("north"), unit: celsius, sources: {radar}, temperature: ok(-3.5), note: none}"#
+        );
+        assert_eq!(parse(&text, forecast_type()), val);
+    }
+
+    #[test]
+    fn parses_printed_literals_back() {
+        let cases = vec![
+            (parse("'\\''", chr()), chr()),
+            (parse("[]", list(str())), list(str())),
+            (
+                parse(
+                    "([some(1), none], err(\"e\"))",
+                    tuple(vec![list(option(u32())), result(u32(), str())]),
+                ),
+                tuple(vec![list(option(u32())), result(u32(), str())]),
+            ),
+            (
+                parse(&example_literal(&forecast_type()), forecast_type()),
+                forecast_type(),
+            ),
+        ];
+
+        for (val, typ) in cases {
+            assert_eq!(parse(&format_val(&val), typ), val);
+        }
+    }
+}
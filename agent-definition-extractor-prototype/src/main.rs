@@ -1,217 +1,304 @@
-use anyhow::anyhow;
-use rib::ParsedFunctionName;
-use std::collections::HashMap;
-use std::path::Path;
-use std::sync::Arc;
-use std::sync::Mutex;
-use wasmtime::component::types::{ComponentInstance, ComponentItem};
-use wasmtime::component::{
-    Component, Linker, LinkerInstance, ResourceTable, ResourceType, Type, Val,
+use agent_definition_extractor_prototype::compat::compare_agent_types;
+use agent_definition_extractor_prototype::host::{HostOptions, NotAnAgentComponent};
+use agent_definition_extractor_prototype::manifest::{embed_manifest, AgentManifest};
+use agent_definition_extractor_prototype::{
+    extract_agent_types, read_embedded_agent_types, read_required_embedded_agent_types,
 };
-use wasmtime::{Engine, Store};
-use wasmtime_wasi::p2::{bindings, IoView, WasiCtx, WasiView};
+use agent_definition_extractor_prototype::model::{AgentType, StatusUpdate};
+use agent_definition_extractor_prototype::runner::AgentRunner;
+use agent_definition_extractor_prototype::stubs::{HostStubs, Uuid};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-#[tokio::main]
-async fn main() {
-    prototype().await.expect("prototype error");
-}
+// Exit codes, other than success
+const EXIT_FAILURE: u8 = 1;
+const EXIT_BREAKING_CHANGES: u8 = 2;
+const EXIT_NOT_AN_AGENT_COMPONENT: u8 = 3;
 
-async fn prototype() -> anyhow::Result<()> {
-    let mut config = wasmtime::Config::default();
-    config.async_support(true);
-    config.wasm_component_model(true);
-    let engine = Engine::new(&config)?;
-    let mut linker: Linker<Host> = Linker::new(&engine);
-
-    wasmtime_wasi::p2::add_to_linker_with_options_async(
-        &mut linker,
-        &bindings::LinkOptions::default(),
-    )?;
-
-    let ctx = WasiCtx::builder().build();
-    let host = Host {
-        table: Arc::new(Mutex::new(ResourceTable::new())),
-        wasi: Arc::new(Mutex::new(ctx)),
-    };
+// Returned by `compat` when there are breaking changes
+#[derive(Debug)]
+struct BreakingChanges(usize);
 
-    let component = Component::from_file(
-        &engine,
-        Path::new(
-            "../golem-agentic-examples/wrapper-agent/target/wasm32-wasip1/debug/plugged.wasm",
-        ),
-    )?;
-    let mut store = Store::new(&engine, host);
-
-    let mut linker_instance = linker.root();
-    let component_type = component.component_type();
-    for (name, item) in component_type.imports(&engine) {
-        let name = name.to_string();
-        match item {
-            ComponentItem::ComponentFunc(_) => {}
-            ComponentItem::CoreFunc(_) => {}
-            ComponentItem::Module(_) => {}
-            ComponentItem::Component(_) => {}
-            ComponentItem::ComponentInstance(ref inst) => {
-                dynamic_import(&name, &engine, &mut linker_instance, inst)?;
-            }
-            ComponentItem::Type(_) => {}
-            ComponentItem::Resource(_) => {}
-        }
+impl std::fmt::Display for BreakingChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} breaking change(s)", self.0)
     }
+}
+
+impl std::error::Error for BreakingChanges {}
+
+#[derive(Parser)]
+#[command(about = "Extracts agent metadata from golem-agentic components")]
+struct Cli {
+    /// Logs the imports that are linked dynamically
+    #[arg(long, global = true)]
+    verbose: bool,
+
+    /// Worker name returned by the stubbed self metadata
+    #[arg(long, global = true)]
+    worker_name: Option<String>,
+
+    /// Component id returned by the stubbed self metadata
+    #[arg(long, global = true, value_parser = Uuid::parse)]
+    component_id: Option<Uuid>,
+
+    /// Component of an agent type living in another component, as AGENT_TYPE=UUID
+    #[arg(long, global = true, value_parser = parse_agent_component)]
+    agent_component: Vec<(String, Uuid)>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Writes the agent types discovered in the component
+    Extract {
+        component: PathBuf,
 
-    let instance = linker.instantiate_async(&mut store, &component).await?;
-
-    let interface_name = "golem:agentic-guest/guest";
-    let function_name = "discover-agent-definitions";
-
-    let (_, exported_instance_id) = instance
-        .get_export(&mut store, None, interface_name)
-        .ok_or_else(|| anyhow!("Interface {interface_name} not found"))?;
-    let (_, func_id) = instance
-        .get_export(&mut store, Some(&exported_instance_id), function_name)
-        .ok_or_else(|| {
-            anyhow!("Function {function_name} not found in interface {interface_name}")
-        })?;
-    let func = instance
-        .get_func(&mut store, func_id)
-        .ok_or_else(|| anyhow!("Function {function_name} not found"))?;
-
-    let mut results = (0..func.results(&mut store).len())
-        .map(|_| Val::Bool(false))
-        .collect::<Vec<_>>();
-    func.call_async(&mut store, &[], &mut results).await?;
-    func.post_return_async(&mut store).await?;
-
-    println!("results: {:?}", results);
-    Ok(())
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+
+        /// Writes to stdout if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Only reads the agent types embedded in the component, failing if there are none
+        #[arg(long = "static", conflicts_with = "instantiate")]
+        static_only: bool,
+
+        /// Instantiates the component even if it has embedded agent types
+        #[arg(long)]
+        instantiate: bool,
+    },
+    /// Instantiates the component once to discover its agent types, and embeds them as a custom section, so they can be read without instantiating it afterwards
+    Embed {
+        component: PathBuf,
+
+        /// Overwrites the component if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Lists the changes between two versions of the agent types, and whether they break existing callers
+    Compat {
+        /// The old version, as a component or a JSON file written by `extract`
+        old: PathBuf,
+
+        /// The new version, as a component or a JSON file written by `extract`
+        new: PathBuf,
+
+        /// Prints the changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Creates an agent and invokes its methods locally, without a Golem cluster
+    Run {
+        component: PathBuf,
+
+        /// Name of the agent type to create
+        agent_type: String,
+
+        /// Other agent components, whose agents can be called through wasm-rpc. Can be repeated
+        #[arg(long = "with")]
+        other_components: Vec<PathBuf>,
+
+        /// Constructor parameters, as a JSON array
+        #[arg(long, default_value = "[]")]
+        args: String,
+
+        /// Method to invoke, as METHOD or METHOD=JSON_ARRAY_OF_PARAMETERS. Can be repeated
+        #[arg(long = "call", value_parser = parse_call)]
+        calls: Vec<(String, String)>,
+
+        /// Prints the status updates as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
-#[derive(Clone)]
-struct Host {
-    pub table: Arc<Mutex<ResourceTable>>,
-    pub wasi: Arc<Mutex<WasiCtx>>,
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Yaml,
 }
 
-impl IoView for Host {
-    fn table(&mut self) -> &mut ResourceTable {
-        Arc::get_mut(&mut self.table)
-            .expect("ResourceTable is shared and cannot be borrowed mutably")
-            .get_mut()
-            .expect("ResourceTable mutex must never fail")
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let mut stubs = HostStubs::default();
+    if let Some(worker_name) = cli.worker_name {
+        stubs.worker_name = worker_name;
     }
-}
+    if let Some(component_id) = cli.component_id {
+        stubs.component_id = component_id;
+    }
+    stubs.agent_components.extend(cli.agent_component);
+
+    let options = HostOptions {
+        verbose: cli.verbose,
+        stubs,
+    };
+
+    match run(cli.command, &options).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
 
-impl WasiView for Host {
-    fn ctx(&mut self) -> &mut WasiCtx {
-        Arc::get_mut(&mut self.wasi)
-            .expect("WasiCtx is shared and cannot be borrowed mutably")
-            .get_mut()
-            .expect("WasiCtx mutex must never fail")
+            if err.downcast_ref::<NotAnAgentComponent>().is_some() {
+                ExitCode::from(EXIT_NOT_AN_AGENT_COMPONENT)
+            } else if err.downcast_ref::<BreakingChanges>().is_some() {
+                ExitCode::from(EXIT_BREAKING_CHANGES)
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
     }
 }
 
-pub fn dynamic_import(
-    name: &str,
-    engine: &Engine,
-    root: &mut LinkerInstance<Host>,
-    inst: &ComponentInstance,
-) -> anyhow::Result<()> {
-    if name.starts_with("wasi:cli")
-        || name.starts_with("wasi:clocks")
-        || name.starts_with("wasi:filesystem")
-        || name.starts_with("wasi:io")
-        || name.starts_with("wasi:random")
-        || name.starts_with("wasi:sockets")
-    {
-        // These does not have to be mocked, we allow them through wasmtime-wasi
-        Ok(())
-    } else {
-        println!("dynamic_import: {}", name);
-
-        let mut instance = root.instance(name)?;
-        let mut resources: HashMap<(String, String), Vec<MethodInfo>> = HashMap::new();
-        let mut functions = Vec::new();
-
-        for (inner_name, inner_item) in inst.exports(engine) {
-            let name = name.to_owned();
-            let inner_name = inner_name.to_owned();
-
-            match inner_item {
-                ComponentItem::ComponentFunc(fun) => {
-                    let param_types: Vec<Type> = fun.params().map(|(_, t)| t).collect();
-                    let result_types: Vec<Type> = fun.results().collect();
-
-                    let function_name = ParsedFunctionName::parse(format!(
-                        "{name}.{{{inner_name}}}"
-                    ))
-                        .map_err(|err| anyhow!(format!("Unexpected linking error: {name}.{{{inner_name}}} is not a valid function name: {err}")))?;
-
-                    if let Some(resource_name) = function_name.function.resource_name() {
-                        let methods = resources
-                            .entry((name.clone(), resource_name.clone()))
-                            .or_default();
-                        methods.push(MethodInfo {
-                            method_name: inner_name.clone(),
-                            params: param_types.clone(),
-                            results: result_types.clone(),
-                        });
-                    }
+async fn run(command: Command, options: &HostOptions) -> anyhow::Result<()> {
+    match command {
+        Command::Extract {
+            component,
+            format,
+            output,
+            static_only,
+            instantiate,
+        } => {
+            let embedded = if instantiate {
+                None
+            } else if static_only {
+                Some(read_required_embedded_agent_types(&component)?)
+            } else {
+                read_embedded_agent_types(&component)?
+            };
+            let agent_types = match embedded {
+                Some(agent_types) => agent_types,
+                None => extract_agent_types(&component, options).await?,
+            };
+            let rendered = render(&agent_types, format)?;
 
-                    functions.push(FunctionInfo {
-                        name: function_name,
-                        params: param_types,
-                        results: result_types,
-                    });
-                }
-                ComponentItem::CoreFunc(_) => {}
-                ComponentItem::Module(_) => {}
-                ComponentItem::Component(_) => {}
-                ComponentItem::ComponentInstance(_) => {}
-                ComponentItem::Type(_) => {}
-                ComponentItem::Resource(_resource) => {
-                    resources.entry((name, inner_name)).or_default();
+            match output {
+                Some(path) => std::fs::write(&path, rendered)
+                    .map_err(|err| anyhow::anyhow!("Failed to write {}: {err}", path.display())),
+                None => {
+                    println!("{rendered}");
+                    Ok(())
                 }
             }
         }
+        Command::Embed { component, output } => {
+            let agent_types = extract_agent_types(&component, options).await?;
+            let wasm = std::fs::read(&component)
+                .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", component.display()))?;
+            let wasm = embed_manifest(&wasm, &AgentManifest::new(agent_types))?;
+
+            let path = output.unwrap_or(component);
+            std::fs::write(&path, wasm)
+                .map_err(|err| anyhow::anyhow!("Failed to write {}: {err}", path.display()))
+        }
+        Command::Compat { old, new, json } => {
+            let old = load_agent_types(&old, options).await?;
+            let new = load_agent_types(&new, options).await?;
+            let changes = compare_agent_types(&old, &new);
 
-        for ((interface_name, resource_name), _methods) in resources {
-            println!("Defining resource: {interface_name}.{resource_name}");
-            instance.resource(
-                &resource_name,
-                ResourceType::host::<ResourceEntry>(),
-                |_store, _rep| Ok(()),
-            )?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&changes)?);
+            } else {
+                for change in &changes {
+                    println!("{change}");
+                }
+            }
+
+            let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+            if breaking > 0 {
+                return Err(BreakingChanges(breaking).into());
+            }
+            Ok(())
         }
+        Command::Run {
+            component,
+            agent_type,
+            other_components,
+            args,
+            calls,
+            json,
+        } => {
+            let components = std::iter::once(component)
+                .chain(other_components)
+                .collect::<Vec<_>>();
+            let runner = AgentRunner::with_components(&components, options).await?;
+            let agent = runner
+                .create_agent(&agent_type, &parse_json_array(&args)?)
+                .await?;
+            eprintln!("Created agent {}", agent.agent_id);
 
-        for function in functions {
-            println!("Defining function: {}", function.name);
-            instance.func_new_async(
-                &function.name.function.function_name(),
-                move |_store, _params, _results| {
-                    let function_name = function.name.clone();
-                    Box::new(async move {
-                        return Err(anyhow!(
-                            "External function called in get-agent-definitions: {function_name}"
-                        ));
-                    })
-                },
-            )?;
+            let mut failed = false;
+            for (method_name, params) in calls {
+                let updates = runner
+                    .invoke(&agent, &method_name, &parse_json_array(&params)?)
+                    .await?;
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&updates)?);
+                } else {
+                    for update in &updates {
+                        match update {
+                            StatusUpdate::Emit { .. } => println!("{update}"),
+                            _ => eprintln!("{update}"),
+                        }
+                    }
+                }
+
+                failed |= matches!(updates.last(), Some(StatusUpdate::Error { .. }));
+            }
+
+            if failed {
+                anyhow::bail!("Some of the invocations failed");
+            }
+            Ok(())
         }
+    }
+}
+
+// Agent types of a component, or of a JSON file written by `extract`
+async fn load_agent_types(path: &Path, options: &HostOptions) -> anyhow::Result<Vec<AgentType>> {
+    if path.extension().is_some_and(|extension| extension == "json") {
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", path.display()))?;
+        return serde_json::from_str(&json)
+            .map_err(|err| anyhow::anyhow!("Invalid agent types in {}: {err}", path.display()));
+    }
 
-        Ok(())
+    match read_embedded_agent_types(path)? {
+        Some(agent_types) => Ok(agent_types),
+        None => extract_agent_types(path, options).await,
     }
 }
 
-struct MethodInfo {
-    method_name: String,
-    params: Vec<Type>,
-    results: Vec<Type>,
+fn parse_call(value: &str) -> anyhow::Result<(String, String)> {
+    Ok(match value.split_once('=') {
+        Some((method_name, params)) => (method_name.to_string(), params.to_string()),
+        None => (value.to_string(), "[]".to_string()),
+    })
 }
 
-struct FunctionInfo {
-    name: ParsedFunctionName,
-    params: Vec<Type>,
-    results: Vec<Type>,
+fn parse_json_array(value: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+    serde_json::from_str(value)
+        .map_err(|err| anyhow::anyhow!("Expected a JSON array of parameters, got {value}: {err}"))
 }
 
-struct ResourceEntry;
+fn render(agent_types: &[AgentType], format: OutputFormat) -> anyhow::Result<String> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::to_string_pretty(agent_types)?,
+        OutputFormat::Yaml => serde_yaml::to_string(agent_types)?,
+    })
+}
+
+fn parse_agent_component(value: &str) -> anyhow::Result<(String, Uuid)> {
+    let (agent_type, component_id) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected AGENT_TYPE=UUID, got {value}"))?;
+    Ok((agent_type.to_string(), Uuid::parse(component_id)?))
+}
@@ -0,0 +1,314 @@
+use crate::model::AgentType;
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+// The agent types of a component can be embedded in it as a custom section, so they can be read
+// without instantiating the component and running guest code.
+// Writing the section still runs guest code once: the `embed` command is a post-build step
+// instantiating the component to discover its agent types, as they are only known after
+// the registration code of the agents ran. Components that didn't go through it can't be inspected statically.
+// The section holds the JSON encoding of `AgentManifest`.
+pub const AGENT_TYPES_SECTION: &str = "golem:agent-types";
+
+// Bumped on incompatible changes of the manifest
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentManifest {
+    pub version: u32,
+    pub agent_types: Vec<AgentType>,
+}
+
+impl AgentManifest {
+    pub fn new(agent_types: Vec<AgentType>) -> Self {
+        AgentManifest {
+            version: MANIFEST_VERSION,
+            agent_types,
+        }
+    }
+}
+
+const WASM_HEADER_LENGTH: usize = 8;
+const CUSTOM_SECTION_ID: u8 = 0;
+
+// Reads the manifest from the top-level custom section of a component, if it has one
+pub fn read_manifest(wasm: &[u8]) -> anyhow::Result<Option<AgentManifest>> {
+    let mut manifest = None;
+
+    for section in sections(wasm)? {
+        if let Some(payload) = section.custom_payload(AGENT_TYPES_SECTION)? {
+            let parsed: AgentManifest = serde_json::from_slice(payload)
+                .map_err(|err| anyhow!("Invalid {AGENT_TYPES_SECTION} section: {err}"))?;
+            if parsed.version > MANIFEST_VERSION {
+                bail!(
+                    "The {AGENT_TYPES_SECTION} section is of version {}, but only versions up to {MANIFEST_VERSION} are supported",
+                    parsed.version
+                );
+            }
+            // The last section wins, as the one appended most recently
+            manifest = Some(parsed);
+        }
+    }
+
+    Ok(manifest)
+}
+
+// Returns the component with the manifest embedded, replacing any manifest embedded earlier
+pub fn embed_manifest(wasm: &[u8], manifest: &AgentManifest) -> anyhow::Result<Vec<u8>> {
+    let mut result = wasm[..WASM_HEADER_LENGTH].to_vec();
+
+    for section in sections(wasm)? {
+        if section.custom_payload(AGENT_TYPES_SECTION)?.is_none() {
+            result.extend_from_slice(section.bytes);
+        }
+    }
+
+    let name = AGENT_TYPES_SECTION.as_bytes();
+    let payload = serde_json::to_vec(manifest)?;
+
+    let mut contents = Vec::new();
+    write_leb128(&mut contents, name.len() as u64);
+    contents.extend_from_slice(name);
+    contents.extend_from_slice(&payload);
+
+    result.push(CUSTOM_SECTION_ID);
+    write_leb128(&mut result, contents.len() as u64);
+    result.extend_from_slice(&contents);
+
+    Ok(result)
+}
+
+struct Section<'a> {
+    id: u8,
+    // The whole section, including its id and size
+    bytes: &'a [u8],
+    contents: &'a [u8],
+}
+
+impl<'a> Section<'a> {
+    fn custom_payload(&self, name: &str) -> anyhow::Result<Option<&'a [u8]>> {
+        if self.id != CUSTOM_SECTION_ID {
+            return Ok(None);
+        }
+        let mut offset = 0;
+        let name_length = read_leb128(self.contents, &mut offset)? as usize;
+        let section_name = self
+            .contents
+            .get(offset..offset + name_length)
+            .ok_or_else(|| anyhow!("Truncated custom section name"))?;
+        if section_name == name.as_bytes() {
+            Ok(Some(&self.contents[offset + name_length..]))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// The top-level sections of a module or component. Sections of nested modules and components are not visited
+fn sections(wasm: &[u8]) -> anyhow::Result<Vec<Section<'_>>> {
+    if wasm.len() < WASM_HEADER_LENGTH || &wasm[..4] != b"\0asm" {
+        bail!("Not a WebAssembly binary");
+    }
+
+    let mut sections = Vec::new();
+    let mut offset = WASM_HEADER_LENGTH;
+
+    while offset < wasm.len() {
+        let start = offset;
+        let id = wasm[offset];
+        offset += 1;
+        let size = read_leb128(wasm, &mut offset)? as usize;
+        let contents = wasm
+            .get(offset..offset + size)
+            .ok_or_else(|| anyhow!("Truncated section at offset {start}"))?;
+        offset += size;
+
+        sections.push(Section {
+            id,
+            bytes: &wasm[start..offset],
+            contents,
+        });
+    }
+
+    Ok(sections)
+}
+
+fn read_leb128(bytes: &[u8], offset: &mut usize) -> anyhow::Result<u64> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes
+            .get(*offset)
+            .ok_or_else(|| anyhow!("Truncated LEB128 number at offset {offset}"))?;
+        *offset += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift >= 64 {
+            bail!("LEB128 number too large at offset {offset}");
+        }
+    }
+}
+
+fn write_leb128(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AgentConstructor, DataSchema};
+
+    // The header of an empty component, followed by a custom section of another tool
+    fn component() -> Vec<u8> {
+        let mut wasm = b"\0asm\x0d\x00\x01\x00".to_vec();
+        wasm.extend_from_slice(&custom_section("producers", b"rustc"));
+        wasm
+    }
+
+    fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut contents = Vec::new();
+        write_leb128(&mut contents, name.len() as u64);
+        contents.extend_from_slice(name.as_bytes());
+        contents.extend_from_slice(payload);
+
+        let mut section = vec![CUSTOM_SECTION_ID];
+        write_leb128(&mut section, contents.len() as u64);
+        section.extend_from_slice(&contents);
+        section
+    }
+
+    fn manifest(type_names: &[&str]) -> AgentManifest {
+        AgentManifest::new(
+            type_names
+                .iter()
+                .map(|type_name| AgentType {
+                    type_name: type_name.to_string(),
+                    description: format!("The {type_name} agent"),
+                    version: 1,
+                    agent_constructor: AgentConstructor {
+                        name: None,
+                        description: String::new(),
+                        prompt_hint: None,
+                        input_schema: DataSchema::Structured { parameters: vec![] },
+                    },
+                    methods: vec![],
+                    requires: vec![],
+                })
+                .collect(),
+        )
+    }
+
+    fn manifest_sections(wasm: &[u8]) -> usize {
+        sections(wasm)
+            .unwrap()
+            .iter()
+            .filter(|section| {
+                section
+                    .custom_payload(AGENT_TYPES_SECTION)
+                    .unwrap()
+                    .is_some()
+            })
+            .count()
+    }
+
+    #[test]
+    fn reads_the_embedded_manifest() {
+        let manifest = manifest(&["weather-agent", "planner"]);
+
+        let wasm = embed_manifest(&component(), &manifest).unwrap();
+
+        assert_eq!(read_manifest(&wasm).unwrap(), Some(manifest));
+        // The other sections are kept as they were
+        assert!(wasm.starts_with(&component()));
+    }
+
+    #[test]
+    fn replaces_manifests_embedded_earlier() {
+        let wasm = embed_manifest(&component(), &manifest(&["weather-agent"])).unwrap();
+        let wasm = embed_manifest(&wasm, &manifest(&["planner"])).unwrap();
+
+        assert_eq!(read_manifest(&wasm).unwrap(), Some(manifest(&["planner"])));
+        assert_eq!(manifest_sections(&wasm), 1);
+    }
+
+    #[test]
+    fn writes_sizes_of_several_bytes() {
+        let type_names = (0..100).map(|i| format!("agent-{i}")).collect::<Vec<_>>();
+        let manifest = manifest(&type_names.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let wasm = embed_manifest(&component(), &manifest).unwrap();
+
+        assert_eq!(read_manifest(&wasm).unwrap(), Some(manifest));
+    }
+
+    #[test]
+    fn rejects_manifests_of_newer_versions() {
+        let mut manifest = manifest(&["weather-agent"]);
+        manifest.version = MANIFEST_VERSION + 1;
+        let wasm = embed_manifest(&component(), &manifest).unwrap();
+
+        assert_eq!(
+            read_manifest(&wasm).unwrap_err().to_string(),
+            format!(
+                "The golem:agent-types section is of version {}, but only versions up to {MANIFEST_VERSION} are supported",
+                MANIFEST_VERSION + 1
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_manifests() {
+        let mut wasm = component();
+        wasm.extend_from_slice(&custom_section(AGENT_TYPES_SECTION, b"{\"version\": 1}"));
+
+        assert!(read_manifest(&wasm)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid golem:agent-types section: missing field `agentTypes`"));
+    }
+
+    #[test]
+    fn finds_no_manifest_in_components_that_were_not_embedded() {
+        assert_eq!(read_manifest(&component()).unwrap(), None);
+
+        let path = std::env::temp_dir().join(format!("no-manifest-{}.wasm", std::process::id()));
+        std::fs::write(&path, component()).unwrap();
+        let result = crate::read_required_embedded_agent_types(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "{} has no embedded agent types, use `embed` to add them",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_component() {
+        assert_eq!(
+            read_manifest(b"{\"version\": 1}").unwrap_err().to_string(),
+            "Not a WebAssembly binary"
+        );
+
+        let mut truncated = component();
+        truncated.truncate(truncated.len() - 1);
+        assert_eq!(
+            read_manifest(&truncated).unwrap_err().to_string(),
+            "Truncated section at offset 8"
+        );
+    }
+}
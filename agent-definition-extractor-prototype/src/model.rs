@@ -0,0 +1,488 @@
+use anyhow::{anyhow, bail};
+use golem_wasm_ast::analysis::analysed_type::{
+    bool, case, chr, f32, f64, field, flags, handle, list, option, r#enum, record, result,
+    result_err, result_ok, s16, s32, s64, s8, str, tuple, u16, u32, u64, u8, unit_case,
+    unit_result, variant,
+};
+use golem_wasm_ast::analysis::{AnalysedResourceId, AnalysedResourceMode, AnalysedType};
+use serde::{Deserialize, Serialize};
+use wasmtime::component::Val;
+
+// Typed model of `golem:agent/common.agent-type`, as returned by `discover-agent-types`.
+// WIT types are represented by `AnalysedType` rather than the flat node list of `wit-type`.
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentType {
+    pub type_name: String,
+    pub description: String,
+    // Catalogues written before agent types were versioned have none
+    #[serde(default = "default_agent_type_version")]
+    pub version: u32,
+    pub agent_constructor: AgentConstructor,
+    pub methods: Vec<AgentMethod>,
+    pub requires: Vec<AgentDependency>,
+}
+
+fn default_agent_type_version() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentConstructor {
+    pub name: Option<String>,
+    pub description: String,
+    pub prompt_hint: Option<String>,
+    pub input_schema: DataSchema,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentDependency {
+    pub agent_name: String,
+    pub methods: Vec<AgentMethod>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentMethod {
+    pub name: String,
+    pub description: String,
+    pub prompt_hint: Option<String>,
+    pub input_schema: DataSchema,
+    pub output_schema: DataSchema,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum DataSchema {
+    Structured { parameters: Vec<ParameterType> },
+    Multimodal { text: Option<Vec<TextType>> },
+}
+
+impl DataSchema {
+    pub fn parameters(&self) -> &[ParameterType] {
+        match self {
+            DataSchema::Structured { parameters } => parameters,
+            DataSchema::Multimodal { .. } => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum ParameterType {
+    Wit(AnalysedType),
+    Text(TextType),
+}
+
+impl ParameterType {
+    // Text parameters are passed as strings
+    pub fn analysed_type(&self) -> AnalysedType {
+        match self {
+            ParameterType::Wit(typ) => typ.clone(),
+            ParameterType::Text(_) => str(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextType {
+    pub language_code: String,
+    pub max_length: Option<u32>,
+    pub format: TextFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextFormat {
+    Plain,
+    Markdown,
+    Json,
+}
+
+// `golem:agent/common.status-update`, reported by agent invocations
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum StatusUpdate {
+    Error { error: AgentError },
+    Progress { report: Option<ProgressReport> },
+    Emit { output: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "message")]
+pub enum AgentError {
+    NetworkError,
+    InvalidInput(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressReport {
+    pub description: String,
+    pub counter: Option<ProgressCounter>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressCounter {
+    pub steps: u32,
+    pub total: u32,
+}
+
+impl StatusUpdate {
+    pub fn from_val(val: &Val) -> anyhow::Result<Self> {
+        match val {
+            Val::Variant(case_name, Some(payload)) if case_name == "error" => {
+                let error = match payload.as_ref() {
+                    Val::Variant(case_name, None) if case_name == "network-error" => {
+                        AgentError::NetworkError
+                    }
+                    Val::Variant(case_name, Some(message)) if case_name == "invalid-input" => {
+                        AgentError::InvalidInput(string(message)?)
+                    }
+                    other => bail!("Unexpected error: {other:?}"),
+                };
+                Ok(StatusUpdate::Error { error })
+            }
+            Val::Variant(case_name, Some(payload)) if case_name == "progress" => {
+                Ok(StatusUpdate::Progress {
+                    report: option_of(payload, ProgressReport::from_val)?,
+                })
+            }
+            Val::Variant(case_name, Some(payload)) if case_name == "emit" => Ok(StatusUpdate::Emit {
+                output: string(payload)?,
+            }),
+            other => bail!("Unexpected status-update: {other:?}"),
+        }
+    }
+
+    // The WIT type of `status-update`, to pass status updates around as `wit-value`s
+    pub fn analysed_type() -> AnalysedType {
+        variant(vec![
+            case(
+                "error",
+                variant(vec![
+                    unit_case("network-error"),
+                    case("invalid-input", str()),
+                ]),
+            ),
+            case(
+                "progress",
+                option(record(vec![
+                    field("description", str()),
+                    field(
+                        "counter",
+                        option(record(vec![field("steps", u32()), field("total", u32())])),
+                    ),
+                ])),
+            ),
+            case("emit", str()),
+        ])
+    }
+}
+
+impl ProgressReport {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        Ok(ProgressReport {
+            description: string(get_field(val, "description")?)?,
+            counter: option_of(get_field(val, "counter")?, |counter| {
+                let number = |name: &str| match get_field(counter, name)? {
+                    Val::U32(n) => Ok(*n),
+                    other => bail!("Expected u32, but got {other:?}"),
+                };
+                Ok(ProgressCounter {
+                    steps: number("steps")?,
+                    total: number("total")?,
+                })
+            })?,
+        })
+    }
+}
+
+impl std::fmt::Display for StatusUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusUpdate::Error {
+                error: AgentError::NetworkError,
+            } => write!(f, "error: network error"),
+            StatusUpdate::Error {
+                error: AgentError::InvalidInput(message),
+            } => write!(f, "error: invalid input: {message}"),
+            StatusUpdate::Progress { report: None } => write!(f, "progress"),
+            StatusUpdate::Progress {
+                report: Some(ProgressReport { description, counter: None }),
+            } => write!(f, "progress: {description}"),
+            StatusUpdate::Progress {
+                report:
+                    Some(ProgressReport {
+                        description,
+                        counter: Some(ProgressCounter { steps, total }),
+                    }),
+            } => write!(f, "progress: {description} ({steps}/{total})"),
+            StatusUpdate::Emit { output } => write!(f, "{output}"),
+        }
+    }
+}
+
+impl AgentType {
+    pub fn from_val(val: &Val) -> anyhow::Result<Self> {
+        Ok(AgentType {
+            type_name: string(get_field(val, "type-name")?)?,
+            description: string(get_field(val, "description")?)?,
+            version: match get_field(val, "version")? {
+                Val::U32(n) => *n,
+                other => bail!("Expected u32, but got {other:?}"),
+            },
+            agent_constructor: AgentConstructor::from_val(get_field(val, "agent-constructor")?)?,
+            methods: list_of(get_field(val, "methods")?, AgentMethod::from_val)?,
+            requires: list_of(get_field(val, "requires")?, AgentDependency::from_val)?,
+        })
+    }
+
+    pub fn method(&self, name: &str) -> Option<&AgentMethod> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+}
+
+impl AgentConstructor {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        Ok(AgentConstructor {
+            name: option_of(get_field(val, "name")?, string)?,
+            description: string(get_field(val, "description")?)?,
+            prompt_hint: option_of(get_field(val, "prompt-hint")?, string)?,
+            input_schema: DataSchema::from_val(get_field(val, "input-schema")?)?,
+        })
+    }
+}
+
+impl AgentDependency {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        Ok(AgentDependency {
+            agent_name: string(get_field(val, "agent-name")?)?,
+            methods: list_of(get_field(val, "methods")?, AgentMethod::from_val)?,
+        })
+    }
+}
+
+impl AgentMethod {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        Ok(AgentMethod {
+            name: string(get_field(val, "name")?)?,
+            description: string(get_field(val, "description")?)?,
+            prompt_hint: option_of(get_field(val, "prompt-hint")?, string)?,
+            input_schema: DataSchema::from_val(get_field(val, "input-schema")?)?,
+            output_schema: DataSchema::from_val(get_field(val, "output-schema")?)?,
+        })
+    }
+}
+
+impl DataSchema {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        match val {
+            Val::Variant(case_name, Some(payload)) if case_name == "structured" => {
+                Ok(DataSchema::Structured {
+                    parameters: list_of(get_field(payload, "parameters")?, ParameterType::from_val)?,
+                })
+            }
+            Val::Variant(case_name, Some(payload)) if case_name == "multimodal" => {
+                Ok(DataSchema::Multimodal {
+                    text: option_of(get_field(payload, "text")?, |text| {
+                        list_of(text, TextType::from_val)
+                    })?,
+                })
+            }
+            other => bail!("Unexpected data-schema: {other:?}"),
+        }
+    }
+}
+
+impl ParameterType {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        match val {
+            Val::Variant(case_name, Some(payload)) if case_name == "wit" => {
+                Ok(ParameterType::Wit(analysed_type_from_wit_type(payload)?))
+            }
+            Val::Variant(case_name, Some(payload)) if case_name == "text" => {
+                Ok(ParameterType::Text(TextType::from_val(payload)?))
+            }
+            other => bail!("Unexpected parameter-type: {other:?}"),
+        }
+    }
+}
+
+impl TextType {
+    fn from_val(val: &Val) -> anyhow::Result<Self> {
+        let format = match get_field(val, "format")? {
+            Val::Enum(case_name) => match case_name.as_str() {
+                "plain" => TextFormat::Plain,
+                "markdown" => TextFormat::Markdown,
+                "json" => TextFormat::Json,
+                other => bail!("Unexpected text-format: {other}"),
+            },
+            other => bail!("Expected text-format to be an enum, but got {other:?}"),
+        };
+
+        Ok(TextType {
+            language_code: string(get_field(val, "language-code")?)?,
+            max_length: option_of(get_field(val, "max-length")?, |v| match v {
+                Val::U32(n) => Ok(*n),
+                other => bail!("Expected u32, but got {other:?}"),
+            })?,
+            format,
+        })
+    }
+}
+
+// `wit-type` is a flat list of nodes, where the first node is the root and the others
+// are referred to by their index
+pub fn analysed_type_from_wit_type(val: &Val) -> anyhow::Result<AnalysedType> {
+    let nodes = match get_field(val, "nodes")? {
+        Val::List(nodes) => nodes,
+        other => bail!("Expected wit-type nodes to be a list, but got {other:?}"),
+    };
+
+    if nodes.is_empty() {
+        bail!("wit-type has no nodes");
+    }
+
+    analysed_type_from_node(nodes, 0)
+}
+
+fn analysed_type_from_node(nodes: &[Val], index: i32) -> anyhow::Result<AnalysedType> {
+    let node = usize::try_from(index)
+        .ok()
+        .and_then(|index| nodes.get(index))
+        .ok_or_else(|| anyhow!("wit-type node index {index} out of range"))?;
+
+    let node_index = |val: &Val| match val {
+        Val::S32(index) => analysed_type_from_node(nodes, *index),
+        other => bail!("Expected node-index, but got {other:?}"),
+    };
+
+    let optional_node_index = |val: &Val| match val {
+        Val::Option(Some(index)) => node_index(index).map(Some),
+        Val::Option(None) => Ok(None),
+        other => bail!("Expected option<node-index>, but got {other:?}"),
+    };
+
+    let (case_name, payload) = match node {
+        Val::Variant(case_name, payload) => (case_name.as_str(), payload.as_deref()),
+        other => bail!("Expected wit-type-node to be a variant, but got {other:?}"),
+    };
+
+    let typ = match (case_name, payload) {
+        ("record-type", Some(Val::List(fields))) => record(
+            fields
+                .iter()
+                .map(|f| match f {
+                    Val::Tuple(items) if items.len() == 2 => {
+                        Ok(field(&string(&items[0])?, node_index(&items[1])?))
+                    }
+                    other => bail!("Unexpected record field: {other:?}"),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        ("variant-type", Some(Val::List(cases))) => variant(
+            cases
+                .iter()
+                .map(|c| match c {
+                    Val::Tuple(items) if items.len() == 2 => {
+                        let name = string(&items[0])?;
+                        Ok(match optional_node_index(&items[1])? {
+                            Some(typ) => case(&name, typ),
+                            None => unit_case(&name),
+                        })
+                    }
+                    other => bail!("Unexpected variant case: {other:?}"),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        ("enum-type", Some(cases)) => {
+            let cases = list_of(cases, string)?;
+            r#enum(&cases.iter().map(|c| c.as_str()).collect::<Vec<_>>())
+        }
+        ("flags-type", Some(names)) => {
+            let names = list_of(names, string)?;
+            flags(&names.iter().map(|c| c.as_str()).collect::<Vec<_>>())
+        }
+        ("tuple-type", Some(items)) => tuple(list_of(items, node_index)?),
+        ("list-type", Some(inner)) => list(node_index(inner)?),
+        ("option-type", Some(inner)) => option(node_index(inner)?),
+        ("result-type", Some(Val::Tuple(items))) if items.len() == 2 => {
+            match (optional_node_index(&items[0])?, optional_node_index(&items[1])?) {
+                (Some(ok), Some(err)) => result(ok, err),
+                (Some(ok), None) => result_ok(ok),
+                (None, Some(err)) => result_err(err),
+                (None, None) => unit_result(),
+            }
+        }
+        ("prim-u8-type", None) => u8(),
+        ("prim-u16-type", None) => u16(),
+        ("prim-u32-type", None) => u32(),
+        ("prim-u64-type", None) => u64(),
+        ("prim-s8-type", None) => s8(),
+        ("prim-s16-type", None) => s16(),
+        ("prim-s32-type", None) => s32(),
+        ("prim-s64-type", None) => s64(),
+        ("prim-f32-type", None) => f32(),
+        ("prim-f64-type", None) => f64(),
+        ("prim-char-type", None) => chr(),
+        ("prim-bool-type", None) => bool(),
+        ("prim-string-type", None) => str(),
+        ("handle-type", Some(Val::Tuple(items))) if items.len() == 2 => {
+            let resource_id = match &items[0] {
+                Val::U64(id) => AnalysedResourceId(*id),
+                other => bail!("Expected resource-id, but got {other:?}"),
+            };
+            let mode = match &items[1] {
+                Val::Enum(mode) if mode == "owned" => AnalysedResourceMode::Owned,
+                Val::Enum(mode) if mode == "borrowed" => AnalysedResourceMode::Borrowed,
+                other => bail!("Expected resource-mode, but got {other:?}"),
+            };
+            handle(resource_id, mode)
+        }
+        (case_name, payload) => bail!("Unexpected wit-type-node {case_name}: {payload:?}"),
+    };
+
+    Ok(typ)
+}
+
+pub fn get_field<'a>(val: &'a Val, name: &str) -> anyhow::Result<&'a Val> {
+    match val {
+        Val::Record(fields) => fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| anyhow!("Field {name} not found in record")),
+        other => bail!("Expected a record with field {name}, but got {other:?}"),
+    }
+}
+
+pub fn string(val: &Val) -> anyhow::Result<String> {
+    match val {
+        Val::String(s) => Ok(s.clone()),
+        other => bail!("Expected string, but got {other:?}"),
+    }
+}
+
+pub fn list_of<T>(val: &Val, f: impl Fn(&Val) -> anyhow::Result<T>) -> anyhow::Result<Vec<T>> {
+    match val {
+        Val::List(items) => items.iter().map(f).collect(),
+        other => bail!("Expected list, but got {other:?}"),
+    }
+}
+
+pub fn option_of<T>(
+    val: &Val,
+    f: impl Fn(&Val) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    match val {
+        Val::Option(Some(inner)) => f(inner).map(Some),
+        Val::Option(None) => Ok(None),
+        other => bail!("Expected option, but got {other:?}"),
+    }
+}
@@ -0,0 +1,322 @@
+use agent_definition_extractor_prototype::host::HostOptions;
+use agent_definition_extractor_prototype::literal::{example_literal, format_type, parse_literals};
+use agent_definition_extractor_prototype::model::{AgentType, DataSchema, ParameterType, StatusUpdate};
+use agent_definition_extractor_prototype::runner::{parameter_types, AgentRunner, LocalAgentRef};
+use anyhow::{anyhow, bail};
+use clap::Parser;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+use std::path::PathBuf;
+
+const COMMANDS: &[&str] = &["types", "new", "call", "agents", "help", "exit"];
+
+const HELP: &str = "\
+types                               lists the agent types of the component
+new <agent-type>(<params>)          creates an agent
+call <agent-id>.<method>(<params>)  invokes a method of an agent
+agents                              lists the live agents (from discover-agents)
+exit                                quits
+
+Parameters are typed literals, separated by commas:
+  42  3.14  true  'c'  \"text\"  [1, 2]  (1, \"a\")  {name: \"x\"}  case(value)  some(1)  none  ok(1)  err(\"e\")
+
+Press <tab> to complete agent types, agent ids and methods, including example parameters.";
+
+#[derive(Parser)]
+#[command(about = "Interactive shell for the agents of a golem-agentic component, running locally")]
+struct Cli {
+    component: PathBuf,
+
+    /// Other agent components, whose agents can be created and called too. Can be repeated
+    #[arg(long = "with")]
+    other_components: Vec<PathBuf>,
+
+    /// Logs the imports that are linked dynamically
+    #[arg(long)]
+    verbose: bool,
+
+    /// Worker name returned by the stubbed self metadata
+    #[arg(long)]
+    worker_name: Option<String>,
+}
+
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct ReplHelper {
+    agent_types: Vec<AgentType>,
+    agents: Vec<LocalAgentRef>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let mut options = HostOptions {
+        verbose: cli.verbose,
+        ..HostOptions::default()
+    };
+    if let Some(worker_name) = cli.worker_name {
+        options.stubs.worker_name = worker_name;
+    }
+
+    let components = std::iter::once(cli.component.clone())
+        .chain(cli.other_components)
+        .collect::<Vec<_>>();
+    let runner = AgentRunner::with_components(&components, &options).await?;
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper {
+        agent_types: runner.agent_types.clone(),
+        agents: Vec::new(),
+    }));
+
+    println!(
+        "{} agent type(s) found in {} component(s). Type `help` for the available commands.",
+        runner.agent_types.len(),
+        components.len()
+    );
+
+    loop {
+        let line = match editor.readline("agent> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let agents = editor
+            .helper()
+            .map(|helper| helper.agents.clone())
+            .unwrap_or_default();
+
+        let result = match command {
+            "exit" | "quit" => break,
+            "help" => {
+                println!("{HELP}");
+                Ok(())
+            }
+            "types" => {
+                print_agent_types(&runner.agent_types);
+                Ok(())
+            }
+            "agents" => print_agents(&runner, &agents).await,
+            "new" => match create_agent(&runner, rest).await {
+                Ok(agent) => {
+                    println!("{}", agent.agent_id);
+                    if let Some(helper) = editor.helper_mut() {
+                        helper.agents.push(agent);
+                    }
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            },
+            "call" => call_method(&runner, &agents, rest).await,
+            other => Err(anyhow!("Unknown command {other}, type `help` for the available commands")),
+        };
+
+        if let Err(err) = result {
+            eprintln!("error: {err:#}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_agent(runner: &AgentRunner, text: &str) -> anyhow::Result<LocalAgentRef> {
+    let (type_name, params) = split_call(text)?;
+    let agent_type = runner.agent_type(type_name)?;
+    let types = parameter_types(&agent_type.agent_constructor.input_schema)?;
+    let params = parse_literals(params, &types)?;
+    runner.create_agent_with_values(type_name, params).await
+}
+
+async fn call_method(
+    runner: &AgentRunner,
+    agents: &[LocalAgentRef],
+    text: &str,
+) -> anyhow::Result<()> {
+    let (target, params) = split_call(text)?;
+    let (agent_id, method_name) = target
+        .rsplit_once('.')
+        .ok_or_else(|| anyhow!("Expected <agent-id>.<method>(<params>)"))?;
+    let agent = agents
+        .iter()
+        .find(|agent| agent.agent_id == agent_id)
+        .ok_or_else(|| anyhow!("Agent {agent_id} was not created in this session"))?;
+    let method = runner
+        .agent_type(&agent.agent_type)?
+        .method(method_name)
+        .ok_or_else(|| anyhow!("Method {method_name} not found in {}", agent.agent_type))?;
+
+    let types = parameter_types(&method.input_schema)?;
+    let params = parse_literals(params, &types)?;
+    let updates = runner.invoke_with_values(agent, method_name, params).await?;
+
+    for update in &updates {
+        match update {
+            StatusUpdate::Emit { .. } => println!("{update}"),
+            _ => eprintln!("{update}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn print_agents(runner: &AgentRunner, agents: &[LocalAgentRef]) -> anyhow::Result<()> {
+    for agent_id in runner.discover_agents().await? {
+        match agents.iter().find(|agent| agent.agent_id == agent_id) {
+            Some(agent) => println!("{agent_id} ({})", agent.agent_type),
+            None => println!("{agent_id}"),
+        }
+    }
+    Ok(())
+}
+
+fn print_agent_types(agent_types: &[AgentType]) {
+    for agent_type in agent_types {
+        println!(
+            "{}({})",
+            agent_type.type_name,
+            format_schema(&agent_type.agent_constructor.input_schema)
+        );
+        if !agent_type.description.is_empty() {
+            println!("  {}", agent_type.description);
+        }
+        for method in &agent_type.methods {
+            println!(
+                "  .{}({}) -> {}",
+                method.name,
+                format_schema(&method.input_schema),
+                format_schema(&method.output_schema)
+            );
+        }
+    }
+}
+
+fn format_schema(schema: &DataSchema) -> String {
+    match schema {
+        DataSchema::Structured { parameters } => parameters
+            .iter()
+            .map(|parameter| match parameter {
+                ParameterType::Wit(typ) => format_type(typ),
+                ParameterType::Text(_) => "text".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        DataSchema::Multimodal { .. } => "multimodal".to_string(),
+    }
+}
+
+// Splits `name(params)` into the name and the text of the params
+fn split_call(text: &str) -> anyhow::Result<(&str, &str)> {
+    let text = text.trim();
+    let (name, params) = text
+        .split_once('(')
+        .ok_or_else(|| anyhow!("Expected <name>(<params>)"))?;
+    match params.strip_suffix(')') {
+        Some(params) => Ok((name.trim(), params)),
+        None => bail!("Missing closing ) in {text}"),
+    }
+}
+
+// `name(<example params>)`, completing a constructor or method call
+fn call_candidate(name: &str, schema: &DataSchema) -> Pair {
+    let params = parameter_types(schema)
+        .map(|types| types.iter().map(example_literal).collect::<Vec<_>>().join(", "))
+        .unwrap_or_default();
+    Pair {
+        display: format!("{name}({})", format_schema(schema)),
+        replacement: format!("{name}({params})"),
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+
+        let Some((command, rest)) = line.split_once(' ') else {
+            let candidates = COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(line))
+                .map(|command| Pair {
+                    display: command.to_string(),
+                    replacement: format!("{command} "),
+                })
+                .collect();
+            return Ok((0, candidates));
+        };
+
+        let start = command.len() + 1;
+        if rest.contains('(') {
+            return Ok((pos, Vec::new()));
+        }
+
+        let candidates = match command {
+            "new" => self
+                .agent_types
+                .iter()
+                .filter(|agent_type| agent_type.type_name.starts_with(rest))
+                .map(|agent_type| {
+                    call_candidate(&agent_type.type_name, &agent_type.agent_constructor.input_schema)
+                })
+                .collect(),
+            "call" => match rest.rsplit_once('.') {
+                Some((agent_id, method_prefix)) => self
+                    .agents
+                    .iter()
+                    .find(|agent| agent.agent_id == agent_id)
+                    .and_then(|agent| {
+                        self.agent_types
+                            .iter()
+                            .find(|agent_type| agent_type.type_name == agent.agent_type)
+                    })
+                    .map(|agent_type| {
+                        agent_type
+                            .methods
+                            .iter()
+                            .filter(|method| method.name.starts_with(method_prefix))
+                            .map(|method| {
+                                let mut candidate = call_candidate(&method.name, &method.input_schema);
+                                candidate.replacement = format!("{agent_id}.{}", candidate.replacement);
+                                candidate
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+
+        // No method to complete yet, so complete the agent id
+        if command == "call" && candidates.is_empty() {
+            let agent_candidates = self
+                .agents
+                .iter()
+                .filter(|agent| agent.agent_id.starts_with(rest))
+                .map(|agent| Pair {
+                    display: format!("{} ({})", agent.agent_id, agent.agent_type),
+                    replacement: format!("{}.", agent.agent_id),
+                })
+                .collect();
+            return Ok((start, agent_candidates));
+        }
+
+        Ok((start, candidates))
+    }
+}
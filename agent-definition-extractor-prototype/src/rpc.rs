@@ -0,0 +1,652 @@
+use crate::host::{AgentComponent, Host, HostOptions, ResourceEntry};
+use crate::model::{get_field, string, AgentType, StatusUpdate};
+use crate::stubs::Uuid;
+use crate::values::{
+    handle_node, handle_resource_id, node, val_to_wit_value, wit_value, wit_value_to_val,
+};
+use crate::worker::LocalWorker;
+use anyhow::{anyhow, bail};
+use golem_wasm_ast::analysis::analysed_type::{field, record, str, tuple, u32};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmtime::component::{Resource, ResourceAny, Val};
+use wasmtime::StoreContextMut;
+
+pub const GOLEM_RPC_INTERFACE_PREFIX: &str = "golem:rpc/types@";
+
+// In-process implementation of `golem:rpc/types.wasm-rpc`, simulating a Golem cluster running
+// one or more agent components. Every worker is a separate instance of its component, created
+// when it is first called, so agents can call other agents (through the generated `Remote*` clients)
+// without a Golem cluster, e.g. to test multi-agent flows in CI.
+pub struct LocalRpc {
+    components: Vec<LocalComponent>,
+    options: HostOptions,
+    workers: tokio::sync::Mutex<HashMap<LocalWorkerId, Arc<tokio::sync::Mutex<LocalWorker>>>>,
+    // Target workers of the `wasm-rpc` resources, indexed by resource rep
+    targets: Mutex<Vec<LocalWorkerId>>,
+    ephemeral_workers: AtomicU64,
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalComponent {
+    pub component_id: Uuid,
+    pub path: PathBuf,
+    pub agent_types: Vec<AgentType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocalWorkerId {
+    pub component_id: Uuid,
+    pub worker_name: String,
+}
+
+impl LocalWorkerId {
+    // `golem:rpc/types.worker-id`
+    pub fn from_val(worker_id: &Val) -> anyhow::Result<Self> {
+        Ok(LocalWorkerId {
+            component_id: component_id_from_val(get_field(worker_id, "component-id")?)?,
+            worker_name: string(get_field(worker_id, "worker-name")?)?,
+        })
+    }
+}
+
+impl Display for LocalWorkerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "urn:worker:{}/{}", self.component_id, self.worker_name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RpcError {
+    ProtocolError(String),
+    NotFound(String),
+    RemoteInternalError(String),
+}
+
+impl RpcError {
+    fn to_val(&self) -> Val {
+        let (case_name, message) = match self {
+            RpcError::ProtocolError(message) => ("protocol-error", message),
+            RpcError::NotFound(message) => ("not-found", message),
+            RpcError::RemoteInternalError(message) => ("remote-internal-error", message),
+        };
+        Val::Variant(
+            case_name.to_string(),
+            Some(Box::new(Val::String(message.clone()))),
+        )
+    }
+}
+
+// Functions called through `wasm-rpc` by the generated remote agent clients
+#[derive(Debug, PartialEq)]
+enum RpcTarget {
+    Constructor { agent_type: String },
+    Method { method_name: String },
+    GetAgent,
+}
+
+impl RpcTarget {
+    // Function names look like `<interface>.{<agent-type>.new}`, `<interface>.{[method]<agent-type>.<method>}`
+    // or `<interface>.{get-agent}`
+    fn parse(function_name: &str) -> Result<Self, RpcError> {
+        let function = function_name
+            .split_once(".{")
+            .and_then(|(_, function)| function.strip_suffix('}'))
+            .ok_or_else(|| RpcError::ProtocolError(format!("Invalid function name {function_name}")))?;
+
+        if let Some(method) = function.strip_prefix("[method]") {
+            let (_, method_name) = method.split_once('.').ok_or_else(|| {
+                RpcError::ProtocolError(format!("Invalid method name {function_name}"))
+            })?;
+            Ok(RpcTarget::Method {
+                method_name: method_name.to_string(),
+            })
+        } else if let Some(agent_type) = function.strip_suffix(".new") {
+            Ok(RpcTarget::Constructor {
+                agent_type: agent_type.to_string(),
+            })
+        } else if function == "get-agent" {
+            Ok(RpcTarget::GetAgent)
+        } else {
+            Err(RpcError::NotFound(format!(
+                "{function_name} is not supported by the local runner"
+            )))
+        }
+    }
+}
+
+impl LocalRpc {
+    // Loads the components and discovers their agent types. The first component gets the
+    // component id of the stubs, and the others the following ids.
+    pub async fn load(component_paths: &[PathBuf], options: &HostOptions) -> anyhow::Result<Arc<Self>> {
+        let mut components = Vec::new();
+
+        for (index, path) in component_paths.iter().enumerate() {
+            let component_id = Uuid {
+                high_bits: options.stubs.component_id.high_bits,
+                low_bits: options.stubs.component_id.low_bits + index as u64,
+            };
+            components.push(LocalComponent {
+                component_id,
+                path: path.clone(),
+                agent_types: crate::extract_agent_types(path, options).await?,
+            });
+        }
+
+        Self::new(components, options)
+    }
+
+    // Routes every agent type to the component defining it, which is used to answer
+    // `get-agent-component`, so remote agent clients call workers of that component
+    pub fn new(components: Vec<LocalComponent>, options: &HostOptions) -> anyhow::Result<Arc<Self>> {
+        let mut options = options.clone();
+
+        for (index, component) in components.iter().enumerate() {
+            for agent_type in &component.agent_types {
+                if let Some(other) = components[..index].iter().find(|other| {
+                    other
+                        .agent_types
+                        .iter()
+                        .any(|other| other.type_name == agent_type.type_name)
+                }) {
+                    bail!(
+                        "Agent type {} is defined by both {} and {}",
+                        agent_type.type_name,
+                        other.path.display(),
+                        component.path.display()
+                    );
+                }
+                options
+                    .stubs
+                    .agent_components
+                    .entry(agent_type.type_name.clone())
+                    .or_insert(component.component_id);
+            }
+        }
+
+        Ok(Arc::new(LocalRpc {
+            components,
+            options,
+            workers: tokio::sync::Mutex::new(HashMap::new()),
+            targets: Mutex::new(Vec::new()),
+            ephemeral_workers: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn components(&self) -> &[LocalComponent] {
+        &self.components
+    }
+
+    // The component defining the agent type
+    pub fn agent_component(&self, agent_type: &str) -> anyhow::Result<&LocalComponent> {
+        self.components
+            .iter()
+            .find(|component| {
+                component
+                    .agent_types
+                    .iter()
+                    .any(|other| other.type_name == agent_type)
+            })
+            .ok_or_else(|| anyhow!("Agent type {agent_type} is not defined by any of the components"))
+    }
+
+    // The worker, instantiating its component if it is not running yet
+    pub async fn worker(
+        self: &Arc<Self>,
+        worker_id: &LocalWorkerId,
+    ) -> anyhow::Result<Arc<tokio::sync::Mutex<LocalWorker>>> {
+        let mut workers = self.workers.lock().await;
+
+        if let Some(worker) = workers.get(worker_id) {
+            return Ok(worker.clone());
+        }
+
+        let component = self
+            .components
+            .iter()
+            .find(|component| component.component_id == worker_id.component_id)
+            .ok_or_else(|| anyhow!("Component {} not found", worker_id.component_id))?;
+
+        let mut options = self.options.clone();
+        options.stubs = options
+            .stubs
+            .for_worker(component.component_id, &worker_id.worker_name);
+
+        let instance =
+            Box::pin(AgentComponent::instantiate_worker(&component.path, &options, self)).await?;
+        let worker = Arc::new(tokio::sync::Mutex::new(LocalWorker::new(
+            &worker_id.worker_name,
+            instance,
+        )));
+        workers.insert(worker_id.clone(), worker.clone());
+
+        Ok(worker)
+    }
+
+    pub async fn worker_ids(&self) -> Vec<LocalWorkerId> {
+        let mut worker_ids = self.workers.lock().await.keys().cloned().collect::<Vec<_>>();
+        worker_ids.sort();
+        worker_ids
+    }
+
+    // Implements the functions of `golem:rpc/types` imported by a worker
+    pub async fn call(
+        self: &Arc<Self>,
+        mut store: StoreContextMut<'_, Host>,
+        function_name: &str,
+        params: &[Val],
+    ) -> anyhow::Result<Vec<Val>> {
+        match function_name {
+            "[constructor]wasm-rpc" => {
+                let worker_id = params
+                    .first()
+                    .ok_or_else(|| anyhow!("{function_name} expects a worker id"))?;
+                let worker_id = LocalWorkerId::from_val(worker_id)?;
+                Ok(vec![self.new_target(&mut store, worker_id)?])
+            }
+            "[static]wasm-rpc.ephemeral" => {
+                let component_id = params
+                    .first()
+                    .ok_or_else(|| anyhow!("{function_name} expects a component id"))?;
+                let index = self.ephemeral_workers.fetch_add(1, Ordering::SeqCst) + 1;
+                let worker_id = LocalWorkerId {
+                    component_id: component_id_from_val(component_id)?,
+                    worker_name: format!("ephemeral-{index}"),
+                };
+                Ok(vec![self.new_target(&mut store, worker_id)?])
+            }
+            "[method]wasm-rpc.invoke-and-await" | "[method]wasm-rpc.invoke" => {
+                let worker_id = self.target(&mut store, params.first())?;
+                let rpc_function_name = match params.get(1) {
+                    Some(Val::String(name)) => name.clone(),
+                    other => bail!("{function_name} expects a function name, but got {other:?}"),
+                };
+                let function_params = match params.get(2) {
+                    Some(Val::List(function_params)) => function_params.clone(),
+                    other => bail!("{function_name} expects function params, but got {other:?}"),
+                };
+
+                let result = self
+                    .invoke_and_await(&worker_id, &rpc_function_name, function_params)
+                    .await;
+
+                let result = match result {
+                    Ok(_) if function_name == "[method]wasm-rpc.invoke" => Ok(None),
+                    Ok(value) => Ok(Some(Box::new(value))),
+                    Err(err) => Err(Some(Box::new(err.to_val()))),
+                };
+                Ok(vec![Val::Result(result)])
+            }
+            "parse-uuid" => {
+                let result = match params.first() {
+                    Some(Val::String(uuid)) => match Uuid::parse(uuid) {
+                        Ok(uuid) => Ok(Some(Box::new(uuid.to_val()))),
+                        Err(err) => Err(Some(Box::new(Val::String(err.to_string())))),
+                    },
+                    other => bail!("{function_name} expects a string, but got {other:?}"),
+                };
+                Ok(vec![Val::Result(result)])
+            }
+            "uuid-to-string" => {
+                let uuid = params
+                    .first()
+                    .ok_or_else(|| anyhow!("{function_name} expects a uuid"))?;
+                Ok(vec![Val::String(Uuid::from_val(uuid)?.to_string())])
+            }
+            "extract-value" | "extract-type" => {
+                let value_and_type = params
+                    .first()
+                    .ok_or_else(|| anyhow!("{function_name} expects a value-and-type"))?;
+                let field_name = if function_name == "extract-value" { "value" } else { "typ" };
+                Ok(vec![get_field(value_and_type, field_name)?.clone()])
+            }
+            _ => bail!("{function_name} is not supported by the local runner"),
+        }
+    }
+
+    async fn invoke_and_await(
+        self: &Arc<Self>,
+        worker_id: &LocalWorkerId,
+        function_name: &str,
+        function_params: Vec<Val>,
+    ) -> Result<Val, RpcError> {
+        let target = RpcTarget::parse(function_name)?;
+
+        let worker = self
+            .worker(worker_id)
+            .await
+            .map_err(|err| RpcError::ProtocolError(format!("{err:#}")))?;
+        // A worker calling itself (directly or through other workers) would deadlock in Golem too
+        let mut worker = worker.try_lock().map_err(|_| {
+            RpcError::RemoteInternalError(format!(
+                "Worker {worker_id} is busy, re-entrant calls are not supported"
+            ))
+        })?;
+
+        let remote_error = |err: anyhow::Error| RpcError::RemoteInternalError(format!("{err:#}"));
+
+        match target {
+            RpcTarget::Constructor { agent_type } => {
+                let agent = worker
+                    .create_agent(&agent_type, function_params)
+                    .await
+                    .map_err(remote_error)?;
+                // Results of remote calls are tuples of the results of the called function
+                Ok(wit_value(vec![
+                    node("tuple-value", Val::List(vec![Val::S32(1)])),
+                    handle_node(&worker_id.to_string(), agent),
+                ]))
+            }
+            RpcTarget::Method { method_name } => {
+                let (handle, input) = function_params.split_first().ok_or_else(|| {
+                    RpcError::ProtocolError(format!("{function_name} expects an agent handle"))
+                })?;
+                let agent = handle_resource_id(handle)
+                    .map_err(|err| RpcError::ProtocolError(err.to_string()))?;
+                let status_update = worker
+                    .invoke(agent, &method_name, input.to_vec())
+                    .await
+                    .map_err(remote_error)?;
+                let status_update = val_to_wit_value(
+                    &Val::Tuple(vec![status_update]),
+                    &tuple(vec![StatusUpdate::analysed_type()]),
+                )
+                .map_err(remote_error)?;
+                Ok(status_update)
+            }
+            RpcTarget::GetAgent => {
+                let agent_id = function_params
+                    .first()
+                    .ok_or_else(|| RpcError::ProtocolError("get-agent expects an agent id".to_string()))
+                    .and_then(|agent_id| {
+                        wit_value_to_val(agent_id, &str())
+                            .and_then(|agent_id| string(&agent_id))
+                            .map_err(|err| RpcError::ProtocolError(err.to_string()))
+                    })?;
+                let agent = worker.find_agent(&agent_id).ok_or_else(|| {
+                    RpcError::NotFound(format!("Agent {agent_id} not found in worker {worker_id}"))
+                })?;
+                let agent_ref = Val::Record(vec![
+                    ("agent-id".to_string(), Val::String(agent_id)),
+                    (
+                        "agent-name".to_string(),
+                        Val::String(worker.agent_type(agent).map_err(remote_error)?.to_string()),
+                    ),
+                    ("agent-handle".to_string(), Val::U32(agent as u32)),
+                ]);
+                val_to_wit_value(
+                    &Val::Tuple(vec![agent_ref]),
+                    &tuple(vec![record(vec![
+                        field("agent-id", str()),
+                        field("agent-name", str()),
+                        field("agent-handle", u32()),
+                    ])]),
+                )
+                .map_err(remote_error)
+            }
+        }
+    }
+
+    fn new_target(
+        &self,
+        store: &mut StoreContextMut<'_, Host>,
+        worker_id: LocalWorkerId,
+    ) -> anyhow::Result<Val> {
+        let mut targets = self.targets.lock().unwrap();
+        targets.push(worker_id);
+        let resource = Resource::<ResourceEntry>::new_own((targets.len() - 1) as u32);
+        Ok(Val::Resource(ResourceAny::try_from_resource(
+            resource, store,
+        )?))
+    }
+
+    fn target(
+        &self,
+        store: &mut StoreContextMut<'_, Host>,
+        handle: Option<&Val>,
+    ) -> anyhow::Result<LocalWorkerId> {
+        let rep = match handle {
+            Some(Val::Resource(resource)) => {
+                resource.try_into_resource::<ResourceEntry>(&mut *store)?.rep()
+            }
+            other => bail!("Expected a wasm-rpc resource, but got {other:?}"),
+        };
+        self.targets
+            .lock()
+            .unwrap()
+            .get(rep as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown wasm-rpc resource {rep}"))
+    }
+}
+
+fn component_id_from_val(component_id: &Val) -> anyhow::Result<Uuid> {
+    Uuid::from_val(get_field(component_id, "uuid")?)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AgentConstructor, DataSchema};
+    use crate::stubs::HostStubs;
+
+    fn component_id(low_bits: u64) -> Uuid {
+        Uuid {
+            high_bits: 0,
+            low_bits,
+        }
+    }
+
+    fn component(low_bits: u64, path: &str, type_names: &[&str]) -> LocalComponent {
+        LocalComponent {
+            component_id: component_id(low_bits),
+            path: PathBuf::from(path),
+            agent_types: type_names
+                .iter()
+                .map(|type_name| AgentType {
+                    type_name: type_name.to_string(),
+                    description: String::new(),
+                    version: 1,
+                    agent_constructor: AgentConstructor {
+                        name: None,
+                        description: String::new(),
+                        prompt_hint: None,
+                        input_schema: DataSchema::Structured { parameters: vec![] },
+                    },
+                    methods: vec![],
+                    requires: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    fn local_rpc() -> Arc<LocalRpc> {
+        LocalRpc::new(
+            vec![
+                component(1, "weather.wasm", &["weather-agent"]),
+                component(2, "search.wasm", &["search-agent", "fetch-agent"]),
+            ],
+            &HostOptions::default(),
+        )
+        .unwrap()
+    }
+
+    // The component id returned by the stubbed `get-agent-component`
+    fn agent_component(stubs: &HostStubs, agent_type: &str) -> Val {
+        stubs
+            .call(
+                "get-agent-component",
+                &[Val::String(agent_type.to_string())],
+                &[],
+            )
+            .unwrap()
+            .unwrap()
+            .remove(0)
+    }
+
+    fn some_component_id(low_bits: u64) -> Val {
+        Val::Option(Some(Box::new(Val::Record(vec![(
+            "uuid".to_string(),
+            component_id(low_bits).to_val(),
+        )]))))
+    }
+
+    #[test]
+    fn parses_the_functions_called_by_remote_agent_clients() {
+        assert_eq!(
+            RpcTarget::parse("golem:agents/weather-agent-client.{weather-agent.new}").unwrap(),
+            RpcTarget::Constructor {
+                agent_type: "weather-agent".to_string()
+            }
+        );
+        assert_eq!(
+            RpcTarget::parse(
+                "golem:agents/weather-agent-client.{[method]weather-agent.get-forecast}"
+            )
+            .unwrap(),
+            RpcTarget::Method {
+                method_name: "get-forecast".to_string()
+            }
+        );
+        assert_eq!(
+            RpcTarget::parse("golem:agents/weather-agent-client.{get-agent}").unwrap(),
+            RpcTarget::GetAgent
+        );
+
+        assert!(matches!(
+            RpcTarget::parse("get-forecast"),
+            Err(RpcError::ProtocolError(_))
+        ));
+        assert!(matches!(
+            RpcTarget::parse("golem:agents/weather-agent-client.{[method]get-forecast}"),
+            Err(RpcError::ProtocolError(_))
+        ));
+        assert!(matches!(
+            RpcTarget::parse("golem:agents/weather-agent-client.{[drop]weather-agent}"),
+            Err(RpcError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn reads_worker_ids() {
+        let worker_id = Val::Record(vec![
+            (
+                "component-id".to_string(),
+                Val::Record(vec![("uuid".to_string(), component_id(2).to_val())]),
+            ),
+            (
+                "worker-name".to_string(),
+                Val::String("weather-worker".to_string()),
+            ),
+        ]);
+
+        let worker_id = LocalWorkerId::from_val(&worker_id).unwrap();
+
+        assert_eq!(
+            worker_id,
+            LocalWorkerId {
+                component_id: component_id(2),
+                worker_name: "weather-worker".to_string(),
+            }
+        );
+        assert_eq!(
+            worker_id.to_string(),
+            "urn:worker:00000000-0000-0000-0000-000000000002/weather-worker"
+        );
+        assert!(LocalWorkerId::from_val(&Val::String("weather-worker".to_string())).is_err());
+    }
+
+    #[test]
+    fn routes_agent_types_to_the_components_defining_them() {
+        let rpc = local_rpc();
+
+        assert_eq!(
+            rpc.agent_component("weather-agent").unwrap().component_id,
+            component_id(1)
+        );
+        assert_eq!(
+            rpc.agent_component("fetch-agent").unwrap().component_id,
+            component_id(2)
+        );
+        assert_eq!(
+            rpc.agent_component("planner").unwrap_err().to_string(),
+            "Agent type planner is not defined by any of the components"
+        );
+
+        // Remote agent clients look up the component of the worker to call through the stubs,
+        // in every worker
+        let stubs = rpc
+            .options
+            .stubs
+            .for_worker(component_id(1), "weather-worker");
+        assert_eq!(
+            agent_component(&stubs, "search-agent"),
+            some_component_id(2)
+        );
+        assert_eq!(
+            agent_component(&stubs, "weather-agent"),
+            some_component_id(1)
+        );
+    }
+
+    #[test]
+    fn keeps_the_components_of_agent_types_given_in_the_options() {
+        let mut options = HostOptions::default();
+        options
+            .stubs
+            .agent_components
+            .insert("planner".to_string(), component_id(7));
+
+        let rpc = LocalRpc::new(
+            vec![component(1, "weather.wasm", &["weather-agent"])],
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            agent_component(&rpc.options.stubs, "planner"),
+            some_component_id(7)
+        );
+        assert_eq!(
+            agent_component(&rpc.options.stubs, "weather-agent"),
+            some_component_id(1)
+        );
+    }
+
+    #[test]
+    fn rejects_agent_types_defined_by_two_components() {
+        let error = LocalRpc::new(
+            vec![
+                component(1, "weather.wasm", &["weather-agent"]),
+                component(2, "search.wasm", &["search-agent", "weather-agent"]),
+            ],
+            &HostOptions::default(),
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Agent type weather-agent is defined by both weather.wasm and search.wasm"
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_to_start_workers_of_unknown_components() {
+        let rpc = local_rpc();
+        let worker_id = LocalWorkerId {
+            component_id: component_id(9),
+            worker_name: "weather-worker".to_string(),
+        };
+
+        let error = rpc.worker(&worker_id).await.err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Component 00000000-0000-0000-0000-000000000009 not found"
+        );
+        assert!(rpc.worker_ids().await.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::host::HostOptions;
+use crate::model::{AgentMethod, AgentType, DataSchema, StatusUpdate};
+use crate::rpc::{LocalRpc, LocalWorkerId};
+use crate::values::{json_to_val, val_to_wit_value};
+use crate::worker::AgentIndex;
+use anyhow::{anyhow, bail};
+use golem_wasm_ast::analysis::AnalysedType;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmtime::component::Val;
+
+// Runs the agents of one or more components locally, without a Golem cluster.
+// Golem host functions are stubbed (see `HostStubs`), and `wasm-rpc` calls made by agents
+// are served by in-process instances of the components (see `LocalRpc`).
+pub struct AgentRunner {
+    pub rpc: Arc<LocalRpc>,
+    pub agent_types: Vec<AgentType>,
+    // Agents created by the runner live in the worker with this name, in the component of their type
+    worker_name: String,
+}
+
+// An agent created by the runner
+#[derive(Clone, Debug)]
+pub struct LocalAgentRef {
+    pub agent_type: String,
+    pub agent_id: String,
+    pub worker_id: LocalWorkerId,
+    index: AgentIndex,
+}
+
+impl AgentRunner {
+    pub async fn new(component_path: &Path, options: &HostOptions) -> anyhow::Result<Self> {
+        Self::with_components(&[component_path.to_path_buf()], options).await
+    }
+
+    pub async fn with_components(
+        component_paths: &[PathBuf],
+        options: &HostOptions,
+    ) -> anyhow::Result<Self> {
+        let rpc = LocalRpc::load(component_paths, options).await?;
+        let agent_types = rpc
+            .components()
+            .iter()
+            .flat_map(|component| component.agent_types.clone())
+            .collect();
+
+        Ok(AgentRunner {
+            rpc,
+            agent_types,
+            worker_name: options.stubs.worker_name.clone(),
+        })
+    }
+
+    pub fn agent_type(&self, type_name: &str) -> anyhow::Result<&AgentType> {
+        self.agent_types
+            .iter()
+            .find(|agent_type| agent_type.type_name == type_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Agent type {type_name} not found. Available agent types: {}",
+                    self.agent_types
+                        .iter()
+                        .map(|agent_type| agent_type.type_name.clone())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    // Creates an agent, with the constructor parameters given as a JSON array
+    pub async fn create_agent(
+        &self,
+        type_name: &str,
+        params: &[serde_json::Value],
+    ) -> anyhow::Result<LocalAgentRef> {
+        let agent_type = self.agent_type(type_name)?;
+        let params = json_params(&agent_type.agent_constructor.input_schema, params)
+            .map_err(|err| anyhow!("Invalid constructor parameters of {type_name}: {err}"))?;
+        self.create_agent_with_values(type_name, params).await
+    }
+
+    pub async fn create_agent_with_values(
+        &self,
+        type_name: &str,
+        params: Vec<Val>,
+    ) -> anyhow::Result<LocalAgentRef> {
+        let agent_type = self.agent_type(type_name)?;
+        let params = wit_value_params(&agent_type.agent_constructor.input_schema, params)
+            .map_err(|err| anyhow!("Invalid constructor parameters of {type_name}: {err}"))?;
+
+        let worker_id = LocalWorkerId {
+            component_id: self.rpc.agent_component(type_name)?.component_id,
+            worker_name: self.worker_name.clone(),
+        };
+        let worker = self.rpc.worker(&worker_id).await?;
+        let mut worker = worker.lock().await;
+        let index = worker.create_agent(type_name, params).await?;
+
+        Ok(LocalAgentRef {
+            agent_type: type_name.to_string(),
+            agent_id: worker.agent_id(index)?.to_string(),
+            worker_id,
+            index,
+        })
+    }
+
+    // Invokes a method with the parameters given as a JSON array, returning every status update
+    // reported by the method, followed by its result
+    pub async fn invoke(
+        &self,
+        agent: &LocalAgentRef,
+        method_name: &str,
+        params: &[serde_json::Value],
+    ) -> anyhow::Result<Vec<StatusUpdate>> {
+        let params = json_params(&self.method(agent, method_name)?.input_schema, params)
+            .map_err(|err| anyhow!("Invalid parameters of {method_name}: {err}"))?;
+        self.invoke_with_values(agent, method_name, params).await
+    }
+
+    pub async fn invoke_with_values(
+        &self,
+        agent: &LocalAgentRef,
+        method_name: &str,
+        params: Vec<Val>,
+    ) -> anyhow::Result<Vec<StatusUpdate>> {
+        let params = wit_value_params(&self.method(agent, method_name)?.input_schema, params)
+            .map_err(|err| anyhow!("Invalid parameters of {method_name}: {err}"))?;
+
+        let worker = self.rpc.worker(&agent.worker_id).await?;
+        let mut worker = worker.lock().await;
+        worker.invoke_with_updates(agent.index, method_name, params).await
+    }
+
+    fn method(&self, agent: &LocalAgentRef, method_name: &str) -> anyhow::Result<&AgentMethod> {
+        self.agent_type(&agent.agent_type)?
+            .method(method_name)
+            .ok_or_else(|| anyhow!("Method {method_name} not found in {}", agent.agent_type))
+    }
+
+    // Ids of the agents living in the running workers of every component
+    pub async fn discover_agents(&self) -> anyhow::Result<Vec<String>> {
+        let mut agent_ids = Vec::new();
+        for worker_id in self.rpc.worker_ids().await {
+            let worker = self.rpc.worker(&worker_id).await?;
+            let mut worker = worker.lock().await;
+            agent_ids.extend(worker.discover_agents().await?);
+        }
+        Ok(agent_ids)
+    }
+}
+
+// The types of the parameters of a data schema
+pub fn parameter_types(schema: &DataSchema) -> anyhow::Result<Vec<AnalysedType>> {
+    match schema {
+        DataSchema::Structured { parameters } => {
+            Ok(parameters.iter().map(|typ| typ.analysed_type()).collect())
+        }
+        DataSchema::Multimodal { .. } => bail!("Multimodal inputs are not supported yet"),
+    }
+}
+
+fn json_params(schema: &DataSchema, params: &[serde_json::Value]) -> anyhow::Result<Vec<Val>> {
+    let types = parameter_types(schema)?;
+
+    if types.len() != params.len() {
+        bail!("Expected {} parameters, but got {}", types.len(), params.len());
+    }
+
+    types
+        .iter()
+        .zip(params)
+        .enumerate()
+        .map(|(index, (typ, param))| {
+            json_to_val(param, typ).map_err(|err| anyhow!("Parameter {index}: {err}"))
+        })
+        .collect()
+}
+
+fn wit_value_params(schema: &DataSchema, params: Vec<Val>) -> anyhow::Result<Vec<Val>> {
+    let types = parameter_types(schema)?;
+
+    if types.len() != params.len() {
+        bail!("Expected {} parameters, but got {}", types.len(), params.len());
+    }
+
+    types
+        .iter()
+        .zip(params)
+        .enumerate()
+        .map(|(index, (typ, param))| {
+            val_to_wit_value(&param, typ).map_err(|err| anyhow!("Parameter {index}: {err}"))
+        })
+        .collect()
+}
@@ -0,0 +1,423 @@
+use crate::model::get_field;
+use anyhow::{anyhow, bail};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wasmtime::component::{Type, Val};
+
+pub const GOLEM_HOST_INTERFACE_PREFIX: &str = "golem:api/host@";
+
+// Deterministic fake implementations of the commonly used functions of `golem:api/host`,
+// so guest code asking for its own metadata (like `discover-agents` or creating agent ids)
+// does not trap when running outside of Golem
+#[derive(Clone, Debug)]
+pub struct HostStubs {
+    pub worker_name: String,
+    pub component_id: Uuid,
+    pub component_version: u64,
+    // Components of agent types living in other components, for `get-agent-component`.
+    // Agent types not listed here are assumed to live in this component.
+    pub agent_components: HashMap<String, Uuid>,
+    state: Arc<Mutex<StubState>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Uuid {
+    pub high_bits: u64,
+    pub low_bits: u64,
+}
+
+impl Uuid {
+    pub fn parse(uuid: &str) -> anyhow::Result<Self> {
+        let hex = uuid.replace('-', "");
+        if hex.len() != 32 {
+            bail!("Invalid UUID: {uuid}");
+        }
+        let value = u128::from_str_radix(&hex, 16).map_err(|_| anyhow!("Invalid UUID: {uuid}"))?;
+        Ok(Uuid {
+            high_bits: (value >> 64) as u64,
+            low_bits: value as u64,
+        })
+    }
+
+    pub fn from_val(val: &Val) -> anyhow::Result<Self> {
+        let bits = |name: &str| match get_field(val, name)? {
+            Val::U64(bits) => Ok(*bits),
+            other => bail!("Expected u64, but got {other:?}"),
+        };
+        Ok(Uuid {
+            high_bits: bits("high-bits")?,
+            low_bits: bits("low-bits")?,
+        })
+    }
+
+    pub fn to_val(self) -> Val {
+        Val::Record(vec![
+            ("high-bits".to_string(), Val::U64(self.high_bits)),
+            ("low-bits".to_string(), Val::U64(self.low_bits)),
+        ])
+    }
+}
+
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = ((self.high_bits as u128) << 64) | self.low_bits as u128;
+        let hex = format!("{value:032x}");
+        write!(
+            f,
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct StubState {
+    oplog_index: u64,
+    idempotency_keys: u64,
+}
+
+impl Default for HostStubs {
+    fn default() -> Self {
+        HostStubs {
+            worker_name: "local-worker".to_string(),
+            component_id: Uuid {
+                high_bits: 0,
+                low_bits: 1,
+            },
+            component_version: 0,
+            agent_components: HashMap::new(),
+            state: Arc::new(Mutex::new(StubState::default())),
+        }
+    }
+}
+
+impl HostStubs {
+    // Stubs of another worker, with its own oplog and idempotency keys
+    pub fn for_worker(&self, component_id: Uuid, worker_name: &str) -> HostStubs {
+        HostStubs {
+            worker_name: worker_name.to_string(),
+            component_id,
+            state: Arc::new(Mutex::new(StubState::default())),
+            ..self.clone()
+        }
+    }
+
+    // Returns None if the function is not stubbed
+    pub fn call(
+        &self,
+        function_name: &str,
+        params: &[Val],
+        result_types: &[Type],
+    ) -> Option<anyhow::Result<Vec<Val>>> {
+        let result_type = result_types.first();
+
+        let result = match function_name {
+            "get-self-metadata" => self.worker_metadata(result_type?, self.worker_id(&self.worker_name)),
+            "get-worker-metadata" => {
+                let worker_id = params.first()?.clone();
+                self.worker_metadata(&option_inner_type(result_type?)?, worker_id)
+                    .map(|metadata| Val::Option(Some(Box::new(metadata))))
+            }
+            "resolve-component-id" => Ok(some(self.component_id_val())),
+            "resolve-worker-id" | "resolve-worker-id-strict" => match params.get(1) {
+                Some(Val::String(worker_name)) => Ok(some(self.worker_id(worker_name))),
+                _ => Err(anyhow!("{function_name} expects a worker name")),
+            },
+            "get-agent-component" => match params.first() {
+                Some(Val::String(agent_type)) => {
+                    let component_id = self
+                        .agent_components
+                        .get(agent_type)
+                        .copied()
+                        .unwrap_or(self.component_id);
+                    Ok(some(component_id_val(component_id)))
+                }
+                _ => Err(anyhow!("{function_name} expects an agent type")),
+            },
+            "get-oplog-index" | "mark-begin-operation" => {
+                let mut state = self.state.lock().unwrap();
+                state.oplog_index += 1;
+                Ok(Val::U64(state.oplog_index))
+            }
+            "generate-idempotency-key" => {
+                let mut state = self.state.lock().unwrap();
+                state.idempotency_keys += 1;
+                Ok(Uuid {
+                    high_bits: self.component_id.high_bits,
+                    low_bits: state.idempotency_keys,
+                }
+                .to_val())
+            }
+            "get-idempotence-mode" => Ok(Val::Bool(true)),
+            "get-retry-policy" | "get-oplog-persistence-level" => default_val(result_type?),
+            "set-retry-policy" | "set-oplog-persistence-level" | "set-idempotence-mode"
+            | "mark-end-operation" | "oplog-commit" => return Some(Ok(vec![])),
+            _ => return None,
+        };
+
+        Some(result.map(|val| vec![val]))
+    }
+
+    fn component_id_val(&self) -> Val {
+        component_id_val(self.component_id)
+    }
+
+    fn worker_id(&self, worker_name: &str) -> Val {
+        Val::Record(vec![
+            ("component-id".to_string(), self.component_id_val()),
+            ("worker-name".to_string(), Val::String(worker_name.to_string())),
+        ])
+    }
+
+    fn worker_metadata(&self, typ: &Type, worker_id: Val) -> anyhow::Result<Val> {
+        let mut metadata = default_val(typ)?;
+        set_field(&mut metadata, "worker-id", worker_id)?;
+        set_field(&mut metadata, "component-version", Val::U64(self.component_version))?;
+        Ok(metadata)
+    }
+}
+
+fn component_id_val(uuid: Uuid) -> Val {
+    Val::Record(vec![("uuid".to_string(), uuid.to_val())])
+}
+
+fn some(val: Val) -> Val {
+    Val::Option(Some(Box::new(val)))
+}
+
+fn option_inner_type(typ: &Type) -> Option<Type> {
+    match typ {
+        Type::Option(option) => Some(option.ty()),
+        _ => None,
+    }
+}
+
+fn set_field(record: &mut Val, name: &str, value: Val) -> anyhow::Result<()> {
+    match record {
+        Val::Record(fields) => {
+            let field = fields
+                .iter_mut()
+                .find(|(field_name, _)| field_name == name)
+                .ok_or_else(|| anyhow!("Field {name} not found"))?;
+            field.1 = value;
+            Ok(())
+        }
+        other => bail!("Expected a record, but got {other:?}"),
+    }
+}
+
+// The zero value of a type: zero numbers, empty strings and lists, the first case of variants and enums
+pub fn default_val(typ: &Type) -> anyhow::Result<Val> {
+    Ok(match typ {
+        Type::Bool => Val::Bool(false),
+        Type::S8 => Val::S8(0),
+        Type::U8 => Val::U8(0),
+        Type::S16 => Val::S16(0),
+        Type::U16 => Val::U16(0),
+        Type::S32 => Val::S32(0),
+        Type::U32 => Val::U32(0),
+        Type::S64 => Val::S64(0),
+        Type::U64 => Val::U64(0),
+        Type::Float32 => Val::Float32(0.0),
+        Type::Float64 => Val::Float64(0.0),
+        Type::Char => Val::Char('\0'),
+        Type::String => Val::String(String::new()),
+        Type::List(_) => Val::List(vec![]),
+        Type::Record(record) => Val::Record(
+            record
+                .fields()
+                .map(|field| default_val(&field.ty).map(|val| (field.name.to_string(), val)))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        Type::Tuple(tuple) => Val::Tuple(
+            tuple
+                .types()
+                .map(|typ| default_val(&typ))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        Type::Variant(variant) => {
+            let case = variant
+                .cases()
+                .next()
+                .ok_or_else(|| anyhow!("Variant without cases"))?;
+            let payload = match &case.ty {
+                Some(typ) => Some(Box::new(default_val(typ)?)),
+                None => None,
+            };
+            Val::Variant(case.name.to_string(), payload)
+        }
+        Type::Enum(enum_type) => Val::Enum(
+            enum_type
+                .names()
+                .next()
+                .ok_or_else(|| anyhow!("Enum without cases"))?
+                .to_string(),
+        ),
+        Type::Option(_) => Val::Option(None),
+        Type::Result(result) => Val::Result(Ok(match result.ok() {
+            Some(typ) => Some(Box::new(default_val(&typ)?)),
+            None => None,
+        })),
+        Type::Flags(_) => Val::Flags(vec![]),
+        other => bail!("Cannot create a default value of type {other:?}"),
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::component::types::ComponentItem;
+    use wasmtime::component::Component;
+    use wasmtime::Engine;
+
+    // The parameter types of the function `f` imported by a component in the text format
+    fn param_types(wat: &str) -> Vec<Type> {
+        let engine = Engine::default();
+        let component = Component::new(&engine, wat).unwrap();
+        component
+            .component_type()
+            .imports(&engine)
+            .find_map(|(name, item)| match item {
+                ComponentItem::ComponentFunc(func) if name == "f" => {
+                    Some(func.params().map(|(_, typ)| typ).collect())
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn default_vals(wat: &str) -> Vec<Val> {
+        param_types(wat)
+            .iter()
+            .map(|typ| default_val(typ).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn defaults_of_primitive_types() {
+        let vals = default_vals(
+            r#"(component
+                (import "f" (func
+                    (param "a" bool) (param "b" s8) (param "c" u8) (param "d" s16) (param "e" u16)
+                    (param "g" s32) (param "h" u32) (param "i" s64) (param "j" u64)
+                    (param "k" f32) (param "l" f64) (param "m" char) (param "n" string)))
+            )"#,
+        );
+
+        assert_eq!(
+            vals,
+            vec![
+                Val::Bool(false),
+                Val::S8(0),
+                Val::U8(0),
+                Val::S16(0),
+                Val::U16(0),
+                Val::S32(0),
+                Val::U32(0),
+                Val::S64(0),
+                Val::U64(0),
+                Val::Float32(0.0),
+                Val::Float64(0.0),
+                Val::Char('\0'),
+                Val::String(String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn defaults_of_lists_tuples_options_and_results() {
+        let vals = default_vals(
+            r#"(component
+                (import "f" (func
+                    (param "a" (list u32))
+                    (param "b" (tuple u32 string))
+                    (param "c" (option string))
+                    (param "d" (result u32 (error string)))
+                    (param "e" (result (error string)))))
+            )"#,
+        );
+
+        assert_eq!(
+            vals,
+            vec![
+                Val::List(vec![]),
+                Val::Tuple(vec![Val::U32(0), Val::String(String::new())]),
+                Val::Option(None),
+                Val::Result(Ok(Some(Box::new(Val::U32(0))))),
+                Val::Result(Ok(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn defaults_of_records_variants_enums_and_flags() {
+        let vals = default_vals(
+            r#"(component
+                (type $forecast' (record
+                    (field "city" string) (field "days" u32) (field "alerts" (option string))))
+                (import "forecast" (type $forecast (eq $forecast')))
+                (type $weather' (variant (case "sunny") (case "rain" u32)))
+                (import "weather" (type $weather (eq $weather')))
+                (type $storm' (variant (case "wind" (tuple u32 string)) (case "calm")))
+                (import "storm" (type $storm (eq $storm')))
+                (type $direction' (enum "north" "south"))
+                (import "direction" (type $direction (eq $direction')))
+                (type $alerts' (flags "wind" "rain"))
+                (import "alerts" (type $alerts (eq $alerts')))
+                (import "f" (func
+                    (param "a" $forecast) (param "b" $weather) (param "c" $storm)
+                    (param "d" $direction) (param "e" $alerts)))
+            )"#,
+        );
+
+        assert_eq!(
+            vals,
+            vec![
+                Val::Record(vec![
+                    ("city".to_string(), Val::String(String::new())),
+                    ("days".to_string(), Val::U32(0)),
+                    ("alerts".to_string(), Val::Option(None)),
+                ]),
+                Val::Variant("sunny".to_string(), None),
+                Val::Variant(
+                    "wind".to_string(),
+                    Some(Box::new(Val::Tuple(vec![
+                        Val::U32(0),
+                        Val::String(String::new()),
+                    ]))),
+                ),
+                Val::Enum("north".to_string()),
+                Val::Flags(vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_on_resources() {
+        let types = param_types(
+            r#"(component
+                (import "agent" (type $agent (sub resource)))
+                (import "f" (func (param "a" (own $agent))))
+            )"#,
+        );
+
+        let error = default_val(&types[0]).unwrap_err().to_string();
+
+        assert!(error.starts_with("Cannot create a default value of type"));
+    }
+
+    #[test]
+    fn formats_and_parses_uuids() {
+        let uuid = Uuid::parse("0195e4b2-7c3a-7d1e-9f00-1234567890ab").unwrap();
+
+        assert_eq!(uuid.high_bits, 0x0195e4b27c3a7d1e);
+        assert_eq!(uuid.low_bits, 0x9f001234567890ab);
+        assert_eq!(uuid.to_string(), "0195e4b2-7c3a-7d1e-9f00-1234567890ab");
+        assert_eq!(Uuid::from_val(&uuid.to_val()).unwrap(), uuid);
+        assert!(Uuid::parse("0195e4b2-7c3a").is_err());
+        assert!(Uuid::parse("0195e4b2-7c3a-7d1e-9f00-1234567890ag").is_err());
+    }
+}
@@ -0,0 +1,730 @@
+use anyhow::{anyhow, bail};
+use golem_wasm_ast::analysis::{
+    AnalysedType, NameOptionTypePair, NameTypePair, TypeEnum, TypeFlags, TypeList, TypeOption,
+    TypeRecord, TypeResult, TypeTuple, TypeVariant,
+};
+use serde_json::{json, Map};
+use wasmtime::component::Val;
+
+// Conversions between JSON, typed wasmtime values and `golem:rpc/types.wit-value` (as a wasmtime value).
+//
+// JSON follows the same conventions as `golem_agentic::json_mapping`:
+// records are objects, variants are `{"<case>": <value>}` or `"<case>"`, enums are strings,
+// flags are lists of the names that are set, options are `null` or the inner value,
+// results are `{"ok": <value>}` or `{"err": <value>}`, tuples and lists are arrays.
+
+pub fn json_to_val(json: &serde_json::Value, typ: &AnalysedType) -> anyhow::Result<Val> {
+    Ok(match typ {
+        AnalysedType::Bool(_) => Val::Bool(json.as_bool().ok_or_else(|| type_error("boolean", json))?),
+        AnalysedType::Str(_) => Val::String(
+            json.as_str()
+                .ok_or_else(|| type_error("string", json))?
+                .to_string(),
+        ),
+        AnalysedType::Chr(_) => {
+            let s = json.as_str().ok_or_else(|| type_error("char", json))?;
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Val::Char(c),
+                _ => return Err(type_error("char", json)),
+            }
+        }
+        AnalysedType::F32(_) => Val::Float32(json.as_f64().ok_or_else(|| type_error("number", json))? as f32),
+        AnalysedType::F64(_) => Val::Float64(json.as_f64().ok_or_else(|| type_error("number", json))?),
+        AnalysedType::U8(_) => Val::U8(unsigned(json, "u8")?),
+        AnalysedType::U16(_) => Val::U16(unsigned(json, "u16")?),
+        AnalysedType::U32(_) => Val::U32(unsigned(json, "u32")?),
+        AnalysedType::U64(_) => Val::U64(unsigned(json, "u64")?),
+        AnalysedType::S8(_) => Val::S8(signed(json, "s8")?),
+        AnalysedType::S16(_) => Val::S16(signed(json, "s16")?),
+        AnalysedType::S32(_) => Val::S32(signed(json, "s32")?),
+        AnalysedType::S64(_) => Val::S64(signed(json, "s64")?),
+        AnalysedType::List(TypeList { inner, .. }) => Val::List(
+            json.as_array()
+                .ok_or_else(|| type_error("array", json))?
+                .iter()
+                .map(|item| json_to_val(item, inner))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        AnalysedType::Tuple(TypeTuple { items, .. }) => {
+            let values = json.as_array().ok_or_else(|| type_error("array", json))?;
+            if values.len() != items.len() {
+                bail!(
+                    "Expected a tuple of {} items, but got {} items",
+                    items.len(),
+                    values.len()
+                );
+            }
+            Val::Tuple(
+                values
+                    .iter()
+                    .zip(items)
+                    .map(|(value, typ)| json_to_val(value, typ))
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )
+        }
+        AnalysedType::Record(TypeRecord { fields, .. }) => {
+            let object = json.as_object().ok_or_else(|| type_error("object", json))?;
+            Val::Record(
+                fields
+                    .iter()
+                    .map(|NameTypePair { name, typ }| {
+                        let field = object.get(name).unwrap_or(&serde_json::Value::Null);
+                        json_to_val(field, typ)
+                            .map(|val| (name.clone(), val))
+                            .map_err(|err| anyhow!("Field '{name}': {err}"))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )
+        }
+        AnalysedType::Option(TypeOption { inner, .. }) => match json {
+            serde_json::Value::Null => Val::Option(None),
+            other => Val::Option(Some(Box::new(json_to_val(other, inner)?))),
+        },
+        AnalysedType::Enum(TypeEnum { cases, .. }) => {
+            let case = json.as_str().ok_or_else(|| type_error("string", json))?;
+            if !cases.iter().any(|c| c == case) {
+                bail!("Unknown enum case '{case}', expected one of {cases:?}");
+            }
+            Val::Enum(case.to_string())
+        }
+        AnalysedType::Flags(TypeFlags { names, .. }) => {
+            let set = json
+                .as_array()
+                .ok_or_else(|| type_error("array", json))?
+                .iter()
+                .map(|flag| {
+                    flag.as_str()
+                        .map(|flag| flag.to_string())
+                        .ok_or_else(|| type_error("string", flag))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if let Some(unknown) = set.iter().find(|flag| !names.contains(flag)) {
+                bail!("Unknown flag '{unknown}', expected any of {names:?}");
+            }
+            Val::Flags(set)
+        }
+        AnalysedType::Variant(TypeVariant { cases, .. }) => {
+            let (case_name, payload) = single_key(json)?;
+            let case = cases
+                .iter()
+                .find(|case| case.name == case_name)
+                .ok_or_else(|| anyhow!("Unknown variant case '{case_name}'"))?;
+            let payload = match (&case.typ, payload) {
+                (Some(typ), Some(payload)) => Some(Box::new(json_to_val(payload, typ)?)),
+                (None, None) | (None, Some(serde_json::Value::Null)) => None,
+                (Some(_), None) => bail!("Variant case '{case_name}' requires a value"),
+                (None, Some(_)) => bail!("Variant case '{case_name}' does not take a value"),
+            };
+            Val::Variant(case_name, payload)
+        }
+        AnalysedType::Result(TypeResult { ok, err, .. }) => {
+            let (case_name, payload) = single_key(json)?;
+            let convert = |typ: &Option<Box<AnalysedType>>| match (typ, payload) {
+                (Some(typ), Some(payload)) => json_to_val(payload, typ).map(|v| Some(Box::new(v))),
+                (Some(_), None) => bail!("Result case '{case_name}' requires a value"),
+                (None, _) => Ok(None),
+            };
+            match case_name.as_str() {
+                "ok" => Val::Result(Ok(convert(ok)?)),
+                "err" => Val::Result(Err(convert(err)?)),
+                other => bail!("Expected 'ok' or 'err', but got '{other}'"),
+            }
+        }
+        AnalysedType::Handle(_) => bail!("Resource handles cannot be created from JSON"),
+    })
+}
+
+pub fn val_to_json(val: &Val) -> anyhow::Result<serde_json::Value> {
+    Ok(match val {
+        Val::Bool(b) => json!(b),
+        Val::S8(n) => json!(n),
+        Val::U8(n) => json!(n),
+        Val::S16(n) => json!(n),
+        Val::U16(n) => json!(n),
+        Val::S32(n) => json!(n),
+        Val::U32(n) => json!(n),
+        Val::S64(n) => json!(n),
+        Val::U64(n) => json!(n),
+        Val::Float32(n) => json!(n),
+        Val::Float64(n) => json!(n),
+        Val::Char(c) => json!(c.to_string()),
+        Val::String(s) => json!(s),
+        Val::List(items) | Val::Tuple(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .map(val_to_json)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        Val::Record(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(name, val)| val_to_json(val).map(|json| (name.clone(), json)))
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        ),
+        Val::Variant(case_name, None) => json!(case_name),
+        Val::Variant(case_name, Some(payload)) => json!({ case_name.clone(): val_to_json(payload)? }),
+        Val::Enum(case_name) => json!(case_name),
+        Val::Option(None) => serde_json::Value::Null,
+        Val::Option(Some(inner)) => val_to_json(inner)?,
+        Val::Result(result) => {
+            let (key, payload) = match result {
+                Ok(payload) => ("ok", payload),
+                Err(payload) => ("err", payload),
+            };
+            let payload = match payload {
+                Some(payload) => val_to_json(payload)?,
+                None => serde_json::Value::Null,
+            };
+            json!({ key: payload })
+        }
+        Val::Flags(names) => json!(names),
+        other => bail!("Cannot convert {other:?} to JSON"),
+    })
+}
+
+// Encodes a typed value as a `wit-value`, whose nodes refer to each other by index
+pub fn val_to_wit_value(val: &Val, typ: &AnalysedType) -> anyhow::Result<Val> {
+    let mut nodes = Vec::new();
+    add_node(&mut nodes, val, typ)?;
+    Ok(wit_value(nodes))
+}
+
+fn add_node(nodes: &mut Vec<Val>, val: &Val, typ: &AnalysedType) -> anyhow::Result<i32> {
+    let index = nodes.len();
+    // Placeholder, so that the children are added after their parent
+    nodes.push(Val::Bool(false));
+
+    let node = match (val, typ) {
+        (Val::Record(values), AnalysedType::Record(TypeRecord { fields, .. })) => {
+            let indices = fields
+                .iter()
+                .map(|NameTypePair { name, typ }| {
+                    let (_, value) = values
+                        .iter()
+                        .find(|(field_name, _)| field_name == name)
+                        .ok_or_else(|| anyhow!("Field {name} is missing"))?;
+                    add_node(nodes, value, typ).map(Val::S32)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            node("record-value", Val::List(indices))
+        }
+        (Val::Variant(case_name, payload), AnalysedType::Variant(TypeVariant { cases, .. })) => {
+            let case_idx = cases
+                .iter()
+                .position(|case| &case.name == case_name)
+                .ok_or_else(|| anyhow!("Unknown variant case {case_name}"))?;
+            let payload = match (payload, &cases[case_idx].typ) {
+                (Some(payload), Some(typ)) => Some(Box::new(Val::S32(add_node(nodes, payload, typ)?))),
+                _ => None,
+            };
+            node(
+                "variant-value",
+                Val::Tuple(vec![Val::U32(case_idx as u32), Val::Option(payload)]),
+            )
+        }
+        (Val::Enum(case_name), AnalysedType::Enum(TypeEnum { cases, .. })) => {
+            let case_idx = cases
+                .iter()
+                .position(|case| case == case_name)
+                .ok_or_else(|| anyhow!("Unknown enum case {case_name}"))?;
+            node("enum-value", Val::U32(case_idx as u32))
+        }
+        (Val::Flags(set), AnalysedType::Flags(TypeFlags { names, .. })) => node(
+            "flags-value",
+            Val::List(names.iter().map(|name| Val::Bool(set.contains(name))).collect()),
+        ),
+        (Val::Tuple(values), AnalysedType::Tuple(TypeTuple { items, .. })) => {
+            let indices = values
+                .iter()
+                .zip(items)
+                .map(|(value, typ)| add_node(nodes, value, typ).map(Val::S32))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            node("tuple-value", Val::List(indices))
+        }
+        (Val::List(values), AnalysedType::List(TypeList { inner, .. })) => {
+            let indices = values
+                .iter()
+                .map(|value| add_node(nodes, value, inner).map(Val::S32))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            node("list-value", Val::List(indices))
+        }
+        (Val::Option(value), AnalysedType::Option(TypeOption { inner, .. })) => {
+            let index = match value {
+                Some(value) => Some(Box::new(Val::S32(add_node(nodes, value, inner)?))),
+                None => None,
+            };
+            node("option-value", Val::Option(index))
+        }
+        (Val::Result(result), AnalysedType::Result(TypeResult { ok, err, .. })) => {
+            let mut add_case = |value: &Option<Box<Val>>, typ: &Option<Box<AnalysedType>>| {
+                let index = match (value, typ) {
+                    (Some(value), Some(typ)) => Some(Box::new(Val::S32(add_node(nodes, value, typ)?))),
+                    _ => None,
+                };
+                anyhow::Ok(Some(Box::new(Val::Option(index))))
+            };
+            let result = match result {
+                Ok(value) => Ok(add_case(value, ok)?),
+                Err(value) => Err(add_case(value, err)?),
+            };
+            node("result-value", Val::Result(result))
+        }
+        (Val::U8(n), AnalysedType::U8(_)) => node("prim-u8", Val::U8(*n)),
+        (Val::U16(n), AnalysedType::U16(_)) => node("prim-u16", Val::U16(*n)),
+        (Val::U32(n), AnalysedType::U32(_)) => node("prim-u32", Val::U32(*n)),
+        (Val::U64(n), AnalysedType::U64(_)) => node("prim-u64", Val::U64(*n)),
+        (Val::S8(n), AnalysedType::S8(_)) => node("prim-s8", Val::S8(*n)),
+        (Val::S16(n), AnalysedType::S16(_)) => node("prim-s16", Val::S16(*n)),
+        (Val::S32(n), AnalysedType::S32(_)) => node("prim-s32", Val::S32(*n)),
+        (Val::S64(n), AnalysedType::S64(_)) => node("prim-s64", Val::S64(*n)),
+        (Val::Float32(n), AnalysedType::F32(_)) => node("prim-float32", Val::Float32(*n)),
+        (Val::Float64(n), AnalysedType::F64(_)) => node("prim-float64", Val::Float64(*n)),
+        (Val::Char(c), AnalysedType::Chr(_)) => node("prim-char", Val::Char(*c)),
+        (Val::Bool(b), AnalysedType::Bool(_)) => node("prim-bool", Val::Bool(*b)),
+        (Val::String(s), AnalysedType::Str(_)) => node("prim-string", Val::String(s.clone())),
+        (val, typ) => bail!("Value {val:?} does not match type {typ:?}"),
+    };
+
+    nodes[index] = node;
+    Ok(index as i32)
+}
+
+// Decodes a `wit-value` into a typed value
+pub fn wit_value_to_val(wit_value: &Val, typ: &AnalysedType) -> anyhow::Result<Val> {
+    let nodes = match crate::model::get_field(wit_value, "nodes")? {
+        Val::List(nodes) => nodes,
+        other => bail!("Expected wit-value nodes to be a list, but got {other:?}"),
+    };
+    node_to_val(nodes, 0, typ)
+}
+
+fn node_to_val(nodes: &[Val], index: i32, typ: &AnalysedType) -> anyhow::Result<Val> {
+    let node = usize::try_from(index)
+        .ok()
+        .and_then(|index| nodes.get(index))
+        .ok_or_else(|| anyhow!("wit-value node index {index} out of range"))?;
+
+    let (case_name, payload) = match node {
+        Val::Variant(case_name, payload) => (case_name.as_str(), payload.as_deref()),
+        other => bail!("Expected wit-node to be a variant, but got {other:?}"),
+    };
+
+    let child = |val: &Val, typ: &AnalysedType| match val {
+        Val::S32(index) => node_to_val(nodes, *index, typ),
+        other => bail!("Expected node-index, but got {other:?}"),
+    };
+
+    let optional_child = |val: &Val, typ: Option<&AnalysedType>| match (val, typ) {
+        (Val::Option(Some(index)), Some(typ)) => child(index, typ).map(|val| Some(Box::new(val))),
+        (Val::Option(_), _) => Ok(None),
+        (other, _) => bail!("Expected option<node-index>, but got {other:?}"),
+    };
+
+    Ok(match (case_name, payload, typ) {
+        ("record-value", Some(Val::List(indices)), AnalysedType::Record(TypeRecord { fields, .. })) => {
+            Val::Record(
+                indices
+                    .iter()
+                    .zip(fields)
+                    .map(|(index, NameTypePair { name, typ })| {
+                        child(index, typ).map(|val| (name.clone(), val))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )
+        }
+        ("variant-value", Some(Val::Tuple(items)), AnalysedType::Variant(TypeVariant { cases, .. }))
+            if items.len() == 2 =>
+        {
+            let NameOptionTypePair { name, typ } = match &items[0] {
+                Val::U32(case_idx) => cases
+                    .get(*case_idx as usize)
+                    .ok_or_else(|| anyhow!("Variant case index {case_idx} out of range"))?,
+                other => bail!("Expected variant case index, but got {other:?}"),
+            };
+            Val::Variant(name.clone(), optional_child(&items[1], typ.as_ref())?)
+        }
+        ("enum-value", Some(Val::U32(case_idx)), AnalysedType::Enum(TypeEnum { cases, .. })) => {
+            Val::Enum(
+                cases
+                    .get(*case_idx as usize)
+                    .ok_or_else(|| anyhow!("Enum case index {case_idx} out of range"))?
+                    .clone(),
+            )
+        }
+        ("flags-value", Some(Val::List(set)), AnalysedType::Flags(TypeFlags { names, .. })) => {
+            Val::Flags(
+                names
+                    .iter()
+                    .zip(set)
+                    .filter(|(_, is_set)| matches!(is_set, Val::Bool(true)))
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            )
+        }
+        ("tuple-value", Some(Val::List(indices)), AnalysedType::Tuple(TypeTuple { items, .. })) => {
+            Val::Tuple(
+                indices
+                    .iter()
+                    .zip(items)
+                    .map(|(index, typ)| child(index, typ))
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )
+        }
+        ("list-value", Some(Val::List(indices)), AnalysedType::List(TypeList { inner, .. })) => {
+            Val::List(
+                indices
+                    .iter()
+                    .map(|index| child(index, inner))
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )
+        }
+        ("option-value", Some(index), AnalysedType::Option(TypeOption { inner, .. })) => {
+            Val::Option(optional_child(index, Some(inner))?)
+        }
+        ("result-value", Some(Val::Result(result)), AnalysedType::Result(TypeResult { ok, err, .. })) => {
+            let case = |index: &Option<Box<Val>>, typ: &Option<Box<AnalysedType>>| match index {
+                Some(index) => optional_child(index.as_ref(), typ.as_deref()),
+                None => Ok(None),
+            };
+            Val::Result(match result {
+                Ok(index) => Ok(case(index, ok)?),
+                Err(index) => Err(case(index, err)?),
+            })
+        }
+        ("prim-u8", Some(Val::U8(n)), AnalysedType::U8(_)) => Val::U8(*n),
+        ("prim-u16", Some(Val::U16(n)), AnalysedType::U16(_)) => Val::U16(*n),
+        ("prim-u32", Some(Val::U32(n)), AnalysedType::U32(_)) => Val::U32(*n),
+        ("prim-u64", Some(Val::U64(n)), AnalysedType::U64(_)) => Val::U64(*n),
+        ("prim-s8", Some(Val::S8(n)), AnalysedType::S8(_)) => Val::S8(*n),
+        ("prim-s16", Some(Val::S16(n)), AnalysedType::S16(_)) => Val::S16(*n),
+        ("prim-s32", Some(Val::S32(n)), AnalysedType::S32(_)) => Val::S32(*n),
+        ("prim-s64", Some(Val::S64(n)), AnalysedType::S64(_)) => Val::S64(*n),
+        ("prim-float32", Some(Val::Float32(n)), AnalysedType::F32(_)) => Val::Float32(*n),
+        ("prim-float64", Some(Val::Float64(n)), AnalysedType::F64(_)) => Val::Float64(*n),
+        ("prim-char", Some(Val::Char(c)), AnalysedType::Chr(_)) => Val::Char(*c),
+        ("prim-bool", Some(Val::Bool(b)), AnalysedType::Bool(_)) => Val::Bool(*b),
+        ("prim-string", Some(Val::String(s)), AnalysedType::Str(_)) => Val::String(s.clone()),
+        (case_name, payload, typ) => {
+            bail!("wit-node {case_name} ({payload:?}) does not match type {typ:?}")
+        }
+    })
+}
+
+// A `wit-value` made of the given nodes, the first one being the root
+pub fn wit_value(nodes: Vec<Val>) -> Val {
+    Val::Record(vec![("nodes".to_string(), Val::List(nodes))])
+}
+
+pub fn handle_node(uri: &str, resource_id: u64) -> Val {
+    node(
+        "handle",
+        Val::Tuple(vec![
+            Val::Record(vec![("value".to_string(), Val::String(uri.to_string()))]),
+            Val::U64(resource_id),
+        ]),
+    )
+}
+
+// The resource id of a `wit-value` holding a resource handle
+pub fn handle_resource_id(wit_value: &Val) -> anyhow::Result<u64> {
+    match crate::model::get_field(wit_value, "nodes")? {
+        Val::List(nodes) => match nodes.first() {
+            Some(Val::Variant(case_name, Some(payload))) if case_name == "handle" => {
+                match payload.as_ref() {
+                    Val::Tuple(items) if items.len() == 2 => match &items[1] {
+                        Val::U64(resource_id) => Ok(*resource_id),
+                        other => bail!("Expected resource id, but got {other:?}"),
+                    },
+                    other => bail!("Unexpected handle: {other:?}"),
+                }
+            }
+            other => bail!("Expected a handle, but got {other:?}"),
+        },
+        other => bail!("Expected wit-value nodes to be a list, but got {other:?}"),
+    }
+}
+
+pub fn node(case_name: &str, payload: Val) -> Val {
+    Val::Variant(case_name.to_string(), Some(Box::new(payload)))
+}
+
+fn single_key(json: &serde_json::Value) -> anyhow::Result<(String, Option<&serde_json::Value>)> {
+    match json {
+        serde_json::Value::String(case_name) => Ok((case_name.clone(), None)),
+        serde_json::Value::Object(object) if object.len() == 1 => {
+            let (key, value) = object.iter().next().unwrap();
+            Ok((key.clone(), Some(value)))
+        }
+        other => bail!("Expected a string or an object with a single key, but got {other}"),
+    }
+}
+
+fn unsigned<T: TryFrom<u64>>(json: &serde_json::Value, name: &str) -> anyhow::Result<T> {
+    json.as_u64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| type_error(name, json))
+}
+
+fn signed<T: TryFrom<i64>>(json: &serde_json::Value, name: &str) -> anyhow::Result<T> {
+    json.as_i64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| type_error(name, json))
+}
+
+fn type_error(expected: &str, json: &serde_json::Value) -> anyhow::Error {
+    anyhow!("Expected {expected}, but got {json}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use golem_wasm_ast::analysis::analysed_type::{
+        bool, case, chr, f64, field, flags, list, option, r#enum, record, result, s16, str, tuple,
+        u32, u64, u8, unit_case, variant,
+    };
+
+    fn boxed(val: Val) -> Option<Box<Val>> {
+        Some(Box::new(val))
+    }
+
+    fn string(s: &str) -> Val {
+        Val::String(s.to_string())
+    }
+
+    fn alert() -> AnalysedType {
+        variant(vec![case("storm", str()), unit_case("heat")])
+    }
+
+    fn forecast() -> AnalysedType {
+        record(vec![
+            field("city", str()),
+            field("days", list(u32())),
+            field("note", option(str())),
+        ])
+    }
+
+    // Values of every type with their JSON
+    fn examples() -> Vec<(serde_json::Value, Val, AnalysedType)> {
+        vec![
+            (json!(true), Val::Bool(true), bool()),
+            (json!(200), Val::U8(200), u8()),
+            (json!(-3), Val::S16(-3), s16()),
+            (json!(u64::MAX), Val::U64(u64::MAX), u64()),
+            (json!(2.5), Val::Float64(2.5), f64()),
+            (json!("x"), Val::Char('x'), chr()),
+            (json!("Berlin"), string("Berlin"), str()),
+            (
+                json!([1, 2]),
+                Val::List(vec![Val::U32(1), Val::U32(2)]),
+                list(u32()),
+            ),
+            (
+                json!([1, "a"]),
+                Val::Tuple(vec![Val::U32(1), string("a")]),
+                tuple(vec![u32(), str()]),
+            ),
+            (
+                json!({ "city": "Berlin", "days": [3], "note": null }),
+                Val::Record(vec![
+                    ("city".to_string(), string("Berlin")),
+                    ("days".to_string(), Val::List(vec![Val::U32(3)])),
+                    ("note".to_string(), Val::Option(None)),
+                ]),
+                forecast(),
+            ),
+            (json!(null), Val::Option(None), option(u32())),
+            (json!(7), Val::Option(boxed(Val::U32(7))), option(u32())),
+            (
+                json!("celsius"),
+                Val::Enum("celsius".to_string()),
+                r#enum(&["celsius", "fahrenheit"]),
+            ),
+            (
+                json!(["satellite"]),
+                Val::Flags(vec!["satellite".to_string()]),
+                flags(&["radar", "satellite"]),
+            ),
+            (
+                json!({ "storm": "north" }),
+                Val::Variant("storm".to_string(), boxed(string("north"))),
+                alert(),
+            ),
+            (
+                json!("heat"),
+                Val::Variant("heat".to_string(), None),
+                alert(),
+            ),
+            (
+                json!({ "ok": 2.5 }),
+                Val::Result(Ok(boxed(Val::Float64(2.5)))),
+                result(f64(), str()),
+            ),
+            (
+                json!({ "err": "offline" }),
+                Val::Result(Err(boxed(string("offline")))),
+                result(f64(), str()),
+            ),
+        ]
+    }
+
+    fn json_error(json: serde_json::Value, typ: AnalysedType) -> String {
+        json_to_val(&json, &typ).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn converts_json_to_values_of_every_type() {
+        for (json, val, typ) in examples() {
+            assert_eq!(json_to_val(&json, &typ).unwrap(), val, "{json} as {typ:?}");
+        }
+    }
+
+    #[test]
+    fn converts_values_of_every_type_to_json() {
+        for (json, val, _) in examples() {
+            assert_eq!(val_to_json(&val).unwrap(), json, "{val:?}");
+        }
+    }
+
+    #[test]
+    fn reads_missing_option_fields_and_variant_nulls_as_none() {
+        assert_eq!(
+            json_to_val(&json!({ "city": "Berlin", "days": [] }), &forecast()).unwrap(),
+            Val::Record(vec![
+                ("city".to_string(), string("Berlin")),
+                ("days".to_string(), Val::List(vec![])),
+                ("note".to_string(), Val::Option(None)),
+            ])
+        );
+        assert_eq!(
+            json_to_val(&json!({ "heat": null }), &alert()).unwrap(),
+            Val::Variant("heat".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn rejects_json_of_other_types() {
+        assert_eq!(json_error(json!("3"), u32()), "Expected u32, but got \"3\"");
+        assert_eq!(json_error(json!(300), u8()), "Expected u8, but got 300");
+        assert_eq!(json_error(json!(-1), u32()), "Expected u32, but got -1");
+        assert_eq!(json_error(json!(1.5), u32()), "Expected u32, but got 1.5");
+        assert_eq!(
+            json_error(json!("ab"), chr()),
+            "Expected char, but got \"ab\""
+        );
+        assert_eq!(json_error(json!(1), bool()), "Expected boolean, but got 1");
+        assert_eq!(
+            json_error(json!({}), list(u32())),
+            "Expected array, but got {}"
+        );
+        assert_eq!(
+            json_error(json!([1]), tuple(vec![u32(), str()])),
+            "Expected a tuple of 2 items, but got 1 items"
+        );
+    }
+
+    #[test]
+    fn rejects_records_with_mismatching_fields() {
+        assert_eq!(
+            json_error(json!({ "city": "Berlin", "days": ["x"] }), forecast()),
+            "Field 'days': Expected u32, but got \"x\""
+        );
+        assert_eq!(
+            json_error(json!({ "city": "Berlin" }), forecast()),
+            "Field 'days': Expected array, but got null"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_cases_and_flags() {
+        assert_eq!(
+            json_error(json!("kelvin"), r#enum(&["celsius", "fahrenheit"])),
+            "Unknown enum case 'kelvin', expected one of [\"celsius\", \"fahrenheit\"]"
+        );
+        assert_eq!(
+            json_error(json!(["sonar"]), flags(&["radar", "satellite"])),
+            "Unknown flag 'sonar', expected any of [\"radar\", \"satellite\"]"
+        );
+        assert_eq!(
+            json_error(json!("frost"), alert()),
+            "Unknown variant case 'frost'"
+        );
+        assert_eq!(
+            json_error(json!({ "maybe": 1 }), result(f64(), str())),
+            "Expected 'ok' or 'err', but got 'maybe'"
+        );
+        assert_eq!(
+            json_error(json!({ "storm": "north", "heat": null }), alert()),
+            "Expected a string or an object with a single key, but got {\"heat\":null,\"storm\":\"north\"}"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_unexpected_payloads() {
+        assert_eq!(
+            json_error(json!("storm"), alert()),
+            "Variant case 'storm' requires a value"
+        );
+        assert_eq!(
+            json_error(json!({ "heat": 40 }), alert()),
+            "Variant case 'heat' does not take a value"
+        );
+        assert_eq!(
+            json_error(json!("ok"), result(f64(), str())),
+            "Result case 'ok' requires a value"
+        );
+    }
+
+    #[test]
+    fn encodes_values_as_wit_value_nodes() {
+        let val = Val::Record(vec![
+            ("city".to_string(), string("Berlin")),
+            ("days".to_string(), Val::List(vec![Val::U32(3)])),
+            ("note".to_string(), Val::Option(None)),
+        ]);
+
+        assert_eq!(
+            val_to_wit_value(&val, &forecast()).unwrap(),
+            wit_value(vec![
+                node(
+                    "record-value",
+                    Val::List(vec![Val::S32(1), Val::S32(2), Val::S32(4)])
+                ),
+                node("prim-string", string("Berlin")),
+                node("list-value", Val::List(vec![Val::S32(3)])),
+                node("prim-u32", Val::U32(3)),
+                node("option-value", Val::Option(None)),
+            ])
+        );
+    }
+
+    #[test]
+    fn decodes_the_wit_values_it_encodes() {
+        for (_, val, typ) in examples() {
+            let encoded = val_to_wit_value(&val, &typ).unwrap();
+            assert_eq!(wit_value_to_val(&encoded, &typ).unwrap(), val, "{val:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_wit_values_of_other_types() {
+        assert!(val_to_wit_value(&string("x"), &u32())
+            .unwrap_err()
+            .to_string()
+            .starts_with("Value String(\"x\") does not match type"));
+
+        let encoded = val_to_wit_value(&string("x"), &str()).unwrap();
+        assert!(wit_value_to_val(&encoded, &u32())
+            .unwrap_err()
+            .to_string()
+            .starts_with("wit-node prim-string"));
+
+        let out_of_range = wit_value(vec![node("list-value", Val::List(vec![Val::S32(5)]))]);
+        assert_eq!(
+            wit_value_to_val(&out_of_range, &list(u32()))
+                .unwrap_err()
+                .to_string(),
+            "wit-value node index 5 out of range"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::host::AgentComponent;
+use crate::model::{list_of, string, AgentType, StatusUpdate};
+use anyhow::{anyhow, bail};
+use wasmtime::component::{ResourceAny, Val};
+
+// An instance of an agent component standing in for a Golem worker,
+// together with the agents constructed in it
+pub struct LocalWorker {
+    pub name: String,
+    pub component: AgentComponent,
+    agents: Vec<LocalAgent>,
+}
+
+struct LocalAgent {
+    agent_type: String,
+    agent_id: String,
+    resource: ResourceAny,
+}
+
+// Agents are referred to by their index in the worker, which is also the resource id
+// of the handles passed to remote agent clients
+pub type AgentIndex = u64;
+
+impl LocalWorker {
+    pub fn new(name: &str, component: AgentComponent) -> Self {
+        LocalWorker {
+            name: name.to_string(),
+            component,
+            agents: Vec::new(),
+        }
+    }
+
+    pub async fn agent_types(&mut self) -> anyhow::Result<Vec<AgentType>> {
+        crate::discover_agent_types(&mut self.component).await
+    }
+
+    // Constructs an agent through `agent.create`, with the parameters encoded as `wit-value`s
+    pub async fn create_agent(
+        &mut self,
+        agent_type: &str,
+        params: Vec<Val>,
+    ) -> anyhow::Result<AgentIndex> {
+        let results = self
+            .component
+            .call_guest_function(
+                "[static]agent.create",
+                &[Val::String(agent_type.to_string()), Val::List(params)],
+            )
+            .await?;
+
+        let resource = match results.first() {
+            Some(Val::Result(Ok(Some(agent)))) => match agent.as_ref() {
+                Val::Resource(resource) => *resource,
+                other => bail!("Expected agent.create to return a resource, but got {other:?}"),
+            },
+            Some(Val::Result(Err(Some(error)))) => {
+                bail!("Failed to create an agent of type {agent_type}: {error:?}")
+            }
+            other => bail!("Expected agent.create to return a result, but got {other:?}"),
+        };
+
+        let agent_id = self.call_agent_string(resource, "[method]agent.get-id").await?;
+
+        self.agents.push(LocalAgent {
+            agent_type: agent_type.to_string(),
+            agent_id,
+            resource,
+        });
+
+        Ok((self.agents.len() - 1) as AgentIndex)
+    }
+
+    pub fn agent_id(&self, agent: AgentIndex) -> anyhow::Result<&str> {
+        Ok(&self.agent(agent)?.agent_id)
+    }
+
+    pub fn agent_type(&self, agent: AgentIndex) -> anyhow::Result<&str> {
+        Ok(&self.agent(agent)?.agent_type)
+    }
+
+    pub fn find_agent(&self, agent_id: &str) -> Option<AgentIndex> {
+        self.agents
+            .iter()
+            .position(|agent| agent.agent_id == agent_id)
+            .map(|index| index as AgentIndex)
+    }
+
+    // Ids of the agents living in this worker, as reported by `discover-agents`
+    pub async fn discover_agents(&mut self) -> anyhow::Result<Vec<String>> {
+        let results = self
+            .component
+            .call_guest_function("discover-agents", &[])
+            .await?;
+        let agent_refs = results
+            .first()
+            .ok_or_else(|| anyhow!("discover-agents returned no result"))?;
+        list_of(agent_refs, |agent_ref| {
+            string(crate::model::get_field(agent_ref, "agent-id")?)
+        })
+    }
+
+    // Invokes a method, returning only its result
+    pub async fn invoke(
+        &mut self,
+        agent: AgentIndex,
+        method_name: &str,
+        input: Vec<Val>,
+    ) -> anyhow::Result<Val> {
+        let resource = self.agent(agent)?.resource;
+        let results = self
+            .component
+            .call_guest_function(
+                "[method]agent.invoke",
+                &[
+                    Val::Resource(resource),
+                    Val::String(method_name.to_string()),
+                    Val::List(input),
+                ],
+            )
+            .await?;
+
+        results
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("agent.invoke returned no result"))
+    }
+
+    // Invokes a method, returning every status update it reported followed by the result
+    pub async fn invoke_with_updates(
+        &mut self,
+        agent: AgentIndex,
+        method_name: &str,
+        input: Vec<Val>,
+    ) -> anyhow::Result<Vec<StatusUpdate>> {
+        let resource = self.agent(agent)?.resource;
+        let results = self
+            .component
+            .call_guest_function(
+                "[method]agent.invoke-with-updates",
+                &[
+                    Val::Resource(resource),
+                    Val::String(method_name.to_string()),
+                    Val::List(input),
+                ],
+            )
+            .await?;
+
+        let updates = results
+            .first()
+            .ok_or_else(|| anyhow!("agent.invoke-with-updates returned no result"))?;
+        list_of(updates, StatusUpdate::from_val)
+    }
+
+    fn agent(&self, agent: AgentIndex) -> anyhow::Result<&LocalAgent> {
+        self.agents
+            .get(agent as usize)
+            .ok_or_else(|| anyhow!("Agent {agent} not found in worker {}", self.name))
+    }
+
+    async fn call_agent_string(
+        &mut self,
+        resource: ResourceAny,
+        function_name: &str,
+    ) -> anyhow::Result<String> {
+        let results = self
+            .component
+            .call_guest_function(function_name, &[Val::Resource(resource)])
+            .await?;
+        string(
+            results
+                .first()
+                .ok_or_else(|| anyhow!("{function_name} returned no result"))?,
+        )
+    }
+}
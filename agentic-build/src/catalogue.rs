@@ -0,0 +1,245 @@
+use agent_definition_extractor_prototype::compat::{compare_agent_types, SchemaChange};
+use agent_definition_extractor_prototype::model::AgentType;
+use anyhow::anyhow;
+use std::path::Path;
+
+// `agents.json`, the agent types of a component in a stable order, so that it can be committed
+// and diffed. Agent types are discovered from registries without a defined order, so they are sorted
+// by name, while methods keep the order in which they are declared.
+pub fn normalize(mut agent_types: Vec<AgentType>) -> Vec<AgentType> {
+    agent_types.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+    for agent_type in &mut agent_types {
+        agent_type
+            .requires
+            .sort_by(|a, b| a.agent_name.cmp(&b.agent_name));
+        for dependency in &mut agent_type.requires {
+            dependency.methods.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+    agent_types
+}
+
+pub fn to_json(agent_types: &[AgentType]) -> anyhow::Result<String> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(agent_types)?))
+}
+
+pub fn read_catalogue(path: &Path) -> anyhow::Result<Vec<AgentType>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))?;
+    serde_json::from_str(&json).map_err(|err| anyhow!("Invalid catalogue {}: {err}", path.display()))
+}
+
+// Changes against the baseline that break callers of its agents, see `compare_agent_types`
+pub fn breaking_changes(baseline: &[AgentType], current: &[AgentType]) -> Vec<SchemaChange> {
+    compare_agent_types(baseline, current)
+        .into_iter()
+        .filter(|change| change.is_breaking())
+        .collect()
+}
+
+pub const EXIT_SUCCESS: u8 = 0;
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_BREAKING_CHANGES: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum BaselineCheck {
+    // The baseline didn't exist yet, or was updated to accept the changes
+    Written,
+    Compatible,
+    Breaking(Vec<SchemaChange>),
+}
+
+impl BaselineCheck {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BaselineCheck::Written | BaselineCheck::Compatible => EXIT_SUCCESS,
+            BaselineCheck::Breaking(_) => EXIT_BREAKING_CHANGES,
+        }
+    }
+}
+
+// Checks the agent types against the committed baseline, which is written if it doesn't exist yet or `update` is set
+pub fn check_baseline(
+    baseline: &Path,
+    agent_types: &[AgentType],
+    update: bool,
+) -> anyhow::Result<BaselineCheck> {
+    if update || !baseline.exists() {
+        std::fs::write(baseline, to_json(agent_types)?)
+            .map_err(|err| anyhow!("Failed to write {}: {err}", baseline.display()))?;
+        return Ok(BaselineCheck::Written);
+    }
+
+    let changes = breaking_changes(&read_catalogue(baseline)?, agent_types);
+    if changes.is_empty() {
+        Ok(BaselineCheck::Compatible)
+    } else {
+        Ok(BaselineCheck::Breaking(changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_definition_extractor_prototype::compat::ChangeKind;
+    use agent_definition_extractor_prototype::model::{
+        AgentConstructor, AgentDependency, AgentMethod, DataSchema, ParameterType,
+    };
+    use golem_wasm_ast::analysis::analysed_type::{str, u32};
+    use std::path::PathBuf;
+
+    fn schema(parameters: Vec<ParameterType>) -> DataSchema {
+        DataSchema::Structured { parameters }
+    }
+
+    fn method(name: &str) -> AgentMethod {
+        AgentMethod {
+            name: name.to_string(),
+            description: String::new(),
+            prompt_hint: None,
+            input_schema: schema(vec![ParameterType::Wit(str()), ParameterType::Wit(u32())]),
+            output_schema: schema(vec![ParameterType::Wit(str())]),
+        }
+    }
+
+    fn agent_type(type_name: &str, methods: &[&str], requires: Vec<AgentDependency>) -> AgentType {
+        AgentType {
+            type_name: type_name.to_string(),
+            description: String::new(),
+            version: 1,
+            agent_constructor: AgentConstructor {
+                name: None,
+                description: String::new(),
+                prompt_hint: None,
+                input_schema: schema(vec![]),
+            },
+            methods: methods.iter().map(|name| method(name)).collect(),
+            requires,
+        }
+    }
+
+    fn dependency(agent_name: &str, methods: &[&str]) -> AgentDependency {
+        AgentDependency {
+            agent_name: agent_name.to_string(),
+            methods: methods.iter().map(|name| method(name)).collect(),
+        }
+    }
+
+    // A baseline path of its own for every test, as the tests run in parallel
+    fn baseline(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("agents-{}-{name}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn sorts_agent_types_and_dependencies_by_name() {
+        let agent_types = normalize(vec![
+            agent_type(
+                "weather-agent",
+                &["get-forecast", "get-alerts"],
+                vec![
+                    dependency("search", &["search", "fetch"]),
+                    dependency("geo", &[]),
+                ],
+            ),
+            agent_type("assistant", &[], vec![]),
+        ]);
+
+        assert_eq!(
+            agent_types,
+            vec![
+                agent_type("assistant", &[], vec![]),
+                agent_type(
+                    "weather-agent",
+                    &["get-forecast", "get-alerts"],
+                    vec![
+                        dependency("geo", &[]),
+                        dependency("search", &["fetch", "search"]),
+                    ],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_the_baseline_if_it_does_not_exist() {
+        let path = baseline("missing");
+        let agent_types = vec![agent_type("weather-agent", &["get-forecast"], vec![])];
+
+        let check = check_baseline(&path, &agent_types, false).unwrap();
+
+        assert_eq!(check, BaselineCheck::Written);
+        assert_eq!(check.exit_code(), EXIT_SUCCESS);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            to_json(&agent_types).unwrap()
+        );
+        assert_eq!(read_catalogue(&path).unwrap(), agent_types);
+    }
+
+    #[test]
+    fn accepts_compatible_changes() {
+        let path = baseline("compatible");
+        let old = vec![agent_type("weather-agent", &["get-forecast"], vec![])];
+        let new = vec![
+            agent_type("weather-agent", &["get-forecast", "get-alerts"], vec![]),
+            agent_type("assistant", &[], vec![]),
+        ];
+        check_baseline(&path, &old, false).unwrap();
+
+        let check = check_baseline(&path, &new, false).unwrap();
+
+        assert_eq!(check, BaselineCheck::Compatible);
+        assert_eq!(check.exit_code(), EXIT_SUCCESS);
+        assert_eq!(read_catalogue(&path).unwrap(), old);
+    }
+
+    #[test]
+    fn fails_on_breaking_changes_only() {
+        let path = baseline("breaking");
+        let old = vec![agent_type("weather-agent", &["get-forecast"], vec![])];
+        let new = vec![agent_type("weather-agent", &["get-alerts"], vec![])];
+        check_baseline(&path, &old, false).unwrap();
+
+        let check = check_baseline(&path, &new, false).unwrap();
+
+        let BaselineCheck::Breaking(changes) = &check else {
+            panic!("Expected breaking changes, but got {check:?}");
+        };
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].agent_type, "weather-agent");
+        assert_eq!(changes[0].method.as_deref(), Some("get-forecast"));
+        assert_eq!(changes[0].kind, ChangeKind::MethodRemoved);
+        assert_eq!(check.exit_code(), EXIT_BREAKING_CHANGES);
+        // The baseline is kept, so that the check fails until the changes are accepted
+        assert_eq!(read_catalogue(&path).unwrap(), old);
+    }
+
+    #[test]
+    fn updates_the_baseline_to_accept_breaking_changes() {
+        let path = baseline("update");
+        let old = vec![agent_type("weather-agent", &["get-forecast"], vec![])];
+        let new = vec![agent_type("weather-agent", &[], vec![])];
+        check_baseline(&path, &old, false).unwrap();
+
+        let check = check_baseline(&path, &new, true).unwrap();
+
+        assert_eq!(check, BaselineCheck::Written);
+        assert_eq!(read_catalogue(&path).unwrap(), new);
+        assert_eq!(
+            check_baseline(&path, &new, false).unwrap(),
+            BaselineCheck::Compatible
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_baselines() {
+        let path = baseline("invalid");
+        std::fs::write(&path, "{}").unwrap();
+
+        let error = check_baseline(&path, &[], false).unwrap_err().to_string();
+
+        assert!(error.starts_with(&format!("Invalid catalogue {}", path.display())));
+    }
+}
@@ -0,0 +1,19 @@
+pub mod catalogue;
+pub mod markdown;
+
+use agent_definition_extractor_prototype::host::HostOptions;
+use agent_definition_extractor_prototype::model::AgentType;
+use agent_definition_extractor_prototype::{extract_agent_types, read_embedded_agent_types};
+use std::path::Path;
+
+// The agent types of a built component, read from its embedded manifest if it has one,
+// otherwise by instantiating it
+pub async fn component_agent_types(
+    component_path: &Path,
+    options: &HostOptions,
+) -> anyhow::Result<Vec<AgentType>> {
+    match read_embedded_agent_types(component_path)? {
+        Some(agent_types) => Ok(agent_types),
+        None => extract_agent_types(component_path, options).await,
+    }
+}
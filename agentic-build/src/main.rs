@@ -0,0 +1,80 @@
+use agent_definition_extractor_prototype::host::HostOptions;
+use clap::Parser;
+use golem_agentic_build::catalogue::{
+    check_baseline, normalize, to_json, BaselineCheck, EXIT_FAILURE, EXIT_SUCCESS,
+};
+use golem_agentic_build::{component_agent_types, markdown};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(
+    about = "Writes the agent catalogue (agents.json and AGENTS.md) of a built golem-agentic component, and checks it for breaking changes"
+)]
+struct Cli {
+    /// The built component
+    component: PathBuf,
+
+    /// Directory to write agents.json and AGENTS.md to
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// Committed agents.json to check for breaking changes against. Written if it doesn't exist yet
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Overwrites the baseline with the current agent types, accepting breaking changes
+    #[arg(long, requires = "baseline")]
+    update_baseline: bool,
+
+    /// Logs the imports that are linked dynamically, if the component is instantiated
+    #[arg(long)]
+    verbose: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(exit_code) => ExitCode::from(exit_code),
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<u8> {
+    let options = HostOptions {
+        verbose: cli.verbose,
+        ..HostOptions::default()
+    };
+
+    let agent_types = normalize(component_agent_types(&cli.component, &options).await?);
+    let json = to_json(&agent_types)?;
+
+    std::fs::create_dir_all(&cli.out_dir)?;
+    std::fs::write(cli.out_dir.join("agents.json"), &json)?;
+    std::fs::write(cli.out_dir.join("AGENTS.md"), markdown::render(&agent_types))?;
+
+    let Some(baseline) = cli.baseline else {
+        return Ok(EXIT_SUCCESS);
+    };
+
+    let check = check_baseline(&baseline, &agent_types, cli.update_baseline)?;
+    match &check {
+        BaselineCheck::Written => eprintln!("Wrote baseline {}", baseline.display()),
+        BaselineCheck::Compatible => {}
+        BaselineCheck::Breaking(changes) => {
+            for change in changes {
+                eprintln!("{change}");
+            }
+            eprintln!(
+                "error: {} breaking change(s) against {}. Run with --update-baseline to accept them",
+                changes.len(),
+                baseline.display()
+            );
+        }
+    }
+
+    Ok(check.exit_code())
+}
@@ -0,0 +1,200 @@
+use agent_definition_extractor_prototype::literal::{format_text_type, format_type};
+use agent_definition_extractor_prototype::model::{AgentType, DataSchema, ParameterType};
+
+// `AGENTS.md`, a human readable catalogue of the agent types of a component
+pub fn render(agent_types: &[AgentType]) -> String {
+    let mut markdown = String::from("# Agents\n");
+
+    for agent_type in agent_types {
+        markdown.push_str(&format!("\n## {}\n\n", agent_type.type_name));
+        if !agent_type.description.is_empty() {
+            markdown.push_str(&format!("{}\n\n", agent_type.description));
+        }
+
+        markdown.push_str(&format!("Version: {}\n\n", agent_type.version));
+        markdown.push_str(&format!(
+            "Constructor: `{}({})`\n",
+            agent_type.type_name,
+            format_schema(&agent_type.agent_constructor.input_schema)
+        ));
+        if let Some(prompt_hint) = &agent_type.agent_constructor.prompt_hint {
+            markdown.push_str(&format!("\nPrompt hint: {prompt_hint}\n"));
+        }
+
+        if !agent_type.methods.is_empty() {
+            markdown.push_str("\n| Method | Parameters | Result | Description |\n");
+            markdown.push_str("|---|---|---|---|\n");
+            for method in &agent_type.methods {
+                markdown.push_str(&format!(
+                    "| `{}` | `{}` | `{}` | {} |\n",
+                    method.name,
+                    format_schema(&method.input_schema),
+                    format_schema(&method.output_schema),
+                    method.description.replace('|', "\\|").replace('\n', " ")
+                ));
+            }
+        }
+
+        if !agent_type.requires.is_empty() {
+            markdown.push_str("\nCalls:\n\n");
+            for dependency in &agent_type.requires {
+                let methods = dependency
+                    .methods
+                    .iter()
+                    .map(|method| format!("`{}`", method.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if methods.is_empty() {
+                    markdown.push_str(&format!("- {}\n", dependency.agent_name));
+                } else {
+                    markdown.push_str(&format!("- {}: {methods}\n", dependency.agent_name));
+                }
+            }
+        }
+    }
+
+    markdown
+}
+
+pub fn format_schema(schema: &DataSchema) -> String {
+    match schema {
+        DataSchema::Structured { parameters } => parameters
+            .iter()
+            .map(|parameter| match parameter {
+                ParameterType::Wit(typ) => format_type(typ),
+                ParameterType::Text(text_type) => format_text_type(text_type),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        DataSchema::Multimodal { .. } => "multimodal".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_definition_extractor_prototype::model::{
+        AgentConstructor, AgentDependency, AgentMethod, TextFormat, TextType,
+    };
+    use golem_wasm_ast::analysis::analysed_type::{list, str, u32};
+
+    const EXPECTED: &str = r#"# Agents
+
+## weather-agent
+
+Forecasts the weather
+
+Version: 2
+
+Constructor: `weather-agent(string)`
+
+Prompt hint: Give a city
+
+| Method | Parameters | Result | Description |
+|---|---|---|---|
+| `get-forecast` | `string, u32` | `list<string>` | Forecast \| next days in the city |
+| `summarize` | `text<en, max 4000, markdown>` | `string` |  |
+| `describe` | `multimodal` | `string` | Describes a picture |
+
+Calls:
+
+- geo
+- search: `search`, `fetch`
+
+## planner
+
+Version: 1
+
+Constructor: `planner()`
+"#;
+
+    fn schema(parameters: Vec<ParameterType>) -> DataSchema {
+        DataSchema::Structured { parameters }
+    }
+
+    fn method(name: &str, description: &str, input_schema: DataSchema) -> AgentMethod {
+        AgentMethod {
+            name: name.to_string(),
+            description: description.to_string(),
+            prompt_hint: None,
+            input_schema,
+            output_schema: schema(vec![ParameterType::Wit(str())]),
+        }
+    }
+
+    fn constructor(input_schema: DataSchema, prompt_hint: Option<&str>) -> AgentConstructor {
+        AgentConstructor {
+            name: None,
+            description: String::new(),
+            prompt_hint: prompt_hint.map(|hint| hint.to_string()),
+            input_schema,
+        }
+    }
+
+    #[test]
+    fn renders_the_catalogue() {
+        let mut get_forecast = method(
+            "get-forecast",
+            "Forecast | next days\nin the city",
+            schema(vec![ParameterType::Wit(str()), ParameterType::Wit(u32())]),
+        );
+        get_forecast.output_schema = schema(vec![ParameterType::Wit(list(str()))]);
+
+        let agent_types = vec![
+            AgentType {
+                type_name: "weather-agent".to_string(),
+                description: "Forecasts the weather".to_string(),
+                version: 2,
+                agent_constructor: constructor(
+                    schema(vec![ParameterType::Wit(str())]),
+                    Some("Give a city"),
+                ),
+                methods: vec![
+                    get_forecast,
+                    method(
+                        "summarize",
+                        "",
+                        schema(vec![ParameterType::Text(TextType {
+                            language_code: "en".to_string(),
+                            max_length: Some(4000),
+                            format: TextFormat::Markdown,
+                        })]),
+                    ),
+                    method(
+                        "describe",
+                        "Describes a picture",
+                        DataSchema::Multimodal { text: None },
+                    ),
+                ],
+                requires: vec![
+                    AgentDependency {
+                        agent_name: "geo".to_string(),
+                        methods: vec![],
+                    },
+                    AgentDependency {
+                        agent_name: "search".to_string(),
+                        methods: vec![
+                            method("search", "", schema(vec![])),
+                            method("fetch", "", schema(vec![])),
+                        ],
+                    },
+                ],
+            },
+            AgentType {
+                type_name: "planner".to_string(),
+                description: String::new(),
+                version: 1,
+                agent_constructor: constructor(schema(vec![]), None),
+                methods: vec![],
+                requires: vec![],
+            },
+        ];
+
+        assert_eq!(render(&agent_types), EXPECTED);
+    }
+
+    #[test]
+    fn renders_no_agents() {
+        assert_eq!(render(&[]), "# Agents\n");
+    }
+}
@@ -9,8 +9,39 @@ use std::path::Path;
 
 
 #[proc_macro_attribute]
-pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
+pub fn agent_definition(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    // The version of the agent type, to be bumped when the state of its agents changes, e.g. `#[agent_definition(version = 2)]`
+    let mut version = 1u32;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("version") {
+            let lit: syn::LitInt = meta.value()?.parse()?;
+            version = lit.base10_parse()?;
+            Ok(())
+        } else {
+            Err(meta.error("expected `version = <number>`"))
+        }
+    });
+    parse_macro_input!(attrs with attr_parser);
+
     let tr = syn::parse_macro_input!(item as syn::ItemTrait);
+
+    for item in &tr.items {
+        if let syn::TraitItem::Fn(method) = item {
+            if let Err(err) = check_result_type(&method.sig) {
+                return err.to_compile_error().into();
+            }
+        }
+    }
+
+    let agent_type = get_agent_type(&tr, version);
+
+    let mut tr = tr;
+    for item in tr.items.iter_mut() {
+        if let syn::TraitItem::Fn(method) = item {
+            strip_text_attributes(&mut method.sig);
+        }
+    }
+
     let generics = &tr.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
@@ -20,7 +51,31 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
     let fn_suffix = &tr_name.to_string().to_lowercase();
     let fn_name = format_ident!("register_generic_agent_type_{}", fn_suffix); // may be ctor is not required. But works now
 
-    let agent_type = get_agent_type(&tr);
+    let method_parameter_names = tr.items.iter().filter_map(|item| {
+        if let syn::TraitItem::Fn(method) = item {
+            let method_name = to_kebab_case(&method.sig.ident.to_string());
+            let parameter_names = method.sig.inputs.iter().filter_map(|arg| {
+                if let syn::FnArg::Typed(pat_type) = arg {
+                    if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                        if !is_injected_parameter(&pat_type.ty) {
+                            return Some(pat_ident.ident.to_string());
+                        }
+                    }
+                }
+                None
+            });
+
+            Some(quote! {
+                golem_agentic::agent_registry::register_method_parameter_names(
+                    #tr_name_str_kebab.to_string(),
+                    #method_name.to_string(),
+                    vec![#(#parameter_names.to_string()),*]
+                );
+            })
+        } else {
+            None
+        }
+    });
 
     let register_fn = quote! {
         #[::ctor::ctor]
@@ -29,6 +84,8 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                #tr_name_str_kebab.to_string(),
                 #agent_type
             );
+
+            #(#method_parameter_names)*
         }
     };
 
@@ -109,7 +166,16 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                 }
             };
 
-            let inputs: Vec<_> = method.sig.inputs.iter().collect();
+            // Injected parameters are provided by the agent itself, and never passed by the caller
+            let inputs: Vec<_> = method
+                .sig
+                .inputs
+                .iter()
+                .filter(|arg| match arg {
+                    syn::FnArg::Typed(pat_type) => !is_injected_parameter(&pat_type.ty),
+                    syn::FnArg::Receiver(_) => true,
+                })
+                .collect();
 
             let input_idents: Vec<_> = method
                 .sig
@@ -117,7 +183,9 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                 .iter()
                 .filter_map(|arg| {
                     if let syn::FnArg::Typed(pat_type) = arg {
-                        if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                        if is_injected_parameter(&pat_type.ty) {
+                            None
+                        } else if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
                             Some(pat_ident.ident.clone())
                         } else {
                             None
@@ -137,8 +205,15 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                 syn::ReturnType::Default => quote! { () },
             };
 
+            let invoke_method_name = format_ident!("invoke_{}", method_name);
+
             Some(quote! {
-                pub async fn #method_name(#(#inputs),*) -> #return_type {
+                pub async fn #method_name(#(#inputs),*) -> Result<#return_type, String> {
+                    self.#invoke_method_name(#(#input_idents),*)
+                }
+
+                #[doc(hidden)]
+                pub fn #invoke_method_name(#(#inputs),*) -> Result<#return_type, String> {
                     let rpc = golem_wasm_rpc::WasmRpc::new(&self.worker_id);
                     let mut inputs = vec![
                         golem_wasm_rpc::WitValue::from(self.handle.clone()),
@@ -151,40 +226,27 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                        inputs.push(arg_wit_value);
                     }
 
-                    let result: golem_wasm_rpc::WitValue = rpc.invoke_and_await(
-                        #wrapped_component_method_name,
-                        inputs.as_slice()
-                    ).map_err(|e| format!("Failed to call agent.invoke with inputs {:?}. {}", inputs, e)).expect(
-                        "Failed to get agent info"
-                    );
-
-                    let first_value = match  golem_wasm_rpc::Value::from(result) {
-                        golem_wasm_rpc::Value::Tuple(values) => {
-                            let value = values[0].clone();
-                            value
-                        }
-                        _ => {
-                            panic!("Expected agent.invoke to return a tuple");
-                        }
-                    };
-
-                    match first_value {
-                        golem_wasm_rpc::Value::Variant{ case_idx, case_value } => {
-                            if case_idx == 2 {
-                                let value: golem_wasm_rpc::Value = case_value.unwrap().as_ref().clone();
-                                let result: #return_type = golem_agentic::FromValue::from_value(value.clone()).expect(
-                                  format!("Failed to convert value {:?} to expected type", value).as_str()
-                                );
-
-                                result
-                            } else {
-                                panic!("Failed to invoke method")
+                    let result: golem_wasm_rpc::WitValue = ::golem_agentic::call_options::with_call_options(
+                        &self.options,
+                        &self.worker_id.worker_name,
+                        #method_name_str_kebab,
+                        || rpc.invoke_and_await(
+                            #wrapped_component_method_name,
+                            inputs.as_slice()
+                        )
+                    ).map_err(|e| format!("Failed to call {} with inputs {:?}: {}", #method_name_str_kebab, inputs, e))?;
+
+                    match golem_wasm_rpc::Value::from(result) {
+                        golem_wasm_rpc::Value::Tuple(mut values) if !values.is_empty() => match values.remove(0) {
+                            // The `emit` case of the status update
+                            golem_wasm_rpc::Value::Variant { case_idx: 2, case_value: Some(value) } => {
+                                golem_agentic::FromValue::from_value(*value.clone()).map_err(|e| {
+                                    format!("Failed to convert the result {:?} of {}: {}", value, #method_name_str_kebab, e)
+                                })
                             }
-                        }
-
-                        _ => {
-                            panic!("Expected agent.invoke to return a tuple, but got");
-                        }
+                            other => Err(format!("{} did not return a result: {:?}", #method_name_str_kebab, other)),
+                        },
+                        other => Err(format!("Expected agent.invoke to return a tuple, but got {:?}", other)),
                     }
                 }
             })
@@ -193,15 +255,138 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
         }
     });
 
+    // The remote client implements the agent trait itself, so it can be injected into agents
+    // that depend on the trait (see `AgentResolution`). Not possible for traits with supertraits or generics.
+    // Unlike the methods of the client, the trait methods panic when a call fails.
+    let implements_agent_trait = tr.supertraits.is_empty()
+        && tr.generics.params.is_empty()
+        && tr.items.iter().all(|item| match item {
+            syn::TraitItem::Fn(method) => matches!(
+                method.sig.inputs.first(),
+                Some(syn::FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none()
+            ),
+            _ => false,
+        });
+
+    let agent_trait_impl = if implements_agent_trait {
+        let trait_methods = tr.items.iter().filter_map(|item| {
+            if let syn::TraitItem::Fn(method) = item {
+                let sig = &method.sig;
+                let invoke_method_name = format_ident!("invoke_{}", sig.ident);
+                let input_idents = sig.inputs.iter().filter_map(|arg| match arg {
+                    syn::FnArg::Typed(pat_type) if !is_injected_parameter(&pat_type.ty) => {
+                        match &*pat_type.pat {
+                            syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                });
+
+                let method_name_str = sig.ident.to_string();
+
+                // The agent trait has no room for errors, so a failed call panics here.
+                // Callers handling failures call the methods of the client instead, which return them
+                Some(quote! {
+                    #[allow(unused_variables)]
+                    #sig {
+                        self.#invoke_method_name(#(#input_idents),*)
+                            .unwrap_or_else(|error| panic!("Failed to call {}: {}", #method_name_str, error))
+                    }
+                })
+            } else {
+                None
+            }
+        });
+
+        quote! {
+            impl #tr_name for #remote_trait_name {
+                #(#trait_methods)*
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let remote_client = quote! {
+        #[derive(Clone)]
         pub struct #remote_trait_name #impl_generics {
             handle: golem_wasm_rpc::Value,
             worker_id: golem_wasm_rpc::WorkerId,
+            options: ::golem_agentic::call_options::CallOptions,
+        }
+
+        impl ::golem_agentic::agent::RemoteAgent for #remote_trait_name {
+            fn agent_type_name() -> &'static str {
+                #tr_name_str_kebab
+            }
+
+            fn create(params: Vec<golem_wasm_rpc::WitValue>) -> Result<Self, String> {
+                Self::create_with_params(params)
+            }
+
+            fn connect(agent_id: &str) -> Result<Self, String> {
+                Self::connect_agent(agent_id)
+            }
+
+            fn agent_id(&self) -> Result<String, String> {
+                let rpc = golem_wasm_rpc::WasmRpc::new(&self.worker_id);
+                let result = rpc.invoke_and_await(
+                    "golem:agentic-guest/guest.{[method]agent.get-id}",
+                    &[golem_wasm_rpc::WitValue::from(self.handle.clone())]
+                ).map_err(|e| format!("Failed to invoke get-id: {}", e))?;
+
+                match golem_wasm_rpc::Value::from(result) {
+                    golem_wasm_rpc::Value::Tuple(values) => match values.first() {
+                        Some(golem_wasm_rpc::Value::String(agent_id)) => Ok(agent_id.clone()),
+                        other => Err(format!("Expected get-id to return a string, but got {:?}", other)),
+                    },
+                    other => Err(format!("Expected get-id to return a tuple, but got {:?}", other)),
+                }
+            }
+
+            fn invoke_method(
+                &self,
+                method_name: &str,
+                input: Vec<golem_wasm_rpc::WitValue>,
+            ) -> Result<golem_wasm_rpc::Value, String> {
+                let rpc = golem_wasm_rpc::WasmRpc::new(&self.worker_id);
+                let mut inputs = vec![golem_wasm_rpc::WitValue::from(self.handle.clone())];
+                inputs.extend(input);
+
+                let result = ::golem_agentic::call_options::with_call_options(
+                    &self.options,
+                    &self.worker_id.worker_name,
+                    method_name,
+                    || rpc.invoke_and_await(
+                        &format!("golem:simulated-agentic/simulated-agent.{{[method]{}.{}}}", #tr_name_str_kebab, method_name),
+                        inputs.as_slice()
+                    )
+                ).map_err(|e| format!("Failed to invoke {}: {}", method_name, e))?;
+
+                match golem_wasm_rpc::Value::from(result) {
+                    golem_wasm_rpc::Value::Tuple(mut values) if !values.is_empty() => match values.remove(0) {
+                        golem_wasm_rpc::Value::Variant { case_idx: 2, case_value: Some(value) } => Ok(*value),
+                        other => Err(format!("Method {} did not return a result: {:?}", method_name, other)),
+                    },
+                    other => Err(format!("Expected agent.invoke to return a tuple, but got {:?}", other)),
+                }
+            }
         }
 
+        #agent_trait_impl
+
         impl #remote_trait_name {
             pub fn new(#(#constructor_params_decl),*) -> Result<Self, String> {
-                let current_component_id_opt = ::golem_agentic::bindings::golem::api::host::get_agent_component(#tr_name_str_kebab);
+                let input_args = vec![
+                    #(#constructor_params_wit),*
+                ];
+
+                Self::create_with_params(input_args)
+            }
+
+            pub fn create_with_params(input_args: Vec<golem_wasm_rpc::WitValue>) -> Result<Self, String> {
+                let current_component_id_opt = ::golem_agentic::host::agent_host().agent_component(#tr_name_str_kebab);
                 let current_component_id = match current_component_id_opt {
                     Some(id) => id,
                     None => return Err(format!("Failed to get current component ID for agent type: {}", #tr_name_str_kebab)),
@@ -211,12 +396,8 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                 let type_name = golem_wasm_rpc::Value::String(#agent_type.type_name.to_string());
                 let type_name_wit_value = &[golem_wasm_rpc::WitValue::from(type_name.clone())];
 
-                 let input_args = vec![
-                    #(#constructor_params_wit),*
-                ];
-
                 let agent_handle_in_vec = rpc.invoke_and_await(
-                    "golem:simulated-agentic/simulated-agent.{weather-agent.new}",
+                    &format!("golem:simulated-agentic/simulated-agent.{{{}.new}}", #tr_name_str_kebab),
                     input_args.as_slice()
                 ).map_err(|e| format!("Failed to invoke get-agent: {}", e))?;
 
@@ -237,7 +418,11 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                             }
                         };
 
-                        Ok(Self { handle: handle.clone(), worker_id: golem_wasm_rpc::WorkerId { component_id: current_component_id, worker_name: worker_name } })
+                        Ok(Self {
+                            handle: handle.clone(),
+                            worker_id: golem_wasm_rpc::WorkerId { component_id: current_component_id, worker_name: worker_name },
+                            options: ::golem_agentic::call_options::CallOptions::default(),
+                        })
                     }
                     _ => {
                         Err(format!("Expected agent_info to be a tuple, but got: {:?}", value))
@@ -249,7 +434,7 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
             pub fn connect_agent(agent_id: &str) -> Result<Self, String> {
                 let agent_id_cloned = agent_id.to_string();
 
-                let current_component_id = ::golem_agentic::bindings::golem::api::host::get_self_metadata().worker_id.component_id;
+                let current_component_id = ::golem_agentic::host::agent_host().component_id();
                 let agent_info = golem_agentic::agent::parse_agent_id(&agent_id);
 
                 let worker_name = match agent_info {
@@ -312,13 +497,22 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
                    }
                };
 
-                Ok(Self { handle: handle, worker_id: worker_id })
+                Ok(Self { handle: handle, worker_id: worker_id, options: ::golem_agentic::call_options::CallOptions::default() })
             }
 
             pub fn get_container_id(&self) -> golem_wasm_rpc::WorkerId {
                 self.worker_id.clone()
             }
 
+            // A client making its calls with the given options, e.g. an idempotency key, see `CallOptions`
+            pub fn with_options(&self, options: ::golem_agentic::call_options::CallOptions) -> Self {
+                Self {
+                    handle: self.handle.clone(),
+                    worker_id: self.worker_id.clone(),
+                    options,
+                }
+            }
+
             #(#method_impls)*
         }
     };
@@ -332,7 +526,176 @@ pub fn agent_definition(_attrs: TokenStream, item: TokenStream) -> TokenStream {
     result.into()
 }
 
-fn get_agent_type(tr: &syn::ItemTrait) -> proc_macro2::TokenStream {
+// `Text` is advertised as a text parameter, with the constraints given in its `#[text(...)]` attribute,
+// and everything else by its WIT type
+fn get_parameter_type(ty: &Type, attrs: &[syn::Attribute]) -> proc_macro2::TokenStream {
+    if !is_text_type(ty) {
+        if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("text")) {
+            return syn::Error::new_spanned(attr, "`#[text(...)]` is only allowed on `Text` parameters")
+                .to_compile_error();
+        }
+
+        return quote! {
+            ::golem_agentic::bindings::golem::agent::common::ParameterType::Wit(
+                <#ty as ::golem_agentic::AgentArg>::get_wit_type()
+            )
+        };
+    }
+
+    let mut language = String::new();
+    let mut max_length: Option<u32> = None;
+    let mut format = format_ident!("Plain");
+
+    for attr in attrs {
+        if attr.path().is_ident("text") {
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("language") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    if !is_language_code(&lit.value()) {
+                        return Err(syn::Error::new_spanned(
+                            &lit,
+                            "expected `language` to be a language code, e.g. \"en\" or \"pt-BR\"",
+                        ));
+                    }
+                    language = lit.value();
+                    Ok(())
+                } else if meta.path.is_ident("max_length") {
+                    let lit: syn::LitInt = meta.value()?.parse()?;
+                    max_length = Some(lit.base10_parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("format") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    format = match lit.value().as_str() {
+                        "plain" => format_ident!("Plain"),
+                        "markdown" => format_ident!("Markdown"),
+                        "json" => format_ident!("Json"),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                &lit,
+                                "expected `format` to be one of \"plain\", \"markdown\", \"json\"",
+                            ))
+                        }
+                    };
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `language = \"...\"`, `max_length = ...` or `format = \"...\"`"))
+                }
+            });
+
+            if let Err(err) = result {
+                return err.to_compile_error();
+            }
+        }
+    }
+
+    let max_length = match max_length {
+        Some(max_length) => quote! { Some(#max_length) },
+        None => quote! { None },
+    };
+
+    // Fails to compile if the `Text` in scope is not the one of golem-agentic
+    quote! {
+        ::golem_agentic::text::text_parameter::<#ty>(
+            ::golem_agentic::text::text_type(
+                #language,
+                #max_length,
+                ::golem_agentic::bindings::golem::agent::common::TextFormat::#format
+            )
+        )
+    }
+}
+
+// A language code such as `en`, `pt-BR` or `zh-Hant`
+fn is_language_code(code: &str) -> bool {
+    let mut subtags = code.split('-');
+
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+// `#[text(...)]` only exists for the agent definition, and shouldn't be left for the compiler
+fn strip_text_attributes(sig: &mut syn::Signature) {
+    for input in sig.inputs.iter_mut() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            pat_type.attrs.retain(|attr| !attr.path().is_ident("text"));
+        }
+    }
+}
+
+// `Text`, `golem_agentic::Text` or `golem_agentic::text::Text`. Other paths ending in `Text`, e.g. `my_module::Text`,
+// are other types, passed by their WIT type
+fn is_text_type(ty: &Type) -> bool {
+    is_golem_agentic_type(ty, "text", "Text")
+}
+
+// `<name>`, `golem_agentic::<name>` or `golem_agentic::<module>::<name>`, without type arguments
+fn is_golem_agentic_type(ty: &Type, module: &str, name: &str) -> bool {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segments = type_path
+                .path
+                .segments
+                .iter()
+                .map(|seg| (seg.ident.to_string(), seg.arguments.is_empty()))
+                .collect::<Vec<_>>();
+
+            segments.iter().all(|(_, no_arguments)| *no_arguments)
+                && match segments
+                    .iter()
+                    .map(|(ident, _)| ident.as_str())
+                    .collect::<Vec<_>>()
+                    .as_slice()
+                {
+                    [ident] | ["golem_agentic", ident] => *ident == name,
+                    ["golem_agentic", in_module, ident] => *in_module == module && *ident == name,
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+// `String` or `std::string::String`
+fn is_string_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segments = type_path
+                .path
+                .segments
+                .iter()
+                .map(|seg| seg.ident.to_string())
+                .collect::<Vec<_>>();
+
+            matches!(
+                segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice(),
+                ["String"] | ["std", "string", "String"] | ["alloc", "string", "String"]
+            )
+        }
+        _ => false,
+    }
+}
+
+// The result of an agent method is emitted as text (see `StatusUpdate::Emit`) and decoded from it by the remote clients,
+// so methods return `String` or `Text`
+fn check_result_type(sig: &syn::Signature) -> Result<(), syn::Error> {
+    match &sig.output {
+        syn::ReturnType::Type(_, ty) if is_string_type(ty) || is_text_type(ty) => Ok(()),
+        syn::ReturnType::Type(_, ty) => Err(syn::Error::new_spanned(
+            ty,
+            "Agent methods return String or Text, as their results are emitted as text",
+        )),
+        syn::ReturnType::Default => Err(syn::Error::new_spanned(
+            &sig.ident,
+            "Agent methods return String or Text, as their results are emitted as text",
+        )),
+    }
+}
+
+fn get_agent_type(tr: &syn::ItemTrait, version: u32) -> proc_macro2::TokenStream {
     let type_name = to_kebab_case(&tr.ident.to_string());
 
     let methods = tr.items.iter().filter_map(|item| {
@@ -368,12 +731,11 @@ fn get_agent_type(tr: &syn::ItemTrait) -> proc_macro2::TokenStream {
             if let syn::TraitItem::Fn(trait_fn) = item {
                 for input in &trait_fn.sig.inputs {
                     if let syn::FnArg::Typed(pat_type) = input {
-                        let ty = &pat_type.ty;
-                        parameter_types.push(quote! {
-                            ::golem_agentic::bindings::golem::agent::common::ParameterType::Wit(
-                                <#ty as ::golem_agentic::AgentArg>::get_wit_type()
-                            )
-                        });
+                        if is_injected_parameter(&pat_type.ty) {
+                            continue;
+                        }
+
+                        parameter_types.push(get_parameter_type(&pat_type.ty, &pat_type.attrs));
                     }
                 }
 
@@ -381,11 +743,7 @@ fn get_agent_type(tr: &syn::ItemTrait) -> proc_macro2::TokenStream {
                 match &trait_fn.sig.output {
                     syn::ReturnType::Default => (),
                     syn::ReturnType::Type(_, ty) => {
-                        result_type.push(quote! {
-                            ::golem_agentic::bindings::golem::agent::common::ParameterType::Wit(
-                                <#ty as ::golem_agentic::AgentArg>::get_wit_type()
-                            )
-                        });
+                        result_type.push(get_parameter_type(ty, &[]));
                     }
                 };
             }
@@ -418,6 +776,7 @@ fn get_agent_type(tr: &syn::ItemTrait) -> proc_macro2::TokenStream {
         golem_agentic::agent_registry::GenericAgentType {
             type_name: #type_name.to_string(),
             description: "".to_string(),
+            version: #version,
             methods: vec![#(#methods),*],
             requires: vec![]
         }
@@ -425,12 +784,30 @@ fn get_agent_type(tr: &syn::ItemTrait) -> proc_macro2::TokenStream {
 }
 
 #[proc_macro_attribute]
-pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStream {
+pub fn agent_implementation(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    // With `#[agent_implementation(state)]` the agent keeps its `AgentState` in the snapshots of the worker
+    let mut has_state = false;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("state") {
+            has_state = true;
+            Ok(())
+        } else {
+            Err(meta.error("expected `state`"))
+        }
+    });
+    parse_macro_input!(attrs with attr_parser);
+
     let item_cloned = item.clone();
-    let impl_block = syn::parse_macro_input!(item_cloned as syn::ItemImpl);
+    let mut impl_block = syn::parse_macro_input!(item_cloned as syn::ItemImpl);
+
+    for item in impl_block.items.iter_mut() {
+        if let syn::ImplItem::Fn(method) = item {
+            strip_text_attributes(&mut method.sig);
+        }
+    }
 
     let generics = &impl_block.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
 
     let trait_name = if let Some((_bang, path, _for_token)) = &impl_block.trait_ {
         &path.segments.last().unwrap().ident
@@ -448,20 +825,27 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
 
     let self_ty = &impl_block.self_ty;
 
+    // Agents generic over the agents they depend on are constructed with the remote clients of these agents
+    let concrete_self_ty = concrete_agent_type(self_ty, generics);
+
     let mut match_arms = Vec::new();
 
     for item in &impl_block.items {
         if let syn::ImplItem::Fn(method) = item {
+            if let Err(err) = check_result_type(&method.sig) {
+                return err.to_compile_error().into();
+            }
+
             let method_name = to_kebab_case(&method.sig.ident.to_string());
 
-            let param_idents: Vec<_> = method
+            let params: Vec<_> = method
                 .sig
                 .inputs
                 .iter()
                 .filter_map(|arg| {
                     if let syn::FnArg::Typed(pat_ty) = arg {
                         if let syn::Pat::Ident(pat_ident) = &*pat_ty.pat {
-                            Some(pat_ident.ident.clone())
+                            Some((pat_ident.ident.clone(), is_injected_parameter(&pat_ty.ty)))
                         } else {
                             None
                         }
@@ -471,36 +855,68 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
                 })
                 .collect();
 
-            let extraction = param_idents.iter().enumerate().map(|(i, ident)| {
+            let param_idents: Vec<_> = params.iter().map(|(ident, _)| ident.clone()).collect();
+
+            let mut input_index = 0usize;
+
+            let extraction: Vec<_> = params.iter().map(|(ident, is_injected)| {
+                if *is_injected {
+                    return quote! {
+                        let #ident = ::golem_agentic::status::ProgressReporter::current();
+                    };
+                }
+
+                let i = input_index;
+                input_index += 1;
+
                 quote! {
                  let #ident = ::golem_agentic::AgentArg::from_wit_value(input
                   .get(#i)
                   .expect("missing argument")
                   .clone()).expect("internal error, failed to convert wit value to expected type");
                 }
-            });
+            }).collect();
 
             let ident = &method.sig.ident;
 
             match_arms.push(quote! {
                 #method_name => {
                     #(#extraction)*
-                    let result: String = self.#ident(#(#param_idents),*);
+                    let result = self.#ident(#(#param_idents),*);
                     ::golem_agentic::bindings::exports::golem::agent::guest::StatusUpdate::Emit(result.to_string())
                 }
             });
         }
     }
 
+    let snapshot_impl = if has_state {
+        quote! {
+            fn save_snapshot(&self) -> Result<Option<Vec<u8>>, String> {
+                ::golem_agentic::versioning::AgentState::save_state(self).map(Some)
+            }
+
+            fn load_snapshot(&self, from_version: u32, state: Vec<u8>) -> Result<(), String> {
+                ::golem_agentic::versioning::load_agent_state(
+                    self,
+                    self.get_definition().version,
+                    from_version,
+                    state
+                )
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let base_agent_impl = quote! {
 
-        impl #impl_generics golem_agentic::agent::GetAgentId for #self_ty #ty_generics #where_clause {
+        impl #impl_generics golem_agentic::agent::GetAgentId for #self_ty #where_clause {
            fn get_agent_id() -> String {
                 golem_agentic::agent_instance_registry::create_agent_id(#trait_name_str.to_string())
            }
         }
 
-        impl #impl_generics golem_agentic::agent::Agent for #self_ty #ty_generics #where_clause {
+        impl #impl_generics golem_agentic::agent::Agent for #self_ty #where_clause {
             fn get_id(&self) -> String {
                 self.agent_id.clone()
             }
@@ -519,6 +935,8 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
                 golem_agentic::agent_registry::get_agent_def_by_name(&#trait_name_str)
                     .expect("Agent definition not found")
             }
+
+            #snapshot_impl
         }
     };
 
@@ -528,17 +946,23 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
         struct #initiator;
 
         impl golem_agentic::agent_registry::AgentInitiator for #initiator {
-            fn initiate(&self, params: Vec<golem_wasm_rpc::WitValue>) -> golem_agentic::ResolvedAgent {
+            fn initiate(&self, params: Vec<golem_wasm_rpc::WitValue>) -> Result<golem_agentic::ResolvedAgent, String> {
 
                  use golem_agentic::agent::{GetAgentId};
 
-                 let agent_id = #self_ty::get_agent_id();
+                 let agent_id = <#concrete_self_ty as GetAgentId>::get_agent_id();
+
+                 self.initiate_with_id(agent_id, params)
+            }
+
+            fn initiate_with_id(&self, agent_id: String, params: Vec<golem_wasm_rpc::WitValue>) -> Result<golem_agentic::ResolvedAgent, String> {
+                ::golem_agentic::agent::replace_agent(&agent_id);
 
                 let agent = ::std::sync::Arc::new(
-                    <#self_ty as ::golem_agentic::AgentConstruct>::construct_from_params(
-                        params,
+                    <#concrete_self_ty as ::golem_agentic::AgentConstruct>::construct_from_params(
+                        params.clone(),
                         agent_id.clone()
-                    )
+                    )?
                 );
 
                  let resolved_agent = golem_agentic::ResolvedAgent {
@@ -546,23 +970,27 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
                       agent_id: agent_id.clone(),
                  };
 
-                 let agent =
-                     golem_agentic::bindings::exports::golem::agent::guest::Agent::new(resolved_agent.clone());
-
-                 let handle = agent.handle();
-
                  golem_agentic::agent_registry::register_agent_instance(
                     golem_agentic::agent_registry::AgentId(agent_id.clone()),
                     #trait_name_str.to_string(),
-                    agent,
-                    resolved_agent.clone()
+                    resolved_agent.clone(),
+                    params
                 );
 
-                 resolved_agent
+                 Ok(resolved_agent)
             }
         }
     };
 
+    let agent_usages = match dirs::cache_dir() {
+        Some(cache_dir) => collect_agent_usages(&impl_block, &cache_dir.join("golem-agentic/agent-traits")),
+        None => std::collections::BTreeMap::new(),
+    };
+    let remote_dependencies = agent_usages.keys().collect::<Vec<_>>();
+    let called_methods = agent_usages.iter().map(|(dependency, methods)| {
+        quote! { (#dependency.to_string(), vec![#(#methods.to_string()),*]) }
+    });
+
     let fn_suffix = &trait_name_str_raw.to_string().to_lowercase();
     let fn_name = format_ident!("register_agent_type_{}", fn_suffix); // may be ctor is not required. But works now
 
@@ -586,13 +1014,12 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
                 }
             };
 
-            let agent_params = <#self_ty #ty_generics as ::golem_agentic::AgentConstruct>::get_params();
+            let agent_params = <#concrete_self_ty as ::golem_agentic::AgentConstruct>::get_params();
 
-            let agent_params_as_parameter_types = agent_params.iter().map(|(param_name, wit_type)| {
-                ::golem_agentic::bindings::golem::agent::common::ParameterType::Wit(
-                    wit_type.clone()
-                )
-            }).collect();
+            let agent_params_as_parameter_types = agent_params
+                .into_iter()
+                .map(|(_, parameter_type)| parameter_type)
+                .collect();
 
             let agent_constructor = golem_agentic::bindings::golem::agent::common::AgentConstructor {
                 name: None,
@@ -609,6 +1036,15 @@ pub fn agent_implementation(_attrs: TokenStream, item: TokenStream) -> TokenStre
                #trait_name_str.to_string(),
                 agent_type
             );
+
+            let mut dependencies = <#concrete_self_ty as ::golem_agentic::AgentConstruct>::get_agent_dependencies();
+            dependencies.extend(vec![#(#remote_dependencies.to_string()),*]);
+
+            golem_agentic::agent_registry::register_agent_dependencies(
+                #trait_name_str.to_string(),
+                dependencies,
+                vec![#(#called_methods),*]
+            );
         }
     };
 
@@ -740,7 +1176,7 @@ pub fn derive_agent_arg(input: TokenStream) -> TokenStream {
 }
 
 
-#[proc_macro_derive(AgentConstruct)]
+#[proc_macro_derive(AgentConstruct, attributes(agent, text))]
 pub fn derive_agent_construct(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let struct_name = &input.ident;
@@ -772,6 +1208,8 @@ pub fn derive_agent_construct(input: TokenStream) -> TokenStream {
 
     let expanded = generate_impls(
         struct_name,
+        &input.generics,
+        &generic_agent_types,
         construct_assignments,
         construct_fields,
         get_params_entries,
@@ -783,8 +1221,9 @@ pub fn derive_agent_construct(input: TokenStream) -> TokenStream {
 }
 
 
-fn extract_generic_agent_types(input: &DeriveInput, cache_dir: &Path) -> std::collections::HashSet<String> {
-    let mut result = std::collections::HashSet::new();
+// Generic parameters bounded by an agent trait, mapped to the name of that trait
+fn extract_generic_agent_types(input: &DeriveInput, cache_dir: &Path) -> std::collections::HashMap<String, String> {
+    let mut result = std::collections::HashMap::new();
 
     if let Some(generics) = input.generics.params.iter().map(|p| match p {
         syn::GenericParam::Type(ty_param) => Some(ty_param),
@@ -797,7 +1236,7 @@ fn extract_generic_agent_types(input: &DeriveInput, cache_dir: &Path) -> std::co
                     let trait_name = trait_bound.path.segments.last().unwrap().ident.to_string();
                     let marker_file = cache_dir.join(format!("{trait_name}.trait"));
                     if marker_file.exists() {
-                        result.insert(ident.to_string());
+                        result.insert(ident.to_string(), trait_name);
                     }
                 }
             }
@@ -819,7 +1258,7 @@ fn get_named_fields(input: &DeriveInput) -> syn::Result<&syn::punctuated::Punctu
 
 fn extract_constructor_fields(
     fields: &syn::punctuated::Punctuated<syn::Field, syn::token::Comma>,
-    generic_agent_types: &std::collections::HashSet<String>,
+    generic_agent_types: &std::collections::HashMap<String, String>,
 ) -> Vec<(String, String)> {
     fields.iter()
         .filter_map(|f| {
@@ -833,12 +1272,12 @@ fn extract_constructor_fields(
                 syn::Type::Path(type_path) => {
                     // Only simple identifiers like `T`, not `std::vec::Vec<T>`
                     type_path.path.segments.len() == 1 &&
-                        generic_agent_types.contains(&type_path.path.segments[0].ident.to_string())
+                        generic_agent_types.contains_key(&type_path.path.segments[0].ident.to_string())
                 }
                 _ => false
             };
 
-            if is_generic_agent_type {
+            if is_generic_agent_type || is_memory_type(&f.ty) {
                 return None;
             }
 
@@ -866,7 +1305,7 @@ fn write_constructor_metadata(constructor_fields: &[(String, String)], struct_na
 
 fn build_constructor_code(
     fields: &syn::punctuated::Punctuated<syn::Field, syn::token::Comma>,
-    generic_agent_types: &std::collections::HashSet<String>,
+    generic_agent_types: &std::collections::HashMap<String, String>,
 ) -> (
     Vec<proc_macro2::TokenStream>,
     Vec<proc_macro2::TokenStream>,
@@ -885,16 +1324,29 @@ fn build_constructor_code(
         let name = field.ident.as_ref().unwrap();
         let ty = &field.ty;
 
-        let is_agent_dep = matches!(
-            ty,
-            syn::Type::Path(type_path)
-                if type_path.path.segments.last()
-                    .map(|seg| generic_agent_types.contains(&seg.ident.to_string()))
-                    .unwrap_or(false)
-        );
+        let agent_dep = match ty {
+            syn::Type::Path(type_path) => type_path
+                .path
+                .segments
+                .last()
+                .and_then(|seg| generic_agent_types.get(&seg.ident.to_string())),
+            _ => None,
+        };
+
+        if let Some(agent_trait_name) = agent_dep {
+            agent_dependencies.push(to_kebab_case(agent_trait_name));
+
+            // Agents are never passed as constructor parameters, they are resolved instead
+            let resolution = get_agent_resolution(field);
+
+            construct_assignments.push(quote! {
+                let #name: #ty = ::golem_agentic::agent::resolve_remote_agent::<#ty>(&agent_id, #resolution)
+                    .map_err(|error| format!("Failed to resolve the agent of field {}: {}", stringify!(#name), error))?;
+            });
 
-        if is_agent_dep {
-            agent_dependencies.push(name.to_string());
+            construct_fields.push(quote! { #name });
+
+            continue;
         }
 
         if name == "agent_id" {
@@ -902,6 +1354,14 @@ fn build_constructor_code(
             continue;
         }
 
+        // Memories start empty, and are not passed as constructor parameters
+        if is_memory_type(ty) {
+            construct_fields.push(quote! {
+                #name: ::golem_agentic::memory::Memory::new(&agent_id, stringify!(#name))
+            });
+            continue;
+        }
+
         construct_assignments.push(quote! {
             let #name: #ty = <#ty as ::golem_agentic::AgentArg>::from_wit_value(
                 params[#index].clone()
@@ -910,8 +1370,9 @@ fn build_constructor_code(
 
         construct_fields.push(quote! { #name });
 
+        let parameter_type = get_parameter_type(ty, &field.attrs);
         get_params_entries.push(quote! {
-            params.push((stringify!(#name).to_string(), <#ty as ::golem_agentic::AgentArg>::get_wit_type()));
+            params.push((stringify!(#name).to_string(), #parameter_type));
         });
 
         constructor_params_const_entries.push(quote! {
@@ -930,28 +1391,96 @@ fn build_constructor_code(
     )
 }
 
+// `#[agent(new)]` (the default), `#[agent(singleton)]` or `#[agent(connect = "<agent-id>")]`.
+// `new(...)` and `singleton(...)` take the constructor parameters of the agent, e.g. `#[agent(new("Berlin", 7))]`
+fn get_agent_resolution(field: &syn::Field) -> proc_macro2::TokenStream {
+    let mut resolution = quote! { ::golem_agentic::agent::AgentResolution::New(vec![]) };
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("agent") {
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("new") {
+                    let params = parse_constructor_params(&meta)?;
+                    resolution = quote! { ::golem_agentic::agent::AgentResolution::New(#params) };
+                    Ok(())
+                } else if meta.path.is_ident("singleton") {
+                    let params = parse_constructor_params(&meta)?;
+                    resolution = quote! { ::golem_agentic::agent::AgentResolution::Singleton(#params) };
+                    Ok(())
+                } else if meta.path.is_ident("connect") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    resolution = quote! { ::golem_agentic::agent::AgentResolution::Connect(#lit.to_string()) };
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `new`, `new(...)`, `singleton`, `singleton(...)` or `connect = \"<agent-id>\"`"))
+                }
+            });
+
+            if let Err(err) = result {
+                return err.to_compile_error();
+            }
+        }
+    }
+
+    resolution
+}
+
+// The optional `(...)` after `new` or `singleton`, as the values of the constructor parameters
+fn parse_constructor_params(meta: &syn::meta::ParseNestedMeta) -> syn::Result<proc_macro2::TokenStream> {
+    if !meta.input.peek(syn::token::Paren) {
+        return Ok(quote! { vec![] });
+    }
+
+    let content;
+    syn::parenthesized!(content in meta.input);
+    let params = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated(&content)?;
+    let params = params.iter();
+
+    Ok(quote! {
+        vec![#(::golem_agentic::AgentArg::to_value(&(#params))),*]
+    })
+}
+
 fn generate_impls(
     struct_name: &syn::Ident,
+    generics: &syn::Generics,
+    generic_agent_types: &std::collections::HashMap<String, String>,
     construct_assignments: Vec<proc_macro2::TokenStream>,
     construct_fields: Vec<proc_macro2::TokenStream>,
     get_params_entries: Vec<proc_macro2::TokenStream>,
     agent_dependencies: Vec<String>,
     constructor_params_const_entries: Vec<proc_macro2::TokenStream>,
 ) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Agent-typed fields are resolved through the remote client implementing the agent trait
+    let mut construct_generics = generics.clone();
+    for param in generics.type_params() {
+        if generic_agent_types.contains_key(&param.ident.to_string()) {
+            let ident = &param.ident;
+            construct_generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote! { #ident: ::golem_agentic::agent::RemoteAgent });
+        }
+    }
+    let (construct_impl_generics, construct_ty_generics, construct_where_clause) =
+        construct_generics.split_for_impl();
+
     quote! {
-        impl ::golem_agentic::AgentConstruct for #struct_name {
+        impl #construct_impl_generics ::golem_agentic::AgentConstruct for #struct_name #construct_ty_generics #construct_where_clause {
             fn construct_from_params(
                 params: Vec<::golem_wasm_rpc::WitValue>,
                 agent_id: String
-            ) -> Self {
+            ) -> Result<Self, String> {
                 #(#construct_assignments)*
 
-                Self {
+                Ok(Self {
                     #(#construct_fields),*
-                }
+                })
             }
 
-            fn get_params() -> Vec<(String, ::golem_wasm_rpc::WitType)> {
+            fn get_params() -> Vec<(String, ::golem_agentic::bindings::golem::agent::common::ParameterType)> {
                 let mut params = Vec::new();
                 #(#get_params_entries)*
                 params
@@ -962,7 +1491,7 @@ fn generate_impls(
             }
         }
 
-        impl #struct_name {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
             pub const CONSTRUCTOR_PARAMS: &'static [(&'static str, &'static str)] = &[
                 #(#constructor_params_const_entries),*
             ];
@@ -971,6 +1500,260 @@ fn generate_impls(
 }
 
 
+// Replaces the type parameters of the agent implementation (bounded by agent traits)
+// with the `Remote*` clients of their first bound, e.g. `MyAssistant<T>` with `MyAssistant<RemoteWeatherAgent>`
+fn concrete_agent_type(self_ty: &Type, generics: &syn::Generics) -> proc_macro2::TokenStream {
+    let substitutions: std::collections::HashMap<String, proc_macro2::TokenStream> = generics
+        .type_params()
+        .filter_map(|param| {
+            param.bounds.iter().find_map(|bound| match bound {
+                syn::TypeParamBound::Trait(trait_bound) => {
+                    let mut path = trait_bound.path.clone();
+                    let last = path.segments.last_mut()?;
+                    last.ident = format_ident!("Remote{}", last.ident);
+                    Some((param.ident.to_string(), quote! { #path }))
+                }
+                _ => None,
+            })
+        })
+        .collect();
+
+    fn substitute(
+        tokens: proc_macro2::TokenStream,
+        substitutions: &std::collections::HashMap<String, proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                proc_macro2::TokenTree::Ident(ident) => match substitutions.get(&ident.to_string()) {
+                    Some(replacement) => replacement.clone(),
+                    None => quote! { #ident },
+                },
+                proc_macro2::TokenTree::Group(group) => {
+                    let mut replaced = proc_macro2::Group::new(
+                        group.delimiter(),
+                        substitute(group.stream(), substitutions),
+                    );
+                    replaced.set_span(group.span());
+                    quote! { #replaced }
+                }
+                other => quote! { #other },
+            })
+            .collect()
+    }
+
+    substitute(quote! { #self_ty }, &substitutions)
+}
+
+// Finds the agents an implementation talks to, with the methods it calls on each of them, in kebab case as they
+// appear in the agent types. The agents are the type parameters bounded by agent traits, and the `Remote*` clients
+// of agent traits, e.g. `RemoteWeatherAgent`. Agent traits are known by the markers `agent_definition` leaves.
+// A method call is attributed to an agent if its receiver is known to be one: a `Remote*` client
+// created in the expression, a variable or parameter of a `Remote*` or agent-bounded type, or a field of `self`.
+// The types of the fields are not known here, so calls on fields are attributed to all the type parameters,
+// and only kept for the agents having the method once the agent types are registered.
+fn collect_agent_usages(
+    impl_block: &syn::ItemImpl,
+    agent_trait_cache_dir: &Path,
+) -> std::collections::BTreeMap<String, std::collections::BTreeSet<String>> {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use syn::visit::Visit;
+
+    struct AgentUsages<'a> {
+        agent_trait_cache_dir: &'a Path,
+        // Type parameters bounded by agent traits, mapped to the agent type name
+        type_parameters: HashMap<String, String>,
+        // Variables holding agents, innermost scope last
+        scopes: Vec<HashMap<String, Vec<String>>>,
+        usages: BTreeMap<String, BTreeSet<String>>,
+    }
+
+    impl AgentUsages<'_> {
+        fn is_agent_trait(&self, trait_name: &str) -> bool {
+            self.agent_trait_cache_dir
+                .join(format!("{trait_name}.trait"))
+                .exists()
+        }
+
+        // The agent type of a `Remote*` client in the path, e.g. `weather-agent` for `RemoteWeatherAgent::new`
+        fn remote_client(&self, path: &syn::Path) -> Option<String> {
+            path.segments.iter().find_map(|segment| {
+                let trait_name = segment.ident.to_string();
+                let trait_name = trait_name.strip_prefix("Remote")?;
+                self.is_agent_trait(trait_name)
+                    .then(|| to_kebab_case(trait_name))
+            })
+        }
+
+        fn type_agents(&self, ty: &Type) -> Vec<String> {
+            match ty {
+                Type::Reference(reference) => self.type_agents(&reference.elem),
+                Type::Paren(paren) => self.type_agents(&paren.elem),
+                Type::Path(type_path) => {
+                    if let Some(ident) = type_path.path.get_ident() {
+                        if let Some(agent) = self.type_parameters.get(&ident.to_string()) {
+                            return vec![agent.clone()];
+                        }
+                    }
+                    self.remote_client(&type_path.path).into_iter().collect()
+                }
+                _ => vec![],
+            }
+        }
+
+        fn expr_agents(&self, expr: &syn::Expr) -> Vec<String> {
+            match expr {
+                syn::Expr::Path(expr_path) => match expr_path.path.get_ident() {
+                    Some(ident) => self
+                        .scopes
+                        .iter()
+                        .rev()
+                        .find_map(|scope| scope.get(&ident.to_string()))
+                        .cloned()
+                        .unwrap_or_default(),
+                    None => vec![],
+                },
+                syn::Expr::Call(call) => match &*call.func {
+                    syn::Expr::Path(func) => self.remote_client(&func.path).into_iter().collect(),
+                    _ => vec![],
+                },
+                // The client itself, with other options or unwrapped
+                syn::Expr::MethodCall(method_call)
+                    if ["with_options", "clone", "unwrap", "expect"]
+                        .contains(&method_call.method.to_string().as_str()) =>
+                {
+                    self.expr_agents(&method_call.receiver)
+                }
+                syn::Expr::Try(expr_try) => self.expr_agents(&expr_try.expr),
+                syn::Expr::Paren(paren) => self.expr_agents(&paren.expr),
+                syn::Expr::Reference(reference) => self.expr_agents(&reference.expr),
+                syn::Expr::Field(field) if is_self(&field.base) => {
+                    self.type_parameters.values().cloned().collect()
+                }
+                _ => vec![],
+            }
+        }
+
+        fn bind(&mut self, pat: &syn::Pat, agents: Vec<String>) {
+            let ident = match pat {
+                syn::Pat::Ident(pat_ident) => &pat_ident.ident,
+                _ => return,
+            };
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(ident.to_string(), agents);
+            }
+        }
+    }
+
+    impl<'ast> Visit<'ast> for AgentUsages<'_> {
+        fn visit_impl_item_fn(&mut self, method: &'ast syn::ImplItemFn) {
+            self.scopes.push(HashMap::new());
+            for input in &method.sig.inputs {
+                if let syn::FnArg::Typed(pat_type) = input {
+                    let agents = self.type_agents(&pat_type.ty);
+                    self.bind(&pat_type.pat, agents);
+                }
+            }
+            syn::visit::visit_impl_item_fn(self, method);
+            self.scopes.pop();
+        }
+
+        fn visit_block(&mut self, block: &'ast syn::Block) {
+            self.scopes.push(HashMap::new());
+            syn::visit::visit_block(self, block);
+            self.scopes.pop();
+        }
+
+        fn visit_local(&mut self, local: &'ast syn::Local) {
+            syn::visit::visit_local(self, local);
+
+            let (pat, agents) = match &local.pat {
+                syn::Pat::Type(pat_type) => (&*pat_type.pat, self.type_agents(&pat_type.ty)),
+                pat => (
+                    pat,
+                    local
+                        .init
+                        .as_ref()
+                        .map(|init| self.expr_agents(&init.expr))
+                        .unwrap_or_default(),
+                ),
+            };
+            self.bind(pat, agents);
+        }
+
+        fn visit_expr_method_call(&mut self, method_call: &'ast syn::ExprMethodCall) {
+            let method_name = method_call.method.to_string();
+            // The synchronous `invoke_<method>` of a client calls the same method
+            let method_name = method_name.strip_prefix("invoke_").unwrap_or(&method_name);
+
+            let client_methods = ["with_options", "clone", "unwrap", "expect", "invoke_method", "get_container_id"];
+            if !client_methods.contains(&method_name) {
+                for agent in self.expr_agents(&method_call.receiver) {
+                    self.usages
+                        .entry(agent)
+                        .or_default()
+                        .insert(to_kebab_case(method_name));
+                }
+            }
+
+            syn::visit::visit_expr_method_call(self, method_call);
+        }
+
+        fn visit_path(&mut self, path: &'ast syn::Path) {
+            if let Some(agent) = self.remote_client(path) {
+                self.usages.entry(agent).or_default();
+            }
+            syn::visit::visit_path(self, path);
+        }
+    }
+
+    fn is_self(expr: &syn::Expr) -> bool {
+        matches!(expr, syn::Expr::Path(expr_path) if expr_path.path.is_ident("self"))
+    }
+
+    let mut usages = AgentUsages {
+        agent_trait_cache_dir,
+        type_parameters: HashMap::new(),
+        scopes: vec![],
+        usages: BTreeMap::new(),
+    };
+
+    for param in impl_block.generics.type_params() {
+        let agent_trait = param.bounds.iter().find_map(|bound| match bound {
+            syn::TypeParamBound::Trait(trait_bound) => trait_bound
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .filter(|trait_name| usages.is_agent_trait(trait_name)),
+            _ => None,
+        });
+
+        if let Some(agent_trait) = agent_trait {
+            let agent = to_kebab_case(&agent_trait);
+            usages.usages.entry(agent.clone()).or_default();
+            usages.type_parameters.insert(param.ident.to_string(), agent);
+        }
+    }
+
+    usages.visit_item_impl(impl_block);
+    usages.usages
+}
+
+// Parameters of agent methods that are provided by golem-agentic at invocation time,
+// rather than being part of the method's input schema: `ProgressReporter` or `Emitter`,
+// optionally as `golem_agentic::` or `golem_agentic::status::` paths
+fn is_injected_parameter(ty: &Type) -> bool {
+    is_golem_agentic_type(ty, "status", "ProgressReporter")
+        || is_golem_agentic_type(ty, "status", "Emitter")
+}
+
+// `Memory`, `golem_agentic::Memory` or `golem_agentic::memory::Memory`. Other types named `Memory`
+// are constructor parameters
+fn is_memory_type(ty: &Type) -> bool {
+    is_golem_agentic_type(ty, "memory", "Memory")
+}
+
 fn to_kebab_case(s: &str) -> String {
     let mut result = String::new();
 
@@ -989,3 +1772,146 @@ fn to_kebab_case(s: &str) -> String {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    // A cache directory with the markers of the given agent traits
+    fn agent_traits(test_name: &str, trait_names: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("golem-agentic-macros-tests")
+            .join(test_name);
+        std::fs::create_dir_all(&dir).unwrap();
+        for trait_name in trait_names {
+            std::fs::write(dir.join(format!("{trait_name}.trait")), "").unwrap();
+        }
+        dir
+    }
+
+    fn usages(entries: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        entries
+            .iter()
+            .map(|(agent, methods)| {
+                (
+                    agent.to_string(),
+                    methods.iter().map(|method| method.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attributes_calls_to_the_remote_clients_they_are_made_on() {
+        let cache_dir = agent_traits("remote-clients", &["WeatherAgent", "CalendarAgent"]);
+        let impl_block: syn::ItemImpl = syn::parse_quote! {
+            impl Planner for MyPlanner {
+                fn plan(&self, city: String) -> String {
+                    let weather = RemoteWeatherAgent::connect_agent(&self.weather_id).unwrap();
+                    let calendar: RemoteCalendarAgent = RemoteCalendarAgent::new().unwrap();
+                    let forecast = weather.get_forecast(city.clone());
+                    calendar.add_event(forecast.clone());
+                    city.trim().to_string()
+                }
+            }
+        };
+
+        assert_eq!(
+            collect_agent_usages(&impl_block, &cache_dir),
+            usages(&[
+                ("calendar-agent", &["add-event"]),
+                ("weather-agent", &["get-forecast"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn ignores_paths_that_are_not_agent_clients() {
+        let cache_dir = agent_traits("not-agent-clients", &["WeatherAgent"]);
+        let impl_block: syn::ItemImpl = syn::parse_quote! {
+            impl Planner for MyPlanner {
+                fn plan(&self, config: RemoteConfig) -> String {
+                    let agent = <RemoteWeatherAgent as RemoteAgent>::agent_type_name();
+                    config.load(agent)
+                }
+            }
+        };
+
+        assert_eq!(
+            collect_agent_usages(&impl_block, &cache_dir),
+            usages(&[("weather-agent", &[])])
+        );
+    }
+
+    #[test]
+    fn attributes_calls_on_fields_to_the_agent_type_parameters() {
+        let cache_dir = agent_traits("type-parameters", &["WeatherAgent"]);
+        let impl_block: syn::ItemImpl = syn::parse_quote! {
+            impl<T: WeatherAgent> Planner for MyPlanner<T> {
+                fn plan(&self, city: String) -> String {
+                    let forecast = self.weather.get_forecast(city);
+                    self.history.push(forecast.clone());
+                    forecast
+                }
+            }
+        };
+
+        assert_eq!(
+            collect_agent_usages(&impl_block, &cache_dir),
+            usages(&[("weather-agent", &["get-forecast", "push"])])
+        );
+    }
+
+    #[test]
+    fn accepts_only_text_of_golem_agentic_as_text() {
+        assert!(is_text_type(&syn::parse_quote!(Text)));
+        assert!(is_text_type(&syn::parse_quote!(golem_agentic::Text)));
+        assert!(is_text_type(&syn::parse_quote!(::golem_agentic::text::Text)));
+        assert!(!is_text_type(&syn::parse_quote!(my_module::Text)));
+        assert!(!is_text_type(&syn::parse_quote!(Vec<Text>)));
+    }
+
+    #[test]
+    fn accepts_only_memory_of_golem_agentic_as_memory() {
+        assert!(is_memory_type(&syn::parse_quote!(Memory)));
+        assert!(is_memory_type(&syn::parse_quote!(golem_agentic::Memory)));
+        assert!(is_memory_type(&syn::parse_quote!(::golem_agentic::memory::Memory)));
+        assert!(!is_memory_type(&syn::parse_quote!(golem_agentic::text::Memory)));
+        assert!(!is_memory_type(&syn::parse_quote!(gpu::Memory)));
+        assert!(!is_memory_type(&syn::parse_quote!(Arc<Memory>)));
+    }
+
+    #[test]
+    fn injects_only_the_progress_reporters_of_golem_agentic() {
+        assert!(is_injected_parameter(&syn::parse_quote!(ProgressReporter)));
+        assert!(is_injected_parameter(&syn::parse_quote!(Emitter)));
+        assert!(is_injected_parameter(&syn::parse_quote!(golem_agentic::Emitter)));
+        assert!(is_injected_parameter(&syn::parse_quote!(golem_agentic::status::ProgressReporter)));
+        assert!(!is_injected_parameter(&syn::parse_quote!(my_events::Emitter)));
+        assert!(!is_injected_parameter(&syn::parse_quote!(golem_agentic::memory::Emitter)));
+        assert!(!is_injected_parameter(&syn::parse_quote!(Option<ProgressReporter>)));
+    }
+
+    #[test]
+    fn accepts_only_string_and_text_results() {
+        let signature = |sig: syn::TraitItemFn| check_result_type(&sig.sig);
+
+        assert!(signature(syn::parse_quote!(fn greet(&self) -> String;)).is_ok());
+        assert!(signature(syn::parse_quote!(fn greet(&self) -> std::string::String;)).is_ok());
+        assert!(signature(syn::parse_quote!(fn greet(&self) -> golem_agentic::Text;)).is_ok());
+        assert!(signature(syn::parse_quote!(fn count(&self) -> u32;)).is_err());
+        assert!(signature(syn::parse_quote!(fn reset(&self);)).is_err());
+        assert!(signature(syn::parse_quote!(fn greet(&self) -> my_module::String;)).is_err());
+    }
+
+    #[test]
+    fn accepts_language_codes() {
+        assert!(is_language_code("en"));
+        assert!(is_language_code("pt-BR"));
+        assert!(is_language_code("zh-Hant"));
+        assert!(!is_language_code("english"));
+        assert!(!is_language_code("en_US"));
+        assert!(!is_language_code(""));
+    }
+}
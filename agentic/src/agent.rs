@@ -1,6 +1,8 @@
 use crate::bindings::exports::golem::agent::guest::{AgentType, StatusUpdate};
-use golem_wasm_rpc::WitValue;
+use golem_wasm_rpc::{Value, WitValue};
 use crate::AgentConstruct;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
 
 // A simple Agent that every agent abstraction has to extend
 // This is auto implemented when using `agent_implementation` attribute.
@@ -28,6 +30,167 @@ pub trait Agent: Send + Sync {
     fn get_id(&self) -> String;
     fn invoke(&self, method_name: String, input: Vec<WitValue>) -> StatusUpdate;
     fn get_definition(&self) -> AgentType;
+
+    // State to be kept in snapshots, for agents implementing `versioning::AgentState`
+    fn save_snapshot(&self) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+
+    // Restores the state saved by `save_snapshot` in a version `from_version` of the agent
+    fn load_snapshot(&self, from_version: u32, state: Vec<u8>) -> Result<(), String> {
+        let _ = (from_version, state);
+        Ok(())
+    }
+}
+
+// An agent method that is running
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurrentInvocation {
+    pub agent_id: String,
+    pub method_name: String,
+    // Numbers the invocations of the worker, the same when the worker is replayed
+    pub invocation_id: u64,
+}
+
+// The agent methods that are running, the innermost last.
+// Like status updates this is a stack, as an agent method can invoke methods of other agents living in the same component
+static CURRENT_INVOCATIONS: Lazy<Mutex<Vec<CurrentInvocation>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+static LAST_INVOCATION_ID: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
+
+// The agent whose method is running, if any
+pub fn current_agent_id() -> Option<String> {
+    current_invocation().map(|invocation| invocation.agent_id)
+}
+
+pub fn current_invocation() -> Option<CurrentInvocation> {
+    CURRENT_INVOCATIONS.lock().unwrap().last().cloned()
+}
+
+pub(crate) fn with_current_agent<T>(agent_id: &str, method_name: &str, f: impl FnOnce() -> T) -> T {
+    let invocation_id = {
+        let mut last_invocation_id = LAST_INVOCATION_ID.lock().unwrap();
+        *last_invocation_id += 1;
+        *last_invocation_id
+    };
+
+    CURRENT_INVOCATIONS.lock().unwrap().push(CurrentInvocation {
+        agent_id: agent_id.to_string(),
+        method_name: method_name.to_string(),
+        invocation_id,
+    });
+    let _invocation = CurrentInvocationGuard;
+    f()
+}
+
+// Pops the current invocation when it finished, also if its method panicked
+struct CurrentInvocationGuard;
+
+impl Drop for CurrentInvocationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut current_invocations) = CURRENT_INVOCATIONS.lock() {
+            current_invocations.pop();
+        }
+    }
+}
+
+// Forgets what a previous agent with this id left behind, called by the initiators before constructing an agent
+pub fn replace_agent(agent_id: &str) {
+    crate::memory::remove_memories(agent_id);
+    crate::agent_registry::remove_sibling_agents(agent_id);
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn reset_invocation_ids() {
+    *LAST_INVOCATION_ID.lock().unwrap() = 0;
+}
+
+// Implemented by the `Remote*` clients generated by `agent_definition`.
+// Agents holding other agents in fields bounded by an agent trait get these fields resolved
+// through this trait when they are constructed (see `derive(AgentConstruct)`):
+//
+// ```
+//  #[derive(AgentConstruct)]
+//  struct MyAssistant<T: WeatherAgent> {
+//    agent_id: String,
+//    city: String,
+//    #[agent(new(city.clone(), 7))]
+//    weather: T,
+//  }
+// ```
+//
+// The constructor parameters of `new(...)` and `singleton(...)` are expressions, which can use the fields before.
+pub trait RemoteAgent: Sized + Clone + Send + 'static {
+    fn agent_type_name() -> &'static str;
+
+    // Creates a new agent, using the wit values as its constructor parameters
+    fn create(params: Vec<WitValue>) -> Result<Self, String>;
+
+    fn connect(agent_id: &str) -> Result<Self, String>;
+
+    // The id of the remote agent, to connect to it again, e.g. once its dependent agent is restored from a snapshot
+    fn agent_id(&self) -> Result<String, String>;
+
+    // Invokes a method by its name in the agent type, e.g. `get-weather`, returning its result.
+    // Lets callers that only know the agent type, like `tools::Toolbox`, call the agent
+    fn invoke_method(&self, method_name: &str, input: Vec<WitValue>) -> Result<Value, String>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AgentResolution {
+    // A new agent, constructed with the given parameters, for each agent that depends on it
+    New(Vec<Value>),
+    Connect(String),
+    // A single agent, shared by all the agents of this worker that depend on it.
+    // It is constructed with the parameters of the first agent resolving it
+    Singleton(Vec<Value>),
+}
+
+// Resolves a remote agent the agent with the given id depends on. The ids of the resolved agents are kept,
+// so that agents restored from a snapshot connect to the same remote agents instead of creating new ones
+pub fn resolve_remote_agent<T: RemoteAgent>(
+    owner_agent_id: &str,
+    resolution: AgentResolution,
+) -> Result<T, String> {
+    let agent_type_name = T::agent_type_name();
+    let restored_agent_id =
+        crate::agent_registry::take_restored_remote_agent(owner_agent_id, agent_type_name);
+
+    let (agent, remote_agent_id) = match (resolution, restored_agent_id) {
+        (AgentResolution::Singleton(params), restored_agent_id) => {
+            match crate::agent_registry::get_singleton_remote_agent::<T>() {
+                Some(agent) => {
+                    let remote_agent_id = agent.agent_id()?;
+                    (agent, remote_agent_id)
+                }
+                None => {
+                    let (agent, remote_agent_id) = match restored_agent_id {
+                        Some(remote_agent_id) => (T::connect(&remote_agent_id)?, remote_agent_id),
+                        None => {
+                            let agent = T::create(params.into_iter().map(WitValue::from).collect())?;
+                            let remote_agent_id = agent.agent_id()?;
+                            (agent, remote_agent_id)
+                        }
+                    };
+                    crate::agent_registry::register_singleton_remote_agent(agent.clone());
+                    (agent, remote_agent_id)
+                }
+            }
+        }
+        (_, Some(remote_agent_id)) => (T::connect(&remote_agent_id)?, remote_agent_id),
+        (AgentResolution::New(params), None) => {
+            let agent = T::create(params.into_iter().map(WitValue::from).collect())?;
+            let remote_agent_id = agent.agent_id()?;
+            (agent, remote_agent_id)
+        }
+        (AgentResolution::Connect(remote_agent_id), None) => {
+            (T::connect(&remote_agent_id)?, remote_agent_id)
+        }
+    };
+
+    crate::agent_registry::register_remote_agent(owner_agent_id, agent_type_name, remote_agent_id);
+    Ok(agent)
 }
 
 pub trait GetAgentId {
@@ -59,3 +222,185 @@ pub fn parse_agent_id(agent_id: &str) -> Result<AgentInfo, String> {
         instance_number: instance,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::exports::golem::agent::guest::{Error, GuestAgent};
+    use crate::test_agents;
+    use crate::testing::MockHost;
+    use crate::ResolvedAgent;
+
+    // A client recording the constructor parameters of the agents it creates
+    #[derive(Clone, Debug, PartialEq)]
+    struct FakeClient {
+        agent_id: String,
+        params: Vec<Value>,
+    }
+
+    impl RemoteAgent for FakeClient {
+        fn agent_type_name() -> &'static str {
+            "fake-agent"
+        }
+
+        fn create(params: Vec<WitValue>) -> Result<Self, String> {
+            let params = params.into_iter().map(Value::from).collect::<Vec<_>>();
+            Ok(FakeClient {
+                agent_id: format!("remote-worker--fake-agent--{:?}", params),
+                params,
+            })
+        }
+
+        fn connect(agent_id: &str) -> Result<Self, String> {
+            Ok(FakeClient {
+                agent_id: agent_id.to_string(),
+                params: vec![],
+            })
+        }
+
+        fn agent_id(&self) -> Result<String, String> {
+            Ok(self.agent_id.clone())
+        }
+
+        fn invoke_method(&self, method_name: &str, _: Vec<WitValue>) -> Result<Value, String> {
+            Err(format!("{} is not available", method_name))
+        }
+    }
+
+    // A client of an agent type that isn't deployed
+    #[derive(Clone)]
+    struct UndeployedClient;
+
+    impl RemoteAgent for UndeployedClient {
+        fn agent_type_name() -> &'static str {
+            "undeployed-agent"
+        }
+
+        fn create(_: Vec<WitValue>) -> Result<Self, String> {
+            Err("undeployed-agent is not deployed".to_string())
+        }
+
+        fn connect(agent_id: &str) -> Result<Self, String> {
+            Err(format!("{} not found", agent_id))
+        }
+
+        fn agent_id(&self) -> Result<String, String> {
+            unreachable!()
+        }
+
+        fn invoke_method(&self, _: &str, _: Vec<WitValue>) -> Result<Value, String> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn creates_new_agents_with_the_given_parameters() {
+        let _host = MockHost::new("test-worker").install();
+
+        let agent = resolve_remote_agent::<FakeClient>(
+            "test-worker--assistant--1",
+            AgentResolution::New(vec![Value::String("Berlin".to_string()), Value::U32(7)]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            agent.params,
+            vec![Value::String("Berlin".to_string()), Value::U32(7)]
+        );
+        assert_eq!(
+            crate::agent_registry::get_remote_agents("test-worker--assistant--1"),
+            vec![("fake-agent".to_string(), agent.agent_id)]
+        );
+    }
+
+    #[test]
+    fn shares_singletons_constructed_by_the_first_agent_resolving_them() {
+        let _host = MockHost::new("test-worker").install();
+
+        let first = resolve_remote_agent::<FakeClient>(
+            "test-worker--assistant--1",
+            AgentResolution::Singleton(vec![Value::U32(1)]),
+        );
+        let second = resolve_remote_agent::<FakeClient>(
+            "test-worker--assistant--2",
+            AgentResolution::Singleton(vec![Value::U32(2)]),
+        );
+
+        assert_eq!(first.as_ref().map(|agent| agent.params.clone()), Ok(vec![Value::U32(1)]));
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn restored_agents_connect_to_the_agents_they_created() {
+        let _host = MockHost::new("test-worker").install();
+        crate::agent_registry::restore_remote_agents(
+            "test-worker--assistant--1",
+            vec![("fake-agent".to_string(), "remote-worker--fake-agent--3".to_string())],
+        );
+
+        let restored = resolve_remote_agent::<FakeClient>(
+            "test-worker--assistant--1",
+            AgentResolution::New(vec![Value::U32(1)]),
+        )
+        .unwrap();
+        let created = resolve_remote_agent::<FakeClient>(
+            "test-worker--assistant--1",
+            AgentResolution::New(vec![Value::U32(2)]),
+        )
+        .unwrap();
+
+        assert_eq!(restored.agent_id, "remote-worker--fake-agent--3");
+        assert!(restored.params.is_empty());
+        assert_eq!(created.params, vec![Value::U32(2)]);
+    }
+
+    #[test]
+    fn fails_creating_agents_whose_remote_agents_cannot_be_resolved() {
+        let _host = MockHost::new("test-worker").install();
+        // Constructs the agent the way `derive(AgentConstruct)` does for a field `weather`
+        test_agents::register_agent("weather-assistant", 0, vec![], |agent_id, _| {
+            resolve_remote_agent::<UndeployedClient>(&agent_id, AgentResolution::New(vec![]))
+                .map_err(|error| {
+                    format!("Failed to resolve the agent of field weather: {}", error)
+                })?;
+            unreachable!()
+        });
+
+        let result = <ResolvedAgent as GuestAgent>::create("weather-assistant".to_string(), vec![]);
+
+        let Err(Error::InvalidInput(error)) = result else {
+            panic!("Expected the construction to fail with invalid input");
+        };
+        assert_eq!(
+            error,
+            "Failed to construct agent weather-assistant: Failed to resolve the agent of field weather: undeployed-agent is not deployed"
+        );
+        assert!(
+            crate::agent_registry::get_resolved_agent(&crate::agent_registry::AgentId(
+                "test-worker--weather-assistant--1".to_string()
+            ))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn forgets_invocations_whose_method_panicked() {
+        let _host = MockHost::new("test-worker").install();
+
+        with_current_agent("test-worker--assistant--1", "ask", || {
+            let result = std::panic::catch_unwind(|| {
+                with_current_agent("test-worker--weather-agent--1", "get-forecast", || {
+                    panic!("The forecast is not available")
+                })
+            });
+
+            assert!(result.is_err());
+            assert_eq!(
+                current_agent_id().as_deref(),
+                Some("test-worker--assistant--1")
+            );
+        });
+
+        assert_eq!(current_invocation(), None);
+    }
+}
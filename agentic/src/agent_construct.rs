@@ -1,7 +1,13 @@
-use golem_wasm_rpc::WitType;
+use crate::bindings::golem::agent::common::ParameterType;
 
 pub trait AgentConstruct: Sized {
-    fn construct_from_params(params: Vec<golem_wasm_rpc::WitValue>, agent_id: String) -> Self;
-    fn get_params() -> Vec<(String, WitType)>;
+    // Fails if one of the remote agents held in its fields can't be resolved
+    fn construct_from_params(
+        params: Vec<golem_wasm_rpc::WitValue>,
+        agent_id: String,
+    ) -> Result<Self, String>;
+    // The constructor parameters, with `Text` parameters as text parameter types
+    fn get_params() -> Vec<(String, ParameterType)>;
+    // Agent type names of the fields holding other agents
     fn get_agent_dependencies() -> Vec<String>;
 }
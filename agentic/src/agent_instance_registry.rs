@@ -29,10 +29,7 @@ pub fn create_agent_id(agent_name: AgentName) -> AgentId {
     let mut counter = AGENT_INSTANCE_COUNTER.lock().unwrap();
     let count = increment_agent_instance_counter_locked(&mut counter, &agent_name);
 
-    let worker_name = crate::bindings::golem::api::host::get_self_metadata()
-        .worker_id
-        .worker_name
-        .clone();
+    let worker_name = crate::host::agent_host().worker_name();
 
     let agent_id = format!("{}--{}--{}", worker_name, agent_name, count);
 
@@ -43,7 +40,30 @@ pub fn create_agent_id(agent_name: AgentName) -> AgentId {
     agent_id
 }
 
+// Registers the id of an agent restored from a snapshot, so that new agents don't get the same id
+pub fn restore_agent_id(agent_name: AgentName, agent_id: AgentId) {
+    let instance_number = crate::agent::parse_agent_id(&agent_id)
+        .ok()
+        .and_then(|agent_info| agent_info.instance_number.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let mut counter = AGENT_INSTANCE_COUNTER.lock().unwrap();
+    let count = counter.entry(agent_name.clone()).or_insert(0);
+    *count = (*count).max(instance_number);
+
+    let mut id_map = AGENT_INSTANCE_ID.lock().unwrap();
+    id_map.entry(agent_name).or_default().push(agent_id);
+}
+
 pub fn get_agent_instance_count(agent_name: AgentName) -> u64 {
     let counter = AGENT_INSTANCE_COUNTER.lock().unwrap();
     *counter.get(&agent_name).unwrap_or(&0)
 }
+
+// Forgets every agent instance, so that ids start from 1 again
+pub fn reset_agent_instance_counters() {
+    let mut counter = AGENT_INSTANCE_COUNTER.lock().unwrap();
+    let mut id_map = AGENT_INSTANCE_ID.lock().unwrap();
+    counter.clear();
+    id_map.clear();
+}
@@ -0,0 +1,533 @@
+use crate::llm::{ChatModel, ChatRequest, ChatResponse, Message, Role, Toolbox, Usage};
+use crate::memory::{estimate_tokens, Memory};
+use crate::status::ProgressReporter;
+use crate::tools::ToolCall;
+use std::time::{Duration, SystemTime};
+
+// A reason-act loop: the tool calls of the model are dispatched to the toolbox and the results fed back,
+// until the model answers or a budget runs out. Every step is reported as a `progress` status update.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoopStop {
+    // The model answered without calling tools
+    Answered,
+    StepBudget,
+    TokenBudget,
+    TimeBudget,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopOutcome {
+    // The last text of the model, which is the answer unless a budget ran out
+    pub answer: String,
+    pub stop: LoopStop,
+    pub steps: u32,
+    pub usage: Usage,
+}
+
+pub struct AgentLoop<M: ChatModel> {
+    model: M,
+    toolbox: Toolbox,
+    system_prompt: Option<String>,
+    memory: Option<Memory>,
+    max_steps: u32,
+    max_tokens: Option<u32>,
+    max_duration: Option<Duration>,
+    max_tokens_per_step: Option<u32>,
+    streaming: bool,
+}
+
+impl<M: ChatModel> AgentLoop<M> {
+    pub fn new(model: M, toolbox: Toolbox) -> Self {
+        AgentLoop {
+            model,
+            toolbox,
+            system_prompt: None,
+            memory: None,
+            max_steps: 10,
+            max_tokens: None,
+            max_duration: None,
+            max_tokens_per_step: None,
+            streaming: false,
+        }
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    // The conversation goes on from the messages of the memory, and the messages of the loop are added to it
+    pub fn with_memory(mut self, memory: Memory) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    // The tokens of all the steps, as reported by the model, or estimated if it doesn't report them
+    pub fn with_token_budget(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_time_budget(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    pub fn with_max_tokens_per_step(mut self, max_tokens: u32) -> Self {
+        self.max_tokens_per_step = Some(max_tokens);
+        self
+    }
+
+    // Reads the response of the model as a stream, emitting its text piece by piece
+    pub fn with_streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
+
+    pub fn run(&self, goal: impl Into<String>) -> Result<LoopOutcome, String> {
+        let mut transcript = Vec::new();
+        self.record(&mut transcript, Message::user(goal));
+        self.run_loop(transcript)
+    }
+
+    // Goes on with the conversation of the memory, e.g. once the agent is restored from a snapshot,
+    // first calling the tools the model asked for if their results are missing
+    pub fn resume(&self) -> Result<LoopOutcome, String> {
+        if self.memory.is_none() {
+            return Err("Only a loop with a memory can be resumed".to_string());
+        }
+
+        let mut transcript = Vec::new();
+        let pending = pending_tool_calls(&self.conversation(&transcript));
+        self.call_tools(&mut transcript, &pending);
+        self.run_loop(transcript)
+    }
+
+    fn run_loop(&self, mut transcript: Vec<Message>) -> Result<LoopOutcome, String> {
+        let reporter = ProgressReporter::current();
+        let started_at = SystemTime::now();
+        let mut usage = Usage::default();
+        let mut answer = String::new();
+
+        for step in 1..=self.max_steps {
+            if let Some(stop) = self.exhausted_budget(started_at, &usage) {
+                return Ok(LoopOutcome {
+                    answer,
+                    stop,
+                    steps: step - 1,
+                    usage,
+                });
+            }
+
+            reporter.progress_step(
+                format!("Step {}: asking the model", step),
+                step,
+                self.max_steps,
+            );
+
+            let mut request = ChatRequest::new(self.request_messages(&transcript))
+                .with_tools(self.toolbox.definitions());
+            if let Some(max_tokens) = self.max_tokens_per_step {
+                request = request.with_max_tokens(max_tokens);
+            }
+
+            let response = if self.streaming {
+                self.model
+                    .complete_streaming(&request, &mut |text: &str| reporter.emit(text))?
+            } else {
+                self.model.complete(&request)?
+            };
+
+            let step_usage = step_usage(&request, &response);
+            usage.input_tokens += step_usage.input_tokens;
+            usage.output_tokens += step_usage.output_tokens;
+
+            let text = response.text();
+            if !text.is_empty() {
+                answer = text;
+            }
+
+            let tool_calls = response.message.tool_calls.clone();
+            self.record(&mut transcript, response.message);
+
+            if tool_calls.is_empty() {
+                return Ok(LoopOutcome {
+                    answer,
+                    stop: LoopStop::Answered,
+                    steps: step,
+                    usage,
+                });
+            }
+
+            self.call_tools(&mut transcript, &tool_calls);
+        }
+
+        Ok(LoopOutcome {
+            answer,
+            stop: LoopStop::StepBudget,
+            steps: self.max_steps,
+            usage,
+        })
+    }
+
+    fn call_tools(&self, transcript: &mut Vec<Message>, tool_calls: &[ToolCall]) {
+        let reporter = ProgressReporter::current();
+
+        for tool_call in tool_calls {
+            reporter.progress(format!("Calling {}", tool_call.name));
+
+            let result = match self.toolbox.call(tool_call) {
+                Ok(output) => output,
+                Err(error) => format!("Error: {}", error),
+            };
+            self.record(transcript, Message::tool_result(&tool_call.id, result));
+        }
+    }
+
+    fn exhausted_budget(&self, started_at: SystemTime, usage: &Usage) -> Option<LoopStop> {
+        if let Some(max_tokens) = self.max_tokens {
+            if usage.input_tokens + usage.output_tokens >= max_tokens {
+                return Some(LoopStop::TokenBudget);
+            }
+        }
+
+        if let Some(max_duration) = self.max_duration {
+            if started_at.elapsed().unwrap_or_default() >= max_duration {
+                return Some(LoopStop::TimeBudget);
+            }
+        }
+
+        None
+    }
+
+    fn record(&self, transcript: &mut Vec<Message>, message: Message) {
+        match &self.memory {
+            Some(memory) => memory.push(message),
+            None => transcript.push(message),
+        }
+    }
+
+    fn conversation(&self, transcript: &[Message]) -> Vec<Message> {
+        match &self.memory {
+            Some(memory) => memory.messages(),
+            None => transcript.to_vec(),
+        }
+    }
+
+    fn request_messages(&self, transcript: &[Message]) -> Vec<Message> {
+        let mut messages = Vec::new();
+        if let Some(system_prompt) = &self.system_prompt {
+            messages.push(Message::system(system_prompt));
+        }
+        messages.extend(self.conversation(transcript));
+        messages
+    }
+}
+
+// The tool calls of the last assistant message that have no result after it
+fn pending_tool_calls(messages: &[Message]) -> Vec<ToolCall> {
+    let Some(index) = messages
+        .iter()
+        .rposition(|message| message.role == Role::Assistant)
+    else {
+        return vec![];
+    };
+
+    let answered = messages[index + 1..]
+        .iter()
+        .filter_map(|message| message.tool_call_id.as_deref())
+        .collect::<Vec<_>>();
+
+    messages[index]
+        .tool_calls
+        .iter()
+        .filter(|tool_call| !answered.contains(&tool_call.id.as_str()))
+        .cloned()
+        .collect()
+}
+
+fn step_usage(request: &ChatRequest, response: &ChatResponse) -> Usage {
+    response.usage.unwrap_or_else(|| Usage {
+        input_tokens: request.messages.iter().map(estimate_tokens).sum::<usize>() as u32,
+        output_tokens: estimate_tokens(&response.message) as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::golem::agent::common::{ProgressCounter, ProgressReport, StatusUpdate};
+    use crate::llm::StopReason;
+    use crate::status::collect_status_updates;
+    use crate::test_agents;
+    use crate::testing::{self, MockHost};
+    use golem_wasm_ast::analysis::analysed_type::{str, u32};
+    use golem_wasm_rpc::Value;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    // A model answering with the responses of its script in order, and recording the requests it got
+    struct ScriptedModel {
+        responses: Mutex<VecDeque<ChatResponse>>,
+        requests: Mutex<Vec<ChatRequest>>,
+        delay: Duration,
+    }
+
+    impl ScriptedModel {
+        fn new(responses: Vec<ChatResponse>) -> Self {
+            ScriptedModel {
+                responses: Mutex::new(responses.into()),
+                requests: Mutex::new(Vec::new()),
+                delay: Duration::ZERO,
+            }
+        }
+
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        fn requests(&self) -> Vec<ChatRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl ChatModel for ScriptedModel {
+        fn complete(&self, request: &ChatRequest) -> Result<ChatResponse, String> {
+            self.requests.lock().unwrap().push(request.clone());
+            std::thread::sleep(self.delay);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| "The script has ended".to_string())
+        }
+
+        fn complete_streaming(
+            &self,
+            request: &ChatRequest,
+            on_text: &mut dyn FnMut(&str),
+        ) -> Result<ChatResponse, String> {
+            let response = self.complete(request)?;
+            on_text(&response.text());
+            Ok(response)
+        }
+    }
+
+    fn forecast_call(id: &str) -> ChatResponse {
+        ChatResponse {
+            message: Message::assistant_tool_calls(vec![ToolCall {
+                id: id.to_string(),
+                name: "weather-agent__get-forecast".to_string(),
+                arguments: json!({ "city": "Berlin", "days": 3 }),
+            }]),
+            stop_reason: StopReason::ToolCalls,
+            usage: Some(Usage {
+                input_tokens: 50,
+                output_tokens: 10,
+            }),
+        }
+    }
+
+    fn answer(text: &str) -> ChatResponse {
+        ChatResponse {
+            message: Message::assistant(text),
+            stop_reason: StopReason::EndTurn,
+            usage: Some(Usage {
+                input_tokens: 80,
+                output_tokens: 5,
+            }),
+        }
+    }
+
+    fn weather_toolbox() -> Toolbox {
+        test_agents::register(
+            "weather-agent",
+            vec![],
+            vec![test_agents::method(
+                "get-forecast",
+                vec![("city", str()), ("days", u32())],
+                str(),
+            )],
+            |_, _, input| match input.as_slice() {
+                [Value::String(city), Value::U32(days)] => {
+                    StatusUpdate::Emit(format!("{} days of sun in {}", days, city))
+                }
+                _ => StatusUpdate::Emit("unexpected input".to_string()),
+            },
+        );
+        let agent = testing::create_agent("weather-agent", vec![]).unwrap();
+        Toolbox::new().with_agent(agent.agent_id())
+    }
+
+    fn is_step(update: &StatusUpdate, step: u32, total: u32) -> bool {
+        matches!(
+            update,
+            StatusUpdate::Progress(Some(ProgressReport {
+                description,
+                counter: Some(ProgressCounter { steps, total: steps_total }),
+            })) if *description == format!("Step {}: asking the model", step) && *steps == step && *steps_total == total
+        )
+    }
+
+    fn is_tool_call(update: &StatusUpdate, tool_name: &str) -> bool {
+        matches!(
+            update,
+            StatusUpdate::Progress(Some(ProgressReport { description, counter: None }))
+                if *description == format!("Calling {}", tool_name)
+        )
+    }
+
+    #[test]
+    fn calls_tools_until_the_model_answers() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(ScriptedModel::new(vec![
+            forecast_call("call-1"),
+            answer("Sunny in Berlin"),
+        ]));
+        let agent_loop =
+            AgentLoop::new(model.clone(), weather_toolbox()).with_system_prompt("Be brief");
+
+        let mut outcome = None;
+        let updates = collect_status_updates(|| {
+            outcome = Some(agent_loop.run("Weather in Berlin?"));
+            StatusUpdate::Emit("done".to_string())
+        });
+
+        assert_eq!(
+            outcome.unwrap(),
+            Ok(LoopOutcome {
+                answer: "Sunny in Berlin".to_string(),
+                stop: LoopStop::Answered,
+                steps: 2,
+                usage: Usage {
+                    input_tokens: 130,
+                    output_tokens: 15,
+                },
+            })
+        );
+
+        assert_eq!(updates.len(), 4);
+        assert!(is_step(&updates[0], 1, 10));
+        assert!(is_tool_call(&updates[1], "weather-agent__get-forecast"));
+        assert!(is_step(&updates[2], 2, 10));
+
+        let requests = model.requests();
+        assert_eq!(requests[0].tools.len(), 1);
+        assert_eq!(
+            requests[1].messages,
+            vec![
+                Message::system("Be brief"),
+                Message::user("Weather in Berlin?"),
+                forecast_call("call-1").message,
+                Message::tool_result("call-1", "3 days of sun in Berlin"),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_once_the_steps_run_out() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(ScriptedModel::new(vec![
+            forecast_call("call-1"),
+            forecast_call("call-2"),
+            answer("Sunny in Berlin"),
+        ]));
+        let agent_loop = AgentLoop::new(model.clone(), weather_toolbox()).with_max_steps(2);
+
+        let mut outcome = None;
+        let updates = collect_status_updates(|| {
+            outcome = Some(agent_loop.run("Weather in Berlin?"));
+            StatusUpdate::Emit("done".to_string())
+        });
+
+        let outcome = outcome.unwrap().unwrap();
+        assert_eq!(outcome.stop, LoopStop::StepBudget);
+        assert_eq!(outcome.steps, 2);
+        assert_eq!(outcome.answer, "");
+        assert_eq!(model.requests().len(), 2);
+
+        assert_eq!(updates.len(), 5);
+        assert!(is_step(&updates[0], 1, 2));
+        assert!(is_step(&updates[2], 2, 2));
+        assert!(is_tool_call(&updates[3], "weather-agent__get-forecast"));
+    }
+
+    #[test]
+    fn stops_once_the_tokens_run_out() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(ScriptedModel::new(vec![
+            forecast_call("call-1"),
+            forecast_call("call-2"),
+            answer("Sunny in Berlin"),
+        ]));
+        let agent_loop = AgentLoop::new(model.clone(), weather_toolbox()).with_token_budget(100);
+
+        let outcome = agent_loop.run("Weather in Berlin?").unwrap();
+
+        // The budget is checked before every step, so the step exceeding it still completes
+        assert_eq!(outcome.stop, LoopStop::TokenBudget);
+        assert_eq!(outcome.steps, 2);
+        assert_eq!(
+            outcome.usage,
+            Usage {
+                input_tokens: 100,
+                output_tokens: 20,
+            }
+        );
+        assert_eq!(model.requests().len(), 2);
+    }
+
+    #[test]
+    fn stops_once_the_time_runs_out() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(
+            ScriptedModel::new(vec![forecast_call("call-1"), answer("Sunny in Berlin")])
+                .with_delay(Duration::from_millis(50)),
+        );
+        let agent_loop = AgentLoop::new(model.clone(), weather_toolbox())
+            .with_time_budget(Duration::from_millis(10));
+
+        let outcome = agent_loop.run("Weather in Berlin?").unwrap();
+
+        assert_eq!(outcome.stop, LoopStop::TimeBudget);
+        assert_eq!(outcome.steps, 1);
+        assert_eq!(model.requests().len(), 1);
+    }
+
+    #[test]
+    fn emits_the_text_of_streamed_responses() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(ScriptedModel::new(vec![answer("Sunny in Berlin")]));
+        let agent_loop = AgentLoop::new(model.clone(), weather_toolbox()).with_streaming();
+
+        let updates = collect_status_updates(|| {
+            let outcome = agent_loop.run("Weather in Berlin?").unwrap();
+            assert_eq!(outcome.stop, LoopStop::Answered);
+            StatusUpdate::Emit("done".to_string())
+        });
+
+        assert_eq!(updates.len(), 3);
+        assert!(is_step(&updates[0], 1, 10));
+        assert!(matches!(&updates[1], StatusUpdate::Emit(text) if text == "Sunny in Berlin"));
+    }
+
+    #[test]
+    fn fails_when_the_model_fails() {
+        let _host = MockHost::new("test-worker").install();
+        let model = Arc::new(ScriptedModel::new(vec![forecast_call("call-1")]));
+        let agent_loop = AgentLoop::new(model.clone(), weather_toolbox());
+
+        assert_eq!(
+            agent_loop.run("Weather in Berlin?"),
+            Err("The script has ended".to_string())
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use crate::agent_instance_registry::AgentName;
 use crate::bindings::exports::golem::agent::guest::{AgentRef, AgentType, WitValue};
 use crate::ResolvedAgent;
+use crate::agent::RemoteAgent;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use crate::bindings::golem::agent::common::{AgentDependency, AgentMethod, AgentConstructor};
 
@@ -36,10 +38,38 @@ pub fn get_constructor(
         .cloned()
 }
 
+// WIT schemas of the methods only carry parameter types,
+// but names are useful for anything that exposes methods to humans or LLMs
+static METHOD_PARAMETER_NAMES_REGISTRY: Lazy<Mutex<HashMap<(AgentTypeName, String), Vec<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_method_parameter_names(
+    agent_type_name: String,
+    method_name: String,
+    parameter_names: Vec<String>,
+) {
+    METHOD_PARAMETER_NAMES_REGISTRY
+        .lock()
+        .unwrap()
+        .insert((agent_type_name, method_name), parameter_names);
+}
+
+pub fn get_method_parameter_names(
+    agent_type_name: &str,
+    method_name: &str,
+) -> Option<Vec<String>> {
+    METHOD_PARAMETER_NAMES_REGISTRY
+        .lock()
+        .unwrap()
+        .get(&(agent_type_name.to_string(), method_name.to_string()))
+        .cloned()
+}
+
 #[derive(Clone, Debug)]
 pub struct GenericAgentType {
     pub type_name: String,
     pub description: String,
+    pub version: u32,
     pub methods: Vec<AgentMethod>,
     pub requires: Vec<AgentDependency>
 }
@@ -49,6 +79,7 @@ impl GenericAgentType {
         AgentType {
             type_name: self.type_name.clone(),
             description: self.description.clone(),
+            version: self.version,
             agent_constructor: agent_constructor,
             methods: self.methods.clone(),
             requires: self.requires.clone(),
@@ -57,9 +88,12 @@ impl GenericAgentType {
 }
 
 pub struct AgentRefInternal {
-    inner_instance: crate::bindings::exports::golem::agent::guest::Agent,
+    // The `agent` resource can only be created inside a component, so natively (see `testing`) there is none
+    inner_instance: Option<crate::bindings::exports::golem::agent::guest::Agent>,
     resolved_agent: ResolvedAgent,
     agent_name: String,
+    // Kept to construct the agent again when it is restored from a snapshot
+    constructor_params: Vec<WitValue>,
 }
 
 static GENERIC_AGENT_TYPE_REGISTRY: Lazy<Mutex<HashMap<AgentTypeName, GenericAgentType>>> =
@@ -129,7 +163,7 @@ pub fn get_agent_instances_by_prefix(
                 Some(AgentRef {
                     agent_id: agent_ref_internal.resolved_agent.agent_id.clone(),
                     agent_name: agent_ref_internal.agent_name.clone(),
-                    agent_handle: agent_ref_internal.inner_instance.handle(),
+                    agent_handle: agent_ref_internal.agent_handle(),
                 })
             } else {
                 None
@@ -138,22 +172,85 @@ pub fn get_agent_instances_by_prefix(
         .collect()
 }
 
+impl AgentRefInternal {
+    fn agent_handle(&self) -> u32 {
+        self.inner_instance
+            .as_ref()
+            .map(|agent| agent.handle())
+            .unwrap_or(0)
+    }
+}
+
 pub fn register_agent_instance(
     agent_id: AgentId,
     agent_name: String,
-    agent_instance: crate::bindings::exports::golem::agent::guest::Agent,
     resolved_agent: ResolvedAgent,
+    constructor_params: Vec<WitValue>,
 ) {
+    #[cfg(target_arch = "wasm32")]
+    let inner_instance = Some(crate::bindings::exports::golem::agent::guest::Agent::new(
+        resolved_agent.clone(),
+    ));
+    #[cfg(not(target_arch = "wasm32"))]
+    let inner_instance = None;
+
     AGENT_INSTANCE_REGISTRY.lock().unwrap().insert(
         agent_id,
         AgentRefInternal {
-            inner_instance: agent_instance,
+            inner_instance,
             resolved_agent,
             agent_name,
+            constructor_params,
         },
     );
 }
 
+// Agent type name, agent and constructor parameters of every agent instance, ordered by agent id
+pub fn get_all_agent_instances() -> Vec<(String, ResolvedAgent, Vec<WitValue>)> {
+    let mut instances = AGENT_INSTANCE_REGISTRY
+        .lock()
+        .unwrap()
+        .values()
+        .map(|agent_ref_internal| {
+            (
+                agent_ref_internal.agent_name.clone(),
+                agent_ref_internal.resolved_agent.clone(),
+                agent_ref_internal.constructor_params.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    instances.sort_by(|a, b| a.1.agent_id.cmp(&b.1.agent_id));
+    instances
+}
+
+pub fn clear_agent_instances() {
+    AGENT_INSTANCE_REGISTRY.lock().unwrap().clear();
+    SINGLETON_REMOTE_AGENT_REGISTRY.lock().unwrap().clear();
+    REMOTE_AGENT_REGISTRY.lock().unwrap().clear();
+    RESTORED_REMOTE_AGENT_REGISTRY.lock().unwrap().clear();
+    SIBLING_AGENT_REGISTRY.lock().unwrap().clear();
+}
+
+// The agents living in this worker
+pub fn discover_agents() -> Vec<AgentRef> {
+    let agent_names = get_all_agent_definitions()
+        .iter()
+        .map(|x| x.type_name.clone())
+        .collect::<Vec<_>>();
+
+    let worker_name = crate::host::agent_host().worker_name();
+
+    let mut agents = Vec::new();
+
+    for agent_name in agent_names {
+        let prefix = format!("{}--{}", worker_name, agent_name);
+
+        agents.extend(get_agent_instances_by_prefix(&prefix));
+    }
+
+    agents
+}
+
 pub fn get_agent_instance(agent_id: AgentId) -> Option<AgentRef> {
     AGENT_INSTANCE_REGISTRY
         .lock()
@@ -162,16 +259,197 @@ pub fn get_agent_instance(agent_id: AgentId) -> Option<AgentRef> {
         .map(|agent_ref_internal| AgentRef {
             agent_id: agent_id.0.clone(),
             agent_name: agent_ref_internal.agent_name.clone(),
-            agent_handle: agent_ref_internal.inner_instance.handle(),
+            agent_handle: agent_ref_internal.agent_handle(),
+        })
+}
+
+// Names of the agent types an agent type depends on, and the names of the methods it calls on each of them.
+// These are resolved into `AgentDependency` only when the agent types are read,
+// as the dependencies may be registered after the agent type that depends on them
+static AGENT_DEPENDENCY_REGISTRY: Lazy<
+    Mutex<HashMap<AgentTypeName, (Vec<AgentTypeName>, HashMap<AgentTypeName, Vec<String>>)>>,
+> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_agent_dependencies(
+    agent_type_name: AgentTypeName,
+    dependencies: Vec<AgentTypeName>,
+    called_methods: Vec<(AgentTypeName, Vec<String>)>,
+) {
+    let mut dependencies = dependencies
+        .into_iter()
+        .filter(|dependency| *dependency != agent_type_name)
+        .collect::<Vec<_>>();
+    dependencies.sort();
+    dependencies.dedup();
+
+    AGENT_DEPENDENCY_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(agent_type_name, (dependencies, called_methods.into_iter().collect()));
+}
+
+// The methods of a dependency are only known if the agent type of the dependency lives in the same component
+pub fn get_agent_dependencies(agent_type_name: &str) -> Vec<AgentDependency> {
+    let (dependencies, called_methods) = match AGENT_DEPENDENCY_REGISTRY
+        .lock()
+        .unwrap()
+        .get(agent_type_name)
+        .cloned()
+    {
+        Some(registered) => registered,
+        None => return vec![],
+    };
+
+    dependencies
+        .into_iter()
+        .map(|dependency| {
+            let called = called_methods.get(&dependency).cloned().unwrap_or_default();
+
+            let methods = get_generic_agent_type_by_name(&dependency)
+                .map(|agent_type| {
+                    agent_type
+                        .methods
+                        .into_iter()
+                        .filter(|method| called.contains(&method.name))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            AgentDependency {
+                agent_name: dependency,
+                methods,
+            }
         })
+        .collect()
+}
+
+fn with_dependencies(mut agent_type: AgentType) -> AgentType {
+    agent_type.requires = get_agent_dependencies(&agent_type.type_name);
+    agent_type
+}
+
+// Remote agents shared by all agents of the worker, see `AgentResolution::Singleton`
+static SINGLETON_REMOTE_AGENT_REGISTRY: Lazy<Mutex<HashMap<AgentTypeName, Box<dyn Any + Send>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_singleton_remote_agent<T: RemoteAgent>(agent: T) {
+    SINGLETON_REMOTE_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(T::agent_type_name().to_string(), Box::new(agent));
+}
+
+pub fn get_singleton_remote_agent<T: RemoteAgent>() -> Option<T> {
+    SINGLETON_REMOTE_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .get(T::agent_type_name())
+        .and_then(|agent| agent.downcast_ref::<T>())
+        .cloned()
+}
+
+// The agent type and id of the remote agents resolved by each agent of this worker, in the order they were resolved.
+// They are kept in snapshots, see `versioning`
+static REMOTE_AGENT_REGISTRY: Lazy<Mutex<HashMap<String, Vec<(AgentTypeName, String)>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// The remote agents saved in a snapshot, by the id of the agent being restored, until it resolves them again
+static RESTORED_REMOTE_AGENT_REGISTRY: Lazy<Mutex<HashMap<String, Vec<(AgentTypeName, String)>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_remote_agent(owner_agent_id: &str, agent_type_name: &str, remote_agent_id: String) {
+    REMOTE_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .entry(owner_agent_id.to_string())
+        .or_default()
+        .push((agent_type_name.to_string(), remote_agent_id));
+}
+
+pub fn get_remote_agents(owner_agent_id: &str) -> Vec<(AgentTypeName, String)> {
+    REMOTE_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .get(owner_agent_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+// Lets the agent restored with the given id connect to the remote agents it resolved before the snapshot
+pub(crate) fn restore_remote_agents(owner_agent_id: &str, remote_agents: Vec<(AgentTypeName, String)>) {
+    REMOTE_AGENT_REGISTRY.lock().unwrap().remove(owner_agent_id);
+    RESTORED_REMOTE_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(owner_agent_id.to_string(), remote_agents);
+}
+
+// Forgets the remote agents saved for an agent that didn't resolve all of them while being restored
+pub(crate) fn finish_restoring_remote_agents(owner_agent_id: &str) {
+    RESTORED_REMOTE_AGENT_REGISTRY.lock().unwrap().remove(owner_agent_id);
+}
+
+pub(crate) fn take_restored_remote_agent(owner_agent_id: &str, agent_type_name: &str) -> Option<String> {
+    let mut restored = RESTORED_REMOTE_AGENT_REGISTRY.lock().unwrap();
+    let remote_agents = restored.get_mut(owner_agent_id)?;
+    let index = remote_agents
+        .iter()
+        .position(|(remote_agent_type, _)| remote_agent_type == agent_type_name)?;
+    Some(remote_agents.remove(index).1)
+}
+
+// The agents of this worker created by the toolboxes of each agent on their first tool call, by agent type,
+// see `tools::Toolbox`. They are kept in snapshots, see `versioning`
+static SIBLING_AGENT_REGISTRY: Lazy<Mutex<HashMap<String, BTreeMap<AgentTypeName, String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn register_sibling_agent(owner_agent_id: &str, agent_type_name: &str, sibling_agent_id: String) {
+    SIBLING_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .entry(owner_agent_id.to_string())
+        .or_default()
+        .insert(agent_type_name.to_string(), sibling_agent_id);
+}
+
+pub fn get_sibling_agent(owner_agent_id: &str, agent_type_name: &str) -> Option<String> {
+    SIBLING_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .get(owner_agent_id)
+        .and_then(|sibling_agents| sibling_agents.get(agent_type_name))
+        .cloned()
+}
+
+pub fn get_sibling_agents(owner_agent_id: &str) -> Vec<(AgentTypeName, String)> {
+    SIBLING_AGENT_REGISTRY
+        .lock()
+        .unwrap()
+        .get(owner_agent_id)
+        .map(|sibling_agents| sibling_agents.clone().into_iter().collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn remove_sibling_agents(owner_agent_id: &str) {
+    SIBLING_AGENT_REGISTRY.lock().unwrap().remove(owner_agent_id);
+}
+
+pub fn get_resolved_agent(agent_id: &AgentId) -> Option<ResolvedAgent> {
+    AGENT_INSTANCE_REGISTRY
+        .lock()
+        .unwrap()
+        .get(agent_id)
+        .map(|agent_ref_internal| agent_ref_internal.resolved_agent.clone())
 }
 
 pub fn get_agent_def_by_name(agent_trait_name: &str) -> Option<AgentType> {
-    AGENT_TYPE_REGISTRY
+    let agent_type = AGENT_TYPE_REGISTRY
         .lock()
         .unwrap()
         .get(agent_trait_name)
-        .cloned()
+        .cloned();
+
+    agent_type.map(with_dependencies)
 }
 
 pub fn get_generic_agent_type_by_name(
@@ -194,12 +472,14 @@ pub fn get_all_generic_agent_types() -> Vec<GenericAgentType> {
 }
 
 pub fn get_all_agent_definitions() -> Vec<AgentType> {
-    AGENT_TYPE_REGISTRY
+    let agent_types = AGENT_TYPE_REGISTRY
         .lock()
         .unwrap()
         .values()
         .cloned()
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>();
+
+    agent_types.into_iter().map(with_dependencies).collect()
 }
 
 pub fn get_agent_initiator(
@@ -213,5 +493,72 @@ pub fn get_agent_initiator(
 }
 
 pub trait AgentInitiator: Send + Sync {
-    fn initiate(&self, params: Vec<WitValue>) -> ResolvedAgent;
+    fn initiate(&self, params: Vec<WitValue>) -> Result<ResolvedAgent, String>;
+
+    // Constructs an agent with a known id, when restoring it from a snapshot
+    fn initiate_with_id(&self, agent_id: String, params: Vec<WitValue>) -> Result<ResolvedAgent, String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::golem::agent::common::StatusUpdate;
+    use crate::test_agents;
+    use crate::testing::MockHost;
+    use golem_wasm_ast::analysis::analysed_type::str;
+
+    #[test]
+    fn keeps_the_called_methods_of_each_dependency() {
+        let _host = MockHost::new("test-worker").install();
+        let handler: test_agents::Handler = |_, _, _| StatusUpdate::Emit(String::new());
+        test_agents::register(
+            "forecaster",
+            vec![],
+            vec![
+                test_agents::method("get-forecast", vec![("city", str())], str()),
+                test_agents::method("get-history", vec![("city", str())], str()),
+            ],
+            handler,
+        );
+        test_agents::register(
+            "calendar",
+            vec![],
+            vec![
+                test_agents::method("add-event", vec![("title", str())], str()),
+                test_agents::method("get-forecast", vec![("day", str())], str()),
+            ],
+            handler,
+        );
+
+        register_agent_dependencies(
+            "trip-planner".to_string(),
+            vec!["forecaster".to_string(), "calendar".to_string()],
+            vec![
+                ("forecaster".to_string(), vec!["get-forecast".to_string()]),
+                ("calendar".to_string(), vec!["add-event".to_string(), "push".to_string()]),
+            ],
+        );
+
+        let dependencies = get_agent_dependencies("trip-planner")
+            .into_iter()
+            .map(|dependency| {
+                (
+                    dependency.agent_name,
+                    dependency
+                        .methods
+                        .into_iter()
+                        .map(|method| method.name)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            dependencies,
+            vec![
+                ("calendar".to_string(), vec!["add-event".to_string()]),
+                ("forecaster".to_string(), vec!["get-forecast".to_string()]),
+            ]
+        );
+    }
 }
@@ -0,0 +1,290 @@
+use crate::agent;
+use crate::bindings::golem::agent::common::ExternalRequest;
+use crate::bindings::golem::api::host::PromiseId;
+use crate::host::agent_host;
+use crate::json_mapping::{json_to_value, value_to_json};
+use crate::validation::validate_value;
+use crate::AgentArg;
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::{Value, WitType, WitValue, WorkerId};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+// Lets an agent ask a human or an external system for what only they can provide, e.g. an approval.
+// A method publishes a request and returns, and a later method reads the response:
+//
+// ```
+//  fn request_review(&self, draft: String) {
+//    *self.review.lock().unwrap() = Some(approval::publish("Review the draft", draft));
+//  }
+//
+//  fn publish_draft(&self) -> Result<String, String> {
+//    match self.review.lock().unwrap().as_ref().and_then(|review| review.response()) {
+//      Some(review) => { let review: Review = review?; ... }
+//      None => Ok("Not reviewed yet".to_string()),
+//    }
+//  }
+// ```
+//
+// Publishing creates a Golem promise and a typed request, listed by `discover-pending-requests` until it is
+// answered by completing the promise with the JSON encoding of the response, e.g. `true`. The response is kept
+// in the promise, so it survives restarts of the worker.
+//
+// The blocking `wait`, `await_external` and `approval` suspend the worker until the promise is completed.
+// A suspended worker doesn't take other invocations, including `discover-pending-requests`, so they can only
+// be used when the promise id is handed out some other way, e.g. sent by the agent to a remote system.
+
+static PENDING_REQUESTS: Lazy<Mutex<Vec<ExternalRequest>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// A published request, answered by a response of type `Resp`.
+// The request is no longer listed once it is answered or dropped
+pub struct PendingRequest<Resp> {
+    promise_id: PromiseId,
+    _response: PhantomData<Resp>,
+}
+
+impl<Resp: AgentArg> PendingRequest<Resp> {
+    pub fn promise_id(&self) -> &PromiseId {
+        &self.promise_id
+    }
+
+    // The response, if the request was answered, without waiting for it
+    pub fn response(&self) -> Option<Result<Resp, String>> {
+        let data = agent_host().poll_promise(&self.promise_id)?;
+        remove_pending_request(&self.promise_id);
+        Some(decode_response(&data))
+    }
+
+    // Blocks until the request is answered, suspending the worker, see above
+    pub fn wait(self) -> Result<Resp, String> {
+        let data = agent_host().await_promise(&self.promise_id);
+        decode_response(&data)
+    }
+}
+
+impl<Resp> Drop for PendingRequest<Resp> {
+    fn drop(&mut self) {
+        remove_pending_request(&self.promise_id);
+    }
+}
+
+// Publishes a request to be answered from outside of the worker, without waiting for it
+pub fn publish<Req: AgentArg, Resp: AgentArg>(
+    description: impl Into<String>,
+    request: Req,
+) -> PendingRequest<Resp> {
+    let promise_id = agent_host().create_promise();
+
+    PENDING_REQUESTS.lock().unwrap().push(ExternalRequest {
+        agent_id: agent::current_agent_id().unwrap_or_default(),
+        description: description.into(),
+        request: WitValue::from(AgentArg::to_value(&request)),
+        request_type: <Req as AgentArg>::get_wit_type(),
+        response_type: <Resp as AgentArg>::get_wit_type(),
+        promise_oplog_index: promise_id.oplog_idx,
+    });
+
+    PendingRequest {
+        promise_id,
+        _response: PhantomData,
+    }
+}
+
+// Publishes a yes or no question, answered with `true` or `false`
+pub fn request_approval(question: impl Into<String>) -> PendingRequest<bool> {
+    let question = question.into();
+    publish(question.clone(), question)
+}
+
+// Publishes a request and blocks until it is answered, suspending the worker, see above
+pub fn await_external<Req: AgentArg, Resp: AgentArg>(
+    description: impl Into<String>,
+    request: Req,
+) -> Result<Resp, String> {
+    publish(description, request).wait()
+}
+
+// Asks a yes or no question and blocks until it is answered, suspending the worker, see above
+pub fn approval(question: impl Into<String>) -> Result<bool, String> {
+    request_approval(question).wait()
+}
+
+// Answers a request published by an agent of the given worker.
+// Returns false if the request was answered before
+pub fn complete_request<Resp: AgentArg>(
+    worker_id: WorkerId,
+    promise_oplog_index: u64,
+    response: &Resp,
+) -> Result<bool, String> {
+    let data = encode_response(response)?;
+
+    Ok(agent_host().complete_promise(
+        &PromiseId {
+            worker_id,
+            oplog_idx: promise_oplog_index,
+        },
+        &data,
+    ))
+}
+
+pub fn pending_requests() -> Vec<ExternalRequest> {
+    PENDING_REQUESTS.lock().unwrap().clone()
+}
+
+// The pending requests, kept in snapshots as the promises they wait on outlive the update of the worker
+pub(crate) fn save_pending_requests() -> Result<serde_json::Value, String> {
+    PENDING_REQUESTS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let request_type = AnalysedType::from(request.request_type.clone());
+            let response_type = AnalysedType::from(request.response_type.clone());
+            let value = value_to_json(&Value::from(request.request.clone()), &request_type)?;
+            Ok(json!({
+                "agentId": request.agent_id,
+                "description": request.description,
+                "request": value,
+                "requestType": serde_json::to_value(&request_type).map_err(|e| e.to_string())?,
+                "responseType": serde_json::to_value(&response_type).map_err(|e| e.to_string())?,
+                "promiseOplogIndex": request.promise_oplog_index,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(serde_json::Value::Array)
+        .map_err(|e| format!("Failed to save the pending requests: {}", e))
+}
+
+pub(crate) fn load_pending_requests(saved: &serde_json::Value) -> Result<(), String> {
+    let Some(saved) = saved.as_array() else {
+        return Ok(());
+    };
+
+    let requests = saved
+        .iter()
+        .map(|saved_request| {
+            let request_type: AnalysedType = serde_json::from_value(saved_request["requestType"].clone())
+                .map_err(|e| e.to_string())?;
+            let response_type: AnalysedType = serde_json::from_value(saved_request["responseType"].clone())
+                .map_err(|e| e.to_string())?;
+            let request = json_to_value(&saved_request["request"], &request_type)?;
+
+            Ok(ExternalRequest {
+                agent_id: saved_request["agentId"].as_str().unwrap_or_default().to_string(),
+                description: saved_request["description"].as_str().unwrap_or_default().to_string(),
+                request: WitValue::from(request),
+                request_type: WitType::from(request_type),
+                response_type: WitType::from(response_type),
+                promise_oplog_index: saved_request["promiseOplogIndex"]
+                    .as_u64()
+                    .ok_or_else(|| "promiseOplogIndex is not a number".to_string())?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| format!("Invalid snapshot: invalid pending request: {}", e))?;
+
+    *PENDING_REQUESTS.lock().unwrap() = requests;
+    Ok(())
+}
+
+fn remove_pending_request(promise_id: &PromiseId) {
+    PENDING_REQUESTS
+        .lock()
+        .unwrap()
+        .retain(|request| request.promise_oplog_index != promise_id.oplog_idx);
+}
+
+pub fn clear_pending_requests() {
+    PENDING_REQUESTS.lock().unwrap().clear();
+}
+
+fn encode_response<Resp: AgentArg>(response: &Resp) -> Result<Vec<u8>, String> {
+    let typ = AnalysedType::from(<Resp as AgentArg>::get_wit_type());
+    let json = value_to_json(&AgentArg::to_value(response), &typ)?;
+    serde_json::to_vec(&json).map_err(|e| e.to_string())
+}
+
+fn decode_response<Resp: AgentArg>(data: &[u8]) -> Result<Resp, String> {
+    let typ = AnalysedType::from(<Resp as AgentArg>::get_wit_type());
+    let json: serde_json::Value =
+        serde_json::from_slice(data).map_err(|e| format!("The response is not JSON: {}", e))?;
+    let value = json_to_value(&json, &typ).map_err(|e| format!("Invalid response: {}", e))?;
+
+    validate_value(&value, &typ).map_err(|e| format!("Invalid response: {}", e))?;
+
+    <Resp as AgentArg>::from_wit_value(WitValue::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockHost};
+
+    fn request_approval_of(agent_id: &str, question: &str) -> PendingRequest<bool> {
+        agent::with_current_agent(agent_id, "transfer", || request_approval(question))
+    }
+
+    #[test]
+    fn publishes_requests_until_they_are_answered() {
+        let _host = MockHost::new("test-worker").install();
+
+        let request = request_approval_of("test-worker--bank--1", "Transfer 2000?");
+        let published = testing::pending_requests();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].agent_id, "test-worker--bank--1");
+        assert_eq!(published[0].description, "Transfer 2000?");
+        assert!(request.response().is_none());
+
+        testing::complete_request(&published[0], Value::Bool(true)).unwrap();
+
+        assert_eq!(request.response(), Some(Ok(true)));
+        assert!(testing::pending_requests().is_empty());
+    }
+
+    #[test]
+    fn waits_for_answered_requests() {
+        let _host = MockHost::new("test-worker").install();
+
+        let request = request_approval_of("test-worker--bank--1", "Transfer 2000?");
+        testing::complete_request(&testing::pending_requests()[0], Value::Bool(false)).unwrap();
+
+        assert_eq!(request.wait(), Ok(false));
+        assert!(testing::pending_requests().is_empty());
+    }
+
+    #[test]
+    fn forgets_dropped_requests() {
+        let _host = MockHost::new("test-worker").install();
+
+        let kept = request_approval_of("test-worker--bank--1", "Transfer 2000?");
+        drop(request_approval_of("test-worker--bank--1", "Transfer 3000?"));
+
+        let published = testing::pending_requests();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].promise_oplog_index, kept.promise_id().oplog_idx);
+    }
+
+    #[test]
+    fn answers_requests_only_once() {
+        let _host = MockHost::new("test-worker").install();
+
+        let _request = request_approval_of("test-worker--bank--1", "Transfer 2000?");
+        let published = testing::pending_requests();
+        testing::complete_request(&published[0], Value::Bool(true)).unwrap();
+
+        assert!(testing::complete_request(&published[0], Value::Bool(false)).is_err());
+    }
+
+    #[test]
+    fn rejects_responses_of_another_type() {
+        let _host = MockHost::new("test-worker").install();
+
+        let request = request_approval_of("test-worker--bank--1", "Transfer 2000?");
+        let worker_id = request.promise_id().worker_id.clone();
+        complete_request(worker_id, request.promise_id().oplog_idx, &"yes".to_string()).unwrap();
+
+        assert!(matches!(request.response(), Some(Err(error)) if error.starts_with("Invalid response")));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::bindings::golem::agent::common::{Error, ProgressCounter, ProgressReport, StatusUpdate};
+use crate::tracing;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+pub use crate::bindings::golem::api::host::RetryPolicy;
+
+// Options of the calls made by a remote agent client, set with its `with_options`:
+//
+// ```
+//  let payments = RemotePayments::connect_agent(&agent_id)?
+//      .with_options(CallOptions::new().with_new_idempotency_key());
+//
+//  payments.charge(order_id, amount).await?;
+// ```
+//
+// A call with an idempotency key runs at most once in the target agent: calling the same method
+// with the same key again, e.g. when the calling worker is retried after a crash, returns the result
+// of the first call. The key travels to the target in the invocation context of the call.
+#[derive(Clone, Debug, Default)]
+pub struct CallOptions {
+    pub idempotency_key: Option<IdempotencyKey>,
+    // The retry policy of the calling worker while the call is made, instead of the worker's own
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+impl CallOptions {
+    pub fn new() -> Self {
+        CallOptions::default()
+    }
+
+    pub fn with_idempotency_key(mut self, idempotency_key: IdempotencyKey) -> Self {
+        self.idempotency_key = Some(idempotency_key);
+        self
+    }
+
+    pub fn with_new_idempotency_key(self) -> Self {
+        self.with_idempotency_key(IdempotencyKey::generate())
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey(pub String);
+
+impl IdempotencyKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        IdempotencyKey(key.into())
+    }
+
+    // Generated by Golem with `generate-idempotency-key`, which is not replayed:
+    // a worker retried after a crash gets the key it generated before
+    pub fn generate() -> Self {
+        let uuid = crate::host::agent_host().generate_idempotency_key();
+        let bits = ((uuid.high_bits as u128) << 64) | uuid.low_bits as u128;
+        let hex = format!("{:032x}", bits);
+
+        IdempotencyKey(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for IdempotencyKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// The attribute of the invocation context holding the key, as `<worker-name>/<method-name>/<key>`.
+// Attributes are inherited by the calls the target makes in turn, so the target only accepts a key addressed to it
+const IDEMPOTENCY_KEY_ATTRIBUTE: &str = "golem-agentic-idempotency-key";
+
+fn addressed_key(worker_name: &str, method_name: &str, idempotency_key: &IdempotencyKey) -> String {
+    format!("{}/{}/{}", worker_name, method_name, idempotency_key)
+}
+
+// The key of an attribute value addressed to the method of this worker, see `addressed_key`
+#[cfg(any(target_arch = "wasm32", test))]
+fn addressed_to(worker_name: &str, method_name: &str, attribute_value: &str) -> Option<IdempotencyKey> {
+    attribute_value
+        .strip_prefix(&format!("{}/{}/", worker_name, method_name))
+        .map(IdempotencyKey::new)
+}
+
+// Makes a call to a method of an agent in the given worker with the options applied, in an `agent-call` span
+// passed on to the target with the invocation context (see `tracing`). Used by the remote agent clients
+pub fn with_call_options<T>(
+    options: &CallOptions,
+    worker_name: &str,
+    method_name: &str,
+    call: impl FnOnce() -> T,
+) -> T {
+    let span = tracing::call_span(worker_name, method_name);
+    if let Some(idempotency_key) = &options.idempotency_key {
+        span.set_attribute(
+            IDEMPOTENCY_KEY_ATTRIBUTE,
+            addressed_key(worker_name, method_name, idempotency_key),
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use crate::bindings::golem::api::host as golem_host;
+
+        let previous_retry_policy = options.retry_policy.map(|retry_policy| {
+            let previous = golem_host::get_retry_policy();
+            golem_host::set_retry_policy(retry_policy);
+            previous
+        });
+
+        let result = call();
+
+        if let Some(previous) = previous_retry_policy {
+            golem_host::set_retry_policy(previous);
+        }
+
+        span.finish();
+        result
+    }
+
+    // Remote agents are not available natively
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let result = call();
+        span.finish();
+        result
+    }
+}
+
+// The idempotency key the caller addressed to this call of the method, if any
+pub(crate) fn current_idempotency_key(method_name: &str) -> Option<IdempotencyKey> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use crate::bindings::golem::api::context::{self, AttributeValue};
+
+        match context::current_context().get_attribute(IDEMPOTENCY_KEY_ATTRIBUTE, true) {
+            Some(AttributeValue::String(value)) => {
+                addressed_to(&crate::host::agent_host().worker_name(), method_name, &value)
+            }
+            None => None,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = method_name;
+        None
+    }
+}
+
+// The results of the calls made with an idempotency key, by agent id and method name, the most recently used last.
+// They are kept in the memory of the worker, which Golem restores by replaying it, and in snapshots, see `versioning`.
+// Only the latest results of each method are kept, so a call retried after many other calls runs again
+static IDEMPOTENT_RESULTS: Lazy<
+    Mutex<HashMap<(String, String), VecDeque<(IdempotencyKey, Vec<StatusUpdate>)>>>,
+> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub const MAX_IDEMPOTENT_RESULTS_PER_METHOD: usize = 100;
+
+pub(crate) fn idempotent_result(
+    agent_id: &str,
+    method_name: &str,
+    idempotency_key: &IdempotencyKey,
+) -> Option<Vec<StatusUpdate>> {
+    let mut results = IDEMPOTENT_RESULTS.lock().unwrap();
+    let method_results = results.get_mut(&(agent_id.to_string(), method_name.to_string()))?;
+    let index = method_results
+        .iter()
+        .position(|(key, _)| key == idempotency_key)?;
+
+    let result = method_results.remove(index)?;
+    let updates = result.1.clone();
+    method_results.push_back(result);
+    Some(updates)
+}
+
+pub(crate) fn record_idempotent_result(
+    agent_id: &str,
+    method_name: &str,
+    idempotency_key: &IdempotencyKey,
+    updates: Vec<StatusUpdate>,
+) {
+    let mut results = IDEMPOTENT_RESULTS.lock().unwrap();
+    let method_results = results
+        .entry((agent_id.to_string(), method_name.to_string()))
+        .or_default();
+
+    method_results.retain(|(key, _)| key != idempotency_key);
+    method_results.push_back((idempotency_key.clone(), updates));
+    while method_results.len() > MAX_IDEMPOTENT_RESULTS_PER_METHOD {
+        method_results.pop_front();
+    }
+}
+
+pub(crate) fn save_idempotent_results() -> serde_json::Value {
+    let results = IDEMPOTENT_RESULTS.lock().unwrap();
+    let mut methods = results.keys().collect::<Vec<_>>();
+    methods.sort();
+
+    methods
+        .into_iter()
+        .flat_map(|method| {
+            let (agent_id, method_name) = method;
+            results[method].iter().map(move |(idempotency_key, updates)| {
+                json!({
+                    "agentId": agent_id,
+                    "methodName": method_name,
+                    "idempotencyKey": idempotency_key.as_str(),
+                    "updates": updates.iter().map(status_update_to_json).collect::<Vec<_>>(),
+                })
+            })
+        })
+        .collect()
+}
+
+// The results are saved the least recently used first, so recording them in order restores the order
+pub(crate) fn load_idempotent_results(saved: &serde_json::Value) -> Result<(), String> {
+    let Some(saved) = saved.as_array() else {
+        return Ok(());
+    };
+
+    IDEMPOTENT_RESULTS.lock().unwrap().clear();
+    for saved_result in saved {
+        let field = |name: &str| {
+            saved_result[name]
+                .as_str()
+                .map(|value| value.to_string())
+                .ok_or_else(|| format!("Invalid snapshot: {} of an idempotent result is not a string", name))
+        };
+        let updates = saved_result["updates"]
+            .as_array()
+            .ok_or_else(|| "Invalid snapshot: updates of an idempotent result is not an array".to_string())?
+            .iter()
+            .map(status_update_from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        record_idempotent_result(
+            &field("agentId")?,
+            &field("methodName")?,
+            &IdempotencyKey(field("idempotencyKey")?),
+            updates,
+        );
+    }
+
+    Ok(())
+}
+
+fn status_update_to_json(update: &StatusUpdate) -> serde_json::Value {
+    match update {
+        StatusUpdate::Emit(output) => json!({ "emit": output }),
+        StatusUpdate::Progress(None) => json!({ "progress": null }),
+        StatusUpdate::Progress(Some(report)) => json!({
+            "progress": {
+                "description": report.description,
+                "counter": report.counter.as_ref().map(|counter| json!({
+                    "steps": counter.steps,
+                    "total": counter.total,
+                })),
+            }
+        }),
+        StatusUpdate::Error(Error::NetworkError) => json!({ "error": { "networkError": null } }),
+        StatusUpdate::Error(Error::InvalidInput(message)) => {
+            json!({ "error": { "invalidInput": message } })
+        }
+    }
+}
+
+fn status_update_from_json(json: &serde_json::Value) -> Result<StatusUpdate, String> {
+    let invalid = || format!("Invalid snapshot: {} is not a status update", json);
+
+    if let Some(output) = json.get("emit") {
+        return output
+            .as_str()
+            .map(|output| StatusUpdate::Emit(output.to_string()))
+            .ok_or_else(invalid);
+    }
+
+    if let Some(progress) = json.get("progress") {
+        if progress.is_null() {
+            return Ok(StatusUpdate::Progress(None));
+        }
+        let description = progress["description"].as_str().ok_or_else(invalid)?.to_string();
+        let counter = match &progress["counter"] {
+            serde_json::Value::Null => None,
+            counter => Some(ProgressCounter {
+                steps: counter["steps"].as_u64().ok_or_else(invalid)? as u32,
+                total: counter["total"].as_u64().ok_or_else(invalid)? as u32,
+            }),
+        };
+        return Ok(StatusUpdate::Progress(Some(ProgressReport { description, counter })));
+    }
+
+    match json.get("error") {
+        Some(error) if error.get("networkError").is_some() => Ok(StatusUpdate::Error(Error::NetworkError)),
+        Some(error) => error["invalidInput"]
+            .as_str()
+            .map(|message| StatusUpdate::Error(Error::InvalidInput(message.to_string())))
+            .ok_or_else(invalid),
+        None => Err(invalid()),
+    }
+}
+
+pub fn clear_idempotent_results() {
+    IDEMPOTENT_RESULTS.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockHost};
+
+    fn emitted(agent_id: &str, key: &str) -> Option<String> {
+        match idempotent_result(agent_id, "charge", &IdempotencyKey::new(key))?.as_slice() {
+            [StatusUpdate::Emit(output)] => Some(output.clone()),
+            _ => None,
+        }
+    }
+
+    fn record(key: &str) {
+        record_idempotent_result(
+            "test-worker--payments--1",
+            "charge",
+            &IdempotencyKey::new(key),
+            vec![StatusUpdate::Emit(format!("charged {}", key))],
+        );
+    }
+
+    #[test]
+    fn keeps_the_latest_results_of_each_method() {
+        let _host = MockHost::new("test-worker").install();
+
+        for index in 0..=MAX_IDEMPOTENT_RESULTS_PER_METHOD {
+            record(&format!("order-{}", index));
+        }
+
+        assert_eq!(emitted("test-worker--payments--1", "order-0"), None);
+        assert_eq!(
+            emitted("test-worker--payments--1", "order-1"),
+            Some("charged order-1".to_string())
+        );
+        assert_eq!(emitted("test-worker--payments--2", "order-1"), None);
+    }
+
+    #[test]
+    fn keeps_the_results_used_recently() {
+        let _host = MockHost::new("test-worker").install();
+
+        for index in 0..MAX_IDEMPOTENT_RESULTS_PER_METHOD {
+            record(&format!("order-{}", index));
+        }
+        emitted("test-worker--payments--1", "order-0");
+        record("order-new");
+
+        assert_eq!(
+            emitted("test-worker--payments--1", "order-0"),
+            Some("charged order-0".to_string())
+        );
+        assert_eq!(emitted("test-worker--payments--1", "order-1"), None);
+    }
+
+    #[test]
+    fn saves_status_updates_as_json() {
+        let updates = vec![
+            StatusUpdate::Progress(Some(ProgressReport {
+                description: "charging".to_string(),
+                counter: Some(ProgressCounter { steps: 1, total: 2 }),
+            })),
+            StatusUpdate::Progress(None),
+            StatusUpdate::Error(Error::InvalidInput("unknown order".to_string())),
+            StatusUpdate::Error(Error::NetworkError),
+            StatusUpdate::Emit("charged".to_string()),
+        ];
+
+        let restored = updates
+            .iter()
+            .map(|update| status_update_from_json(&status_update_to_json(update)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(format!("{:?}", restored), format!("{:?}", updates));
+    }
+
+    #[test]
+    fn accepts_only_keys_addressed_to_the_method_of_this_worker() {
+        let value = addressed_key("payments", "charge", &IdempotencyKey::new("order/1"));
+
+        assert_eq!(
+            addressed_to("payments", "charge", &value),
+            Some(IdempotencyKey::new("order/1"))
+        );
+        assert_eq!(addressed_to("payments", "refund", &value), None);
+        assert_eq!(addressed_to("orders", "charge", &value), None);
+        assert_eq!(current_idempotency_key("charge"), None);
+    }
+
+    #[test]
+    fn calls_in_a_span_with_the_addressed_key() {
+        let _host = MockHost::new("test-worker").install();
+        let options = CallOptions::new().with_idempotency_key(IdempotencyKey::new("order-1"));
+
+        let result = with_call_options(&options, "payments", "charge", || 42);
+
+        assert_eq!(result, 42);
+        let spans = testing::spans();
+        assert_eq!(spans[0].name, "agent-call");
+        assert_eq!(spans[0].attribute("agent.worker"), Some("payments"));
+        assert_eq!(
+            spans[0].attribute(IDEMPOTENCY_KEY_ATTRIBUTE),
+            Some("payments/charge/order-1")
+        );
+        assert!(spans[0].finished);
+    }
+
+    #[test]
+    fn saves_results_the_least_recently_used_first() {
+        let _host = MockHost::new("test-worker").install();
+        record("order-1");
+        record("order-2");
+        emitted("test-worker--payments--1", "order-1");
+
+        let saved = save_idempotent_results();
+        clear_idempotent_results();
+        load_idempotent_results(&saved).unwrap();
+        for index in 0..MAX_IDEMPOTENT_RESULTS_PER_METHOD - 1 {
+            record(&format!("order-new-{}", index));
+        }
+
+        assert_eq!(emitted("test-worker--payments--1", "order-2"), None);
+        assert_eq!(
+            emitted("test-worker--payments--1", "order-1"),
+            Some("charged order-1".to_string())
+        );
+    }
+}
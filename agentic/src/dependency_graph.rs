@@ -0,0 +1,274 @@
+use crate::agent_registry;
+use crate::bindings::golem::agent::common::AgentType;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Dependencies between agent types, built from `AgentType.requires`.
+// The agent types may come from this component (`from_registry`) or from the
+// `discover-agent-types` of several components, to validate and deploy a multi-agent system as a whole.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AgentDependencyGraph {
+    // agent type name -> (dependency agent type name -> names of the methods used)
+    pub dependencies: BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl AgentDependencyGraph {
+    pub fn from_registry() -> Self {
+        Self::from_agent_types(&agent_registry::get_all_agent_definitions())
+    }
+
+    pub fn from_agent_types(agent_types: &[AgentType]) -> Self {
+        let dependencies = agent_types
+            .iter()
+            .map(|agent_type| {
+                let requires = agent_type
+                    .requires
+                    .iter()
+                    .map(|dependency| {
+                        let methods = dependency
+                            .methods
+                            .iter()
+                            .map(|method| method.name.clone())
+                            .collect();
+                        (dependency.agent_name.clone(), methods)
+                    })
+                    .collect();
+                (agent_type.type_name.clone(), requires)
+            })
+            .collect();
+
+        AgentDependencyGraph { dependencies }
+    }
+
+    pub fn agent_types(&self) -> Vec<String> {
+        self.dependencies.keys().cloned().collect()
+    }
+
+    pub fn dependencies_of(&self, agent_type_name: &str) -> Vec<String> {
+        self.dependencies
+            .get(agent_type_name)
+            .map(|requires| requires.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn dependants_of(&self, agent_type_name: &str) -> Vec<String> {
+        self.dependencies
+            .iter()
+            .filter(|(_, requires)| requires.contains_key(agent_type_name))
+            .map(|(agent_type, _)| agent_type.clone())
+            .collect()
+    }
+
+    // Dependencies that are not part of the graph, as (agent type, missing dependency) pairs
+    pub fn missing_dependencies(&self) -> Vec<(String, String)> {
+        self.dependencies
+            .iter()
+            .flat_map(|(agent_type, requires)| {
+                requires
+                    .keys()
+                    .filter(|dependency| !self.dependencies.contains_key(*dependency))
+                    .map(move |dependency| (agent_type.clone(), dependency.clone()))
+            })
+            .collect()
+    }
+
+    // Agent types ordered such that every agent type comes after its dependencies.
+    // Missing dependencies are ignored, and cycles are reported as an error.
+    pub fn deployment_order(&self) -> Result<Vec<String>, String> {
+        let mut order = Vec::new();
+        let mut done = BTreeSet::new();
+        let mut in_progress = Vec::new();
+
+        for agent_type in self.dependencies.keys() {
+            self.visit(agent_type, &mut done, &mut in_progress, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    fn visit(
+        &self,
+        agent_type: &str,
+        done: &mut BTreeSet<String>,
+        in_progress: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if done.contains(agent_type) || !self.dependencies.contains_key(agent_type) {
+            return Ok(());
+        }
+
+        if let Some(position) = in_progress.iter().position(|t| t == agent_type) {
+            let mut cycle = in_progress[position..].to_vec();
+            cycle.push(agent_type.to_string());
+            return Err(format!("Cyclic agent dependencies: {}", cycle.join(" -> ")));
+        }
+
+        in_progress.push(agent_type.to_string());
+
+        for dependency in self.dependencies_of(agent_type) {
+            self.visit(&dependency, done, in_progress, order)?;
+        }
+
+        in_progress.pop();
+        done.insert(agent_type.to_string());
+        order.push(agent_type.to_string());
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = self
+            .missing_dependencies()
+            .into_iter()
+            .map(|(agent_type, dependency)| {
+                format!(
+                    "Agent type {} depends on {}, which is not available",
+                    agent_type, dependency
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if let Err(cycle) = self.deployment_order() {
+            errors.push(cycle);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::golem::agent::common::{AgentConstructor, AgentDependency};
+    use crate::test_agents;
+    use golem_wasm_ast::analysis::analysed_type::str;
+
+    // An agent type using the given methods of the agent types it depends on
+    fn agent_type(type_name: &str, requires: &[(&str, &[&str])]) -> AgentType {
+        AgentType {
+            type_name: type_name.to_string(),
+            description: format!("The {} agent", type_name),
+            agent_constructor: AgentConstructor {
+                name: None,
+                description: "".to_string(),
+                prompt_hint: None,
+                input_schema: test_agents::schema(vec![]),
+            },
+            methods: vec![test_agents::method("run", vec![], str()).0],
+            requires: requires
+                .iter()
+                .map(|(agent_name, methods)| AgentDependency {
+                    agent_name: agent_name.to_string(),
+                    methods: methods
+                        .iter()
+                        .map(|method| test_agents::method(method, vec![], str()).0)
+                        .collect(),
+                })
+                .collect(),
+            version: 0,
+        }
+    }
+
+    fn graph(agent_types: &[AgentType]) -> AgentDependencyGraph {
+        AgentDependencyGraph::from_agent_types(agent_types)
+    }
+
+    #[test]
+    fn orders_a_chain_from_its_last_dependency() {
+        let graph = graph(&[
+            agent_type("planner", &[("researcher", &["research"])]),
+            agent_type("researcher", &[("search", &["search", "fetch"])]),
+            agent_type("search", &[]),
+        ]);
+
+        assert_eq!(
+            graph.deployment_order(),
+            Ok(vec![
+                "search".to_string(),
+                "researcher".to_string(),
+                "planner".to_string()
+            ])
+        );
+        assert_eq!(graph.dependencies_of("researcher"), vec!["search"]);
+        assert_eq!(graph.dependants_of("researcher"), vec!["planner"]);
+        assert_eq!(
+            graph.dependencies["researcher"]["search"],
+            BTreeSet::from(["fetch".to_string(), "search".to_string()])
+        );
+        assert!(graph.missing_dependencies().is_empty());
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn orders_the_shared_dependency_of_a_diamond_once() {
+        let graph = graph(&[
+            agent_type("app", &[("left", &["run"]), ("right", &["run"])]),
+            agent_type("left", &[("base", &["run"])]),
+            agent_type("right", &[("base", &["run"])]),
+            agent_type("base", &[]),
+        ]);
+
+        assert_eq!(
+            graph.deployment_order(),
+            Ok(vec![
+                "base".to_string(),
+                "left".to_string(),
+                "right".to_string(),
+                "app".to_string()
+            ])
+        );
+        assert_eq!(graph.dependants_of("base"), vec!["left", "right"]);
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reports_the_agent_types_of_a_cycle() {
+        let graph = graph(&[
+            agent_type("a", &[("b", &["run"])]),
+            agent_type("b", &[("c", &["run"])]),
+            agent_type("c", &[("a", &["run"])]),
+            agent_type("d", &[("a", &["run"])]),
+        ]);
+
+        assert_eq!(
+            graph.deployment_order(),
+            Err("Cyclic agent dependencies: a -> b -> c -> a".to_string())
+        );
+        assert_eq!(
+            graph.validate(),
+            Err(vec![
+                "Cyclic agent dependencies: a -> b -> c -> a".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_dependencies_on_unavailable_agent_types() {
+        let graph = graph(&[
+            agent_type(
+                "planner",
+                &[("weather", &["get-forecast"]), ("search", &["search"])],
+            ),
+            agent_type("search", &[]),
+        ]);
+
+        assert_eq!(
+            graph.missing_dependencies(),
+            vec![("planner".to_string(), "weather".to_string())]
+        );
+        // Missing dependencies don't keep the available agent types from being deployed
+        assert_eq!(
+            graph.deployment_order(),
+            Ok(vec!["search".to_string(), "planner".to_string()])
+        );
+        assert_eq!(
+            graph.validate(),
+            Err(vec![
+                "Agent type planner depends on weather, which is not available".to_string()
+            ])
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::bindings::golem::api::context::{self, AttributeValue, Span};
+use crate::bindings::golem::api::host as golem_host;
+use crate::bindings::golem::api::host::PromiseId;
+use crate::bindings::wasi::config::store as config_store;
+use crate::bindings::wasi::logging::logging as wasi_logging;
+use crate::logging::LogRecord;
+use golem_wasm_rpc::golem_rpc_0_2_x::types::{CancellationToken, Datetime};
+use golem_wasm_rpc::{ComponentId, Uuid, Value, WasmRpc, WitValue};
+use once_cell::sync::Lazy;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// The parts of the Golem host API the agent runtime depends on.
+// Inside a component these are answered by Golem, while native tests install
+// another implementation with `set_agent_host` (see `testing::MockHost`)
+pub trait AgentHost: Send + Sync {
+    fn worker_name(&self) -> String;
+
+    fn component_id(&self) -> ComponentId;
+
+    // The component defining the given agent type, if any
+    fn agent_component(&self, agent_type_name: &str) -> Option<ComponentId>;
+
+    // A key that is the same when the worker is replayed, see `call_options::IdempotencyKey`
+    fn generate_idempotency_key(&self) -> Uuid;
+
+    fn create_promise(&self) -> PromiseId;
+
+    // Blocks until the promise is completed, and returns the data it was completed with
+    fn await_promise(&self, promise_id: &PromiseId) -> Vec<u8>;
+
+    // The data the promise was completed with, if it is completed
+    fn poll_promise(&self, promise_id: &PromiseId) -> Option<Vec<u8>>;
+
+    // False if the promise was completed before
+    fn complete_promise(&self, promise_id: &PromiseId, data: &[u8]) -> bool;
+
+    // Makes Golem invoke `run-scheduled` of this worker with the schedule id at the given time,
+    // replacing the invocation scheduled before with the same id, see `scheduling`
+    fn schedule_invocation(&self, at: Datetime, schedule_id: u64);
+
+    fn cancel_scheduled_invocation(&self, schedule_id: u64);
+
+    // Starts a span of the invocation context, a child of the current span, see `tracing`.
+    // Returns a handle to the span, which is open until it is finished
+    fn start_span(&self, name: &str) -> u64;
+
+    fn set_span_attribute(&self, span: u64, name: &str, value: &str);
+
+    fn finish_span(&self, span: u64);
+
+    // The `traceparent` and `tracestate` headers of the current span, for calls made outside of Golem
+    fn trace_context_headers(&self) -> Vec<(String, String)>;
+
+    // Writes a record of the `log` crate to the logs of the worker, see `logging`
+    fn log(&self, record: &LogRecord);
+
+    // A value of the configuration of the worker
+    fn config_value(&self, key: &str) -> Option<String>;
+}
+
+pub struct GolemHost;
+
+impl AgentHost for GolemHost {
+    fn worker_name(&self) -> String {
+        golem_host::get_self_metadata().worker_id.worker_name
+    }
+
+    fn component_id(&self) -> ComponentId {
+        golem_host::get_self_metadata().worker_id.component_id
+    }
+
+    fn agent_component(&self, agent_type_name: &str) -> Option<ComponentId> {
+        golem_host::get_agent_component(agent_type_name)
+    }
+
+    fn generate_idempotency_key(&self) -> Uuid {
+        golem_host::generate_idempotency_key()
+    }
+
+    fn create_promise(&self) -> PromiseId {
+        golem_host::create_promise()
+    }
+
+    fn await_promise(&self, promise_id: &PromiseId) -> Vec<u8> {
+        golem_host::await_promise(promise_id)
+    }
+
+    fn poll_promise(&self, promise_id: &PromiseId) -> Option<Vec<u8>> {
+        golem_host::poll_promise(promise_id)
+    }
+
+    fn complete_promise(&self, promise_id: &PromiseId, data: &[u8]) -> bool {
+        golem_host::complete_promise(promise_id, data)
+    }
+
+    fn schedule_invocation(&self, at: Datetime, schedule_id: u64) {
+        let rpc = WasmRpc::new(&golem_host::get_self_metadata().worker_id);
+        let cancellation_token = rpc.schedule_cancelable_invocation(
+            at,
+            "golem:agent/guest.{run-scheduled}",
+            &[WitValue::from(Value::U64(schedule_id))],
+        );
+
+        CANCELLATION_TOKENS.with(|tokens| {
+            if let Some(previous) = tokens.borrow_mut().insert(schedule_id, cancellation_token) {
+                previous.cancel();
+            }
+        });
+    }
+
+    fn cancel_scheduled_invocation(&self, schedule_id: u64) {
+        CANCELLATION_TOKENS.with(|tokens| {
+            if let Some(cancellation_token) = tokens.borrow_mut().remove(&schedule_id) {
+                cancellation_token.cancel();
+            }
+        });
+    }
+
+    fn start_span(&self, name: &str) -> u64 {
+        let span = context::start_span(name);
+        let handle = LAST_SPAN.with(|last_span| {
+            last_span.set(last_span.get() + 1);
+            last_span.get()
+        });
+
+        SPANS.with(|spans| spans.borrow_mut().insert(handle, span));
+        handle
+    }
+
+    fn set_span_attribute(&self, span: u64, name: &str, value: &str) {
+        SPANS.with(|spans| {
+            if let Some(span) = spans.borrow().get(&span) {
+                span.set_attribute(name, &AttributeValue::String(value.to_string()));
+            }
+        });
+    }
+
+    fn finish_span(&self, span: u64) {
+        if let Some(span) = SPANS.with(|spans| spans.borrow_mut().remove(&span)) {
+            span.finish();
+        }
+    }
+
+    fn trace_context_headers(&self) -> Vec<(String, String)> {
+        context::current_context().trace_context_headers()
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let level = match record.level {
+            log::Level::Trace => wasi_logging::Level::Trace,
+            log::Level::Debug => wasi_logging::Level::Debug,
+            log::Level::Info => wasi_logging::Level::Info,
+            log::Level::Warn => wasi_logging::Level::Warn,
+            log::Level::Error => wasi_logging::Level::Error,
+        };
+
+        wasi_logging::log(level, &record.context(), &record.message);
+    }
+
+    fn config_value(&self, key: &str) -> Option<String> {
+        config_store::get(key).ok().flatten()
+    }
+}
+
+// Components are single threaded, and the tokens and spans are resources that can't be shared between threads
+thread_local! {
+    static CANCELLATION_TOKENS: RefCell<HashMap<u64, CancellationToken>> = RefCell::new(HashMap::new());
+
+    static SPANS: RefCell<HashMap<u64, Span>> = RefCell::new(HashMap::new());
+
+    static LAST_SPAN: Cell<u64> = const { Cell::new(0) };
+}
+
+static AGENT_HOST: Lazy<Mutex<Option<Arc<dyn AgentHost>>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn set_agent_host(host: Arc<dyn AgentHost>) {
+    *AGENT_HOST.lock().unwrap() = Some(host);
+}
+
+pub fn reset_agent_host() {
+    *AGENT_HOST.lock().unwrap() = None;
+}
+
+pub(crate) fn is_agent_host_set() -> bool {
+    AGENT_HOST.lock().unwrap().is_some()
+}
+
+pub fn agent_host() -> Arc<dyn AgentHost> {
+    AGENT_HOST
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| Arc::new(GolemHost))
+}
@@ -0,0 +1,403 @@
+use golem_wasm_ast::analysis::{
+    AnalysedType, NameOptionTypePair, NameTypePair, TypeEnum, TypeFlags, TypeList, TypeOption,
+    TypeRecord, TypeResult, TypeTuple, TypeVariant,
+};
+use golem_wasm_rpc::Value;
+use serde_json::{json, Map};
+
+// JSON representation of values, shared by everything that talks to the outside world
+// using JSON (LLM tool calls, CLIs etc).
+//
+// record  -> object with the field names as keys
+// variant -> `{"<case>": <value>}`, or just `"<case>"` for cases without a payload
+// enum    -> string
+// flags   -> list of the names of the flags that are set
+// option  -> `null` or the inner value
+// result  -> `{"ok": <value>}` or `{"err": <value>}`
+// tuple   -> array
+// list    -> array
+
+pub fn wit_type_to_json_schema(typ: &AnalysedType) -> serde_json::Value {
+    match typ {
+        AnalysedType::Bool(_) => json!({ "type": "boolean" }),
+        AnalysedType::Str(_) => json!({ "type": "string" }),
+        AnalysedType::Chr(_) => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        AnalysedType::F32(_) | AnalysedType::F64(_) => json!({ "type": "number" }),
+        AnalysedType::U8(_) | AnalysedType::U16(_) | AnalysedType::U32(_) | AnalysedType::U64(_) => {
+            json!({ "type": "integer", "minimum": 0 })
+        }
+        AnalysedType::S8(_) | AnalysedType::S16(_) | AnalysedType::S32(_) | AnalysedType::S64(_) => {
+            json!({ "type": "integer" })
+        }
+        AnalysedType::List(TypeList { inner, .. }) => {
+            json!({ "type": "array", "items": wit_type_to_json_schema(inner) })
+        }
+        AnalysedType::Tuple(TypeTuple { items, .. }) => json!({
+            "type": "array",
+            "prefixItems": items.iter().map(wit_type_to_json_schema).collect::<Vec<_>>(),
+            "minItems": items.len(),
+            "maxItems": items.len(),
+        }),
+        AnalysedType::Record(TypeRecord { fields, .. }) => object_schema(
+            fields
+                .iter()
+                .map(|NameTypePair { name, typ }| (name.clone(), wit_type_to_json_schema(typ)))
+                .collect(),
+        ),
+        AnalysedType::Option(TypeOption { inner, .. }) => {
+            json!({ "anyOf": [wit_type_to_json_schema(inner), { "type": "null" }] })
+        }
+        AnalysedType::Enum(TypeEnum { cases, .. }) => json!({ "type": "string", "enum": cases }),
+        AnalysedType::Flags(TypeFlags { names, .. }) => json!({
+            "type": "array",
+            "items": { "type": "string", "enum": names },
+            "uniqueItems": true,
+        }),
+        AnalysedType::Variant(TypeVariant { cases, .. }) => {
+            let alternatives = cases
+                .iter()
+                .map(|NameOptionTypePair { name, typ }| match typ {
+                    Some(typ) => object_schema(vec![(name.clone(), wit_type_to_json_schema(typ))]),
+                    None => json!({ "type": "string", "const": name }),
+                })
+                .collect::<Vec<_>>();
+            json!({ "oneOf": alternatives })
+        }
+        AnalysedType::Result(TypeResult { ok, err, .. }) => {
+            let case_schema = |case: &Option<Box<AnalysedType>>| match case {
+                Some(typ) => wit_type_to_json_schema(typ),
+                None => json!({ "type": "null" }),
+            };
+            json!({
+                "oneOf": [
+                    object_schema(vec![("ok".to_string(), case_schema(ok))]),
+                    object_schema(vec![("err".to_string(), case_schema(err))]),
+                ]
+            })
+        }
+        AnalysedType::Handle(_) => json!({ "type": "string", "description": "resource handle uri" }),
+    }
+}
+
+// Schema of an object with the given properties, all of them required
+pub fn object_schema(properties: Vec<(String, serde_json::Value)>) -> serde_json::Value {
+    let required = properties
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+
+    json!({
+        "type": "object",
+        "properties": properties.into_iter().collect::<Map<_, _>>(),
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+pub fn json_to_value(json: &serde_json::Value, typ: &AnalysedType) -> Result<Value, String> {
+    match typ {
+        AnalysedType::Bool(_) => json
+            .as_bool()
+            .map(Value::Bool)
+            .ok_or_else(|| type_error("boolean", json)),
+        AnalysedType::Str(_) => json
+            .as_str()
+            .map(|s| Value::String(s.to_string()))
+            .ok_or_else(|| type_error("string", json)),
+        AnalysedType::Chr(_) => {
+            let s = json.as_str().ok_or_else(|| type_error("char", json))?;
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Value::Char(c)),
+                _ => Err(type_error("char", json)),
+            }
+        }
+        AnalysedType::F32(_) => json
+            .as_f64()
+            .map(|n| Value::F32(n as f32))
+            .ok_or_else(|| type_error("number", json)),
+        AnalysedType::F64(_) => json
+            .as_f64()
+            .map(Value::F64)
+            .ok_or_else(|| type_error("number", json)),
+        AnalysedType::U8(_) => unsigned(json, "u8").map(Value::U8),
+        AnalysedType::U16(_) => unsigned(json, "u16").map(Value::U16),
+        AnalysedType::U32(_) => unsigned(json, "u32").map(Value::U32),
+        AnalysedType::U64(_) => unsigned(json, "u64").map(Value::U64),
+        AnalysedType::S8(_) => signed(json, "s8").map(Value::S8),
+        AnalysedType::S16(_) => signed(json, "s16").map(Value::S16),
+        AnalysedType::S32(_) => signed(json, "s32").map(Value::S32),
+        AnalysedType::S64(_) => signed(json, "s64").map(Value::S64),
+        AnalysedType::List(TypeList { inner, .. }) => {
+            let items = json.as_array().ok_or_else(|| type_error("array", json))?;
+            items
+                .iter()
+                .map(|item| json_to_value(item, inner))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::List)
+        }
+        AnalysedType::Tuple(TypeTuple { items, .. }) => {
+            let values = json.as_array().ok_or_else(|| type_error("array", json))?;
+            if values.len() != items.len() {
+                return Err(format!(
+                    "Expected a tuple of {} items, but got {} items",
+                    items.len(),
+                    values.len()
+                ));
+            }
+            values
+                .iter()
+                .zip(items)
+                .map(|(value, typ)| json_to_value(value, typ))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Tuple)
+        }
+        AnalysedType::Record(TypeRecord { fields, .. }) => {
+            let object = json.as_object().ok_or_else(|| type_error("object", json))?;
+            fields
+                .iter()
+                .map(|NameTypePair { name, typ }| {
+                    let field = object.get(name).unwrap_or(&serde_json::Value::Null);
+                    json_to_value(field, typ).map_err(|e| format!("Field '{}': {}", name, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Record)
+        }
+        AnalysedType::Option(TypeOption { inner, .. }) => match json {
+            serde_json::Value::Null => Ok(Value::Option(None)),
+            other => Ok(Value::Option(Some(Box::new(json_to_value(other, inner)?)))),
+        },
+        AnalysedType::Enum(TypeEnum { cases, .. }) => {
+            let case = json.as_str().ok_or_else(|| type_error("string", json))?;
+            cases
+                .iter()
+                .position(|c| c == case)
+                .map(|idx| Value::Enum(idx as u32))
+                .ok_or_else(|| format!("Unknown enum case '{}', expected one of {:?}", case, cases))
+        }
+        AnalysedType::Flags(TypeFlags { names, .. }) => {
+            let set = json.as_array().ok_or_else(|| type_error("array", json))?;
+            let set = set
+                .iter()
+                .map(|flag| flag.as_str().ok_or_else(|| type_error("string", flag)))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(unknown) = set.iter().find(|flag| !names.iter().any(|n| n == *flag)) {
+                return Err(format!("Unknown flag '{}', expected any of {:?}", unknown, names));
+            }
+            Ok(Value::Flags(
+                names.iter().map(|name| set.contains(&name.as_str())).collect(),
+            ))
+        }
+        AnalysedType::Variant(TypeVariant { cases, .. }) => {
+            let (case_name, payload) = single_key(json)?;
+            let case_idx = cases
+                .iter()
+                .position(|c| c.name == case_name)
+                .ok_or_else(|| format!("Unknown variant case '{}'", case_name))?;
+            let case_value = match (&cases[case_idx].typ, payload) {
+                (Some(typ), Some(payload)) => Some(Box::new(json_to_value(payload, typ)?)),
+                (None, None) | (None, Some(serde_json::Value::Null)) => None,
+                (Some(_), None) => {
+                    return Err(format!("Variant case '{}' requires a value", case_name))
+                }
+                (None, Some(_)) => {
+                    return Err(format!("Variant case '{}' does not take a value", case_name))
+                }
+            };
+            Ok(Value::Variant {
+                case_idx: case_idx as u32,
+                case_value,
+            })
+        }
+        AnalysedType::Result(TypeResult { ok, err, .. }) => {
+            let (case_name, payload) = single_key(json)?;
+            let convert = |typ: &Option<Box<AnalysedType>>| match (typ, payload) {
+                (Some(typ), Some(payload)) => json_to_value(payload, typ).map(|v| Some(Box::new(v))),
+                (Some(_), None) => Err(format!("Result case '{}' requires a value", case_name)),
+                (None, _) => Ok(None),
+            };
+            match case_name.as_str() {
+                "ok" => Ok(Value::Result(Ok(convert(ok)?))),
+                "err" => Ok(Value::Result(Err(convert(err)?))),
+                other => Err(format!("Expected 'ok' or 'err', but got '{}'", other)),
+            }
+        }
+        AnalysedType::Handle(_) => Err("Resource handles cannot be created from JSON".to_string()),
+    }
+}
+
+pub fn value_to_json(value: &Value, typ: &AnalysedType) -> Result<serde_json::Value, String> {
+    match (value, typ) {
+        (Value::Bool(b), AnalysedType::Bool(_)) => Ok(json!(b)),
+        (Value::String(s), AnalysedType::Str(_)) => Ok(json!(s)),
+        (Value::Char(c), AnalysedType::Chr(_)) => Ok(json!(c.to_string())),
+        (Value::F32(n), AnalysedType::F32(_)) => Ok(json!(n)),
+        (Value::F64(n), AnalysedType::F64(_)) => Ok(json!(n)),
+        (Value::U8(n), AnalysedType::U8(_)) => Ok(json!(n)),
+        (Value::U16(n), AnalysedType::U16(_)) => Ok(json!(n)),
+        (Value::U32(n), AnalysedType::U32(_)) => Ok(json!(n)),
+        (Value::U64(n), AnalysedType::U64(_)) => Ok(json!(n)),
+        (Value::S8(n), AnalysedType::S8(_)) => Ok(json!(n)),
+        (Value::S16(n), AnalysedType::S16(_)) => Ok(json!(n)),
+        (Value::S32(n), AnalysedType::S32(_)) => Ok(json!(n)),
+        (Value::S64(n), AnalysedType::S64(_)) => Ok(json!(n)),
+        (Value::List(values), AnalysedType::List(TypeList { inner, .. })) => values
+            .iter()
+            .map(|v| value_to_json(v, inner))
+            .collect::<Result<Vec<_>, _>>()
+            .map(serde_json::Value::Array),
+        (Value::Tuple(values), AnalysedType::Tuple(TypeTuple { items, .. })) => {
+            if values.len() != items.len() {
+                return Err(format!(
+                    "Expected a tuple of {} items, but got {} items",
+                    items.len(),
+                    values.len()
+                ));
+            }
+            values
+                .iter()
+                .zip(items)
+                .map(|(v, t)| value_to_json(v, t))
+                .collect::<Result<Vec<_>, _>>()
+                .map(serde_json::Value::Array)
+        }
+        (Value::Record(values), AnalysedType::Record(TypeRecord { fields, .. })) => {
+            if values.len() != fields.len() {
+                return Err(format!(
+                    "Expected a record with the fields {}, but got {} fields",
+                    fields
+                        .iter()
+                        .map(|field| field.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    values.len()
+                ));
+            }
+            values
+                .iter()
+                .zip(fields)
+                .map(|(v, field)| value_to_json(v, &field.typ).map(|j| (field.name.clone(), j)))
+                .collect::<Result<Map<_, _>, _>>()
+                .map(serde_json::Value::Object)
+        }
+        (Value::Option(None), AnalysedType::Option(_)) => Ok(serde_json::Value::Null),
+        (Value::Option(Some(v)), AnalysedType::Option(TypeOption { inner, .. })) => {
+            value_to_json(v, inner)
+        }
+        (Value::Enum(idx), AnalysedType::Enum(TypeEnum { cases, .. })) => cases
+            .get(*idx as usize)
+            .map(|case| json!(case))
+            .ok_or_else(|| format!("Enum case index {} out of range", idx)),
+        (Value::Flags(set), AnalysedType::Flags(TypeFlags { names, .. })) => Ok(json!(names
+            .iter()
+            .zip(set)
+            .filter(|(_, is_set)| **is_set)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>())),
+        (
+            Value::Variant {
+                case_idx,
+                case_value,
+            },
+            AnalysedType::Variant(TypeVariant { cases, .. }),
+        ) => {
+            let case = cases
+                .get(*case_idx as usize)
+                .ok_or_else(|| format!("Variant case index {} out of range", case_idx))?;
+            match (case_value, &case.typ) {
+                (Some(v), Some(t)) => Ok(json!({ case.name.clone(): value_to_json(v, t)? })),
+                _ => Ok(json!(case.name)),
+            }
+        }
+        (Value::Result(result), AnalysedType::Result(TypeResult { ok, err, .. })) => {
+            let (key, v, t) = match result {
+                Ok(v) => ("ok", v, ok),
+                Err(v) => ("err", v, err),
+            };
+            let inner = match (v, t) {
+                (Some(v), Some(t)) => value_to_json(v, t)?,
+                _ => serde_json::Value::Null,
+            };
+            Ok(json!({ key: inner }))
+        }
+        (Value::Handle { uri, resource_id }, AnalysedType::Handle(_)) => {
+            Ok(json!(format!("{}/{}", uri, resource_id)))
+        }
+        (value, typ) => Err(format!(
+            "Value {:?} does not match the type {:?}",
+            value, typ
+        )),
+    }
+}
+
+fn single_key(
+    json: &serde_json::Value,
+) -> Result<(String, Option<&serde_json::Value>), String> {
+    match json {
+        serde_json::Value::String(name) => Ok((name.clone(), None)),
+        serde_json::Value::Object(object) if object.len() == 1 => {
+            let (name, value) = object.iter().next().unwrap();
+            Ok((name.clone(), Some(value)))
+        }
+        other => Err(type_error("object with a single key", other)),
+    }
+}
+
+fn unsigned<T: TryFrom<u64>>(json: &serde_json::Value, name: &str) -> Result<T, String> {
+    json.as_u64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| type_error(name, json))
+}
+
+fn signed<T: TryFrom<i64>>(json: &serde_json::Value, name: &str) -> Result<T, String> {
+    json.as_i64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| type_error(name, json))
+}
+
+fn type_error(expected: &str, json: &serde_json::Value) -> String {
+    format!("Expected {}, but got {}", expected, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use golem_wasm_ast::analysis::analysed_type::{field, record, str, tuple, u32};
+
+    #[test]
+    fn records_round_trip_through_json() {
+        let typ = record(vec![field("city", str()), field("days", u32())]);
+        let value = Value::Record(vec![Value::String("Berlin".to_string()), Value::U32(3)]);
+
+        let json = value_to_json(&value, &typ).unwrap();
+
+        assert_eq!(json, json!({ "city": "Berlin", "days": 3 }));
+        assert_eq!(json_to_value(&json, &typ), Ok(value));
+    }
+
+    #[test]
+    fn records_with_missing_fields_are_rejected() {
+        let typ = record(vec![field("city", str()), field("days", u32())]);
+        let value = Value::Record(vec![Value::String("Berlin".to_string())]);
+
+        assert_eq!(
+            value_to_json(&value, &typ),
+            Err("Expected a record with the fields city, days, but got 1 fields".to_string())
+        );
+    }
+
+    #[test]
+    fn tuples_with_extra_items_are_rejected() {
+        let typ = tuple(vec![str(), u32()]);
+        let value = Value::Tuple(vec![
+            Value::String("Berlin".to_string()),
+            Value::U32(3),
+            Value::U32(4),
+        ]);
+
+        assert_eq!(
+            value_to_json(&value, &typ),
+            Err("Expected a tuple of 2 items, but got 3 items".to_string())
+        );
+    }
+}
@@ -1,17 +1,40 @@
 use crate::agent_registry::AgentId;
 use crate::bindings::exports::golem::agent::guest::{AgentRef, StatusUpdate};
-use crate::bindings::exports::golem::agent::guest::{AgentType, Guest, GuestAgent};
-use crate::bindings::golem::api::host;
+use crate::bindings::exports::golem::agent::guest::{Agent, AgentType, Guest, GuestAgent};
+use crate::bindings::golem::agent::common::{AgentMemory, Error, ExternalRequest};
 use golem_wasm_rpc::WitValue;
 
 pub use type_mapping::*;
 pub use agent_construct::*;
+pub use memory::Memory;
+pub use status::{Emitter, ProgressReporter};
+pub use text::Text;
 
 pub mod agent;
+pub mod agent_loop;
+pub mod approval;
 pub mod agent_instance_registry;
 pub mod agent_registry;
 pub mod bindings;
+pub mod call_options;
+pub mod dependency_graph;
+pub mod host;
+pub mod json_mapping;
+pub mod logging;
+pub mod llm;
+pub mod memory;
+pub mod scheduling;
+pub mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod text;
+pub mod tools;
+pub mod tracing;
+pub mod validation;
+pub mod versioning;
 mod type_mapping;
+#[cfg(test)]
+mod test_agents;
 mod agent_construct;
 
 #[derive(Clone)]
@@ -20,6 +43,80 @@ pub struct ResolvedAgent {
     pub agent_id: String,
 }
 
+impl ResolvedAgent {
+    pub(crate) fn run(&self, method_name: String, input: Vec<WitValue>) -> Vec<StatusUpdate> {
+        let idempotency_key = call_options::current_idempotency_key(&method_name);
+        self.run_with_idempotency_key(idempotency_key.as_ref(), method_name, input)
+    }
+
+    // A call with an idempotency key that already ran returns the updates of the first call, see `call_options`
+    pub(crate) fn run_with_idempotency_key(
+        &self,
+        idempotency_key: Option<&call_options::IdempotencyKey>,
+        method_name: String,
+        input: Vec<WitValue>,
+    ) -> Vec<StatusUpdate> {
+        let span = tracing::invoke_span(
+            &self.agent.get_definition().type_name,
+            &self.agent_id,
+            &method_name,
+            &input,
+        );
+
+        let updates =
+            self.run_with_idempotency_key_in_span(&span, idempotency_key, method_name, input);
+        tracing::set_outcome(&span, &updates);
+        updates
+    }
+
+    fn run_with_idempotency_key_in_span(
+        &self,
+        span: &tracing::SpanGuard,
+        idempotency_key: Option<&call_options::IdempotencyKey>,
+        method_name: String,
+        input: Vec<WitValue>,
+    ) -> Vec<StatusUpdate> {
+        let Some(idempotency_key) = idempotency_key else {
+            return self.run_once(method_name, input);
+        };
+
+        span.set_attribute("agent.idempotency-key", idempotency_key.as_str());
+
+        if let Some(updates) =
+            call_options::idempotent_result(&self.agent_id, &method_name, idempotency_key)
+        {
+            span.set_attribute("agent.idempotent-replay", "true");
+            return updates;
+        }
+
+        let updates = self.run_once(method_name.clone(), input);
+        call_options::record_idempotent_result(
+            &self.agent_id,
+            &method_name,
+            idempotency_key,
+            updates.clone(),
+        );
+        updates
+    }
+
+    fn run_once(&self, method_name: String, input: Vec<WitValue>) -> Vec<StatusUpdate> {
+        let definition = self.agent.get_definition();
+
+        if let Err(error) = validation::validate_method_input(&definition, &method_name, &input) {
+            return vec![StatusUpdate::Error(Error::InvalidInput(error.to_string()))];
+        }
+
+        if let Err(error) = text::validate_text_parameters(&definition, &method_name, &input) {
+            return vec![StatusUpdate::Error(Error::InvalidInput(error))];
+        }
+
+        let invoked_method = method_name.clone();
+        agent::with_current_agent(&self.agent_id, &invoked_method, || {
+            status::collect_status_updates(|| self.agent.invoke(method_name, input))
+        })
+    }
+}
+
 struct Component;
 
 impl Guest for Component {
@@ -49,38 +146,43 @@ impl Guest for Component {
     }
 
     fn discover_agents() -> Vec<AgentRef> {
-        let agent_names = agent_registry::get_all_agent_definitions()
-            .iter()
-            .map(|x| x.type_name.clone())
-            .collect::<Vec<_>>();
+        agent_registry::discover_agents()
+    }
 
-        let worker_name = host::get_self_metadata().worker_id.worker_name.clone();
+    fn discover_pending_requests() -> Vec<ExternalRequest> {
+        approval::pending_requests()
+    }
 
-        let mut agents = Vec::new();
+    fn get_memories(agent_id: String) -> Vec<AgentMemory> {
+        memory::agent_memories(&agent_id)
+    }
 
-        for agent_name in agent_names {
-            let prefix = format!("{}--{}", worker_name, agent_name);
+    fn run_scheduled(schedule_id: u64) {
+        scheduling::run_scheduled(schedule_id)
+    }
+}
 
-            agent_registry::get_agent_instances_by_prefix(&prefix)
-                .into_iter()
-                .for_each(|agent_instance| {
-                    agents.push(agent_instance);
-                });
-        }
+// Snapshots hold the agents of the worker, so that they survive snapshot-based updates, see `versioning`
+impl bindings::exports::golem::api::save_snapshot::Guest for Component {
+    fn save() -> Vec<u8> {
+        versioning::save_snapshot().unwrap_or_else(|error| versioning::failed_snapshot(&error))
+    }
+}
 
-        agents
+impl bindings::exports::golem::api::load_snapshot::Guest for Component {
+    fn load(bytes: Vec<u8>) -> Result<(), String> {
+        versioning::load_snapshot(bytes)
     }
 }
 
 impl GuestAgent for ResolvedAgent {
-    fn new(agent_type: String, params: Vec<golem_wasm_rpc::WitValue>) -> ResolvedAgent {
+    fn create(agent_type: String, params: Vec<golem_wasm_rpc::WitValue>) -> Result<Agent, Error> {
+        let span = tracing::construct_span(&agent_type, &params);
+
         let agent_types = agent_registry::get_all_agent_definitions();
 
-        let agent_type = agent_types
-            .iter()
-            .find(|x| x.type_name == agent_type)
-            .expect(
-            format!(
+        let Some(agent_type) = agent_types.iter().find(|x| x.type_name == agent_type) else {
+            let error = format!(
                 "Agent definition not found for agent name: {}. Available agents in this app is {}",
                 agent_type,
                 agent_types
@@ -88,15 +190,43 @@ impl GuestAgent for ResolvedAgent {
                     .map(|x| x.type_name.clone())
                     .collect::<Vec<_>>()
                     .join(", ")
-            )
-            .as_str(),
-        );
+            );
+            span.set_attribute("agent.outcome", "invalid-input");
+            span.set_attribute("agent.error", &error);
+            return Err(Error::InvalidInput(error));
+        };
+
+        if let Err(error) = validation::validate_constructor_input(agent_type, &params)
+            .map_err(|error| error.to_string())
+            .and_then(|_| text::validate_constructor_text(agent_type, &params))
+        {
+            span.set_attribute("agent.outcome", "invalid-input");
+            span.set_attribute("agent.error", &error);
+            return Err(Error::InvalidInput(format!(
+                "Invalid constructor parameters for agent {}: {}",
+                agent_type.type_name, error
+            )));
+        }
 
         let agent_initiator = agent_registry::get_agent_initiator(agent_type.type_name.clone());
 
         if let Some(agent) = agent_initiator {
-            let agent = agent.initiate(params);
-            agent
+            match agent.initiate(params) {
+                Ok(agent) => {
+                    span.set_attribute("agent.id", &agent.agent_id);
+                    span.set_attribute("agent.outcome", "success");
+                    Ok(Agent::new(agent))
+                }
+                // E.g. a remote agent it depends on that can't be resolved
+                Err(error) => {
+                    span.set_attribute("agent.outcome", "invalid-input");
+                    span.set_attribute("agent.error", &error);
+                    Err(Error::InvalidInput(format!(
+                        "Failed to construct agent {}: {}",
+                        agent_type.type_name, error
+                    )))
+                }
+            }
         } else {
             panic!(
                 "No agent implementation found for agent definition: {}",
@@ -110,7 +240,12 @@ impl GuestAgent for ResolvedAgent {
     }
 
     fn invoke(&self, method_name: String, input: Vec<WitValue>) -> StatusUpdate {
-        self.agent.invoke(method_name, input)
+        let updates = self.run(method_name, input);
+        updates.into_iter().last().expect("an invocation always has a result")
+    }
+
+    fn invoke_with_updates(&self, method_name: String, input: Vec<WitValue>) -> Vec<StatusUpdate> {
+        self.run(method_name, input)
     }
 
     fn get_definition(&self) -> AgentType {
@@ -119,3 +254,46 @@ impl GuestAgent for ResolvedAgent {
 }
 
 bindings::export!(Component with_types_in bindings);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockHost;
+    use golem_wasm_ast::analysis::analysed_type::str;
+    use golem_wasm_rpc::Value;
+
+    fn register_greeter() {
+        test_agents::register("greeter", vec![str()], vec![], |_, _, _| {
+            StatusUpdate::Emit("Hello".to_string())
+        });
+    }
+
+    // Successful creations wrap the agent in a resource, which is only possible in a component
+    #[test]
+    fn rejects_invalid_constructor_parameters() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+
+        let result = ResolvedAgent::create("greeter".to_string(), vec![WitValue::from(Value::U32(1))]);
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidInput(error))
+                if error == "Invalid constructor parameters for agent greeter: parameter 0: expected a value of type string, but got u32"
+        ));
+        assert!(testing::live_agents().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_agent_types() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+
+        let result = ResolvedAgent::create("weather-agent".to_string(), vec![]);
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidInput(error)) if error.starts_with("Agent definition not found for agent name: weather-agent")
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+mod anthropic;
+mod http;
+#[cfg(test)]
+mod mock_server;
+mod openai;
+mod sse;
+
+pub use crate::memory::{Message, MessagePart, Role};
+pub use crate::tools::{ToolCall, ToolDefinition, Toolbox};
+pub use anthropic::Anthropic;
+pub use http::{default_http_client, DefaultHttpClient, HttpClient, HttpResponse};
+pub use openai::OpenAiCompatible;
+
+// Chat completion with tool calling, independent of the model provider. The providers talk to the model
+// over wasi:http, so the responses are kept in the oplog of the worker, and natively over plain HTTP.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChatRequest {
+    pub messages: Vec<Message>,
+    pub tools: Vec<ToolDefinition>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+impl ChatRequest {
+    pub fn new(messages: Vec<Message>) -> Self {
+        ChatRequest {
+            messages,
+            ..ChatRequest::default()
+        }
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    EndTurn,
+    ToolCalls,
+    MaxTokens,
+    Other(String),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatResponse {
+    // An assistant message, with the text and the tool calls of the model
+    pub message: Message,
+    pub stop_reason: StopReason,
+    pub usage: Option<Usage>,
+}
+
+impl ChatResponse {
+    pub fn text(&self) -> String {
+        self.message.text()
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.message.tool_calls
+    }
+}
+
+pub trait ChatModel: Send + Sync {
+    fn complete(&self, request: &ChatRequest) -> Result<ChatResponse, String>;
+
+    // Calls `on_text` with every piece of text as the model generates it.
+    // The response is the same as the one of `complete`
+    fn complete_streaming(
+        &self,
+        request: &ChatRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, String>;
+}
+
+impl<T: ChatModel + ?Sized> ChatModel for std::sync::Arc<T> {
+    fn complete(&self, request: &ChatRequest) -> Result<ChatResponse, String> {
+        (**self).complete(request)
+    }
+
+    fn complete_streaming(
+        &self,
+        request: &ChatRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, String> {
+        (**self).complete_streaming(request, on_text)
+    }
+}
+
+fn assistant_message(text: String, tool_calls: Vec<ToolCall>) -> Message {
+    let mut message = Message::assistant_tool_calls(tool_calls);
+    if !text.is_empty() {
+        message.parts.push(MessagePart::Text(text));
+    }
+    message
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+fn post_json(
+    http: &dyn HttpClient,
+    url: &str,
+    headers: &[(&str, String)],
+    body: &serde_json::Value,
+) -> Result<HttpResponse, String> {
+    let body = serde_json::to_vec(body).map_err(|e| e.to_string())?;
+    let mut headers = headers.to_vec();
+    headers.push(("content-type", "application/json".to_string()));
+
+    let response = http.post(url, &headers, body)?;
+
+    if !(200..300).contains(&response.status) {
+        let status = response.status;
+        let body = response.text().unwrap_or_default();
+        return Err(format!("{} responded with status {}: {}", url, status, body));
+    }
+
+    Ok(response)
+}
+
+fn response_json(response: HttpResponse) -> Result<serde_json::Value, String> {
+    let text = response.text()?;
+    serde_json::from_str(&text).map_err(|e| format!("The response is not JSON: {}", e))
+}
@@ -0,0 +1,451 @@
+use super::http::{default_http_client, HttpClient};
+use super::{
+    assistant_message, base64_encode, post_json, response_json, sse, ChatModel, ChatRequest,
+    ChatResponse, Message, MessagePart, Role, StopReason, Usage,
+};
+use crate::tools::{parse_tool_call, ToolProvider};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const API_VERSION: &str = "2023-06-01";
+
+// The messages API of Anthropic
+#[derive(Clone)]
+pub struct Anthropic {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    // Required by the API, used when the request doesn't set one
+    default_max_tokens: u32,
+    http: Arc<dyn HttpClient>,
+}
+
+impl Anthropic {
+    pub fn new(model: impl Into<String>) -> Self {
+        Anthropic {
+            base_url: "https://api.anthropic.com".to_string(),
+            model: model.into(),
+            api_key: None,
+            default_max_tokens: 4096,
+            http: default_http_client(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    // The base URL is the one before `/v1/messages`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_default_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.default_max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    fn post(&self, request: &ChatRequest, stream: bool) -> Result<super::HttpResponse, String> {
+        let system = request
+            .messages
+            .iter()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.text())
+            .collect::<Vec<_>>();
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens.unwrap_or(self.default_max_tokens),
+            "messages": messages_json(&request.messages),
+        });
+        if !system.is_empty() {
+            body["system"] = json!(system.join("\n\n"));
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| tool.to_json(ToolProvider::Anthropic))
+                .collect();
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+
+        let mut headers = vec![("anthropic-version", API_VERSION.to_string())];
+        if let Some(api_key) = &self.api_key {
+            headers.push(("x-api-key", api_key.clone()));
+        }
+
+        post_json(
+            self.http.as_ref(),
+            &format!("{}/v1/messages", self.base_url),
+            &headers,
+            &body,
+        )
+    }
+}
+
+impl ChatModel for Anthropic {
+    fn complete(&self, request: &ChatRequest) -> Result<ChatResponse, String> {
+        let json = response_json(self.post(request, false)?)?;
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+
+        for block in json["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => {
+                    tool_calls.push(parse_tool_call(ToolProvider::Anthropic, block)?)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ChatResponse {
+            message: assistant_message(text, tool_calls),
+            stop_reason: stop_reason(json["stop_reason"].as_str()),
+            usage: Some(Usage {
+                input_tokens: json["usage"]["input_tokens"].as_u64().unwrap_or_default() as u32,
+                output_tokens: json["usage"]["output_tokens"].as_u64().unwrap_or_default() as u32,
+            }),
+        })
+    }
+
+    fn complete_streaming(
+        &self,
+        request: &ChatRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, String> {
+        let mut response = self.post(request, true)?;
+
+        let mut text = String::new();
+        // The tool use blocks by index, with their input arriving as pieces of JSON
+        let mut tool_uses: BTreeMap<u64, (serde_json::Value, String)> = BTreeMap::new();
+        let mut stop = None;
+        let mut usage = Usage::default();
+
+        sse::read_events(response.body.as_mut(), |data| {
+            let event: serde_json::Value = serde_json::from_str(data)
+                .map_err(|e| format!("Invalid event in the response stream: {}", e))?;
+            let index = event["index"].as_u64().unwrap_or_default();
+
+            match event["type"].as_str() {
+                Some("message_start") => {
+                    let message_usage = &event["message"]["usage"];
+                    usage.input_tokens =
+                        message_usage["input_tokens"].as_u64().unwrap_or_default() as u32;
+                }
+                Some("content_block_start") if event["content_block"]["type"] == "tool_use" => {
+                    tool_uses.insert(index, (event["content_block"].clone(), String::new()));
+                }
+                Some("content_block_delta") => {
+                    let delta = &event["delta"];
+                    match delta["type"].as_str() {
+                        Some("text_delta") => {
+                            let piece = delta["text"].as_str().unwrap_or_default();
+                            text.push_str(piece);
+                            on_text(piece);
+                        }
+                        Some("input_json_delta") => {
+                            if let Some((_, input)) = tool_uses.get_mut(&index) {
+                                input.push_str(delta["partial_json"].as_str().unwrap_or_default());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Some("message_delta") => {
+                    stop = event["delta"]["stop_reason"]
+                        .as_str()
+                        .map(|s| s.to_string());
+                    usage.output_tokens =
+                        event["usage"]["output_tokens"].as_u64().unwrap_or_default() as u32;
+                }
+                Some("message_stop") => return Ok(false),
+                Some("error") => {
+                    return Err(format!("The response stream failed: {}", event["error"]));
+                }
+                _ => {}
+            }
+
+            Ok(true)
+        })?;
+
+        let tool_calls = tool_uses
+            .into_values()
+            .map(|(mut block, input)| {
+                block["input"] = if input.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&input)
+                        .map_err(|e| format!("Tool call arguments are not valid JSON: {}", e))?
+                };
+                parse_tool_call(ToolProvider::Anthropic, &block)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ChatResponse {
+            message: assistant_message(text, tool_calls),
+            stop_reason: stop_reason(stop.as_deref()),
+            usage: Some(usage),
+        })
+    }
+}
+
+// System messages go to the `system` field. Tool results are content blocks of user messages,
+// and consecutive messages of the same role are merged, as the roles have to alternate
+fn messages_json(messages: &[Message]) -> Vec<serde_json::Value> {
+    let mut result: Vec<serde_json::Value> = Vec::new();
+
+    for message in messages {
+        let (role, content) = match message.role {
+            Role::System => continue,
+            Role::User => (
+                "user",
+                message.parts.iter().map(part_json).collect::<Vec<_>>(),
+            ),
+            Role::Assistant => {
+                let mut content = message.parts.iter().map(part_json).collect::<Vec<_>>();
+                content.extend(message.tool_calls.iter().map(|tool_call| {
+                    json!({
+                        "type": "tool_use",
+                        "id": tool_call.id,
+                        "name": tool_call.name,
+                        "input": tool_call.arguments,
+                    })
+                }));
+                ("assistant", content)
+            }
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                    "content": message.text(),
+                })],
+            ),
+        };
+
+        match result.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(last_content) = last["content"].as_array_mut() {
+                    last_content.extend(content);
+                }
+            }
+            _ => result.push(json!({ "role": role, "content": content })),
+        }
+    }
+
+    result
+}
+
+fn part_json(part: &MessagePart) -> serde_json::Value {
+    match part {
+        MessagePart::Text(text) => json!({ "type": "text", "text": text }),
+        MessagePart::ImageUrl(url) => json!({
+            "type": "image",
+            "source": { "type": "url", "url": url },
+        }),
+        MessagePart::Binary { mime_type, data } => json!({
+            "type": if mime_type == "application/pdf" { "document" } else { "image" },
+            "source": { "type": "base64", "media_type": mime_type, "data": base64_encode(data) },
+        }),
+    }
+}
+
+fn stop_reason(stop_reason: Option<&str>) -> StopReason {
+    match stop_reason {
+        Some("end_turn") | Some("stop_sequence") | None => StopReason::EndTurn,
+        Some("tool_use") => StopReason::ToolCalls,
+        Some("max_tokens") => StopReason::MaxTokens,
+        Some(other) => StopReason::Other(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock_server::{event_stream, json_response, MockServer};
+    use crate::tools::ToolCall;
+
+    fn model(server: &MockServer) -> Anthropic {
+        Anthropic::new("claude-sonnet-4-0")
+            .with_base_url(&server.url)
+            .with_api_key("secret")
+            .with_default_max_tokens(1024)
+    }
+
+    fn weather_call() -> ToolCall {
+        ToolCall {
+            id: "toolu_1".to_string(),
+            name: "weather-agent__get-weather".to_string(),
+            arguments: json!({ "city": "Berlin" }),
+        }
+    }
+
+    #[test]
+    fn completes_with_tool_calls() {
+        // Recorded from the messages API
+        let server = MockServer::start(json_response(
+            200,
+            &json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-sonnet-4-0",
+                "content": [
+                    { "type": "text", "text": "Let me check." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "weather-agent__get-weather", "input": { "city": "Berlin" } }
+                ],
+                "stop_reason": "tool_use",
+                "stop_sequence": null,
+                "usage": { "input_tokens": 380, "output_tokens": 54 }
+            }),
+        ));
+
+        let response = model(&server)
+            .complete(&ChatRequest::new(vec![
+                Message::system("You are a travel planner"),
+                Message::user("What's the weather in Berlin?"),
+            ]))
+            .unwrap();
+
+        assert_eq!(response.text(), "Let me check.");
+        assert_eq!(response.tool_calls(), &[weather_call()]);
+        assert_eq!(response.stop_reason, StopReason::ToolCalls);
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input_tokens: 380,
+                output_tokens: 54
+            })
+        );
+
+        let request = server.request();
+        assert!(request.head.starts_with("POST /v1/messages HTTP/1.1\r\n"));
+        assert!(request.head.contains("x-api-key: secret\r\n"));
+        assert!(request.head.contains("anthropic-version: 2023-06-01\r\n"));
+        assert_eq!(
+            request.body,
+            json!({
+                "model": "claude-sonnet-4-0",
+                "max_tokens": 1024,
+                "system": "You are a travel planner",
+                "messages": [
+                    { "role": "user", "content": [{ "type": "text", "text": "What's the weather in Berlin?" }] }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn sends_tool_results_as_user_messages() {
+        let server = MockServer::start(json_response(
+            200,
+            &json!({
+                "content": [{ "type": "text", "text": "Sunny in Berlin" }],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 420, "output_tokens": 6 }
+            }),
+        ));
+
+        let response = model(&server)
+            .complete(&ChatRequest::new(vec![
+                Message::user("What's the weather in Berlin?"),
+                Message::assistant_tool_calls(vec![weather_call()]),
+                Message::tool_result("toolu_1", "sunny"),
+                Message::user("And tomorrow?"),
+            ]))
+            .unwrap();
+
+        assert_eq!(response.text(), "Sunny in Berlin");
+        assert_eq!(response.stop_reason, StopReason::EndTurn);
+        assert_eq!(
+            server.request().body["messages"],
+            json!([
+                { "role": "user", "content": [{ "type": "text", "text": "What's the weather in Berlin?" }] },
+                {
+                    "role": "assistant",
+                    "content": [{ "type": "tool_use", "id": "toolu_1", "name": "weather-agent__get-weather", "input": { "city": "Berlin" } }]
+                },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "tool_result", "tool_use_id": "toolu_1", "content": "sunny" },
+                        { "type": "text", "text": "And tomorrow?" }
+                    ]
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn streams_text_and_tool_calls() {
+        // Recorded from the messages API
+        let server = MockServer::start(event_stream(&[
+            json!({ "type": "message_start", "message": { "id": "msg_1", "type": "message", "role": "assistant", "content": [], "stop_reason": null, "usage": { "input_tokens": 380, "output_tokens": 1 } } }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "ping" }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Let me " } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "check." } }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "weather-agent__get-weather", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"city\": " } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"Berlin\"}" } }),
+            json!({ "type": "content_block_stop", "index": 1 }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use", "stop_sequence": null }, "usage": { "output_tokens": 54 } }),
+            json!({ "type": "message_stop" }),
+        ]));
+
+        let mut pieces = Vec::new();
+        let response = model(&server)
+            .complete_streaming(
+                &ChatRequest::new(vec![Message::user("What's the weather in Berlin?")]),
+                &mut |text: &str| pieces.push(text.to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(pieces, vec!["Let me ", "check."]);
+        assert_eq!(response.text(), "Let me check.");
+        assert_eq!(response.tool_calls(), &[weather_call()]);
+        assert_eq!(response.stop_reason, StopReason::ToolCalls);
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input_tokens: 380,
+                output_tokens: 54
+            })
+        );
+        assert_eq!(server.request().body["stream"], json!(true));
+    }
+
+    #[test]
+    fn fails_with_errors_of_the_stream() {
+        let server = MockServer::start(event_stream(&[
+            json!({ "type": "message_start", "message": { "usage": { "input_tokens": 380 } } }),
+            json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } }),
+        ]));
+
+        let error = model(&server)
+            .complete_streaming(&ChatRequest::new(vec![Message::user("Hi")]), &mut |_: &str| {})
+            .unwrap_err();
+
+        assert!(error.starts_with("The response stream failed: "));
+        assert!(error.contains("Overloaded"));
+        server.request();
+    }
+}
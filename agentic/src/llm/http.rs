@@ -0,0 +1,258 @@
+use std::io::{BufRead, Read};
+use std::sync::Arc;
+
+pub struct HttpResponse {
+    pub status: u16,
+    // Read as it arrives, for streamed responses
+    pub body: Box<dyn BufRead + Send>,
+}
+
+impl HttpResponse {
+    pub fn text(mut self) -> Result<String, String> {
+        let mut text = String::new();
+        self.body
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Failed to read the response: {}", e))?;
+        Ok(text)
+    }
+}
+
+// How the providers send their requests, to be replaced in tests
+pub trait HttpClient: Send + Sync {
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, String>;
+}
+
+pub fn default_http_client() -> Arc<dyn HttpClient> {
+    Arc::new(DefaultHttpClient)
+}
+
+// wasi:http inside a component, plain HTTP/1.1 natively
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHttpClient;
+
+#[cfg(target_arch = "wasm32")]
+impl HttpClient for DefaultHttpClient {
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, String> {
+        let mut request = reqwest::Client::new().post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(*name, value.as_str());
+        }
+
+        let response = request
+            .send()
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+        let status = response.status().as_u16();
+
+        // The incoming body stream is read as it arrives, so that streamed completions report their text
+        Ok(HttpResponse {
+            status,
+            body: Box::new(std::io::BufReader::new(response)),
+        })
+    }
+}
+
+// Only `http` URLs, which is what local mock servers use
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpClient for DefaultHttpClient {
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, String> {
+        use std::io::{BufReader, Write};
+        use std::net::TcpStream;
+
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Only http URLs can be called natively: {}", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let address = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+
+        let io_error = |e: std::io::Error| format!("Request to {} failed: {}", url, e);
+
+        let mut stream = TcpStream::connect(&address).map_err(io_error)?;
+
+        let mut head = format!(
+            "POST {} HTTP/1.1\r\nhost: {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+            path,
+            authority,
+            body.len()
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes()).map_err(io_error)?;
+        stream.write_all(&body).map_err(io_error)?;
+
+        let mut reader = BufReader::new(stream);
+
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).map_err(io_error)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| format!("Invalid response status line: {}", status_line.trim_end()))?;
+
+        let mut chunked = false;
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).map_err(io_error)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "transfer-encoding" => chunked = value.trim().eq_ignore_ascii_case("chunked"),
+                    "content-length" => content_length = value.trim().parse::<u64>().ok(),
+                    _ => {}
+                }
+            }
+        }
+
+        let body: Box<dyn BufRead + Send> = if chunked {
+            Box::new(BufReader::new(ChunkedBody {
+                inner: reader,
+                remaining: 0,
+                done: false,
+            }))
+        } else if let Some(content_length) = content_length {
+            Box::new(reader.take(content_length))
+        } else {
+            Box::new(reader)
+        };
+
+        Ok(HttpResponse { status, body })
+    }
+}
+
+// A body sent with `transfer-encoding: chunked`, which is how servers stream
+#[cfg(not(target_arch = "wasm32"))]
+struct ChunkedBody<R> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<R: BufRead> Read for ChunkedBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::{Error, ErrorKind};
+
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut line = String::new();
+            self.inner.read_line(&mut line)?;
+            let size = line.trim().split(';').next().unwrap_or_default();
+            self.remaining =
+                u64::from_str_radix(size, 16).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+
+        self.remaining -= read as u64;
+        if self.remaining == 0 {
+            // The line break ending the chunk
+            let mut line = String::new();
+            self.inner.read_line(&mut line)?;
+        }
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock_server::{chunk, json_response, last_chunk, MockServer};
+    use serde_json::json;
+
+    #[test]
+    fn sends_the_headers_and_the_body() {
+        let server = MockServer::start(json_response(200, &json!({ "ok": true })));
+
+        let response = DefaultHttpClient
+            .post(
+                &format!("{}/v1/messages", server.url),
+                &[("x-api-key", "secret".to_string())],
+                br#"{"model":"small"}"#.to_vec(),
+            )
+            .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text(), Ok(r#"{"ok":true}"#.to_string()));
+
+        let request = server.request();
+        assert!(request.head.starts_with("POST /v1/messages HTTP/1.1\r\n"));
+        assert!(request.head.contains("x-api-key: secret\r\n"));
+        assert_eq!(request.body, json!({ "model": "small" }));
+    }
+
+    #[test]
+    fn reads_chunked_bodies_as_they_arrive() {
+        let server = MockServer::start(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n{}",
+                chunk("Hello, ")
+            ),
+            format!("{}{}", chunk("world"), last_chunk()),
+        ]);
+
+        let mut response = DefaultHttpClient
+            .post(&server.url, &[], b"{}".to_vec())
+            .unwrap();
+
+        let mut first = [0; 7];
+        response.body.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"Hello, ");
+
+        server.send_next_part();
+        assert_eq!(response.text(), Ok("world".to_string()));
+        server.request();
+    }
+
+    #[test]
+    fn calls_only_http_urls_natively() {
+        assert_eq!(
+            DefaultHttpClient
+                .post("https://api.openai.com/v1/chat/completions", &[], vec![])
+                .err(),
+            Some("Only http URLs can be called natively: https://api.openai.com/v1/chat/completions".to_string())
+        );
+    }
+}
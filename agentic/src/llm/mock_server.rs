@@ -0,0 +1,114 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// A local HTTP server answering a single request with a recorded response, for the tests of the providers.
+// The response can be sent in parts, each part but the first once the client read the previous one
+
+pub(super) struct ReceivedRequest {
+    // The request line and the headers
+    pub head: String,
+    pub body: serde_json::Value,
+}
+
+pub(super) struct MockServer {
+    pub url: String,
+    next_part: Sender<()>,
+    handle: JoinHandle<ReceivedRequest>,
+}
+
+impl MockServer {
+    pub fn start(parts: Vec<String>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (next_part, next_part_received) = channel::<()>();
+
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut head = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                head.push_str(&line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = stream;
+            for (index, part) in parts.iter().enumerate() {
+                if index > 0 {
+                    // A client reading the whole body first never gets here
+                    next_part_received
+                        .recv_timeout(Duration::from_secs(5))
+                        .expect("The client didn't read the previous part of the response");
+                }
+                stream.write_all(part.as_bytes()).unwrap();
+                stream.flush().unwrap();
+            }
+
+            ReceivedRequest {
+                head,
+                body: serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+            }
+        });
+
+        MockServer {
+            url,
+            next_part,
+            handle,
+        }
+    }
+
+    pub fn send_next_part(&self) {
+        let _ = self.next_part.send(());
+    }
+
+    pub fn request(self) -> ReceivedRequest {
+        self.handle.join().unwrap()
+    }
+}
+
+pub(super) fn json_response(status: u16, body: &serde_json::Value) -> Vec<String> {
+    let body = body.to_string();
+    vec![format!(
+        "HTTP/1.1 {} Recorded\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )]
+}
+
+// The events sent as a chunked `text/event-stream`, a chunk an event
+pub(super) fn event_stream(events: &[serde_json::Value]) -> Vec<String> {
+    let mut response = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n".to_string();
+    for event in events {
+        let data = match event {
+            serde_json::Value::String(data) => data.clone(),
+            event => event.to_string(),
+        };
+        response.push_str(&chunk(&format!("data: {}\n\n", data)));
+    }
+    response.push_str(&last_chunk());
+    vec![response]
+}
+
+pub(super) fn chunk(data: &str) -> String {
+    format!("{:x}\r\n{}\r\n", data.len(), data)
+}
+
+pub(super) fn last_chunk() -> String {
+    "0\r\n\r\n".to_string()
+}
@@ -0,0 +1,426 @@
+use super::http::{default_http_client, HttpClient};
+use super::{
+    assistant_message, base64_encode, post_json, response_json, sse, ChatModel, ChatRequest,
+    ChatResponse, Message, MessagePart, Role, StopReason, Usage,
+};
+use crate::tools::{parse_tool_call, ToolCall, ToolProvider};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// The chat completions API of OpenAI, also served by many other providers and local model servers
+#[derive(Clone)]
+pub struct OpenAiCompatible {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    http: Arc<dyn HttpClient>,
+}
+
+impl OpenAiCompatible {
+    // The base URL is the one before `/chat/completions`, e.g. `https://api.openai.com/v1`
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        OpenAiCompatible {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+            api_key: None,
+            http: default_http_client(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    fn post(&self, request: &ChatRequest, stream: bool) -> Result<super::HttpResponse, String> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": request.messages.iter().map(message_json).collect::<Vec<_>>(),
+        });
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| tool.to_json(ToolProvider::OpenAi))
+                .collect();
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if stream {
+            body["stream"] = json!(true);
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+
+        let headers = self
+            .api_key
+            .iter()
+            .map(|api_key| ("authorization", format!("Bearer {}", api_key)))
+            .collect::<Vec<_>>();
+
+        post_json(
+            self.http.as_ref(),
+            &format!("{}/chat/completions", self.base_url),
+            &headers,
+            &body,
+        )
+    }
+}
+
+impl ChatModel for OpenAiCompatible {
+    fn complete(&self, request: &ChatRequest) -> Result<ChatResponse, String> {
+        let json = response_json(self.post(request, false)?)?;
+        let choice = &json["choices"][0];
+        let message = &choice["message"];
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|tool_calls| {
+                tool_calls
+                    .iter()
+                    .map(|tool_call| parse_tool_call(ToolProvider::OpenAi, tool_call))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(ChatResponse {
+            message: assistant_message(
+                message["content"].as_str().unwrap_or_default().to_string(),
+                tool_calls,
+            ),
+            stop_reason: stop_reason(choice["finish_reason"].as_str()),
+            usage: usage(&json["usage"]),
+        })
+    }
+
+    fn complete_streaming(
+        &self,
+        request: &ChatRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, String> {
+        let mut response = self.post(request, true)?;
+
+        let mut text = String::new();
+        // The tool calls arrive in pieces, by index: the id and the name first, then the arguments
+        let mut tool_calls: BTreeMap<u64, (String, String, String)> = BTreeMap::new();
+        let mut finish_reason = None;
+        let mut usage_json = serde_json::Value::Null;
+
+        sse::read_events(response.body.as_mut(), |data| {
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+
+            let chunk: serde_json::Value = serde_json::from_str(data)
+                .map_err(|e| format!("Invalid event in the response stream: {}", e))?;
+
+            if !chunk["usage"].is_null() {
+                usage_json = chunk["usage"].clone();
+            }
+
+            let choice = &chunk["choices"][0];
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                finish_reason = Some(reason.to_string());
+            }
+
+            let delta = &choice["delta"];
+            if let Some(content) = delta["content"].as_str() {
+                if !content.is_empty() {
+                    text.push_str(content);
+                    on_text(content);
+                }
+            }
+
+            for tool_call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let (id, name, arguments) = tool_calls
+                    .entry(tool_call["index"].as_u64().unwrap_or_default())
+                    .or_default();
+                if let Some(value) = tool_call["id"].as_str() {
+                    id.push_str(value);
+                }
+                if let Some(value) = tool_call["function"]["name"].as_str() {
+                    name.push_str(value);
+                }
+                if let Some(value) = tool_call["function"]["arguments"].as_str() {
+                    arguments.push_str(value);
+                }
+            }
+
+            Ok(true)
+        })?;
+
+        let tool_calls = tool_calls
+            .into_values()
+            .map(|(id, name, arguments)| {
+                parse_tool_call(
+                    ToolProvider::OpenAi,
+                    &json!({ "id": id, "function": { "name": name, "arguments": arguments } }),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ChatResponse {
+            message: assistant_message(text, tool_calls),
+            stop_reason: stop_reason(finish_reason.as_deref()),
+            usage: usage(&usage_json),
+        })
+    }
+}
+
+fn message_json(message: &Message) -> serde_json::Value {
+    match message.role {
+        Role::System => json!({ "role": "system", "content": message.text() }),
+        Role::User => {
+            let only_text = message
+                .parts
+                .iter()
+                .all(|part| matches!(part, MessagePart::Text(_)));
+
+            if only_text {
+                json!({ "role": "user", "content": message.text() })
+            } else {
+                json!({
+                    "role": "user",
+                    "content": message.parts.iter().map(part_json).collect::<Vec<_>>(),
+                })
+            }
+        }
+        Role::Assistant => {
+            let mut json = json!({ "role": "assistant", "content": message.text() });
+            if !message.tool_calls.is_empty() {
+                json["tool_calls"] = message.tool_calls.iter().map(tool_call_json).collect();
+            }
+            json
+        }
+        Role::Tool => json!({
+            "role": "tool",
+            "tool_call_id": message.tool_call_id.clone().unwrap_or_default(),
+            "content": message.text(),
+        }),
+    }
+}
+
+fn part_json(part: &MessagePart) -> serde_json::Value {
+    match part {
+        MessagePart::Text(text) => json!({ "type": "text", "text": text }),
+        MessagePart::ImageUrl(url) => json!({ "type": "image_url", "image_url": { "url": url } }),
+        MessagePart::Binary { mime_type, data } => json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{};base64,{}", mime_type, base64_encode(data)) },
+        }),
+    }
+}
+
+fn tool_call_json(tool_call: &ToolCall) -> serde_json::Value {
+    json!({
+        "id": tool_call.id,
+        "type": "function",
+        "function": {
+            "name": tool_call.name,
+            "arguments": tool_call.arguments.to_string(),
+        },
+    })
+}
+
+fn stop_reason(finish_reason: Option<&str>) -> StopReason {
+    match finish_reason {
+        Some("stop") | None => StopReason::EndTurn,
+        Some("tool_calls") => StopReason::ToolCalls,
+        Some("length") => StopReason::MaxTokens,
+        Some(other) => StopReason::Other(other.to_string()),
+    }
+}
+
+fn usage(json: &serde_json::Value) -> Option<Usage> {
+    Some(Usage {
+        input_tokens: json["prompt_tokens"].as_u64()? as u32,
+        output_tokens: json["completion_tokens"].as_u64()? as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock_server::{event_stream, json_response, MockServer};
+
+    fn model(server: &MockServer) -> OpenAiCompatible {
+        OpenAiCompatible::new(format!("{}/v1/", server.url), "gpt-4o").with_api_key("secret")
+    }
+
+    fn weather_call() -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: "weather-agent__get-weather".to_string(),
+            arguments: json!({ "city": "Berlin" }),
+        }
+    }
+
+    #[test]
+    fn completes_with_tool_calls() {
+        // Recorded from the chat completions API
+        let server = MockServer::start(json_response(
+            200,
+            &json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "weather-agent__get-weather", "arguments": "{\"city\":\"Berlin\"}" }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }],
+                "usage": { "prompt_tokens": 52, "completion_tokens": 17, "total_tokens": 69 }
+            }),
+        ));
+
+        let response = model(&server)
+            .complete(
+                &ChatRequest::new(vec![
+                    Message::system("You are a travel planner"),
+                    Message::user("What's the weather in Berlin?"),
+                ])
+                .with_max_tokens(100),
+            )
+            .unwrap();
+
+        assert_eq!(response.tool_calls(), &[weather_call()]);
+        assert_eq!(response.text(), "");
+        assert_eq!(response.stop_reason, StopReason::ToolCalls);
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input_tokens: 52,
+                output_tokens: 17
+            })
+        );
+
+        let request = server.request();
+        assert!(request.head.starts_with("POST /v1/chat/completions HTTP/1.1\r\n"));
+        assert!(request.head.contains("authorization: Bearer secret\r\n"));
+        assert_eq!(
+            request.body,
+            json!({
+                "model": "gpt-4o",
+                "messages": [
+                    { "role": "system", "content": "You are a travel planner" },
+                    { "role": "user", "content": "What's the weather in Berlin?" }
+                ],
+                "max_tokens": 100
+            })
+        );
+    }
+
+    #[test]
+    fn sends_tool_calls_and_results() {
+        let server = MockServer::start(json_response(
+            200,
+            &json!({
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Sunny in Berlin" },
+                    "finish_reason": "stop"
+                }]
+            }),
+        ));
+
+        let response = model(&server)
+            .complete(&ChatRequest::new(vec![
+                Message::assistant_tool_calls(vec![weather_call()]),
+                Message::tool_result("call_1", "sunny"),
+            ]))
+            .unwrap();
+
+        assert_eq!(response.text(), "Sunny in Berlin");
+        assert_eq!(response.stop_reason, StopReason::EndTurn);
+        assert_eq!(response.usage, None);
+        assert_eq!(
+            server.request().body["messages"],
+            json!([
+                {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "weather-agent__get-weather", "arguments": "{\"city\":\"Berlin\"}" }
+                    }]
+                },
+                { "role": "tool", "tool_call_id": "call_1", "content": "sunny" }
+            ])
+        );
+    }
+
+    #[test]
+    fn streams_text_and_tool_calls() {
+        // Recorded from the chat completions API, with `stream_options.include_usage`
+        let server = MockServer::start(event_stream(&[
+            json!({ "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "" }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": { "content": "Let me " }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": { "content": "check." }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [{ "index": 0, "id": "call_1", "type": "function", "function": { "name": "weather-agent__get-weather", "arguments": "" } }] }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "{\"city\":" } }] }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "\"Berlin\"}" } }] }, "finish_reason": null }], "usage": null }),
+            json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }], "usage": null }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 52, "completion_tokens": 21, "total_tokens": 73 } }),
+            json!("[DONE]"),
+        ]));
+
+        let mut pieces = Vec::new();
+        let response = model(&server)
+            .complete_streaming(
+                &ChatRequest::new(vec![Message::user("What's the weather in Berlin?")]),
+                &mut |text: &str| pieces.push(text.to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(pieces, vec!["Let me ", "check."]);
+        assert_eq!(response.text(), "Let me check.");
+        assert_eq!(response.tool_calls(), &[weather_call()]);
+        assert_eq!(response.stop_reason, StopReason::ToolCalls);
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input_tokens: 52,
+                output_tokens: 21
+            })
+        );
+
+        let body = server.request().body;
+        assert_eq!(body["stream"], json!(true));
+        assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+    }
+
+    #[test]
+    fn fails_with_the_error_of_the_provider() {
+        let server = MockServer::start(json_response(
+            401,
+            &json!({ "error": { "message": "Incorrect API key provided", "type": "invalid_request_error" } }),
+        ));
+        let url = format!("{}/v1/chat/completions", server.url);
+
+        let error = model(&server)
+            .complete(&ChatRequest::new(vec![Message::user("Hi")]))
+            .unwrap_err();
+
+        assert!(error.starts_with(&format!("{} responded with status 401: ", url)));
+        assert!(error.contains("Incorrect API key provided"));
+        server.request();
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::BufRead;
+
+// Reads server-sent events, calling `on_event` with the data of each until the stream ends or it returns false
+pub(super) fn read_events(
+    body: &mut dyn BufRead,
+    mut on_event: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<(), String> {
+    let mut data = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        let read = body
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read the response stream: {}", e))?;
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if read == 0 || line.is_empty() {
+            if !data.is_empty() {
+                let event = data.join("\n");
+                data.clear();
+                if !on_event(&event)? {
+                    return Ok(());
+                }
+            }
+            if read == 0 {
+                return Ok(());
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        // Other fields, e.g. `event:`, are repeated in the data by both providers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock_server::{chunk, last_chunk, MockServer};
+    use crate::llm::{DefaultHttpClient, HttpClient};
+    use std::io::Cursor;
+
+    fn events(body: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        read_events(&mut Cursor::new(body.as_bytes()), |data| {
+            events.push(data.to_string());
+            Ok(true)
+        })
+        .unwrap();
+        events
+    }
+
+    #[test]
+    fn joins_the_data_lines_of_an_event() {
+        assert_eq!(
+            events("event: message\r\ndata: {\"text\":\r\ndata:\"Hi\"}\r\n\r\n: ping\n\ndata: [DONE]"),
+            vec!["{\"text\":\n\"Hi\"}", "[DONE]"]
+        );
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let mut events = Vec::new();
+        read_events(&mut Cursor::new(b"data: 1\n\ndata: 2\n\n".to_vec()), |data| {
+            events.push(data.to_string());
+            Ok(false)
+        })
+        .unwrap();
+
+        assert_eq!(events, vec!["1"]);
+    }
+
+    #[test]
+    fn fails_with_the_error_of_the_handler() {
+        assert_eq!(
+            read_events(&mut Cursor::new(b"data: 1\n\n".to_vec()), |_| Err("Invalid event".to_string())),
+            Err("Invalid event".to_string())
+        );
+    }
+
+    #[test]
+    fn reads_chunked_events_as_they_arrive() {
+        // The second event is split across chunks, mid line
+        let server = MockServer::start(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n{}",
+                chunk("data: Hello\n\ndata: wo")
+            ),
+            format!("{}{}{}", chunk("rld\n"), chunk("\n"), last_chunk()),
+        ]);
+
+        let mut response = DefaultHttpClient
+            .post(&server.url, &[], b"{}".to_vec())
+            .unwrap();
+        let mut events = Vec::new();
+        read_events(response.body.as_mut(), |data| {
+            if events.is_empty() {
+                server.send_next_part();
+            }
+            events.push(data.to_string());
+            Ok(true)
+        })
+        .unwrap();
+
+        assert_eq!(events, vec!["Hello", "world"]);
+        server.request();
+    }
+}
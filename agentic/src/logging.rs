@@ -0,0 +1,227 @@
+use crate::agent;
+use crate::host::{self, agent_host};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+// Sends the records of the `log` crate to wasi:logging, with the agent, method and invocation that logged them.
+// The levels are read from the `log-level` worker configuration, e.g. `warn,my_component::agents=debug`.
+
+pub const LOG_LEVEL_CONFIG_KEY: &str = "log-level";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    pub level: Level,
+    // The module that logged the record, unless set with `target:`
+    pub target: String,
+    pub message: String,
+    pub agent_id: Option<String>,
+    pub method_name: Option<String>,
+    pub invocation_id: Option<u64>,
+}
+
+impl LogRecord {
+    // The context of the record in wasi:logging, e.g. `my_component::agents agent-id=... method=forecast invocation-id=3`
+    pub fn context(&self) -> String {
+        let mut context = self.target.clone();
+
+        if let Some(agent_id) = &self.agent_id {
+            context.push_str(&format!(" agent-id={}", agent_id));
+        }
+        if let Some(method_name) = &self.method_name {
+            context.push_str(&format!(" method={}", method_name));
+        }
+        if let Some(invocation_id) = self.invocation_id {
+            context.push_str(&format!(" invocation-id={}", invocation_id));
+        }
+
+        context
+    }
+}
+
+struct AgentLogger;
+
+static LOGGER: AgentLogger = AgentLogger;
+
+// The levels by log target, read from the configuration on the first record. The default level has no target
+static LEVELS: Lazy<Mutex<Option<Vec<(Option<String>, LevelFilter)>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+impl Log for AgentLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Natively there is nowhere to log to, unless a test installed a `MockHost`
+        if !cfg!(target_arch = "wasm32") && !host::is_agent_host_set() {
+            return false;
+        }
+
+        metadata.level() <= level_of(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let invocation = agent::current_invocation();
+
+        agent_host().log(&LogRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            agent_id: invocation
+                .as_ref()
+                .map(|invocation| invocation.agent_id.clone()),
+            method_name: invocation
+                .as_ref()
+                .map(|invocation| invocation.method_name.clone()),
+            invocation_id: invocation.map(|invocation| invocation.invocation_id),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+// Installs the logger, unless another logger is installed. Done when the component starts
+pub fn install() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[::ctor::ctor]
+fn install_logger() {
+    install();
+}
+
+// Makes the levels be read from the configuration again on the next record
+pub fn reset_levels() {
+    *LEVELS.lock().unwrap() = None;
+}
+
+// The level of the longest target matching the given one, or the default level
+fn level_of(target: &str) -> LevelFilter {
+    let mut levels = LEVELS.lock().unwrap();
+    let levels = levels.get_or_insert_with(|| {
+        parse_levels(
+            &agent_host()
+                .config_value(LOG_LEVEL_CONFIG_KEY)
+                .unwrap_or_default(),
+        )
+    });
+
+    levels
+        .iter()
+        .filter(|(prefix, _)| match prefix {
+            Some(prefix) => {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            }
+            None => true,
+        })
+        .max_by_key(|(prefix, _)| prefix.as_ref().map_or(0, |prefix| prefix.len() + 1))
+        .map(|(_, level)| *level)
+        .unwrap_or(LevelFilter::Info)
+}
+
+// Invalid parts are skipped, as logging has nowhere to report them
+fn parse_levels(config: &str) -> Vec<(Option<String>, LevelFilter)> {
+    config
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| match part.split_once('=') {
+            Some((target, level)) => level
+                .trim()
+                .parse::<LevelFilter>()
+                .ok()
+                .map(|level| (Some(target.trim().to_string()), level)),
+            None => part.parse::<LevelFilter>().ok().map(|level| (None, level)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockHost};
+
+    const TARGET: &str = "my_component::agents";
+
+    fn logged() -> Vec<LogRecord> {
+        testing::logs()
+            .into_iter()
+            .filter(|record| record.target.starts_with("my_component"))
+            .collect()
+    }
+
+    #[test]
+    fn parses_levels_skipping_invalid_parts() {
+        assert_eq!(
+            parse_levels(" warn , my_component::agents=debug,,verbose,other=loud"),
+            vec![
+                (None, LevelFilter::Warn),
+                (Some("my_component::agents".to_string()), LevelFilter::Debug),
+            ]
+        );
+        assert!(parse_levels("").is_empty());
+    }
+
+    #[test]
+    fn uses_the_level_of_the_longest_matching_target() {
+        let _host = MockHost::new("test-worker")
+            .with_config(LOG_LEVEL_CONFIG_KEY, "warn,my_component=error,my_component::agents=debug")
+            .install();
+
+        assert_eq!(level_of("my_component::agents"), LevelFilter::Debug);
+        assert_eq!(level_of("my_component::agents::weather"), LevelFilter::Debug);
+        assert_eq!(level_of("my_component::agents_v2"), LevelFilter::Error);
+        assert_eq!(level_of("other_crate"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn logs_at_info_without_configuration() {
+        let _host = MockHost::new("test-worker").install();
+
+        log::info!(target: TARGET, "Fetching the forecast");
+        log::debug!(target: TARGET, "Connecting");
+
+        assert_eq!(
+            logged().iter().map(|record| record.message.as_str()).collect::<Vec<_>>(),
+            vec!["Fetching the forecast"]
+        );
+    }
+
+    #[test]
+    fn logs_with_the_context_of_the_agent_method() {
+        let _host = MockHost::new("test-worker")
+            .with_config(LOG_LEVEL_CONFIG_KEY, "my_component::agents=trace")
+            .install();
+
+        agent::with_current_agent("test-worker--weather--1", "forecast", || {
+            log::trace!(target: TARGET, "Fetching the forecast of {}", "Berlin");
+        });
+        log::warn!(target: TARGET, "Idle");
+
+        let records = logged();
+        assert_eq!(
+            records[0],
+            LogRecord {
+                level: Level::Trace,
+                target: TARGET.to_string(),
+                message: "Fetching the forecast of Berlin".to_string(),
+                agent_id: Some("test-worker--weather--1".to_string()),
+                method_name: Some("forecast".to_string()),
+                invocation_id: Some(1),
+            }
+        );
+        assert_eq!(
+            records[0].context(),
+            "my_component::agents agent-id=test-worker--weather--1 method=forecast invocation-id=1"
+        );
+        assert_eq!(records[1].agent_id, None);
+        assert_eq!(records[1].context(), TARGET);
+    }
+}
@@ -0,0 +1,671 @@
+use crate::bindings::golem::agent::common::{
+    AgentMemory, BinaryPart, MemoryMessage, MemoryToolCall, MessagePart as WitMessagePart,
+    MessageRole,
+};
+use crate::tools::ToolCall;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// The conversation history of an AI agent, kept in the memory of the worker and in its snapshots.
+// A memory can trim itself to a window of messages or a token budget, keeping the system messages.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessagePart {
+    Text(String),
+    ImageUrl(String),
+    Binary { mime_type: String, data: Vec<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub role: Role,
+    pub parts: Vec<MessagePart>,
+    pub tool_calls: Vec<ToolCall>,
+    // The tool call a tool message is the result of
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: Role, text: impl Into<String>) -> Self {
+        Message {
+            role,
+            parts: vec![MessagePart::Text(text.into())],
+            tool_calls: vec![],
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(text: impl Into<String>) -> Self {
+        Message::new(Role::System, text)
+    }
+
+    pub fn user(text: impl Into<String>) -> Self {
+        Message::new(Role::User, text)
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Message::new(Role::Assistant, text)
+    }
+
+    pub fn assistant_tool_calls(tool_calls: Vec<ToolCall>) -> Self {
+        Message {
+            role: Role::Assistant,
+            parts: vec![],
+            tool_calls,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn tool_result(tool_call_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Message {
+            role: Role::Tool,
+            parts: vec![MessagePart::Text(text.into())],
+            tool_calls: vec![],
+            tool_call_id: Some(tool_call_id.into()),
+        }
+    }
+
+    pub fn with_part(mut self, part: MessagePart) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    // The text parts of the message
+    pub fn text(&self) -> String {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                MessagePart::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "role": role_name(self.role),
+            "parts": self.parts.iter().map(|part| match part {
+                MessagePart::Text(text) => json!({ "text": text }),
+                MessagePart::ImageUrl(url) => json!({ "imageUrl": url }),
+                MessagePart::Binary { mime_type, data } => json!({ "mimeType": mime_type, "data": data }),
+            }).collect::<Vec<_>>(),
+            "toolCalls": self.tool_calls.iter().map(|tool_call| json!({
+                "id": tool_call.id,
+                "name": tool_call.name,
+                "arguments": tool_call.arguments,
+            })).collect::<Vec<_>>(),
+            "toolCallId": self.tool_call_id,
+        })
+    }
+
+    pub fn from_json(json: &serde_json::Value) -> Result<Self, String> {
+        let role = match json["role"].as_str() {
+            Some("system") => Role::System,
+            Some("user") => Role::User,
+            Some("assistant") => Role::Assistant,
+            Some("tool") => Role::Tool,
+            other => return Err(format!("Invalid message role: {:?}", other)),
+        };
+
+        let parts = json["parts"]
+            .as_array()
+            .ok_or_else(|| "Invalid message: missing parts".to_string())?
+            .iter()
+            .map(|part| {
+                if let Some(text) = part["text"].as_str() {
+                    Ok(MessagePart::Text(text.to_string()))
+                } else if let Some(url) = part["imageUrl"].as_str() {
+                    Ok(MessagePart::ImageUrl(url.to_string()))
+                } else if let Some(mime_type) = part["mimeType"].as_str() {
+                    let data = serde_json::from_value(part["data"].clone())
+                        .map_err(|e| format!("Invalid binary message part: {}", e))?;
+                    Ok(MessagePart::Binary {
+                        mime_type: mime_type.to_string(),
+                        data,
+                    })
+                } else {
+                    Err(format!("Invalid message part: {}", part))
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let tool_calls = json["toolCalls"]
+            .as_array()
+            .map(|tool_calls| {
+                tool_calls
+                    .iter()
+                    .map(|tool_call| ToolCall {
+                        id: tool_call["id"].as_str().unwrap_or_default().to_string(),
+                        name: tool_call["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: tool_call["arguments"].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Message {
+            role,
+            parts,
+            tool_calls,
+            tool_call_id: json["toolCallId"].as_str().map(|id| id.to_string()),
+        })
+    }
+}
+
+// Estimates the number of tokens of a message, for trimming to a token budget
+pub type TokenCounter = fn(&Message) -> usize;
+
+// Roughly four characters a token, as for English text with the common tokenizers
+pub fn estimate_tokens(message: &Message) -> usize {
+    const MESSAGE_OVERHEAD: usize = 4;
+    const IMAGE_TOKENS: usize = 85;
+
+    let characters = |text: &str| text.chars().count().div_ceil(4);
+
+    let parts: usize = message
+        .parts
+        .iter()
+        .map(|part| match part {
+            MessagePart::Text(text) => characters(text),
+            MessagePart::ImageUrl(_) | MessagePart::Binary { .. } => IMAGE_TOKENS,
+        })
+        .sum();
+    let tool_calls: usize = message
+        .tool_calls
+        .iter()
+        .map(|tool_call| characters(&tool_call.name) + characters(&tool_call.arguments.to_string()))
+        .sum();
+
+    MESSAGE_OVERHEAD + parts + tool_calls
+}
+
+struct MemoryState {
+    messages: Vec<Message>,
+    // Limits of the messages that are not system messages, applied every time a message is pushed
+    max_messages: Option<usize>,
+    token_budget: Option<usize>,
+    token_counter: TokenCounter,
+}
+
+#[derive(Clone)]
+pub struct Memory {
+    name: String,
+    state: Arc<Mutex<MemoryState>>,
+}
+
+// The memories of every agent by agent id, to be inspected and saved in snapshots
+static MEMORIES: Lazy<Mutex<HashMap<String, Vec<Memory>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl Memory {
+    // The memory of the agent with this name, created empty the first time
+    pub fn new(agent_id: &str, name: &str) -> Self {
+        let mut memories = MEMORIES.lock().unwrap();
+        let agent_memories = memories.entry(agent_id.to_string()).or_default();
+
+        if let Some(existing) = agent_memories.iter().find(|existing| existing.name == name) {
+            return existing.clone();
+        }
+
+        let memory = Memory {
+            name: name.to_string(),
+            state: Arc::new(Mutex::new(MemoryState {
+                messages: vec![],
+                max_messages: None,
+                token_budget: None,
+                token_counter: estimate_tokens,
+            })),
+        };
+        agent_memories.push(memory.clone());
+
+        memory
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Keeps at most this many messages besides the system messages
+    pub fn with_window(self, max_messages: usize) -> Self {
+        self.state.lock().unwrap().max_messages = Some(max_messages);
+        self.trim();
+        self
+    }
+
+    // Keeps the messages within this many tokens, dropping the oldest ones but the system messages
+    pub fn with_token_budget(self, max_tokens: usize) -> Self {
+        self.state.lock().unwrap().token_budget = Some(max_tokens);
+        self.trim();
+        self
+    }
+
+    pub fn with_token_counter(self, token_counter: TokenCounter) -> Self {
+        self.state.lock().unwrap().token_counter = token_counter;
+        self.trim();
+        self
+    }
+
+    pub fn push(&self, message: Message) {
+        self.state.lock().unwrap().messages.push(message);
+        self.trim();
+    }
+
+    pub fn extend(&self, messages: impl IntoIterator<Item = Message>) {
+        self.state.lock().unwrap().messages.extend(messages);
+        self.trim();
+    }
+
+    pub fn messages(&self) -> Vec<Message> {
+        self.state.lock().unwrap().messages.clone()
+    }
+
+    // The system messages followed by the last messages, at most `max_messages` of them
+    pub fn window(&self, max_messages: usize) -> Vec<Message> {
+        let state = self.state.lock().unwrap();
+        let others = state
+            .messages
+            .iter()
+            .filter(|message| message.role != Role::System)
+            .count();
+        let mut skip = others.saturating_sub(max_messages);
+
+        state
+            .messages
+            .iter()
+            .filter(|message| {
+                if message.role == Role::System {
+                    true
+                } else if skip > 0 {
+                    skip -= 1;
+                    false
+                } else {
+                    true
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().messages.clear();
+    }
+
+    pub fn tokens(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.messages.iter().map(state.token_counter).sum()
+    }
+
+    // Drops the oldest messages but the system messages until the rest fits in the budget
+    pub fn trim_to_tokens(&self, max_tokens: usize) {
+        let mut state = self.state.lock().unwrap();
+        while state.messages.iter().map(state.token_counter).sum::<usize>() > max_tokens {
+            if !drop_oldest(&mut state.messages) {
+                break;
+            }
+        }
+    }
+
+    // Replaces all the messages but the system messages and the last `keep_last` ones with a summary
+    pub fn compact<F>(&self, keep_last: usize, summarize: F) -> Result<(), String>
+    where
+        F: FnOnce(&[Message]) -> Result<String, String>,
+    {
+        let messages = self.messages();
+        let (system, others): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|message| message.role == Role::System);
+
+        let mut split = others.len().saturating_sub(keep_last);
+        // Tool results are kept with the tool calls they answer
+        while split > 0 && split < others.len() && others[split].role == Role::Tool {
+            split -= 1;
+        }
+        if split == 0 {
+            return Ok(());
+        }
+
+        let summary = summarize(&others[..split])?;
+
+        let mut compacted = system;
+        compacted.push(Message::system(format!(
+            "Summary of the earlier conversation:\n{}",
+            summary
+        )));
+        compacted.extend(others[split..].iter().cloned());
+
+        self.state.lock().unwrap().messages = compacted;
+        Ok(())
+    }
+
+    fn trim(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(max_messages) = state.max_messages {
+            while state
+                .messages
+                .iter()
+                .filter(|message| message.role != Role::System)
+                .count()
+                > max_messages
+            {
+                if !drop_oldest(&mut state.messages) {
+                    break;
+                }
+            }
+        }
+
+        if let Some(token_budget) = state.token_budget {
+            while state.messages.iter().map(state.token_counter).sum::<usize>() > token_budget {
+                if !drop_oldest(&mut state.messages) {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn to_agent_memory(&self) -> AgentMemory {
+        let state = self.state.lock().unwrap();
+
+        AgentMemory {
+            name: self.name.clone(),
+            messages: state.messages.iter().map(to_memory_message).collect(),
+            tokens: state.messages.iter().map(state.token_counter).sum::<usize>() as u32,
+        }
+    }
+}
+
+// Drops the oldest message that is not a system message, with the tool results that would be left without their call.
+// False if there are only system messages
+fn drop_oldest(messages: &mut Vec<Message>) -> bool {
+    match messages
+        .iter()
+        .position(|message| message.role != Role::System)
+    {
+        Some(index) => {
+            messages.remove(index);
+            while index < messages.len() && messages[index].role == Role::Tool {
+                messages.remove(index);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn agent_memories(agent_id: &str) -> Vec<AgentMemory> {
+    MEMORIES
+        .lock()
+        .unwrap()
+        .get(agent_id)
+        .map(|memories| memories.iter().map(Memory::to_agent_memory).collect())
+        .unwrap_or_default()
+}
+
+// The messages of every memory of the agent, for snapshots
+pub(crate) fn save_memories(agent_id: &str) -> serde_json::Value {
+    let memories = MEMORIES
+        .lock()
+        .unwrap()
+        .get(agent_id)
+        .cloned()
+        .unwrap_or_default();
+
+    memories
+        .iter()
+        .map(|memory| {
+            json!({
+                "name": memory.name,
+                "messages": memory.messages().iter().map(Message::to_json).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+// Restores the messages saved by `save_memories` into the memories the agent was constructed with
+pub(crate) fn load_memories(agent_id: &str, saved: &serde_json::Value) -> Result<(), String> {
+    let Some(saved) = saved.as_array() else {
+        return Ok(());
+    };
+
+    let memories = MEMORIES
+        .lock()
+        .unwrap()
+        .get(agent_id)
+        .cloned()
+        .unwrap_or_default();
+
+    for saved_memory in saved {
+        let name = saved_memory["name"].as_str().unwrap_or_default();
+        // Memories removed from the agent are dropped
+        if let Some(memory) = memories.iter().find(|memory| memory.name == name) {
+            let messages = saved_memory["messages"]
+                .as_array()
+                .map(|messages| {
+                    messages
+                        .iter()
+                        .map(Message::from_json)
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .unwrap_or_else(|| Ok(vec![]))?;
+            memory.clear();
+            memory.extend(messages);
+        }
+    }
+
+    Ok(())
+}
+
+// Forgets the memories of an agent, see `agent::replace_agent`
+pub(crate) fn remove_memories(agent_id: &str) {
+    MEMORIES.lock().unwrap().remove(agent_id);
+}
+
+pub fn clear_memories() {
+    MEMORIES.lock().unwrap().clear();
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    }
+}
+
+fn to_memory_message(message: &Message) -> MemoryMessage {
+    MemoryMessage {
+        role: match message.role {
+            Role::System => MessageRole::System,
+            Role::User => MessageRole::User,
+            Role::Assistant => MessageRole::Assistant,
+            Role::Tool => MessageRole::Tool,
+        },
+        parts: message
+            .parts
+            .iter()
+            .map(|part| match part {
+                MessagePart::Text(text) => WitMessagePart::Text(text.clone()),
+                MessagePart::ImageUrl(url) => WitMessagePart::ImageUrl(url.clone()),
+                MessagePart::Binary { mime_type, data } => WitMessagePart::Binary(BinaryPart {
+                    mime_type: mime_type.clone(),
+                    data: data.clone(),
+                }),
+            })
+            .collect(),
+        tool_calls: message
+            .tool_calls
+            .iter()
+            .map(|tool_call| MemoryToolCall {
+                id: tool_call.id.clone(),
+                name: tool_call.name.clone(),
+                arguments: tool_call.arguments.to_string(),
+            })
+            .collect(),
+        tool_call_id: message.tool_call_id.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent;
+    use crate::testing::MockHost;
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages.iter().map(Message::text).collect()
+    }
+
+    fn tool_call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "get-weather".to_string(),
+            arguments: json!({ "city": "Berlin" }),
+        }
+    }
+
+    #[test]
+    fn windows_keep_the_system_messages() {
+        let _host = MockHost::new("test-worker").install();
+        let memory = Memory::new("test-worker--assistant--1", "history");
+
+        memory.push(Message::system("Be brief"));
+        memory.extend((1..=4).map(|n| Message::user(format!("question {}", n))));
+
+        assert_eq!(
+            texts(&memory.window(2)),
+            vec!["Be brief", "question 3", "question 4"]
+        );
+        assert_eq!(memory.window(10).len(), 5);
+        assert_eq!(memory.len(), 5);
+    }
+
+    #[test]
+    fn trims_to_the_window_when_pushing() {
+        let _host = MockHost::new("test-worker").install();
+        let memory = Memory::new("test-worker--assistant--1", "history").with_window(2);
+
+        memory.push(Message::system("Be brief"));
+        memory.extend((1..=3).map(|n| Message::user(format!("question {}", n))));
+
+        assert_eq!(
+            texts(&memory.messages()),
+            vec!["Be brief", "question 2", "question 3"]
+        );
+    }
+
+    #[test]
+    fn drops_tool_results_with_their_calls() {
+        let mut messages = vec![
+            Message::system("Be brief"),
+            Message::assistant_tool_calls(vec![tool_call("call-1"), tool_call("call-2")]),
+            Message::tool_result("call-1", "sunny"),
+            Message::tool_result("call-2", "rainy"),
+            Message::assistant("Sunny, then rainy"),
+        ];
+
+        assert!(drop_oldest(&mut messages));
+        assert_eq!(texts(&messages), vec!["Be brief", "Sunny, then rainy"]);
+
+        assert!(drop_oldest(&mut messages));
+        assert!(!drop_oldest(&mut messages));
+        assert_eq!(texts(&messages), vec!["Be brief"]);
+    }
+
+    #[test]
+    fn trims_to_the_token_budget() {
+        let _host = MockHost::new("test-worker").install();
+        let memory = Memory::new("test-worker--assistant--1", "history")
+            .with_token_counter(|message| message.text().len())
+            .with_token_budget(10);
+
+        memory.push(Message::system("rules"));
+        memory.push(Message::user("abc"));
+        memory.push(Message::user("defg"));
+
+        assert_eq!(texts(&memory.messages()), vec!["rules", "defg"]);
+        assert_eq!(memory.tokens(), 9);
+    }
+
+    #[test]
+    fn compacts_without_separating_tool_results_from_their_calls() {
+        let _host = MockHost::new("test-worker").install();
+        let memory = Memory::new("test-worker--assistant--1", "history");
+        memory.extend(vec![
+            Message::user("What's the weather?"),
+            Message::assistant_tool_calls(vec![tool_call("call-1")]),
+            Message::tool_result("call-1", "sunny"),
+        ]);
+
+        memory
+            .compact(1, |messages| Ok(format!("{} messages", messages.len())))
+            .unwrap();
+
+        assert_eq!(
+            texts(&memory.messages()),
+            vec!["Summary of the earlier conversation:\n1 messages", "", "sunny"]
+        );
+    }
+
+    #[test]
+    fn shares_the_memory_of_the_same_name() {
+        let _host = MockHost::new("test-worker").install();
+        let memory = Memory::new("test-worker--assistant--1", "history");
+        memory.push(Message::user("Hello"));
+
+        let again = Memory::new("test-worker--assistant--1", "history");
+        again.push(Message::user("Hello again"));
+
+        assert_eq!(texts(&memory.messages()), vec!["Hello", "Hello again"]);
+        assert_eq!(agent_memories("test-worker--assistant--1").len(), 1);
+        assert!(Memory::new("test-worker--assistant--1", "notes").is_empty());
+    }
+
+    #[test]
+    fn forgets_the_memories_of_replaced_agents() {
+        let _host = MockHost::new("test-worker").install();
+        Memory::new("test-worker--assistant--1", "history").push(Message::user("Hello"));
+        Memory::new("test-worker--assistant--2", "history").push(Message::user("Hello"));
+
+        agent::replace_agent("test-worker--assistant--1");
+
+        assert!(agent_memories("test-worker--assistant--1").is_empty());
+        assert!(Memory::new("test-worker--assistant--1", "history").is_empty());
+        assert_eq!(agent_memories("test-worker--assistant--2").len(), 1);
+    }
+
+    #[test]
+    fn round_trips_messages_through_json() {
+        let message = Message::assistant_tool_calls(vec![tool_call("call-1")])
+            .with_part(MessagePart::ImageUrl("https://example.com/map.png".to_string()))
+            .with_part(MessagePart::Binary {
+                mime_type: "image/png".to_string(),
+                data: vec![1, 2, 3],
+            });
+
+        assert_eq!(Message::from_json(&message.to_json()), Ok(message));
+        assert_eq!(
+            Message::from_json(&json!({ "role": "robot", "parts": [] })),
+            Err("Invalid message role: Some(\"robot\")".to_string())
+        );
+    }
+}
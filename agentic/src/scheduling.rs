@@ -0,0 +1,479 @@
+use crate::agent::Agent;
+use crate::agent_registry::{self, AgentId};
+use crate::host::agent_host;
+use crate::json_mapping::{json_to_value, value_to_json};
+use crate::{validation, versioning};
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::golem_rpc_0_2_x::types::Datetime;
+use golem_wasm_rpc::{Value, WitValue};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Lets an agent invoke its own methods later, once or periodically:
+//
+// ```
+//  fn remind_me(&self, message: String) {
+//    self.schedule_in("notify", vec![message.to_value()], Duration::from_secs(2 * 60 * 60)).unwrap();
+//  }
+//
+//  fn start_polling(&self) {
+//    *self.polling.lock().unwrap() = Some(self.every("poll", vec![], Duration::from_secs(60)).unwrap());
+//  }
+// ```
+//
+// The invocations are scheduled with `schedule-cancelable-invocation` of `run-scheduled` in the agent's own worker,
+// which finds the agent the way `get-agent` does and invokes the method. The schedules are kept in the memory
+// of the worker, which Golem restores by replaying it, and in snapshots, see `versioning`.
+//
+// The cancellation tokens of the host are resources that snapshots can't keep, so cancelling is by id:
+// `run-scheduled` ignores ids that are no longer scheduled, and schedule ids are never reused.
+
+struct Schedule {
+    agent_id: String,
+    method_name: String,
+    input: Vec<WitValue>,
+    // Periodic schedules are scheduled again every time they run
+    interval: Option<Duration>,
+}
+
+static SCHEDULES: Lazy<Mutex<HashMap<u64, Schedule>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static LAST_SCHEDULE_ID: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
+
+// A handle to a scheduled invocation, to be kept by the agent to cancel it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScheduledInvocation {
+    pub(crate) schedule_id: u64,
+}
+
+impl ScheduledInvocation {
+    pub fn schedule_id(&self) -> u64 {
+        self.schedule_id
+    }
+
+    // False once a one-off invocation has run, or once cancelled
+    pub fn is_active(&self) -> bool {
+        SCHEDULES.lock().unwrap().contains_key(&self.schedule_id)
+    }
+
+    // Cancelling twice, or after the invocation ran, does nothing
+    pub fn cancel(&self) {
+        if SCHEDULES.lock().unwrap().remove(&self.schedule_id).is_some() {
+            agent_host().cancel_scheduled_invocation(self.schedule_id);
+        }
+    }
+}
+
+// Implemented for every agent. Method names are the ones in the agent type, e.g. `get-weather`,
+// and the input is validated against the method's parameters when scheduling
+pub trait Scheduling {
+    fn schedule(
+        &self,
+        method_name: &str,
+        input: Vec<Value>,
+        at: SystemTime,
+    ) -> Result<ScheduledInvocation, String>;
+
+    fn schedule_in(
+        &self,
+        method_name: &str,
+        input: Vec<Value>,
+        delay: Duration,
+    ) -> Result<ScheduledInvocation, String> {
+        self.schedule(method_name, input, SystemTime::now() + delay)
+    }
+
+    // Invokes the method every interval, starting an interval from now, until cancelled
+    fn every(
+        &self,
+        method_name: &str,
+        input: Vec<Value>,
+        interval: Duration,
+    ) -> Result<ScheduledInvocation, String>;
+}
+
+impl<T: Agent + ?Sized> Scheduling for T {
+    fn schedule(
+        &self,
+        method_name: &str,
+        input: Vec<Value>,
+        at: SystemTime,
+    ) -> Result<ScheduledInvocation, String> {
+        add_schedule(self, method_name, input, at, None)
+    }
+
+    fn every(
+        &self,
+        method_name: &str,
+        input: Vec<Value>,
+        interval: Duration,
+    ) -> Result<ScheduledInvocation, String> {
+        if interval.is_zero() {
+            return Err("The interval of a periodic invocation can't be zero".to_string());
+        }
+        add_schedule(
+            self,
+            method_name,
+            input,
+            SystemTime::now() + interval,
+            Some(interval),
+        )
+    }
+}
+
+fn add_schedule<T: Agent + ?Sized>(
+    agent: &T,
+    method_name: &str,
+    input: Vec<Value>,
+    at: SystemTime,
+    interval: Option<Duration>,
+) -> Result<ScheduledInvocation, String> {
+    let definition = agent.get_definition();
+    let method_name = method_name.replace('_', "-");
+
+    if !definition.methods.iter().any(|method| method.name == method_name) {
+        return Err(format!(
+            "Method {} not found in agent type {}",
+            method_name, definition.type_name
+        ));
+    }
+
+    let input = input.into_iter().map(WitValue::from).collect::<Vec<_>>();
+    validation::validate_method_input(&definition, &method_name, &input)
+        .map_err(|error| format!("Invalid input of {}: {}", method_name, error))?;
+
+    let schedule_id = {
+        let mut last_schedule_id = LAST_SCHEDULE_ID.lock().unwrap();
+        *last_schedule_id += 1;
+        *last_schedule_id
+    };
+
+    SCHEDULES.lock().unwrap().insert(
+        schedule_id,
+        Schedule {
+            agent_id: agent.get_id(),
+            method_name,
+            input,
+            interval,
+        },
+    );
+
+    agent_host().schedule_invocation(to_datetime(at), schedule_id);
+
+    Ok(ScheduledInvocation { schedule_id })
+}
+
+// Runs a scheduled invocation, unless it was cancelled or its agent no longer exists.
+// The result has nobody to observe it
+pub fn run_scheduled(schedule_id: u64) {
+    let (agent_id, method_name, input, interval) = match SCHEDULES.lock().unwrap().get(&schedule_id) {
+        Some(schedule) => (
+            schedule.agent_id.clone(),
+            schedule.method_name.clone(),
+            schedule.input.clone(),
+            schedule.interval,
+        ),
+        None => return,
+    };
+
+    let Some(resolved_agent) = agent_registry::get_resolved_agent(&AgentId(agent_id)) else {
+        SCHEDULES.lock().unwrap().remove(&schedule_id);
+        return;
+    };
+
+    // Scheduled again before running, so that a failing run doesn't stop a periodic invocation
+    match interval {
+        Some(interval) => {
+            agent_host().schedule_invocation(to_datetime(SystemTime::now() + interval), schedule_id)
+        }
+        None => {
+            SCHEDULES.lock().unwrap().remove(&schedule_id);
+        }
+    }
+
+    resolved_agent.run(method_name, input);
+}
+
+// The invocations scheduled by the given agent
+pub fn scheduled_invocations(agent_id: &str) -> Vec<ScheduledInvocation> {
+    let mut schedule_ids = SCHEDULES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, schedule)| schedule.agent_id == agent_id)
+        .map(|(schedule_id, _)| *schedule_id)
+        .collect::<Vec<_>>();
+    schedule_ids.sort();
+
+    schedule_ids
+        .into_iter()
+        .map(|schedule_id| ScheduledInvocation { schedule_id })
+        .collect()
+}
+
+// The schedules with their input encoded as JSON, with the parameter types of their methods
+pub(crate) fn save_schedules() -> Result<serde_json::Value, String> {
+    let schedules = SCHEDULES.lock().unwrap();
+    let mut schedule_ids = schedules.keys().copied().collect::<Vec<_>>();
+    schedule_ids.sort();
+
+    let saved = schedule_ids
+        .into_iter()
+        .map(|schedule_id| {
+            let schedule = &schedules[&schedule_id];
+            let types = method_parameter_types(&schedule.agent_id, &schedule.method_name)?;
+            let input = schedule
+                .input
+                .iter()
+                .zip(&types)
+                .map(|(value, typ)| value_to_json(&Value::from(value.clone()), typ))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to save the input of schedule {}: {}", schedule_id, e))?;
+
+            Ok(json!({
+                "scheduleId": schedule_id,
+                "agentId": schedule.agent_id,
+                "methodName": schedule.method_name,
+                "input": input,
+                "intervalMillis": schedule.interval.map(|interval| interval.as_millis() as u64),
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(json!({
+        "lastScheduleId": *LAST_SCHEDULE_ID.lock().unwrap(),
+        "schedules": saved,
+    }))
+}
+
+// Restores the schedules saved by `save_schedules`, once the agents are restored.
+// Golem keeps the scheduled invocations of the worker, so they are not scheduled again
+pub(crate) fn load_schedules(saved: &serde_json::Value) -> Result<(), String> {
+    let Some(saved_schedules) = saved["schedules"].as_array() else {
+        return Ok(());
+    };
+
+    let mut schedules = HashMap::new();
+    for saved_schedule in saved_schedules {
+        let schedule_id = saved_schedule["scheduleId"]
+            .as_u64()
+            .ok_or_else(|| "Invalid snapshot: scheduleId is not a number".to_string())?;
+        let agent_id = saved_schedule["agentId"]
+            .as_str()
+            .ok_or_else(|| "Invalid snapshot: agentId of a schedule is not a string".to_string())?
+            .to_string();
+        let method_name = saved_schedule["methodName"]
+            .as_str()
+            .ok_or_else(|| "Invalid snapshot: methodName of a schedule is not a string".to_string())?
+            .to_string();
+        let input = saved_schedule["input"]
+            .as_array()
+            .ok_or_else(|| "Invalid snapshot: input of a schedule is not an array".to_string())?;
+
+        let types = method_parameter_types(&agent_id, &method_name)?;
+        if input.len() != types.len() {
+            return Err(format!(
+                "Schedule {} can't be restored, as {} now takes {} parameters instead of {}",
+                schedule_id,
+                method_name,
+                types.len(),
+                input.len()
+            ));
+        }
+        let input = input
+            .iter()
+            .zip(&types)
+            .map(|(value, typ)| json_to_value(value, typ).map(WitValue::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                format!(
+                    "Schedule {} can't be restored, as the parameters of {} changed: {}",
+                    schedule_id, method_name, e
+                )
+            })?;
+
+        schedules.insert(
+            schedule_id,
+            Schedule {
+                agent_id,
+                method_name,
+                input,
+                interval: saved_schedule["intervalMillis"].as_u64().map(Duration::from_millis),
+            },
+        );
+    }
+
+    *SCHEDULES.lock().unwrap() = schedules;
+    *LAST_SCHEDULE_ID.lock().unwrap() = saved["lastScheduleId"].as_u64().unwrap_or_default();
+    Ok(())
+}
+
+fn method_parameter_types(agent_id: &str, method_name: &str) -> Result<Vec<AnalysedType>, String> {
+    let definition = agent_registry::get_resolved_agent(&AgentId(agent_id.to_string()))
+        .map(|resolved_agent| resolved_agent.agent.get_definition())
+        .ok_or_else(|| format!("Agent {} of a schedule not found", agent_id))?;
+
+    definition
+        .methods
+        .iter()
+        .find(|method| method.name == method_name)
+        .map(|method| versioning::parameter_types(&method.input_schema))
+        .ok_or_else(|| {
+            format!(
+                "Method {} of a schedule not found in agent type {}",
+                method_name, definition.type_name
+            )
+        })
+}
+
+pub fn clear_schedules() {
+    SCHEDULES.lock().unwrap().clear();
+    *LAST_SCHEDULE_ID.lock().unwrap() = 0;
+}
+
+fn to_datetime(time: SystemTime) -> Datetime {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    Datetime {
+        seconds: since_epoch.as_secs(),
+        nanoseconds: since_epoch.subsec_nanos(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::golem::agent::common::StatusUpdate;
+    use crate::test_agents;
+    use crate::testing::{self, MockHost};
+    use golem_wasm_ast::analysis::analysed_type::{str, u32};
+    use std::sync::Arc;
+
+    static RUNS: Lazy<Mutex<Vec<Vec<Value>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+    fn create_reminder() -> Arc<dyn Agent + Send + Sync> {
+        test_agents::register(
+            "reminder",
+            vec![],
+            vec![test_agents::method("notify", vec![("count", u32())], str())],
+            |_, _, input| {
+                RUNS.lock().unwrap().push(input);
+                StatusUpdate::Emit("notified".to_string())
+            },
+        );
+        RUNS.lock().unwrap().clear();
+
+        let agent = testing::create_agent("reminder", vec![]).unwrap();
+        agent_registry::get_resolved_agent(&AgentId(agent.agent_id().to_string()))
+            .unwrap()
+            .agent
+    }
+
+    fn runs() -> Vec<Vec<Value>> {
+        RUNS.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn runs_one_off_invocations_once() {
+        let _host = MockHost::new("test-worker").install();
+        let agent = create_reminder();
+
+        let reminder = agent
+            .schedule_in("notify", vec![Value::U32(1)], Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(testing::scheduled_invocations(), vec![reminder]);
+
+        testing::run_scheduled(&reminder);
+        run_scheduled(reminder.schedule_id());
+
+        assert_eq!(runs(), vec![vec![Value::U32(1)]]);
+        assert!(!reminder.is_active());
+        assert!(testing::scheduled_invocations().is_empty());
+    }
+
+    #[test]
+    fn replaces_periodic_invocations_with_the_next_one() {
+        let _host = MockHost::new("test-worker").install();
+        let agent = create_reminder();
+
+        let polling = agent
+            .every("notify", vec![Value::U32(2)], Duration::from_secs(60))
+            .unwrap();
+        testing::run_scheduled(&polling);
+        testing::run_scheduled(&polling);
+
+        assert_eq!(runs(), vec![vec![Value::U32(2)], vec![Value::U32(2)]]);
+        assert!(polling.is_active());
+        assert_eq!(testing::scheduled_invocations(), vec![polling]);
+        assert_eq!(scheduled_invocations(&agent.get_id()), vec![polling]);
+    }
+
+    #[test]
+    fn cancels_invocations_by_id() {
+        let _host = MockHost::new("test-worker").install();
+        let agent = create_reminder();
+
+        let polling = agent
+            .every("notify", vec![Value::U32(3)], Duration::from_secs(60))
+            .unwrap();
+        polling.cancel();
+        polling.cancel();
+        run_scheduled(polling.schedule_id());
+
+        assert!(runs().is_empty());
+        assert!(!polling.is_active());
+        assert!(testing::scheduled_invocations().is_empty());
+    }
+
+    #[test]
+    fn ignores_invocations_cancelled_after_a_restore() {
+        let (polling, snapshot) = {
+            let _host = MockHost::new("test-worker").install();
+            let agent = create_reminder();
+            let polling = agent
+                .every("notify", vec![Value::U32(4)], Duration::from_secs(60))
+                .unwrap();
+            (polling, versioning::save_snapshot().unwrap())
+        };
+
+        let _host = MockHost::new("test-worker").install();
+        versioning::load_snapshot(snapshot).unwrap();
+        assert!(polling.is_active());
+
+        // The restored worker has no cancellation token, so the invocation scheduled before still comes
+        polling.cancel();
+        run_scheduled(polling.schedule_id());
+
+        assert!(runs().is_empty());
+        let agent = agent_registry::get_resolved_agent(&AgentId("test-worker--reminder--1".to_string()))
+            .unwrap()
+            .agent;
+        let reminder = agent
+            .schedule_in("notify", vec![Value::U32(5)], Duration::from_secs(60))
+            .unwrap();
+        assert_ne!(reminder.schedule_id(), polling.schedule_id());
+    }
+
+    #[test]
+    fn rejects_invalid_invocations() {
+        let _host = MockHost::new("test-worker").install();
+        let agent = create_reminder();
+
+        assert_eq!(
+            agent.schedule_in("forget", vec![], Duration::from_secs(60)),
+            Err("Method forget not found in agent type reminder".to_string())
+        );
+        assert!(agent
+            .schedule_in("notify", vec![Value::String("once".to_string())], Duration::from_secs(60))
+            .unwrap_err()
+            .starts_with("Invalid input of notify"));
+        assert_eq!(
+            agent.every("notify", vec![Value::U32(6)], Duration::ZERO),
+            Err("The interval of a periodic invocation can't be zero".to_string())
+        );
+        assert!(testing::scheduled_invocations().is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::bindings::golem::agent::common::{ProgressCounter, ProgressReport, StatusUpdate};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+// Status updates reported by the method that is currently being invoked.
+// This is a stack, because an agent method can invoke methods of other agents living in the same component,
+// and their updates shouldn't end up in the caller's updates
+static STATUS_UPDATE_COLLECTORS: Lazy<Mutex<Vec<Vec<StatusUpdate>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+// A handle to report progress and partial outputs from within an agent method.
+// Declaring a parameter of this type in an agent method makes `agent_implementation`
+// inject it, instead of expecting it as an input of the method:
+//
+// ```
+//  #[agent_definition]
+//  trait ResearchAgent {
+//    fn research(&self, topic: String, reporter: ProgressReporter) -> String;
+//  }
+// ```
+//
+// The updates are returned by `invoke-with-updates` once the method finished, while `invoke` only returns the final result.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgressReporter;
+
+pub type Emitter = ProgressReporter;
+
+impl ProgressReporter {
+    pub fn current() -> Self {
+        ProgressReporter
+    }
+
+    pub fn progress(&self, description: impl Into<String>) {
+        report(StatusUpdate::Progress(Some(ProgressReport {
+            description: description.into(),
+            counter: None,
+        })));
+    }
+
+    pub fn progress_step(&self, description: impl Into<String>, steps: u32, total: u32) {
+        report(StatusUpdate::Progress(Some(ProgressReport {
+            description: description.into(),
+            counter: Some(ProgressCounter { steps, total }),
+        })));
+    }
+
+    pub fn emit(&self, output: impl Into<String>) {
+        report(StatusUpdate::Emit(output.into()));
+    }
+}
+
+fn report(status_update: StatusUpdate) {
+    // Updates reported outside an invocation have nobody to observe them
+    if let Some(collector) = STATUS_UPDATE_COLLECTORS.lock().unwrap().last_mut() {
+        collector.push(status_update);
+    }
+}
+
+// Runs the invocation, and returns every update reported during it followed by its result
+pub fn collect_status_updates<F>(invocation: F) -> Vec<StatusUpdate>
+where
+    F: FnOnce() -> StatusUpdate,
+{
+    STATUS_UPDATE_COLLECTORS.lock().unwrap().push(Vec::new());
+
+    let result = invocation();
+
+    let mut updates = STATUS_UPDATE_COLLECTORS
+        .lock()
+        .unwrap()
+        .pop()
+        .unwrap_or_default();
+
+    updates.push(result);
+    updates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockHost;
+
+    #[test]
+    fn collects_the_updates_of_nested_invocations_separately() {
+        // The collectors are global, like the registries the lock of the host guards
+        let _host = MockHost::new("test-worker").install();
+
+        let updates = collect_status_updates(|| {
+            ProgressReporter::current().progress("outer");
+
+            let inner = collect_status_updates(|| {
+                ProgressReporter::current().emit("inner");
+                StatusUpdate::Emit("inner result".to_string())
+            });
+            assert_eq!(inner.len(), 2);
+
+            StatusUpdate::Emit("outer result".to_string())
+        });
+
+        assert_eq!(updates.len(), 2);
+        assert!(matches!(&updates[0], StatusUpdate::Progress(Some(report)) if report.description == "outer"));
+        assert!(matches!(&updates[1], StatusUpdate::Emit(output) if output == "outer result"));
+    }
+}
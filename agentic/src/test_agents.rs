@@ -0,0 +1,188 @@
+use crate::agent::Agent;
+use crate::agent_instance_registry::create_agent_id;
+use crate::agent_registry::{self, AgentId, AgentInitiator, GenericAgentType};
+use crate::bindings::golem::agent::common::{
+    AgentConstructor, AgentMethod, AgentType, DataSchema, ParameterType, StatusUpdate, Structured,
+};
+use crate::ResolvedAgent;
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::{Value, WitType, WitValue};
+use std::sync::Arc;
+
+// Agents for the tests of this crate. The macros generate code referring to `golem_agentic`,
+// so the agents are written by hand here, the way the macros would write them
+
+// Handles the invocations of a test agent, given its id, the method and the inputs
+pub(crate) type Handler = fn(&str, &str, Vec<Value>) -> StatusUpdate;
+
+struct FixtureAgent {
+    agent_id: String,
+    agent_type: AgentType,
+    handler: Handler,
+}
+
+impl Agent for FixtureAgent {
+    fn get_id(&self) -> String {
+        self.agent_id.clone()
+    }
+
+    fn invoke(&self, method_name: String, input: Vec<WitValue>) -> StatusUpdate {
+        (self.handler)(
+            &self.agent_id,
+            &method_name,
+            input.into_iter().map(Value::from).collect(),
+        )
+    }
+
+    fn get_definition(&self) -> AgentType {
+        self.agent_type.clone()
+    }
+}
+
+// Constructs the agent of a type registered with `register_agent`, given its id and its agent type
+pub(crate) type Construct = fn(String, AgentType) -> Result<Arc<dyn Agent + Send + Sync>, String>;
+
+struct FixtureInitiator {
+    agent_type: AgentType,
+    construct: Box<
+        dyn Fn(String, AgentType) -> Result<Arc<dyn Agent + Send + Sync>, String> + Send + Sync,
+    >,
+}
+
+impl AgentInitiator for FixtureInitiator {
+    fn initiate(&self, params: Vec<WitValue>) -> Result<ResolvedAgent, String> {
+        let agent_id = create_agent_id(self.agent_type.type_name.clone());
+        self.initiate_with_id(agent_id, params)
+    }
+
+    fn initiate_with_id(
+        &self,
+        agent_id: String,
+        params: Vec<WitValue>,
+    ) -> Result<ResolvedAgent, String> {
+        crate::agent::replace_agent(&agent_id);
+
+        let resolved_agent = ResolvedAgent {
+            agent: (self.construct)(agent_id.clone(), self.agent_type.clone())?,
+            agent_id: agent_id.clone(),
+        };
+
+        agent_registry::register_agent_instance(
+            AgentId(agent_id),
+            self.agent_type.type_name.clone(),
+            resolved_agent.clone(),
+            params,
+        );
+
+        Ok(resolved_agent)
+    }
+}
+
+pub(crate) fn schema(parameters: Vec<AnalysedType>) -> DataSchema {
+    DataSchema::Structured(Structured {
+        parameters: parameters
+            .into_iter()
+            .map(|typ| ParameterType::Wit(WitType::from(typ)))
+            .collect(),
+    })
+}
+
+// A method taking named parameters
+pub(crate) fn method(
+    name: &str,
+    parameters: Vec<(&str, AnalysedType)>,
+    output: AnalysedType,
+) -> (AgentMethod, Vec<String>) {
+    let names = parameters
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    (
+        AgentMethod {
+            name: name.to_string(),
+            description: format!("The {} method", name),
+            prompt_hint: None,
+            input_schema: schema(parameters.into_iter().map(|(_, typ)| typ).collect()),
+            output_schema: schema(vec![output]),
+        },
+        names,
+    )
+}
+
+// Registers an agent type with its implementation, as the `agent_definition` and
+// `agent_implementation` macros do
+pub(crate) fn register(
+    type_name: &str,
+    constructor: Vec<AnalysedType>,
+    methods: Vec<(AgentMethod, Vec<String>)>,
+    handler: Handler,
+) {
+    register_fixture(
+        type_name,
+        0,
+        constructor,
+        methods,
+        move |agent_id, agent_type| {
+            Ok(Arc::new(FixtureAgent {
+                agent_id,
+                agent_type,
+                handler,
+            }))
+        },
+    );
+}
+
+// Registers an agent type of the given version with an implementation of its own
+pub(crate) fn register_agent(
+    type_name: &str,
+    version: u32,
+    methods: Vec<(AgentMethod, Vec<String>)>,
+    construct: Construct,
+) {
+    register_fixture(type_name, version, vec![], methods, construct);
+}
+
+fn register_fixture(
+    type_name: &str,
+    version: u32,
+    constructor: Vec<AnalysedType>,
+    methods: Vec<(AgentMethod, Vec<String>)>,
+    construct: impl Fn(String, AgentType) -> Result<Arc<dyn Agent + Send + Sync>, String>
+        + Send
+        + Sync
+        + 'static,
+) {
+    for (method, names) in &methods {
+        agent_registry::register_method_parameter_names(
+            type_name.to_string(),
+            method.name.clone(),
+            names.clone(),
+        );
+    }
+
+    let generic_agent_type = GenericAgentType {
+        type_name: type_name.to_string(),
+        description: format!("The {} agent", type_name),
+        version,
+        methods: methods.into_iter().map(|(method, _)| method).collect(),
+        requires: vec![],
+    };
+
+    let agent_type = generic_agent_type.to_agent_type(AgentConstructor {
+        name: None,
+        description: "".to_string(),
+        prompt_hint: None,
+        input_schema: schema(constructor),
+    });
+
+    agent_registry::register_generic_agent_type(type_name.to_string(), generic_agent_type);
+    agent_registry::register_agent_type(type_name.to_string(), agent_type.clone());
+    agent_registry::register_agent_initiator(
+        type_name.to_string(),
+        Arc::new(FixtureInitiator {
+            agent_type,
+            construct: Box::new(construct),
+        }),
+    );
+}
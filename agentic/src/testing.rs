@@ -0,0 +1,783 @@
+use crate::agent_registry::{self, AgentId};
+use crate::approval;
+use crate::bindings::exports::golem::agent::guest::AgentRef;
+use crate::bindings::golem::agent::common::{AgentMemory, AgentType, ExternalRequest, StatusUpdate};
+use crate::bindings::golem::api::host::PromiseId;
+use crate::call_options::{self, IdempotencyKey};
+use crate::host::{self, AgentHost};
+use crate::logging::{self, LogRecord};
+use crate::memory;
+use crate::text;
+use crate::scheduling::{self, ScheduledInvocation};
+use crate::validation::{self, validate_value};
+use crate::{agent, agent_instance_registry, AgentArg, ResolvedAgent};
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::golem_rpc_0_2_x::types::Datetime;
+use golem_wasm_rpc::{ComponentId, Uuid, Value, WitValue, WorkerId};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Helpers to unit test agent implementations natively, with `cargo test`, enabled by the `testing` feature.
+// Agents are constructed through their `AgentInitiator` and invoked with `WitValue` encoded
+// parameters, the same way the component does it, while the Golem host is replaced by a `MockHost`:
+//
+// ```
+//  #[test]
+//  fn greets() {
+//    let _host = MockHost::new("test-worker").install();
+//
+//    let agent = testing::create_agent("Greeter", vec!["Golem".to_string().to_value()]).unwrap();
+//
+//    assert_eq!(agent.invoke_output("greet", vec![]), Ok("Hello, Golem".to_string()));
+//    assert_eq!(testing::live_agents().len(), 1);
+//  }
+// ```
+//
+// Remote agents (`wasm-rpc`) are not available natively.
+
+// Registries are global, while tests run in parallel, so a test holds this lock as long as its host is installed
+static TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// Idempotency keys generated by the mock host are numbered, so that tests are deterministic
+static IDEMPOTENCY_KEY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Promises created through the mock host by their oplog index, with the data they were completed with
+static PROMISES: Lazy<Mutex<Vec<Option<Vec<u8>>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Invocations scheduled through the mock host, by schedule id with their time as seconds and nanoseconds.
+// They only run when a test runs them with `run_scheduled`
+static SCHEDULED: Lazy<Mutex<HashMap<u64, (u64, u32)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Spans started through the mock host, by handle, and the handles of the open spans, innermost last
+static SPANS: Lazy<Mutex<Vec<RecordedSpan>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+static OPEN_SPANS: Lazy<Mutex<Vec<u64>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Records logged through the mock host, see `logging`
+static LOGS: Lazy<Mutex<Vec<LogRecord>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// A span recorded by the mock host, see `tracing`
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedSpan {
+    pub name: String,
+    // The index of the parent span in `spans()`
+    pub parent: Option<usize>,
+    pub attributes: Vec<(String, String)>,
+    pub finished: bool,
+}
+
+impl RecordedSpan {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockHost {
+    pub worker_name: String,
+    pub component_id: ComponentId,
+    pub agent_components: HashMap<String, ComponentId>,
+    // The configuration of the worker, e.g. `log-level`
+    pub config: HashMap<String, String>,
+}
+
+impl MockHost {
+    pub fn new(worker_name: impl Into<String>) -> Self {
+        MockHost {
+            worker_name: worker_name.into(),
+            component_id: ComponentId {
+                uuid: Uuid {
+                    high_bits: 0,
+                    low_bits: 0,
+                },
+            },
+            agent_components: HashMap::new(),
+            config: HashMap::new(),
+        }
+    }
+
+    pub fn with_component_id(mut self, component_id: ComponentId) -> Self {
+        self.component_id = component_id;
+        self
+    }
+
+    pub fn with_agent_component(
+        mut self,
+        agent_type_name: impl Into<String>,
+        component_id: ComponentId,
+    ) -> Self {
+        self.agent_components
+            .insert(agent_type_name.into(), component_id);
+        self
+    }
+
+    pub fn with_config(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.insert(key.into(), value.into());
+        self
+    }
+
+    // Makes this the host of the agents, starting from an empty worker.
+    // The real host is back once the returned guard is dropped
+    pub fn install(self) -> MockHostGuard {
+        let lock = TEST_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        agent_registry::clear_agent_instances();
+        agent_instance_registry::reset_agent_instance_counters();
+        call_options::clear_idempotent_results();
+        approval::clear_pending_requests();
+        PROMISES.lock().unwrap().clear();
+        scheduling::clear_schedules();
+        memory::clear_memories();
+        SCHEDULED.lock().unwrap().clear();
+        SPANS.lock().unwrap().clear();
+        OPEN_SPANS.lock().unwrap().clear();
+        LOGS.lock().unwrap().clear();
+        agent::reset_invocation_ids();
+        logging::reset_levels();
+        logging::install();
+        IDEMPOTENCY_KEY_COUNTER.store(0, Ordering::SeqCst);
+        host::set_agent_host(Arc::new(self));
+
+        MockHostGuard { _lock: lock }
+    }
+}
+
+impl AgentHost for MockHost {
+    fn worker_name(&self) -> String {
+        self.worker_name.clone()
+    }
+
+    fn component_id(&self) -> ComponentId {
+        self.component_id.clone()
+    }
+
+    fn agent_component(&self, agent_type_name: &str) -> Option<ComponentId> {
+        self.agent_components.get(agent_type_name).cloned()
+    }
+
+    fn generate_idempotency_key(&self) -> Uuid {
+        Uuid {
+            high_bits: 0,
+            low_bits: IDEMPOTENCY_KEY_COUNTER.fetch_add(1, Ordering::SeqCst) + 1,
+        }
+    }
+
+    fn create_promise(&self) -> PromiseId {
+        let mut promises = PROMISES.lock().unwrap();
+        promises.push(None);
+
+        PromiseId {
+            worker_id: WorkerId {
+                component_id: self.component_id.clone(),
+                worker_name: self.worker_name.clone(),
+            },
+            oplog_idx: promises.len() as u64,
+        }
+    }
+
+    // Natively nothing can complete a promise while an agent method waits for it,
+    // so a test completes it before invoking the method that waits
+    fn await_promise(&self, promise_id: &PromiseId) -> Vec<u8> {
+        PROMISES
+            .lock()
+            .unwrap()
+            .get((promise_id.oplog_idx as usize).wrapping_sub(1))
+            .cloned()
+            .flatten()
+            .unwrap_or_else(|| {
+                panic!(
+                    "Promise {} is awaited, but it is not completed",
+                    promise_id.oplog_idx
+                )
+            })
+    }
+
+    fn poll_promise(&self, promise_id: &PromiseId) -> Option<Vec<u8>> {
+        PROMISES
+            .lock()
+            .unwrap()
+            .get((promise_id.oplog_idx as usize).wrapping_sub(1))
+            .cloned()
+            .flatten()
+    }
+
+    fn complete_promise(&self, promise_id: &PromiseId, data: &[u8]) -> bool {
+        match PROMISES
+            .lock()
+            .unwrap()
+            .get_mut((promise_id.oplog_idx as usize).wrapping_sub(1))
+        {
+            Some(promise @ None) => {
+                *promise = Some(data.to_vec());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn schedule_invocation(&self, at: Datetime, schedule_id: u64) {
+        SCHEDULED
+            .lock()
+            .unwrap()
+            .insert(schedule_id, (at.seconds, at.nanoseconds));
+    }
+
+    fn cancel_scheduled_invocation(&self, schedule_id: u64) {
+        SCHEDULED.lock().unwrap().remove(&schedule_id);
+    }
+
+    fn start_span(&self, name: &str) -> u64 {
+        let mut spans = SPANS.lock().unwrap();
+        let mut open_spans = OPEN_SPANS.lock().unwrap();
+
+        spans.push(RecordedSpan {
+            name: name.to_string(),
+            parent: open_spans.last().map(|parent| *parent as usize),
+            attributes: vec![],
+            finished: false,
+        });
+
+        let span = (spans.len() - 1) as u64;
+        open_spans.push(span);
+        span
+    }
+
+    fn set_span_attribute(&self, span: u64, name: &str, value: &str) {
+        if let Some(span) = SPANS.lock().unwrap().get_mut(span as usize) {
+            span.attributes.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    fn finish_span(&self, span: u64) {
+        if let Some(recorded) = SPANS.lock().unwrap().get_mut(span as usize) {
+            recorded.finished = true;
+        }
+        OPEN_SPANS.lock().unwrap().retain(|open_span| *open_span != span);
+    }
+
+    // A fixed trace with the innermost open span as the parent
+    fn trace_context_headers(&self) -> Vec<(String, String)> {
+        let span = OPEN_SPANS.lock().unwrap().last().map_or(0, |span| *span + 1);
+
+        vec![(
+            "traceparent".to_string(),
+            format!("00-{:032x}-{:016x}-01", 1, span),
+        )]
+    }
+
+    fn log(&self, record: &LogRecord) {
+        LOGS.lock().unwrap().push(record.clone());
+    }
+
+    fn config_value(&self, key: &str) -> Option<String> {
+        self.config.get(key).cloned()
+    }
+}
+
+pub struct MockHostGuard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for MockHostGuard {
+    fn drop(&mut self) {
+        agent_registry::clear_agent_instances();
+        host::reset_agent_host();
+    }
+}
+
+// An agent constructed by `create_agent`
+#[derive(Clone)]
+pub struct TestAgent {
+    resolved_agent: ResolvedAgent,
+}
+
+// Constructs an agent through the registered `AgentInitiator` of its type
+pub fn create_agent(agent_type_name: &str, params: Vec<Value>) -> Result<TestAgent, String> {
+    let agent_initiator = agent_registry::get_agent_initiator(agent_type_name.to_string())
+        .ok_or_else(|| {
+            format!(
+                "No agent implementation found for agent type: {}. Available agent types: {}",
+                agent_type_name,
+                agent_types()
+                    .iter()
+                    .map(|x| x.type_name.clone())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+    let params = params.into_iter().map(WitValue::from).collect::<Vec<_>>();
+
+    if let Some(agent_type) = agent_registry::get_agent_def_by_name(agent_type_name) {
+        validation::validate_constructor_input(&agent_type, &params)
+            .map_err(|error| format!("Invalid constructor parameters: {}", error))?;
+        text::validate_constructor_text(&agent_type, &params)
+            .map_err(|error| format!("Invalid constructor parameters: {}", error))?;
+    }
+
+    Ok(TestAgent {
+        resolved_agent: agent_initiator.initiate(params)?,
+    })
+}
+
+impl TestAgent {
+    pub fn agent_id(&self) -> &str {
+        &self.resolved_agent.agent_id
+    }
+
+    pub fn definition(&self) -> AgentType {
+        self.resolved_agent.agent.get_definition()
+    }
+
+    // The memories of the agent, as returned by `get-memories`
+    pub fn memories(&self) -> Vec<AgentMemory> {
+        memory::agent_memories(&self.resolved_agent.agent_id)
+    }
+
+    // The result of the method, as returned by `invoke`
+    pub fn invoke(&self, method_name: &str, params: Vec<Value>) -> StatusUpdate {
+        self.invoke_with_updates(method_name, params)
+            .into_iter()
+            .last()
+            .expect("an invocation always has a result")
+    }
+
+    // Every status update reported by the method followed by its result, as returned by `invoke-with-updates`
+    pub fn invoke_with_updates(&self, method_name: &str, params: Vec<Value>) -> Vec<StatusUpdate> {
+        let params = params.into_iter().map(WitValue::from).collect();
+        self.resolved_agent.run(method_name.to_string(), params)
+    }
+
+    // The result of the method called with an idempotency key, as if it was called by a remote agent
+    // with `CallOptions::with_idempotency_key`. Calling it again with the same key returns the first result
+    pub fn invoke_idempotent(
+        &self,
+        idempotency_key: &IdempotencyKey,
+        method_name: &str,
+        params: Vec<Value>,
+    ) -> StatusUpdate {
+        let params = params.into_iter().map(WitValue::from).collect();
+        self.resolved_agent
+            .run_with_idempotency_key(Some(idempotency_key), method_name.to_string(), params)
+            .into_iter()
+            .last()
+            .expect("an invocation always has a result")
+    }
+
+    // The emitted output of the method, or a description of the error it failed with
+    pub fn invoke_output(&self, method_name: &str, params: Vec<Value>) -> Result<String, String> {
+        match self.invoke(method_name, params) {
+            StatusUpdate::Emit(output) => Ok(output),
+            StatusUpdate::Error(error) => Err(format!("{:?}", error)),
+            StatusUpdate::Progress(report) => Err(format!(
+                "Expected a result, but the invocation ended with progress: {:?}",
+                report
+            )),
+        }
+    }
+}
+
+pub fn agent_types() -> Vec<AgentType> {
+    agent_registry::get_all_agent_definitions()
+}
+
+// The agents living in the mocked worker, as returned by `discover-agents`
+pub fn live_agents() -> Vec<AgentRef> {
+    agent_registry::discover_agents()
+}
+
+// The requests published with `approval::publish` and not answered yet
+pub fn pending_requests() -> Vec<ExternalRequest> {
+    approval::pending_requests()
+}
+
+// Answers a request, as it would be answered from outside of the worker
+pub fn complete_request(request: &ExternalRequest, response: Value) -> Result<(), String> {
+    let typ = AnalysedType::from(request.response_type.clone());
+    validate_value(&response, &typ).map_err(|error| format!("Invalid response: {}", error))?;
+
+    let json = crate::json_mapping::value_to_json(&response, &typ)?;
+    let data = serde_json::to_vec(&json).map_err(|error| error.to_string())?;
+
+    let completed = host::agent_host().complete_promise(
+        &PromiseId {
+            worker_id: WorkerId {
+                component_id: host::agent_host().component_id(),
+                worker_name: host::agent_host().worker_name(),
+            },
+            oplog_idx: request.promise_oplog_index,
+        },
+        &data,
+    );
+
+    if completed {
+        Ok(())
+    } else {
+        Err(format!(
+            "The request at oplog index {} was completed before",
+            request.promise_oplog_index
+        ))
+    }
+}
+
+// The invocations scheduled by agents and not run yet, the earliest first
+pub fn scheduled_invocations() -> Vec<ScheduledInvocation> {
+    let mut scheduled = SCHEDULED
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(schedule_id, at)| (*at, *schedule_id))
+        .collect::<Vec<_>>();
+    scheduled.sort();
+
+    scheduled
+        .into_iter()
+        .map(|(_, schedule_id)| ScheduledInvocation { schedule_id })
+        .collect()
+}
+
+// Runs a scheduled invocation as Golem would at its time, regardless of the current time
+pub fn run_scheduled(scheduled_invocation: &ScheduledInvocation) {
+    SCHEDULED
+        .lock()
+        .unwrap()
+        .remove(&scheduled_invocation.schedule_id);
+    scheduling::run_scheduled(scheduled_invocation.schedule_id);
+}
+
+// The spans started since the mock host was installed, in order, see `tracing`
+pub fn spans() -> Vec<RecordedSpan> {
+    SPANS.lock().unwrap().clone()
+}
+
+// The records logged since the mock host was installed, with the levels of its `log-level` configuration
+pub fn logs() -> Vec<LogRecord> {
+    LOGS.lock().unwrap().clone()
+}
+
+pub fn is_registered(agent_id: &str) -> bool {
+    agent_registry::get_resolved_agent(&AgentId(agent_id.to_string())).is_some()
+}
+
+// Checks that a value survives being encoded as a `WitValue` and decoded again,
+// and that its encoding matches the WIT type advertised for its type
+pub fn check_round_trip<T: AgentArg + PartialEq + Debug>(value: &T) -> Result<(), String> {
+    let typ = AnalysedType::from(<T as AgentArg>::get_wit_type());
+    let encoded = AgentArg::to_value(value);
+
+    validate_value(&encoded, &typ)
+        .map_err(|error| format!("{:?} is encoded as {:?}: {}", value, encoded, error))?;
+
+    let decoded = <T as AgentArg>::from_wit_value(WitValue::from(encoded.clone()))
+        .map_err(|error| format!("{:?} is encoded as {:?}, which fails to decode: {}", value, encoded, error))?;
+
+    if decoded != *value {
+        return Err(format!(
+            "{:?} is encoded as {:?}, which decodes to {:?}",
+            value, encoded, decoded
+        ));
+    }
+
+    Ok(())
+}
+
+// Checks `check_round_trip` for values generated from the WIT type advertised by the type,
+// which also checks that every value of that WIT type decodes and encodes back to itself:
+//
+// ```
+//  #[test]
+//  fn weather_report_round_trips() {
+//    testing::check_round_trips::<WeatherReport>().unwrap();
+//  }
+// ```
+#[cfg(feature = "proptest")]
+pub fn check_round_trips<T>() -> Result<(), String>
+where
+    T: AgentArg + PartialEq + Debug,
+{
+    let typ = AnalysedType::from(<T as AgentArg>::get_wit_type());
+    let strategy = value_strategy(&typ)?;
+
+    proptest::test_runner::TestRunner::default()
+        .run(&strategy, |value| {
+            check_value_round_trip::<T>(value).map_err(proptest::test_runner::TestCaseError::fail)
+        })
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(feature = "proptest")]
+fn check_value_round_trip<T: AgentArg + PartialEq + Debug>(value: Value) -> Result<(), String> {
+    let decoded = <T as AgentArg>::from_wit_value(WitValue::from(value.clone()))
+        .map_err(|error| format!("{:?} fails to decode: {}", value, error))?;
+
+    let encoded = AgentArg::to_value(&decoded);
+    if encoded != value {
+        return Err(format!(
+            "{:?} decodes to {:?}, which is encoded as {:?}",
+            value, decoded, encoded
+        ));
+    }
+
+    check_round_trip(&decoded)
+}
+
+// Checks `check_round_trip` for values generated by the given strategy,
+// for types accepting only some of the values of their WIT type
+#[cfg(feature = "proptest")]
+pub fn check_round_trips_with<T, S>(strategy: S) -> Result<(), String>
+where
+    T: AgentArg + PartialEq + Debug,
+    S: proptest::strategy::Strategy<Value = T>,
+{
+    proptest::test_runner::TestRunner::default()
+        .run(&strategy, |value| {
+            check_round_trip(&value).map_err(proptest::test_runner::TestCaseError::fail)
+        })
+        .map_err(|error| error.to_string())
+}
+
+// Generates the values of a WIT type. Lists have up to 4 items, and floats are never NaN,
+// as NaN is not equal to itself. Handles refer to resources of a worker, so they are not generated
+#[cfg(feature = "proptest")]
+pub fn value_strategy(typ: &AnalysedType) -> Result<proptest::strategy::BoxedStrategy<Value>, String> {
+    use golem_wasm_ast::analysis::{
+        TypeEnum, TypeFlags, TypeList, TypeOption, TypeRecord, TypeResult, TypeTuple, TypeVariant,
+    };
+    use proptest::prelude::*;
+
+    let strategy = match typ {
+        AnalysedType::Bool(_) => any::<bool>().prop_map(Value::Bool).boxed(),
+        AnalysedType::U8(_) => any::<u8>().prop_map(Value::U8).boxed(),
+        AnalysedType::U16(_) => any::<u16>().prop_map(Value::U16).boxed(),
+        AnalysedType::U32(_) => any::<u32>().prop_map(Value::U32).boxed(),
+        AnalysedType::U64(_) => any::<u64>().prop_map(Value::U64).boxed(),
+        AnalysedType::S8(_) => any::<i8>().prop_map(Value::S8).boxed(),
+        AnalysedType::S16(_) => any::<i16>().prop_map(Value::S16).boxed(),
+        AnalysedType::S32(_) => any::<i32>().prop_map(Value::S32).boxed(),
+        AnalysedType::S64(_) => any::<i64>().prop_map(Value::S64).boxed(),
+        AnalysedType::F32(_) => any::<f32>()
+            .prop_filter("NaN", |value| !value.is_nan())
+            .prop_map(Value::F32)
+            .boxed(),
+        AnalysedType::F64(_) => any::<f64>()
+            .prop_filter("NaN", |value| !value.is_nan())
+            .prop_map(Value::F64)
+            .boxed(),
+        AnalysedType::Chr(_) => any::<char>().prop_map(Value::Char).boxed(),
+        AnalysedType::Str(_) => any::<String>().prop_map(Value::String).boxed(),
+        AnalysedType::List(TypeList { inner, .. }) => {
+            proptest::collection::vec(value_strategy(inner)?, 0..4)
+                .prop_map(Value::List)
+                .boxed()
+        }
+        AnalysedType::Tuple(TypeTuple { items, .. }) => items
+            .iter()
+            .map(value_strategy)
+            .collect::<Result<Vec<_>, _>>()?
+            .prop_map(Value::Tuple)
+            .boxed(),
+        AnalysedType::Record(TypeRecord { fields, .. }) => fields
+            .iter()
+            .map(|field| value_strategy(&field.typ))
+            .collect::<Result<Vec<_>, _>>()?
+            .prop_map(Value::Record)
+            .boxed(),
+        AnalysedType::Variant(TypeVariant { cases, .. }) => {
+            let cases = cases
+                .iter()
+                .enumerate()
+                .map(|(case_idx, case)| {
+                    let case_idx = case_idx as u32;
+                    Ok(match &case.typ {
+                        Some(typ) => value_strategy(typ)?
+                            .prop_map(move |value| Value::Variant {
+                                case_idx,
+                                case_value: Some(Box::new(value)),
+                            })
+                            .boxed(),
+                        None => Just(Value::Variant {
+                            case_idx,
+                            case_value: None,
+                        })
+                        .boxed(),
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            proptest::strategy::Union::new(cases).boxed()
+        }
+        AnalysedType::Enum(TypeEnum { cases, .. }) => {
+            (0..cases.len() as u32).prop_map(Value::Enum).boxed()
+        }
+        AnalysedType::Flags(TypeFlags { names, .. }) => {
+            proptest::collection::vec(any::<bool>(), names.len())
+                .prop_map(Value::Flags)
+                .boxed()
+        }
+        AnalysedType::Option(TypeOption { inner, .. }) => {
+            proptest::option::of(value_strategy(inner)?)
+                .prop_map(|value| Value::Option(value.map(Box::new)))
+                .boxed()
+        }
+        AnalysedType::Result(TypeResult { ok, err, .. }) => {
+            let ok = payload_strategy(ok.as_deref())?.prop_map(|value| Value::Result(Ok(value)));
+            let err = payload_strategy(err.as_deref())?.prop_map(|value| Value::Result(Err(value)));
+            prop_oneof![ok, err].boxed()
+        }
+        AnalysedType::Handle(_) => {
+            return Err("Values of handle types can't be generated".to_string())
+        }
+    };
+
+    Ok(strategy)
+}
+
+#[cfg(feature = "proptest")]
+fn payload_strategy(
+    typ: Option<&AnalysedType>,
+) -> Result<proptest::strategy::BoxedStrategy<Option<Box<Value>>>, String> {
+    use proptest::prelude::*;
+
+    match typ {
+        Some(typ) => Ok(value_strategy(typ)?
+            .prop_map(|value| Some(Box::new(value)))
+            .boxed()),
+        None => Ok(Just(None).boxed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::golem::agent::common::{Error, ProgressReport};
+    use crate::status::ProgressReporter;
+    use crate::test_agents;
+    use golem_wasm_ast::analysis::analysed_type::{str, u32};
+
+    fn register_greeter() {
+        test_agents::register(
+            "greeter",
+            vec![str()],
+            vec![test_agents::method("greet", vec![("times", u32())], str())],
+            |_, _, input| match input.as_slice() {
+                [Value::U32(times)] => {
+                    ProgressReporter::current().progress("greeting");
+                    StatusUpdate::Emit("Hello! ".repeat(*times as usize).trim_end().to_string())
+                }
+                _ => StatusUpdate::Error(Error::InvalidInput("unexpected input".to_string())),
+            },
+        );
+    }
+
+    #[test]
+    fn creates_and_invokes_agents() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+
+        let agent = create_agent("greeter", vec![Value::String("Golem".to_string())]).unwrap();
+
+        assert_eq!(agent.agent_id(), "test-worker--greeter--1");
+        assert_eq!(
+            agent.invoke_output("greet", vec![Value::U32(2)]),
+            Ok("Hello! Hello!".to_string())
+        );
+        assert_eq!(
+            live_agents()
+                .into_iter()
+                .map(|agent_ref| agent_ref.agent_id)
+                .collect::<Vec<_>>(),
+            vec!["test-worker--greeter--1".to_string()]
+        );
+    }
+
+    #[test]
+    fn returns_the_updates_before_the_result() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+        let agent = create_agent("greeter", vec![Value::String("Golem".to_string())]).unwrap();
+
+        let updates = agent.invoke_with_updates("greet", vec![Value::U32(1)]);
+
+        assert_eq!(updates.len(), 2);
+        assert!(matches!(
+            &updates[0],
+            StatusUpdate::Progress(Some(ProgressReport { description, counter: None })) if description == "greeting"
+        ));
+        assert!(matches!(&updates[1], StatusUpdate::Emit(output) if output == "Hello!"));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+
+        assert_eq!(
+            create_agent("greeter", vec![Value::U32(1)]).err(),
+            Some(
+                "Invalid constructor parameters: parameter 0: expected a value of type string, but got u32"
+                    .to_string()
+            )
+        );
+
+        let agent = create_agent("greeter", vec![Value::String("Golem".to_string())]).unwrap();
+        assert!(matches!(
+            agent.invoke("greet", vec![Value::String("twice".to_string())]),
+            StatusUpdate::Error(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn starts_every_test_from_an_empty_worker() {
+        {
+            let _host = MockHost::new("test-worker").install();
+            register_greeter();
+            create_agent("greeter", vec![Value::String("Golem".to_string())]).unwrap();
+        }
+
+        let _host = MockHost::new("test-worker").install();
+        let agent = create_agent("greeter", vec![Value::String("Golem".to_string())]).unwrap();
+
+        assert_eq!(agent.agent_id(), "test-worker--greeter--1");
+        assert_eq!(live_agents().len(), 1);
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn generates_values_of_the_given_type() {
+        use golem_wasm_ast::analysis::analysed_type::{
+            case, f64, field, flags, list, option, r#enum, record, result, tuple, unit_case, variant,
+        };
+
+        let typ = record(vec![
+            field("city", str()),
+            field("days", list(tuple(vec![u32(), f64()]))),
+            field("alert", option(variant(vec![case("storm", str()), unit_case("heat")]))),
+            field("unit", r#enum(&["celsius", "fahrenheit"])),
+            field("sources", flags(&["radar", "satellite"])),
+            field("status", result(u32(), str())),
+        ]);
+        let strategy = value_strategy(&typ).unwrap();
+
+        let result = proptest::test_runner::TestRunner::default()
+            .run(&strategy, |value| {
+                validate_value(&value, &typ)
+                    .map_err(|error| proptest::test_runner::TestCaseError::fail(error.to_string()))
+            })
+            .map_err(|error| error.to_string());
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn checks_round_trips_from_the_wit_type() {
+        assert_eq!(check_round_trips::<Vec<Option<u64>>>(), Ok(()));
+        assert_eq!(check_round_trips::<Option<Vec<String>>>(), Ok(()));
+    }
+}
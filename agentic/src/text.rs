@@ -0,0 +1,336 @@
+use crate::bindings::golem::agent::common::{
+    AgentType, DataSchema, ParameterType, TextFormat, TextType,
+};
+use crate::type_mapping::{FromValue, FromWitValue, ToValue, ToWitType};
+use golem_wasm_ast::analysis::analysed_type::str;
+use golem_wasm_rpc::{Value, WitType, WitValue};
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+
+// Natural language input of an agent method.
+// Unlike a `String`, which is advertised as a plain `wit(string)` parameter, a `Text` parameter
+// is registered as a `text` parameter type, so callers (and LLMs) can tell it apart from identifiers.
+// Constraints are declared on the parameter in the agent definition:
+//
+// ```
+//  #[agent_definition]
+//  trait Summarizer {
+//    fn summarize(&self, #[text(language = "en", max_length = 4000, format = "markdown")] document: Text) -> String;
+//  }
+// ```
+//
+// and are checked before the method is invoked. Constructor fields of type `Text` take the same attribute.
+// A language restricts the text to the script of the language, for the languages written in a single script.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Text(pub String);
+
+impl Text {
+    pub fn new(text: impl Into<String>) -> Self {
+        Text(text.into())
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Text {
+    fn from(value: String) -> Self {
+        Text(value)
+    }
+}
+
+impl From<&str> for Text {
+    fn from(value: &str) -> Self {
+        Text(value.to_string())
+    }
+}
+
+impl ToValue for Text {
+    fn to_value(&self) -> Value {
+        Value::String(self.0.clone())
+    }
+}
+
+impl FromValue for Text {
+    fn from_value(value: Value) -> Result<Self, String> {
+        match value {
+            Value::String(s) => Ok(Text(s)),
+            _ => Err("Expected a String value".to_string()),
+        }
+    }
+}
+
+impl FromWitValue for Text {
+    fn from_wit_value(value: WitValue) -> Result<Self, String> {
+        Text::from_value(Value::from(value))
+    }
+}
+
+impl ToWitType for Text {
+    fn get_wit_type() -> WitType {
+        WitType::from(str())
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Text {
+    type Parameters = ();
+    type Strategy = proptest::strategy::Map<proptest::arbitrary::StrategyFor<String>, fn(String) -> Text>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::arbitrary::any::<String>().prop_map(Text as fn(String) -> Text)
+    }
+}
+
+// Implemented by `Text` only. The macros advertise a parameter named `Text` as a text parameter,
+// and check with it that the name refers to this type
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not `golem_agentic::Text`",
+    note = "refer to other types named `Text` by a path, e.g. `my_module::Text`, to pass them as WIT values"
+)]
+pub trait TextParameter {}
+
+impl TextParameter for Text {}
+
+#[doc(hidden)]
+pub fn text_parameter<T: TextParameter>(text_type: TextType) -> ParameterType {
+    ParameterType::Text(text_type)
+}
+
+// An empty language code means the text is not restricted to a language
+pub fn text_type(language_code: &str, max_length: Option<u32>, format: TextFormat) -> TextType {
+    TextType {
+        language_code: language_code.to_string(),
+        max_length,
+        format,
+    }
+}
+
+pub fn validate_text(text: &str, text_type: &TextType) -> Result<(), String> {
+    if let Some(max_length) = text_type.max_length {
+        let length = text.chars().count();
+        if length > max_length as usize {
+            return Err(format!(
+                "Text is {} characters long, but at most {} characters are allowed",
+                length, max_length
+            ));
+        }
+    }
+
+    if !text_type.language_code.is_empty() {
+        validate_script(text, &text_type.language_code)?;
+    }
+
+    if let TextFormat::Json = text_type.format {
+        serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|e| format!("Text is expected to be JSON: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+    Han,
+    Japanese,
+    Hangul,
+}
+
+// The script a language is written in, for the languages written in a single script
+fn language_script(language_code: &str) -> Option<Script> {
+    let language = language_code
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match language.as_str() {
+        "en" | "de" | "fr" | "es" | "it" | "pt" | "nl" | "sv" | "da" | "no" | "nb" | "nn"
+        | "fi" | "is" | "pl" | "cs" | "sk" | "sl" | "hr" | "hu" | "ro" | "et" | "lv" | "lt"
+        | "tr" | "id" | "ms" | "vi" | "ca" | "eu" | "gl" | "ga" | "cy" | "sq" | "sw" | "tl" => {
+            Some(Script::Latin)
+        }
+        "ru" | "uk" | "be" | "bg" | "mk" | "kk" | "ky" | "mn" => Some(Script::Cyrillic),
+        "el" => Some(Script::Greek),
+        "ar" | "fa" | "ur" => Some(Script::Arabic),
+        "he" | "yi" => Some(Script::Hebrew),
+        "hi" | "mr" | "ne" => Some(Script::Devanagari),
+        "th" => Some(Script::Thai),
+        "zh" => Some(Script::Han),
+        "ja" => Some(Script::Japanese),
+        "ko" => Some(Script::Hangul),
+        _ => None,
+    }
+}
+
+fn in_script(c: char, script: Script) -> bool {
+    let han = matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}'
+    );
+
+    match script {
+        Script::Latin => {
+            c.is_ascii_alphabetic() || matches!(c, '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}')
+        }
+        Script::Cyrillic => matches!(c, '\u{0400}'..='\u{052F}'),
+        Script::Greek => matches!(c, '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}'),
+        Script::Arabic => matches!(c, '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}'),
+        Script::Hebrew => matches!(c, '\u{0590}'..='\u{05FF}'),
+        Script::Devanagari => matches!(c, '\u{0900}'..='\u{097F}'),
+        Script::Thai => matches!(c, '\u{0E00}'..='\u{0E7F}'),
+        Script::Han => han,
+        Script::Japanese => han || matches!(c, '\u{3040}'..='\u{30FF}'),
+        Script::Hangul => matches!(
+            c,
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}'
+        ),
+    }
+}
+
+// Text in a language is expected to be mostly written in the script of the language.
+// Names and quotes in other scripts are fine, as long as most of the letters are in the script.
+// Languages written in several scripts, or not known here, are not checked
+fn validate_script(text: &str, language_code: &str) -> Result<(), String> {
+    let Some(script) = language_script(language_code) else {
+        return Ok(());
+    };
+
+    let letters = text.chars().filter(|c| c.is_alphabetic()).collect::<Vec<_>>();
+    let written_in_script = letters.iter().filter(|c| in_script(**c, script)).count();
+
+    if written_in_script * 2 < letters.len() {
+        return Err(format!(
+            "Text is expected to be in language {}, but {} of its {} letters are not in the {:?} script",
+            language_code,
+            letters.len() - written_in_script,
+            letters.len(),
+            script
+        ));
+    }
+
+    Ok(())
+}
+
+fn text_parameters(schema: &DataSchema) -> &[ParameterType] {
+    match schema {
+        DataSchema::Structured(structured) => &structured.parameters,
+        DataSchema::Multimodal(_) => &[],
+    }
+}
+
+fn validate_text_input(parameters: &[ParameterType], input: &[WitValue]) -> Result<(), String> {
+    for (index, (parameter, value)) in parameters.iter().zip(input).enumerate() {
+        if let ParameterType::Text(text_type) = parameter {
+            match Value::from(value.clone()) {
+                Value::String(text) => validate_text(&text, text_type)
+                    .map_err(|e| format!("Invalid text for parameter {}: {}", index, e))?,
+                other => {
+                    return Err(format!(
+                        "Expected text for parameter {}, but got {:?}",
+                        index, other
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the text parameters of the method against the constraints in the agent definition
+pub fn validate_text_parameters(
+    agent_type: &AgentType,
+    method_name: &str,
+    input: &[WitValue],
+) -> Result<(), String> {
+    match agent_type.methods.iter().find(|m| m.name == method_name) {
+        Some(method) => validate_text_input(text_parameters(&method.input_schema), input),
+        None => Ok(()),
+    }
+}
+
+// Checks the text parameters of the constructor against the constraints in the agent definition
+pub fn validate_constructor_text(agent_type: &AgentType, input: &[WitValue]) -> Result<(), String> {
+    validate_text_input(
+        text_parameters(&agent_type.agent_constructor.input_schema),
+        input,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_the_maximum_length_in_characters() {
+        let text_type = text_type("", Some(5), TextFormat::Plain);
+
+        assert_eq!(validate_text("héllo", &text_type), Ok(()));
+        assert_eq!(
+            validate_text("hello!", &text_type),
+            Err("Text is 6 characters long, but at most 5 characters are allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn checks_json_texts() {
+        let text_type = text_type("", None, TextFormat::Json);
+
+        assert_eq!(validate_text(r#"{"city": "Berlin"}"#, &text_type), Ok(()));
+        assert!(validate_text("Berlin", &text_type)
+            .unwrap_err()
+            .starts_with("Text is expected to be JSON"));
+    }
+
+    #[test]
+    fn checks_the_script_of_the_language() {
+        let english = text_type("en", None, TextFormat::Plain);
+        let russian = text_type("ru-RU", None, TextFormat::Plain);
+
+        assert_eq!(validate_text("The weather in Zürich, 12°C", &english), Ok(()));
+        assert_eq!(validate_text("Погода в Москве", &russian), Ok(()));
+        assert_eq!(
+            validate_text("Погода в Москве", &english),
+            Err("Text is expected to be in language en, but 13 of its 13 letters are not in the Latin script".to_string())
+        );
+    }
+
+    #[test]
+    fn allows_names_in_other_scripts() {
+        let english = text_type("en", None, TextFormat::Plain);
+
+        assert_eq!(validate_text("A trip from Berlin to Москва", &english), Ok(()));
+    }
+
+    #[test]
+    fn does_not_check_languages_of_several_scripts() {
+        let serbian = text_type("sr", None, TextFormat::Plain);
+
+        assert_eq!(validate_text("Време у Београду", &serbian), Ok(()));
+        assert_eq!(validate_text("Vreme u Beogradu", &serbian), Ok(()));
+    }
+}
@@ -0,0 +1,716 @@
+use crate::agent::RemoteAgent;
+use crate::agent_registry::{self, AgentId};
+use crate::bindings::exports::golem::agent::guest::GuestAgent;
+use crate::bindings::golem::agent::common::{
+    AgentConstructor, AgentMethod, AgentType, DataSchema, Error, ParameterType, StatusUpdate,
+    Structured, TextFormat,
+};
+use crate::json_mapping::{json_to_value, object_schema, value_to_json, wit_type_to_json_schema};
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::{Value, WitValue};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+// Exposes agent methods as tools for LLM function calling.
+// Every method of an agent type becomes a tool named `{agent-type}__{method-name}`,
+// and the dispatcher turns the tool calls of the model back into `Agent::invoke` calls.
+
+const TOOL_NAME_SEPARATOR: &str = "__";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolProvider {
+    OpenAi,
+    Anthropic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+impl ToolCall {
+    pub fn agent_type_name(&self) -> Result<&str, String> {
+        parse_tool_name(&self.name).map(|(agent_type, _)| agent_type)
+    }
+
+    pub fn method_name(&self) -> Result<&str, String> {
+        parse_tool_name(&self.name).map(|(_, method)| method)
+    }
+}
+
+pub fn tool_name(agent_type_name: &str, method_name: &str) -> String {
+    format!("{}{}{}", agent_type_name, TOOL_NAME_SEPARATOR, method_name)
+}
+
+pub fn parse_tool_name(tool_name: &str) -> Result<(&str, &str), String> {
+    tool_name.split_once(TOOL_NAME_SEPARATOR).ok_or_else(|| {
+        format!(
+            "Invalid tool name: {}. Expected format is {{agent_type}}{}{{method_name}}",
+            tool_name, TOOL_NAME_SEPARATOR
+        )
+    })
+}
+
+pub fn tool_definitions(provider: ToolProvider, agent_type: &AgentType) -> Vec<serde_json::Value> {
+    agent_type
+        .methods
+        .iter()
+        .map(|method| tool_definition(provider, &agent_type.type_name, method))
+        .collect()
+}
+
+pub fn tool_definition(
+    provider: ToolProvider,
+    agent_type_name: &str,
+    method: &AgentMethod,
+) -> serde_json::Value {
+    method_tool(agent_type_name, method).to_json(provider)
+}
+
+// A tool as offered to a model, independent of the provider
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    // The JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    pub fn to_json(&self, provider: ToolProvider) -> serde_json::Value {
+        match provider {
+            ToolProvider::OpenAi => json!({
+                "type": "function",
+                "function": {
+                    "name": self.name,
+                    "description": self.description,
+                    "parameters": self.parameters,
+                }
+            }),
+            ToolProvider::Anthropic => json!({
+                "name": self.name,
+                "description": self.description,
+                "input_schema": self.parameters,
+            }),
+        }
+    }
+}
+
+pub fn method_tool(agent_type_name: &str, method: &AgentMethod) -> ToolDefinition {
+    let description = match &method.prompt_hint {
+        Some(hint) if !method.description.is_empty() => format!("{} {}", method.description, hint),
+        Some(hint) => hint.clone(),
+        None => method.description.clone(),
+    };
+
+    ToolDefinition {
+        name: tool_name(agent_type_name, &method.name),
+        description,
+        parameters: parameters_schema(agent_type_name, method),
+    }
+}
+
+pub fn parameters_schema(agent_type_name: &str, method: &AgentMethod) -> serde_json::Value {
+    let properties = parameter_names(agent_type_name, method)
+        .into_iter()
+        .zip(parameter_types(&method.input_schema))
+        .map(|(name, parameter_type)| (name, parameter_type_schema(&parameter_type)))
+        .collect();
+
+    object_schema(properties)
+}
+
+fn parameter_type_schema(parameter_type: &ParameterType) -> serde_json::Value {
+    match parameter_type {
+        ParameterType::Wit(wit_type) => wit_type_to_json_schema(&AnalysedType::from(wit_type.clone())),
+        ParameterType::Text(text_type) => {
+            let mut schema = json!({ "type": "string" });
+
+            let format = match text_type.format {
+                TextFormat::Plain => "plain text",
+                TextFormat::Markdown => "markdown",
+                TextFormat::Json => "JSON",
+            };
+            schema["description"] = if text_type.language_code.is_empty() {
+                json!(format!("Natural language text, formatted as {}", format))
+            } else {
+                json!(format!(
+                    "Natural language text in language '{}', formatted as {}",
+                    text_type.language_code, format
+                ))
+            };
+
+            if let Some(max_length) = text_type.max_length {
+                schema["maxLength"] = json!(max_length);
+            }
+
+            schema
+        }
+    }
+}
+
+fn parameter_types(schema: &DataSchema) -> Vec<ParameterType> {
+    match schema {
+        DataSchema::Structured(structured) => structured.parameters.clone(),
+        DataSchema::Multimodal(_) => vec![],
+    }
+}
+
+// Falls back to positional names if the names of the parameters were not registered
+fn parameter_names(agent_type_name: &str, method: &AgentMethod) -> Vec<String> {
+    let arity = parameter_types(&method.input_schema).len();
+
+    agent_registry::get_method_parameter_names(agent_type_name, &method.name)
+        .filter(|names| names.len() == arity)
+        .unwrap_or_else(|| (0..arity).map(|i| format!("arg{}", i)).collect())
+}
+
+pub fn parse_tool_call(
+    provider: ToolProvider,
+    payload: &serde_json::Value,
+) -> Result<ToolCall, String> {
+    let field = |value: &serde_json::Value, name: &str| -> Result<String, String> {
+        value
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+            .ok_or_else(|| format!("Tool call payload is missing the string field '{}'", name))
+    };
+
+    match provider {
+        ToolProvider::OpenAi => {
+            let function = payload
+                .get("function")
+                .ok_or("Tool call payload is missing the field 'function'")?;
+            // OpenAI sends the arguments as a JSON encoded string
+            let arguments = match function.get("arguments") {
+                Some(serde_json::Value::String(s)) if s.trim().is_empty() => json!({}),
+                Some(serde_json::Value::String(s)) => serde_json::from_str(s)
+                    .map_err(|e| format!("Tool call arguments are not valid JSON: {}", e))?,
+                Some(other) => other.clone(),
+                None => json!({}),
+            };
+
+            Ok(ToolCall {
+                id: field(payload, "id")?,
+                name: field(function, "name")?,
+                arguments,
+            })
+        }
+        ToolProvider::Anthropic => Ok(ToolCall {
+            id: field(payload, "id")?,
+            name: field(payload, "name")?,
+            arguments: payload.get("input").cloned().unwrap_or_else(|| json!({})),
+        }),
+    }
+}
+
+pub fn format_tool_result(
+    provider: ToolProvider,
+    tool_call: &ToolCall,
+    result: &Result<String, String>,
+) -> serde_json::Value {
+    let content = match result {
+        Ok(output) => output.clone(),
+        Err(error) => format!("Error: {}", error),
+    };
+
+    match provider {
+        ToolProvider::OpenAi => json!({
+            "role": "tool",
+            "tool_call_id": tool_call.id,
+            "content": content,
+        }),
+        ToolProvider::Anthropic => json!({
+            "type": "tool_result",
+            "tool_use_id": tool_call.id,
+            "content": content,
+            "is_error": result.is_err(),
+        }),
+    }
+}
+
+// Converts the JSON arguments of a tool call into the positional inputs of the method
+pub fn tool_call_inputs(
+    agent_type: &AgentType,
+    tool_call: &ToolCall,
+) -> Result<(AgentMethod, Vec<WitValue>), String> {
+    let (agent_type_name, method_name) = parse_tool_name(&tool_call.name)?;
+
+    if agent_type_name != agent_type.type_name {
+        return Err(format!(
+            "Tool {} belongs to agent type {}, not {}",
+            tool_call.name, agent_type_name, agent_type.type_name
+        ));
+    }
+
+    let method = agent_type
+        .methods
+        .iter()
+        .find(|m| m.name == method_name)
+        .ok_or_else(|| format!("Method {} not found in agent type {}", method_name, agent_type_name))?;
+
+    let names = parameter_names(agent_type_name, method);
+
+    let inputs = names
+        .iter()
+        .zip(parameter_types(&method.input_schema))
+        .map(|(name, parameter_type)| {
+            let argument = tool_call
+                .arguments
+                .get(name)
+                .unwrap_or(&serde_json::Value::Null);
+
+            let value = match parameter_type {
+                ParameterType::Wit(wit_type) => {
+                    json_to_value(argument, &AnalysedType::from(wit_type))
+                }
+                ParameterType::Text(_) => argument
+                    .as_str()
+                    .map(|s| Value::String(s.to_string()))
+                    .ok_or_else(|| "Expected a string".to_string()),
+            };
+
+            value
+                .map(WitValue::from)
+                .map_err(|e| format!("Invalid argument '{}': {}", name, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((method.clone(), inputs))
+}
+
+pub fn invoke_tool_call(agent_id: &str, tool_call: &ToolCall) -> Result<String, String> {
+    let resolved_agent = agent_registry::get_resolved_agent(&AgentId(agent_id.to_string()))
+        .ok_or_else(|| format!("Agent with id {} not found", agent_id))?;
+
+    let agent_type = resolved_agent.agent.get_definition();
+    let (method, inputs) = tool_call_inputs(&agent_type, tool_call)?;
+
+    match GuestAgent::invoke(&resolved_agent, method.name.clone(), inputs) {
+        StatusUpdate::Emit(output) => Ok(output),
+        StatusUpdate::Error(Error::InvalidInput(error)) => Err(error),
+        StatusUpdate::Error(error) => Err(format!("{:?}", error)),
+        StatusUpdate::Progress(_) => Err(format!(
+            "Method {} did not produce a result",
+            method.name
+        )),
+    }
+}
+
+// Takes the raw tool call payload of the provider, invokes the agent,
+// and returns the tool result message to send back to the model
+pub fn dispatch_tool_call(
+    provider: ToolProvider,
+    agent_id: &str,
+    payload: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let tool_call = parse_tool_call(provider, payload)?;
+    let result = invoke_tool_call(agent_id, &tool_call);
+    Ok(format_tool_result(provider, &tool_call, &result))
+}
+
+// The tools offered to a model, and the agents their calls go to:
+//
+// ```
+//  let toolbox = Toolbox::new()
+//      .with_agent(&self.agent_id)
+//      .with_remote_agent(RemoteWeatherAgent::connect_agent(&weather_agent_id)?)
+//      .with_sibling_agents();
+//  let request = ChatRequest::new(self.history.messages()).with_tools(toolbox.definitions());
+//  ...
+//  for tool_call in response.tool_calls() {
+//    let result = toolbox.call(tool_call);
+//    ...
+//  }
+// ```
+type RemoteInvoker = Arc<dyn Fn(&str, Vec<WitValue>) -> Result<Value, String> + Send + Sync>;
+
+#[derive(Default)]
+pub struct Toolbox {
+    // The live agents, by agent type
+    agents: Vec<(String, String)>,
+    // The agents of other workers, by agent type
+    remote_agents: Vec<(String, RemoteInvoker)>,
+    // The agent types whose agents are created on their first tool call
+    sibling_types: Vec<String>,
+}
+
+impl Toolbox {
+    pub fn new() -> Self {
+        Toolbox::default()
+    }
+
+    // Offers the methods of a live agent
+    pub fn with_agent(mut self, agent_id: &str) -> Self {
+        if let Some(resolved_agent) =
+            agent_registry::get_resolved_agent(&AgentId(agent_id.to_string()))
+        {
+            let agent_type = resolved_agent.agent.get_definition();
+            self.agents.push((agent_type.type_name, agent_id.to_string()));
+        }
+        self
+    }
+
+    // Offers the methods of an agent living in another worker, through its `Remote*` client
+    pub fn with_remote_agent<T: RemoteAgent>(mut self, agent: T) -> Self {
+        let agent = Mutex::new(agent);
+        let invoker: RemoteInvoker = Arc::new(move |method_name, input| {
+            agent.lock().unwrap().invoke_method(method_name, input)
+        });
+
+        self.remote_agents.push((T::agent_type_name().to_string(), invoker));
+        self
+    }
+
+    // Offers the methods of the other agent types of the component, but those whose constructors take parameters.
+    // An agent of the type is created on the first call of one of its tools, and used by the later calls
+    // of the same agent, through any toolbox, see `agent_registry::register_sibling_agent`
+    pub fn with_sibling_agents(mut self) -> Self {
+        let current_type = crate::agent::current_agent_id()
+            .and_then(|agent_id| agent_registry::get_resolved_agent(&AgentId(agent_id)))
+            .map(|resolved_agent| resolved_agent.agent.get_definition().type_name);
+
+        for agent_type in agent_registry::get_all_agent_definitions() {
+            let constructible = match &agent_type.agent_constructor.input_schema {
+                DataSchema::Structured(structured) => structured.parameters.is_empty(),
+                DataSchema::Multimodal(_) => false,
+            };
+
+            if constructible
+                && Some(&agent_type.type_name) != current_type.as_ref()
+                && !self.offers(&agent_type.type_name)
+            {
+                self.sibling_types.push(agent_type.type_name);
+            }
+        }
+        self
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.agents
+            .iter()
+            .map(|(agent_type_name, _)| agent_type_name)
+            .chain(self.remote_agents.iter().map(|(agent_type_name, _)| agent_type_name))
+            .chain(self.sibling_types.iter())
+            .filter_map(|agent_type_name| known_agent_type(agent_type_name))
+            .flat_map(|agent_type| {
+                agent_type
+                    .methods
+                    .iter()
+                    .map(|method| method_tool(&agent_type.type_name, method))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn call(&self, tool_call: &ToolCall) -> Result<String, String> {
+        let agent_type_name = tool_call.agent_type_name()?;
+
+        if let Some((_, agent_id)) = self.agents.iter().find(|(name, _)| name == agent_type_name) {
+            return invoke_tool_call(agent_id, tool_call);
+        }
+
+        if let Some((_, invoker)) = self
+            .remote_agents
+            .iter()
+            .find(|(name, _)| name == agent_type_name)
+        {
+            let agent_type = known_agent_type(agent_type_name)
+                .ok_or_else(|| format!("Agent type {} not found", agent_type_name))?;
+            let (method, inputs) = tool_call_inputs(&agent_type, tool_call)?;
+            let result = invoker(&method.name, inputs)?;
+            return Ok(result_text(&method, result));
+        }
+
+        if !self.sibling_types.iter().any(|name| name == agent_type_name) {
+            return Err(format!("Tool {} is not offered", tool_call.name));
+        }
+
+        let owner_agent_id = crate::agent::current_agent_id().unwrap_or_default();
+        let created_agent_id = agent_registry::get_sibling_agent(&owner_agent_id, agent_type_name)
+            .filter(|agent_id| agent_registry::get_resolved_agent(&AgentId(agent_id.clone())).is_some());
+
+        let agent_id = match created_agent_id {
+            Some(agent_id) => agent_id,
+            None => {
+                let initiator = agent_registry::get_agent_initiator(agent_type_name.to_string())
+                    .ok_or_else(|| format!("Agent type {} not found", agent_type_name))?;
+                let agent_id = initiator.initiate(vec![])?.agent_id;
+                agent_registry::register_sibling_agent(&owner_agent_id, agent_type_name, agent_id.clone());
+                agent_id
+            }
+        };
+
+        invoke_tool_call(&agent_id, tool_call)
+    }
+
+    fn offers(&self, agent_type_name: &str) -> bool {
+        self.agents.iter().any(|(name, _)| name == agent_type_name)
+            || self.remote_agents.iter().any(|(name, _)| name == agent_type_name)
+    }
+}
+
+// Agent types implemented in other components are only known by their definition, without a constructor
+fn known_agent_type(agent_type_name: &str) -> Option<AgentType> {
+    agent_registry::get_agent_def_by_name(agent_type_name).or_else(|| {
+        agent_registry::get_generic_agent_type_by_name(agent_type_name).map(|generic_agent_type| {
+            generic_agent_type.to_agent_type(AgentConstructor {
+                name: None,
+                description: "".to_string(),
+                prompt_hint: None,
+                input_schema: DataSchema::Structured(Structured { parameters: vec![] }),
+            })
+        })
+    })
+}
+
+// The result of a remote call as the text of a tool result, as JSON unless it is a string
+fn result_text(method: &AgentMethod, result: Value) -> String {
+    if let Value::String(text) = result {
+        return text;
+    }
+
+    match parameter_types(&method.output_schema).first() {
+        Some(ParameterType::Wit(wit_type)) => {
+            value_to_json(&result, &AnalysedType::from(wit_type.clone()))
+                .map(|json| json.to_string())
+                .unwrap_or_else(|_| format!("{:?}", result))
+        }
+        _ => format!("{:?}", result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_agents;
+    use crate::testing::{self, MockHost};
+    use golem_wasm_ast::analysis::analysed_type::{str, u32};
+
+    // Tool calls as sent by the providers, captured from their APIs
+    const OPENAI_TOOL_CALL: &str = r#"{
+        "id": "call_Xk2bT0n9Qe4R",
+        "type": "function",
+        "function": {
+            "name": "weather-agent__get-forecast",
+            "arguments": "{\"city\":\"Berlin\",\"days\":3}"
+        }
+    }"#;
+
+    const ANTHROPIC_TOOL_CALL: &str = r#"{
+        "type": "tool_use",
+        "id": "toolu_01A09q90qw90lq917835lq9",
+        "name": "weather-agent__get-forecast",
+        "input": { "city": "Berlin", "days": 3 }
+    }"#;
+
+    fn register_weather_agent() {
+        test_agents::register(
+            "weather-agent",
+            vec![],
+            vec![test_agents::method(
+                "get-forecast",
+                vec![("city", str()), ("days", u32())],
+                str(),
+            )],
+            |_, _, input| match input.as_slice() {
+                [Value::String(city), Value::U32(days)] => {
+                    StatusUpdate::Emit(format!("{} days of sun in {}", days, city))
+                }
+                _ => StatusUpdate::Error(Error::InvalidInput("unexpected input".to_string())),
+            },
+        );
+    }
+
+    fn payload(json: &str) -> serde_json::Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parses_openai_tool_calls() {
+        let tool_call = parse_tool_call(ToolProvider::OpenAi, &payload(OPENAI_TOOL_CALL)).unwrap();
+
+        assert_eq!(
+            tool_call,
+            ToolCall {
+                id: "call_Xk2bT0n9Qe4R".to_string(),
+                name: "weather-agent__get-forecast".to_string(),
+                arguments: json!({ "city": "Berlin", "days": 3 }),
+            }
+        );
+        assert_eq!(tool_call.agent_type_name(), Ok("weather-agent"));
+        assert_eq!(tool_call.method_name(), Ok("get-forecast"));
+    }
+
+    #[test]
+    fn parses_anthropic_tool_calls() {
+        let tool_call =
+            parse_tool_call(ToolProvider::Anthropic, &payload(ANTHROPIC_TOOL_CALL)).unwrap();
+
+        assert_eq!(
+            tool_call,
+            ToolCall {
+                id: "toolu_01A09q90qw90lq917835lq9".to_string(),
+                name: "weather-agent__get-forecast".to_string(),
+                arguments: json!({ "city": "Berlin", "days": 3 }),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_openai_tool_calls_with_invalid_arguments() {
+        let payload = json!({
+            "id": "call_1",
+            "type": "function",
+            "function": { "name": "weather-agent__get-forecast", "arguments": "{\"city\":" },
+        });
+
+        assert!(parse_tool_call(ToolProvider::OpenAi, &payload)
+            .unwrap_err()
+            .starts_with("Tool call arguments are not valid JSON"));
+    }
+
+    #[test]
+    fn formats_tool_results() {
+        let tool_call = ToolCall {
+            id: "toolu_1".to_string(),
+            name: "weather-agent__get-forecast".to_string(),
+            arguments: json!({}),
+        };
+
+        assert_eq!(
+            format_tool_result(ToolProvider::OpenAi, &tool_call, &Ok("Sunny".to_string())),
+            json!({ "role": "tool", "tool_call_id": "toolu_1", "content": "Sunny" })
+        );
+        assert_eq!(
+            format_tool_result(ToolProvider::Anthropic, &tool_call, &Err("Offline".to_string())),
+            json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_1",
+                "content": "Error: Offline",
+                "is_error": true,
+            })
+        );
+    }
+
+    #[test]
+    fn dispatches_openai_tool_calls() {
+        let _host = MockHost::new("test-worker").install();
+        register_weather_agent();
+        let agent = testing::create_agent("weather-agent", vec![]).unwrap();
+
+        let result =
+            dispatch_tool_call(ToolProvider::OpenAi, agent.agent_id(), &payload(OPENAI_TOOL_CALL));
+
+        assert_eq!(
+            result,
+            Ok(json!({
+                "role": "tool",
+                "tool_call_id": "call_Xk2bT0n9Qe4R",
+                "content": "3 days of sun in Berlin",
+            }))
+        );
+    }
+
+    #[test]
+    fn dispatches_anthropic_tool_calls() {
+        let _host = MockHost::new("test-worker").install();
+        register_weather_agent();
+        let agent = testing::create_agent("weather-agent", vec![]).unwrap();
+
+        let result = dispatch_tool_call(
+            ToolProvider::Anthropic,
+            agent.agent_id(),
+            &payload(ANTHROPIC_TOOL_CALL),
+        );
+
+        assert_eq!(
+            result,
+            Ok(json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_01A09q90qw90lq917835lq9",
+                "content": "3 days of sun in Berlin",
+                "is_error": false,
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_arguments_as_tool_errors() {
+        let _host = MockHost::new("test-worker").install();
+        register_weather_agent();
+        let agent = testing::create_agent("weather-agent", vec![]).unwrap();
+
+        let payload = json!({
+            "type": "tool_use",
+            "id": "toolu_2",
+            "name": "weather-agent__get-forecast",
+            "input": { "city": "Berlin", "days": "three" },
+        });
+
+        let result = dispatch_tool_call(ToolProvider::Anthropic, agent.agent_id(), &payload).unwrap();
+
+        assert_eq!(result["is_error"], json!(true));
+        assert_eq!(
+            result["content"],
+            json!("Error: Invalid argument 'days': Expected u32, but got \"three\"")
+        );
+    }
+
+    fn forecast_call() -> ToolCall {
+        parse_tool_call(ToolProvider::Anthropic, &payload(ANTHROPIC_TOOL_CALL)).unwrap()
+    }
+
+    // Calls the tool through a new toolbox, as an agent loop built in every invocation of the planner does
+    fn call_as_planner(planner_id: &str) -> Result<String, String> {
+        crate::agent::with_current_agent(planner_id, "plan", || {
+            Toolbox::new().with_sibling_agents().call(&forecast_call())
+        })
+    }
+
+    fn create_planner() -> String {
+        register_weather_agent();
+        test_agents::register("planner", vec![], vec![], |_, _, _| {
+            StatusUpdate::Emit("planned".to_string())
+        });
+        testing::create_agent("planner", vec![]).unwrap().agent_id().to_string()
+    }
+
+    #[test]
+    fn creates_sibling_agents_once_per_agent() {
+        let _host = MockHost::new("test-worker").install();
+        let planner_id = create_planner();
+
+        assert_eq!(call_as_planner(&planner_id), Ok("3 days of sun in Berlin".to_string()));
+        assert_eq!(call_as_planner(&planner_id), Ok("3 days of sun in Berlin".to_string()));
+
+        let sibling_agents = agent_registry::get_sibling_agents(&planner_id);
+        assert_eq!(sibling_agents.len(), 1);
+        assert_eq!(sibling_agents[0].0, "weather-agent");
+        assert_eq!(agent_registry::get_all_agent_instances().len(), 2);
+    }
+
+    #[test]
+    fn keeps_sibling_agents_in_snapshots() {
+        let (planner_id, sibling_agents, snapshot) = {
+            let _host = MockHost::new("test-worker").install();
+            let planner_id = create_planner();
+            call_as_planner(&planner_id).unwrap();
+            (
+                planner_id.clone(),
+                agent_registry::get_sibling_agents(&planner_id),
+                crate::versioning::save_snapshot().unwrap(),
+            )
+        };
+
+        let _host = MockHost::new("test-worker").install();
+        crate::versioning::load_snapshot(snapshot).unwrap();
+
+        assert_eq!(agent_registry::get_sibling_agents(&planner_id), sibling_agents);
+        assert_eq!(call_as_planner(&planner_id), Ok("3 days of sun in Berlin".to_string()));
+        assert_eq!(agent_registry::get_all_agent_instances().len(), 2);
+    }
+}
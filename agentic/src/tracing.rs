@@ -0,0 +1,268 @@
+use crate::bindings::golem::agent::common::{Error, StatusUpdate};
+use crate::host::agent_host;
+use golem_wasm_rpc::{Value, WitValue};
+
+// Spans of the Golem invocation context. Every construction and invocation of an agent gets a span,
+// `agent-construct` and `agent-invoke`, with the agent type, agent id, method, argument sizes and outcome as attributes.
+// Calls through the `Remote*` clients get an `agent-call` span, and Golem passes the invocation context
+// with the call, so the span of the invoked agent is a child of it.
+//
+// Agent methods can open spans of their own, which are children of the span of the invocation:
+//
+// ```
+//  fn forecast(&self, city: String) -> String {
+//    let span = tracing::span("fetch-forecast");
+//    span.set_attribute("city", &city);
+//    ...
+//  } // the span is finished when dropped
+// ```
+
+// An open span, finished when dropped
+pub struct SpanGuard {
+    span: u64,
+    finished: bool,
+}
+
+impl SpanGuard {
+    pub fn set_attribute(&self, name: &str, value: impl AsRef<str>) {
+        agent_host().set_span_attribute(self.span, name, value.as_ref());
+    }
+
+    pub fn finish(mut self) {
+        self.finish_span();
+    }
+
+    fn finish_span(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            agent_host().finish_span(self.span);
+        }
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        self.finish_span();
+    }
+}
+
+// Starts a child span of the current span
+pub fn span(name: &str) -> SpanGuard {
+    SpanGuard {
+        span: agent_host().start_span(name),
+        finished: false,
+    }
+}
+
+// Runs `f` in a child span of the current span
+pub fn in_span<T>(name: &str, f: impl FnOnce(&SpanGuard) -> T) -> T {
+    let span = span(name);
+    f(&span)
+}
+
+// The `traceparent` and `tracestate` headers of the current span, to pass the trace on to HTTP calls
+pub fn trace_context_headers() -> Vec<(String, String)> {
+    agent_host().trace_context_headers()
+}
+
+pub(crate) fn construct_span(agent_type_name: &str, params: &[WitValue]) -> SpanGuard {
+    let span = span("agent-construct");
+    span.set_attribute("agent.type", agent_type_name);
+    set_argument_attributes(&span, params);
+    span
+}
+
+pub(crate) fn invoke_span(
+    agent_type_name: &str,
+    agent_id: &str,
+    method_name: &str,
+    input: &[WitValue],
+) -> SpanGuard {
+    let span = span("agent-invoke");
+    span.set_attribute("agent.type", agent_type_name);
+    span.set_attribute("agent.id", agent_id);
+    span.set_attribute("agent.method", method_name);
+    set_argument_attributes(&span, input);
+    span
+}
+
+pub(crate) fn call_span(worker_name: &str, method_name: &str) -> SpanGuard {
+    let span = span("agent-call");
+    span.set_attribute("agent.worker", worker_name);
+    span.set_attribute("agent.method", method_name);
+    span
+}
+
+// The outcome of an invocation is the one of its last update
+pub(crate) fn set_outcome(span: &SpanGuard, updates: &[StatusUpdate]) {
+    match updates.last() {
+        Some(StatusUpdate::Error(Error::InvalidInput(error))) => {
+            span.set_attribute("agent.outcome", "invalid-input");
+            span.set_attribute("agent.error", error);
+        }
+        Some(StatusUpdate::Error(Error::NetworkError)) => {
+            span.set_attribute("agent.outcome", "network-error");
+        }
+        Some(_) => span.set_attribute("agent.outcome", "success"),
+        None => span.set_attribute("agent.outcome", "no-result"),
+    }
+}
+
+// The sizes of the arguments as a comma separated list of approximate byte counts, e.g. `12,8`
+fn set_argument_attributes(span: &SpanGuard, arguments: &[WitValue]) {
+    let sizes = arguments
+        .iter()
+        .map(|argument| value_size(&Value::from(argument.clone())).to_string())
+        .collect::<Vec<_>>();
+
+    span.set_attribute("agent.argument-count", arguments.len().to_string());
+    span.set_attribute("agent.argument-sizes", sizes.join(","));
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::Bool(_) | Value::U8(_) | Value::S8(_) => 1,
+        Value::U16(_) | Value::S16(_) => 2,
+        Value::U32(_) | Value::S32(_) | Value::F32(_) | Value::Char(_) | Value::Enum(_) => 4,
+        Value::U64(_) | Value::S64(_) | Value::F64(_) => 8,
+        Value::String(string) => string.len(),
+        Value::List(values) | Value::Tuple(values) | Value::Record(values) => {
+            values.iter().map(value_size).sum()
+        }
+        Value::Variant { case_value, .. } => 4 + case_value.as_deref().map_or(0, value_size),
+        Value::Flags(flags) => flags.len().div_ceil(8),
+        Value::Option(value) => 1 + value.as_deref().map_or(0, value_size),
+        Value::Result(result) => {
+            1 + match result {
+                Ok(value) | Err(value) => value.as_deref().map_or(0, value_size),
+            }
+        }
+        Value::Handle { uri, .. } => uri.len() + 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::exports::golem::agent::guest::GuestAgent;
+    use crate::test_agents;
+    use crate::testing::{self, MockHost};
+    use crate::ResolvedAgent;
+    use golem_wasm_ast::analysis::analysed_type::str;
+
+    fn register_greeter() {
+        test_agents::register(
+            "traced-greeter",
+            vec![],
+            vec![test_agents::method("greet", vec![("name", str())], str())],
+            |_, _, input| {
+                let span = span("lookup");
+                span.set_attribute("greeting", "hello");
+                StatusUpdate::Emit(format!("Hello, {:?}", input))
+            },
+        );
+    }
+
+    #[test]
+    fn records_invocations_with_their_arguments_and_outcome() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+        let agent = testing::create_agent("traced-greeter", vec![]).unwrap();
+
+        agent.invoke("greet", vec![Value::String("Alice".to_string())]);
+
+        let spans = testing::spans();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].name, "agent-invoke");
+        assert_eq!(spans[0].attribute("agent.type"), Some("traced-greeter"));
+        assert_eq!(spans[0].attribute("agent.id"), Some(agent.agent_id()));
+        assert_eq!(spans[0].attribute("agent.method"), Some("greet"));
+        assert_eq!(spans[0].attribute("agent.argument-count"), Some("1"));
+        assert_eq!(spans[0].attribute("agent.argument-sizes"), Some("5"));
+        assert_eq!(spans[0].attribute("agent.outcome"), Some("success"));
+        assert!(spans[0].finished);
+
+        assert_eq!(spans[1].name, "lookup");
+        assert_eq!(spans[1].parent, Some(0));
+        assert_eq!(spans[1].attribute("greeting"), Some("hello"));
+        assert!(spans[1].finished);
+    }
+
+    #[test]
+    fn records_invalid_input() {
+        let _host = MockHost::new("test-worker").install();
+        register_greeter();
+        let agent = testing::create_agent("traced-greeter", vec![]).unwrap();
+
+        agent.invoke("greet", vec![Value::U32(1)]);
+
+        let spans = testing::spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].attribute("agent.outcome"), Some("invalid-input"));
+        assert!(spans[0].attribute("agent.error").is_some());
+    }
+
+    #[test]
+    fn records_failed_constructions() {
+        let _host = MockHost::new("test-worker").install();
+
+        assert!(<ResolvedAgent as GuestAgent>::create("missing-agent".to_string(), vec![]).is_err());
+
+        let spans = testing::spans();
+        assert_eq!(spans[0].name, "agent-construct");
+        assert_eq!(spans[0].attribute("agent.type"), Some("missing-agent"));
+        assert_eq!(spans[0].attribute("agent.argument-count"), Some("0"));
+        assert_eq!(spans[0].attribute("agent.outcome"), Some("invalid-input"));
+        assert!(spans[0].finished);
+    }
+
+    #[test]
+    fn nests_spans_and_finishes_them_once() {
+        let _host = MockHost::new("test-worker").install();
+
+        let headers = in_span("outer", |outer| {
+            outer.set_attribute("step", "1");
+            let inner = span("inner");
+            let headers = trace_context_headers();
+            inner.finish();
+            headers
+        });
+
+        let spans = testing::spans();
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| (span.name.as_str(), span.parent, span.finished))
+                .collect::<Vec<_>>(),
+            vec![("outer", None, true), ("inner", Some(0), true)]
+        );
+        assert_eq!(spans[0].attributes, vec![("step".to_string(), "1".to_string())]);
+        assert_eq!(
+            headers,
+            vec![(
+                "traceparent".to_string(),
+                "00-00000000000000000000000000000001-0000000000000002-01".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn estimates_the_size_of_values() {
+        assert_eq!(value_size(&Value::String("Berlin".to_string())), 6);
+        assert_eq!(
+            value_size(&Value::Record(vec![
+                Value::U64(1),
+                Value::Option(Some(Box::new(Value::Bool(true)))),
+                Value::List(vec![Value::U16(1), Value::U16(2)]),
+            ])),
+            8 + 2 + 4
+        );
+        assert_eq!(
+            value_size(&Value::Variant {
+                case_idx: 1,
+                case_value: None
+            }),
+            4
+        );
+    }
+}
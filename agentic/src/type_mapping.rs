@@ -1,4 +1,4 @@
-use golem_wasm_ast::analysis::analysed_type::{str, u32, u64};
+use golem_wasm_ast::analysis::analysed_type::{bool, list, option, str, u32, u64};
 use golem_wasm_ast::analysis::AnalysedType;
 use golem_wasm_rpc::{NodeBuilder, WitValue};
 use golem_wasm_rpc::{Value, WitType};
@@ -61,6 +61,18 @@ impl ToValue for u64 {
     }
 }
 
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        golem_wasm_rpc::Value::List(self.iter().map(ToValue::to_value).collect())
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        golem_wasm_rpc::Value::Option(self.as_ref().map(|v| Box::new(v.to_value())))
+    }
+}
+
 impl FromValue for String {
     fn from_value(value: golem_wasm_rpc::Value) -> Result<Self, String> {
         match value {
@@ -97,6 +109,25 @@ impl FromValue for u64 {
     }
 }
 
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: golem_wasm_rpc::Value) -> Result<Self, String> {
+        match value {
+            golem_wasm_rpc::Value::List(list) => list.into_iter().map(T::from_value).collect(),
+            _ => Err("Expected a List value".to_string()),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: golem_wasm_rpc::Value) -> Result<Self, String> {
+        match value {
+            golem_wasm_rpc::Value::Option(Some(v)) => T::from_value(*v).map(Some),
+            golem_wasm_rpc::Value::Option(None) => Ok(None),
+            _ => Err("Expected an Option value".to_string()),
+        }
+    }
+}
+
 pub trait ToWitValue {
     fn to_wit_value(&self) -> golem_wasm_rpc::WitValue;
 }
@@ -133,6 +164,27 @@ impl ToWitType for u64 {
     }
 }
 
+impl ToWitType for bool {
+    fn get_wit_type() -> WitType {
+        let analysed_type = bool();
+        WitType::from(analysed_type)
+    }
+}
+
+impl<T: ToWitType> ToWitType for Vec<T> {
+    fn get_wit_type() -> WitType {
+        let analysed_type = list(AnalysedType::from(T::get_wit_type()));
+        WitType::from(analysed_type)
+    }
+}
+
+impl<T: ToWitType> ToWitType for Option<T> {
+    fn get_wit_type() -> WitType {
+        let analysed_type = option(AnalysedType::from(T::get_wit_type()));
+        WitType::from(analysed_type)
+    }
+}
+
 pub trait FromWitValue {
     fn from_wit_value(value: WitValue) -> Result<Self, String>
     where
@@ -187,7 +239,7 @@ impl FromWitValue for u64 {
 
         match value {
             golem_wasm_rpc::Value::U64(n) => Ok(n),
-            _ => Err("Expected a u32 WitValue".to_string()),
+            _ => Err("Expected a u64 WitValue".to_string()),
         }
     }
 }
@@ -248,3 +300,56 @@ impl FromWitValue for golem_wasm_rpc::Value {
         Ok(golem_wasm_rpc::Value::from(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::check_round_trip;
+
+    #[test]
+    fn round_trips_primitives() {
+        assert_eq!(check_round_trip(&"Berlin".to_string()), Ok(()));
+        assert_eq!(check_round_trip(&String::new()), Ok(()));
+        assert_eq!(check_round_trip(&7u32), Ok(()));
+        assert_eq!(check_round_trip(&u32::MAX), Ok(()));
+        assert_eq!(check_round_trip(&u64::MAX), Ok(()));
+        assert_eq!(check_round_trip(&true), Ok(()));
+        assert_eq!(check_round_trip(&false), Ok(()));
+    }
+
+    #[test]
+    fn round_trips_lists_and_options() {
+        assert_eq!(check_round_trip(&vec!["a".to_string(), "b".to_string()]), Ok(()));
+        assert_eq!(check_round_trip(&Vec::<u32>::new()), Ok(()));
+        assert_eq!(check_round_trip(&Some(3u64)), Ok(()));
+        assert_eq!(check_round_trip(&None::<bool>), Ok(()));
+        assert_eq!(check_round_trip(&vec![Some(1u32), None]), Ok(()));
+        assert_eq!(check_round_trip(&Some(vec![true, false])), Ok(()));
+    }
+
+    #[test]
+    fn advertises_the_wit_types() {
+        assert_eq!(AnalysedType::from(<Vec<Option<u64>> as AgentArg>::get_wit_type()), list(option(u64())));
+        assert_eq!(AnalysedType::from(<Option<Vec<bool>> as AgentArg>::get_wit_type()), option(list(bool())));
+    }
+
+    #[test]
+    fn rejects_values_of_other_types() {
+        assert_eq!(
+            <u64 as FromWitValue>::from_wit_value(WitValue::from(Value::U32(7))),
+            Err("Expected a u64 WitValue".to_string())
+        );
+        assert_eq!(
+            <u64 as FromValue>::from_value(Value::U32(7)),
+            Err("Expected a u64 value".to_string())
+        );
+        assert_eq!(
+            <Vec<bool> as FromWitValue>::from_wit_value(WitValue::from(Value::List(vec![Value::U32(1)]))),
+            Err("Expected a bool WitValue".to_string())
+        );
+        assert_eq!(
+            <Option<String> as FromWitValue>::from_wit_value(WitValue::from(Value::String("a".to_string()))),
+            Err("Expected an Option WitValue".to_string())
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use crate::bindings::golem::agent::common::{AgentType, DataSchema, ParameterType};
+use golem_wasm_ast::analysis::{
+    AnalysedType, TypeEnum, TypeFlags, TypeList, TypeOption, TypeRecord, TypeResult, TypeTuple,
+    TypeVariant,
+};
+use golem_wasm_rpc::{Value, WitValue};
+use std::fmt::{Display, Formatter};
+
+// Checks the values received by an agent against the types in its agent definition,
+// so that a mismatch is reported to the caller instead of panicking in the generated code.
+// Errors point at the offending part of the value, e.g. `parameter 0 > field city > list item 2`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: Vec<String>,
+    pub kind: ValidationErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationErrorKind {
+    #[error("expected {expected} parameters, but got {actual}")]
+    ParameterCount { expected: usize, actual: usize },
+    #[error("expected a value of type {expected}, but got {actual}")]
+    TypeMismatch { expected: String, actual: String },
+    #[error("expected a tuple of {expected} items, but got {actual}")]
+    TupleArity { expected: usize, actual: usize },
+    #[error("expected a record with the fields {}, but got {actual} fields", expected.join(", "))]
+    RecordArity { expected: Vec<String>, actual: usize },
+    #[error("variant case {case_idx} is out of range, as there are {case_count} cases")]
+    VariantCaseOutOfRange { case_idx: u32, case_count: usize },
+    #[error("case {case} expects a payload, but got none")]
+    MissingPayload { case: String },
+    #[error("case {case} has no payload, but got one")]
+    UnexpectedPayload { case: String },
+    #[error("enum case {case_idx} is out of range, as there are {case_count} cases")]
+    EnumCaseOutOfRange { case_idx: u32, case_count: usize },
+    #[error("expected {expected} flags, but got {actual}")]
+    FlagsLength { expected: usize, actual: usize },
+}
+
+impl ValidationError {
+    fn new(kind: ValidationErrorKind) -> Self {
+        ValidationError {
+            path: Vec::new(),
+            kind,
+        }
+    }
+
+    fn within(mut self, segment: impl Into<String>) -> Self {
+        self.path.insert(0, segment.into());
+        self
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.path.join(" > "), self.kind)
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+pub fn validate_constructor_input(
+    agent_type: &AgentType,
+    input: &[WitValue],
+) -> Result<(), ValidationError> {
+    validate_input(&agent_type.agent_constructor.input_schema, input)
+}
+
+// Unknown methods are left to the agent implementation to report
+pub fn validate_method_input(
+    agent_type: &AgentType,
+    method_name: &str,
+    input: &[WitValue],
+) -> Result<(), ValidationError> {
+    match agent_type.methods.iter().find(|m| m.name == method_name) {
+        Some(method) => validate_input(&method.input_schema, input),
+        None => Ok(()),
+    }
+}
+
+pub fn validate_input(schema: &DataSchema, input: &[WitValue]) -> Result<(), ValidationError> {
+    let parameters = match schema {
+        DataSchema::Structured(structured) => &structured.parameters,
+        DataSchema::Multimodal(_) => return Ok(()),
+    };
+
+    if parameters.len() != input.len() {
+        return Err(ValidationError::new(ValidationErrorKind::ParameterCount {
+            expected: parameters.len(),
+            actual: input.len(),
+        }));
+    }
+
+    for (index, (parameter, value)) in parameters.iter().zip(input).enumerate() {
+        let value = Value::from(value.clone());
+        let result = match parameter {
+            ParameterType::Wit(wit_type) => validate_value(&value, &AnalysedType::from(wit_type.clone())),
+            ParameterType::Text(_) => match value {
+                Value::String(_) => Ok(()),
+                other => Err(ValidationError::new(ValidationErrorKind::TypeMismatch {
+                    expected: "text".to_string(),
+                    actual: value_kind(&other).to_string(),
+                })),
+            },
+        };
+        result.map_err(|error| error.within(format!("parameter {}", index)))?;
+    }
+
+    Ok(())
+}
+
+pub fn validate_value(value: &Value, typ: &AnalysedType) -> Result<(), ValidationError> {
+    match (value, typ) {
+        (Value::Bool(_), AnalysedType::Bool(_))
+        | (Value::U8(_), AnalysedType::U8(_))
+        | (Value::U16(_), AnalysedType::U16(_))
+        | (Value::U32(_), AnalysedType::U32(_))
+        | (Value::U64(_), AnalysedType::U64(_))
+        | (Value::S8(_), AnalysedType::S8(_))
+        | (Value::S16(_), AnalysedType::S16(_))
+        | (Value::S32(_), AnalysedType::S32(_))
+        | (Value::S64(_), AnalysedType::S64(_))
+        | (Value::F32(_), AnalysedType::F32(_))
+        | (Value::F64(_), AnalysedType::F64(_))
+        | (Value::Char(_), AnalysedType::Chr(_))
+        | (Value::String(_), AnalysedType::Str(_))
+        | (Value::Handle { .. }, AnalysedType::Handle(_)) => Ok(()),
+        (Value::List(values), AnalysedType::List(TypeList { inner, .. })) => {
+            values.iter().enumerate().try_for_each(|(index, value)| {
+                validate_value(value, inner).map_err(|error| error.within(format!("list item {}", index)))
+            })
+        }
+        (Value::Tuple(values), AnalysedType::Tuple(TypeTuple { items, .. })) => {
+            if values.len() != items.len() {
+                return Err(ValidationError::new(ValidationErrorKind::TupleArity {
+                    expected: items.len(),
+                    actual: values.len(),
+                }));
+            }
+            values
+                .iter()
+                .zip(items)
+                .enumerate()
+                .try_for_each(|(index, (value, typ))| {
+                    validate_value(value, typ)
+                        .map_err(|error| error.within(format!("tuple item {}", index)))
+                })
+        }
+        (Value::Record(values), AnalysedType::Record(TypeRecord { fields, .. })) => {
+            if values.len() != fields.len() {
+                return Err(ValidationError::new(ValidationErrorKind::RecordArity {
+                    expected: fields.iter().map(|field| field.name.clone()).collect(),
+                    actual: values.len(),
+                }));
+            }
+            values.iter().zip(fields).try_for_each(|(value, field)| {
+                validate_value(value, &field.typ)
+                    .map_err(|error| error.within(format!("field {}", field.name)))
+            })
+        }
+        (
+            Value::Variant {
+                case_idx,
+                case_value,
+            },
+            AnalysedType::Variant(TypeVariant { cases, .. }),
+        ) => {
+            let case = cases.get(*case_idx as usize).ok_or_else(|| {
+                ValidationError::new(ValidationErrorKind::VariantCaseOutOfRange {
+                    case_idx: *case_idx,
+                    case_count: cases.len(),
+                })
+            })?;
+            validate_payload(case_value, case.typ.as_ref(), &case.name)
+        }
+        (Value::Enum(case_idx), AnalysedType::Enum(TypeEnum { cases, .. })) => {
+            if (*case_idx as usize) < cases.len() {
+                Ok(())
+            } else {
+                Err(ValidationError::new(ValidationErrorKind::EnumCaseOutOfRange {
+                    case_idx: *case_idx,
+                    case_count: cases.len(),
+                }))
+            }
+        }
+        (Value::Flags(flags), AnalysedType::Flags(TypeFlags { names, .. })) => {
+            if flags.len() == names.len() {
+                Ok(())
+            } else {
+                Err(ValidationError::new(ValidationErrorKind::FlagsLength {
+                    expected: names.len(),
+                    actual: flags.len(),
+                }))
+            }
+        }
+        (Value::Option(value), AnalysedType::Option(TypeOption { inner, .. })) => match value {
+            Some(value) => validate_value(value, inner).map_err(|error| error.within("some")),
+            None => Ok(()),
+        },
+        (Value::Result(result), AnalysedType::Result(TypeResult { ok, err, .. })) => match result {
+            Ok(value) => validate_payload(value, ok.as_deref(), "ok"),
+            Err(value) => validate_payload(value, err.as_deref(), "err"),
+        },
+        (value, typ) => Err(ValidationError::new(ValidationErrorKind::TypeMismatch {
+            expected: type_kind(typ).to_string(),
+            actual: value_kind(value).to_string(),
+        })),
+    }
+}
+
+fn validate_payload(
+    value: &Option<Box<Value>>,
+    typ: Option<&AnalysedType>,
+    case: &str,
+) -> Result<(), ValidationError> {
+    match (value, typ) {
+        (Some(value), Some(typ)) => {
+            validate_value(value, typ).map_err(|error| error.within(case))
+        }
+        (None, None) => Ok(()),
+        (None, Some(_)) => Err(ValidationError::new(ValidationErrorKind::MissingPayload {
+            case: case.to_string(),
+        })),
+        (Some(_), None) => Err(ValidationError::new(ValidationErrorKind::UnexpectedPayload {
+            case: case.to_string(),
+        })),
+    }
+}
+
+fn type_kind(typ: &AnalysedType) -> &'static str {
+    match typ {
+        AnalysedType::Bool(_) => "bool",
+        AnalysedType::U8(_) => "u8",
+        AnalysedType::U16(_) => "u16",
+        AnalysedType::U32(_) => "u32",
+        AnalysedType::U64(_) => "u64",
+        AnalysedType::S8(_) => "s8",
+        AnalysedType::S16(_) => "s16",
+        AnalysedType::S32(_) => "s32",
+        AnalysedType::S64(_) => "s64",
+        AnalysedType::F32(_) => "f32",
+        AnalysedType::F64(_) => "f64",
+        AnalysedType::Chr(_) => "char",
+        AnalysedType::Str(_) => "string",
+        AnalysedType::List(_) => "list",
+        AnalysedType::Tuple(_) => "tuple",
+        AnalysedType::Record(_) => "record",
+        AnalysedType::Variant(_) => "variant",
+        AnalysedType::Enum(_) => "enum",
+        AnalysedType::Flags(_) => "flags",
+        AnalysedType::Option(_) => "option",
+        AnalysedType::Result(_) => "result",
+        AnalysedType::Handle(_) => "handle",
+    }
+}
+
+fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::U8(_) => "u8",
+        Value::U16(_) => "u16",
+        Value::U32(_) => "u32",
+        Value::U64(_) => "u64",
+        Value::S8(_) => "s8",
+        Value::S16(_) => "s16",
+        Value::S32(_) => "s32",
+        Value::S64(_) => "s64",
+        Value::F32(_) => "f32",
+        Value::F64(_) => "f64",
+        Value::Char(_) => "char",
+        Value::String(_) => "string",
+        Value::List(_) => "list",
+        Value::Tuple(_) => "tuple",
+        Value::Record(_) => "record",
+        Value::Variant { .. } => "variant",
+        Value::Enum(_) => "enum",
+        Value::Flags(_) => "flags",
+        Value::Option(_) => "option",
+        Value::Result(_) => "result",
+        Value::Handle { .. } => "handle",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_agents;
+    use golem_wasm_ast::analysis::analysed_type::{
+        case, field, flags, list, option, r#enum, record, result, str, tuple, u32, u64, unit_case,
+        variant,
+    };
+
+    fn validate(value: Value, typ: AnalysedType) -> Result<(), ValidationErrorKind> {
+        validate_value(&value, &typ).map_err(|error| error.kind)
+    }
+
+    #[test]
+    fn accepts_values_of_their_types() {
+        let typ = record(vec![
+            field("city", str()),
+            field("days", list(tuple(vec![u32(), option(u64())]))),
+            field("alert", variant(vec![case("storm", str()), unit_case("heat")])),
+            field("unit", r#enum(&["celsius", "fahrenheit"])),
+            field("sources", flags(&["radar", "satellite"])),
+            field("forecast", result(str(), u32())),
+        ]);
+        let value = Value::Record(vec![
+            Value::String("Berlin".to_string()),
+            Value::List(vec![Value::Tuple(vec![
+                Value::U32(1),
+                Value::Option(Some(Box::new(Value::U64(2)))),
+            ])]),
+            Value::Variant {
+                case_idx: 1,
+                case_value: None,
+            },
+            Value::Enum(0),
+            Value::Flags(vec![true, false]),
+            Value::Result(Err(Some(Box::new(Value::U32(404))))),
+        ]);
+
+        assert_eq!(validate(value, typ), Ok(()));
+    }
+
+    #[test]
+    fn rejects_values_of_other_types() {
+        assert_eq!(
+            validate(Value::U64(1), u32()),
+            Err(ValidationErrorKind::TypeMismatch {
+                expected: "u32".to_string(),
+                actual: "u64".to_string(),
+            })
+        );
+        assert_eq!(
+            validate(Value::S32(1), u32()),
+            Err(ValidationErrorKind::TypeMismatch {
+                expected: "u32".to_string(),
+                actual: "s32".to_string(),
+            })
+        );
+        assert_eq!(
+            validate(Value::String("1".to_string()), list(u32())),
+            Err(ValidationErrorKind::TypeMismatch {
+                expected: "list".to_string(),
+                actual: "string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_tuples_with_other_numbers_of_items() {
+        assert_eq!(
+            validate(Value::Tuple(vec![Value::U32(1)]), tuple(vec![u32(), str()])),
+            Err(ValidationErrorKind::TupleArity {
+                expected: 2,
+                actual: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_records_with_other_numbers_of_fields() {
+        assert_eq!(
+            validate(
+                Value::Record(vec![Value::U32(1), Value::U32(2), Value::U32(3)]),
+                record(vec![field("x", u32()), field("y", u32())])
+            ),
+            Err(ValidationErrorKind::RecordArity {
+                expected: vec!["x".to_string(), "y".to_string()],
+                actual: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_variant_cases_out_of_range() {
+        assert_eq!(
+            validate(
+                Value::Variant {
+                    case_idx: 2,
+                    case_value: None,
+                },
+                variant(vec![case("storm", str()), unit_case("heat")])
+            ),
+            Err(ValidationErrorKind::VariantCaseOutOfRange {
+                case_idx: 2,
+                case_count: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_payloads() {
+        assert_eq!(
+            validate(
+                Value::Variant {
+                    case_idx: 0,
+                    case_value: None,
+                },
+                variant(vec![case("storm", str()), unit_case("heat")])
+            ),
+            Err(ValidationErrorKind::MissingPayload {
+                case: "storm".to_string(),
+            })
+        );
+        assert_eq!(
+            validate(Value::Result(Ok(None)), result(str(), u32())),
+            Err(ValidationErrorKind::MissingPayload {
+                case: "ok".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unexpected_payloads() {
+        assert_eq!(
+            validate(
+                Value::Variant {
+                    case_idx: 1,
+                    case_value: Some(Box::new(Value::String("hot".to_string()))),
+                },
+                variant(vec![case("storm", str()), unit_case("heat")])
+            ),
+            Err(ValidationErrorKind::UnexpectedPayload {
+                case: "heat".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_enum_cases_out_of_range() {
+        assert_eq!(
+            validate(Value::Enum(2), r#enum(&["celsius", "fahrenheit"])),
+            Err(ValidationErrorKind::EnumCaseOutOfRange {
+                case_idx: 2,
+                case_count: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_flags_of_other_lengths() {
+        assert_eq!(
+            validate(Value::Flags(vec![true]), flags(&["radar", "satellite"])),
+            Err(ValidationErrorKind::FlagsLength {
+                expected: 2,
+                actual: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_other_numbers_of_parameters() {
+        let error = validate_input(
+            &test_agents::schema(vec![str(), u32()]),
+            &[WitValue::from(Value::String("Berlin".to_string()))],
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            ValidationErrorKind::ParameterCount {
+                expected: 2,
+                actual: 1,
+            }
+        );
+        assert_eq!(error.to_string(), "expected 2 parameters, but got 1");
+    }
+
+    #[test]
+    fn points_at_the_mismatching_part_of_the_input() {
+        let typ = record(vec![
+            field("city", str()),
+            field("days", list(option(variant(vec![case("storm", tuple(vec![str(), u32()]))])))),
+        ]);
+        let value = Value::Record(vec![
+            Value::String("Berlin".to_string()),
+            Value::List(vec![
+                Value::Option(None),
+                Value::Option(Some(Box::new(Value::Variant {
+                    case_idx: 0,
+                    case_value: Some(Box::new(Value::Tuple(vec![
+                        Value::String("north".to_string()),
+                        Value::S32(7),
+                    ]))),
+                }))),
+            ]),
+        ]);
+
+        let error = validate_input(
+            &test_agents::schema(vec![u32(), typ]),
+            &[WitValue::from(Value::U32(1)), WitValue::from(value)],
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.path,
+            vec!["parameter 1", "field days", "list item 1", "some", "storm", "tuple item 1"]
+        );
+        assert_eq!(
+            error.to_string(),
+            "parameter 1 > field days > list item 1 > some > storm > tuple item 1: \
+             expected a value of type u32, but got s32"
+        );
+    }
+}
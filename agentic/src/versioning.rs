@@ -0,0 +1,591 @@
+use crate::agent_instance_registry;
+use crate::memory;
+use crate::agent_registry::{self, AgentId};
+use crate::{approval, call_options, scheduling};
+use crate::bindings::golem::agent::common::{DataSchema, ParameterType};
+use crate::json_mapping::{json_to_value, value_to_json};
+use golem_wasm_ast::analysis::analysed_type::str;
+use golem_wasm_ast::analysis::AnalysedType;
+use golem_wasm_rpc::{Value, WitValue};
+use serde_json::json;
+
+// Agents keep their state across snapshot-based worker updates by implementing `AgentState`,
+// and opting in with `#[agent_implementation(state)]`. The version of the agent type is declared
+// in the agent definition, and is bumped whenever the encoding of the state changes:
+//
+// ```
+//  #[agent_definition(version = 2)]
+//  trait Counter {
+//    fn increment(&self) -> String;
+//  }
+//
+//  impl AgentState for MyCounter {
+//    fn save_state(&self) -> Result<Vec<u8>, String> { .. }
+//    fn load_state(&self, state: Vec<u8>) -> Result<(), String> { .. }
+//
+//    fn migrate(from_version: u32, old_state: Vec<u8>) -> Result<Vec<u8>, String> {
+//      match from_version {
+//        1 => Ok(u32_to_u64_counter(old_state)),
+//        _ => Err(format!("Unknown version {}", from_version)),
+//      }
+//    }
+//  }
+// ```
+//
+// The snapshot of a worker holds every agent living in it, with its constructor parameters,
+// the version of its agent type, its state, its memories and the ids of the remote agents it resolved,
+// which it connects to again once restored. It also holds the scheduled invocations,
+// the results of idempotent calls and the pending requests of the worker.
+pub trait AgentState {
+    fn save_state(&self) -> Result<Vec<u8>, String>;
+
+    fn load_state(&self, state: Vec<u8>) -> Result<(), String>;
+
+    // Turns the state saved by an older version of the agent into the state of the current version
+    fn migrate(from_version: u32, old_state: Vec<u8>) -> Result<Vec<u8>, String>
+    where
+        Self: Sized,
+    {
+        let _ = old_state;
+        Err(format!(
+            "No migration of the agent state from version {}",
+            from_version
+        ))
+    }
+}
+
+// Used by `agent_implementation` to restore an agent's state, migrating it if it is from an older version
+pub fn load_agent_state<T: AgentState>(
+    agent: &T,
+    current_version: u32,
+    from_version: u32,
+    state: Vec<u8>,
+) -> Result<(), String> {
+    let state = if from_version < current_version {
+        T::migrate(from_version, state)?
+    } else if from_version > current_version {
+        return Err(format!(
+            "The state is from version {}, which is newer than the current version {}",
+            from_version, current_version
+        ));
+    } else {
+        state
+    };
+
+    agent.load_state(state)
+}
+
+// Bumped on incompatible changes of the snapshot encoding
+const SNAPSHOT_FORMAT: u64 = 1;
+
+pub fn save_snapshot() -> Result<Vec<u8>, String> {
+    let mut agents = Vec::new();
+
+    for (agent_name, resolved_agent, constructor_params) in agent_registry::get_all_agent_instances() {
+        let definition = resolved_agent.agent.get_definition();
+        let types = parameter_types(&definition.agent_constructor.input_schema);
+
+        let params = constructor_params
+            .into_iter()
+            .zip(&types)
+            .map(|(param, typ)| value_to_json(&Value::from(param), typ))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                format!(
+                    "Failed to save the constructor parameters of {}: {}",
+                    resolved_agent.agent_id, e
+                )
+            })?;
+
+        let state = resolved_agent
+            .agent
+            .save_snapshot()
+            .map_err(|e| format!("Failed to save the state of {}: {}", resolved_agent.agent_id, e))?;
+
+        agents.push(json!({
+            "agentId": resolved_agent.agent_id,
+            "agentType": agent_name,
+            "version": definition.version,
+            "constructorParams": params,
+            "state": state.map(|state| to_hex(&state)),
+            "memories": memory::save_memories(&resolved_agent.agent_id),
+            "remoteAgents": agent_registry::get_remote_agents(&resolved_agent.agent_id)
+                .into_iter()
+                .map(|(agent_type, agent_id)| json!({ "agentType": agent_type, "agentId": agent_id }))
+                .collect::<Vec<_>>(),
+            "siblingAgents": agent_registry::get_sibling_agents(&resolved_agent.agent_id)
+                .into_iter()
+                .map(|(agent_type, agent_id)| json!({ "agentType": agent_type, "agentId": agent_id }))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    serde_json::to_vec(&json!({
+        "format": SNAPSHOT_FORMAT,
+        "agents": agents,
+        "schedules": scheduling::save_schedules()?,
+        "idempotentResults": call_options::save_idempotent_results(),
+        "pendingRequests": approval::save_pending_requests()?,
+    }))
+    .map_err(|e| e.to_string())
+}
+
+// Saving a snapshot can't fail, so a failure is saved instead, failing the update once the snapshot is loaded
+pub fn failed_snapshot(error: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({
+        "format": SNAPSHOT_FORMAT,
+        "error": error,
+    }))
+    .unwrap_or_default()
+}
+
+pub fn load_snapshot(bytes: Vec<u8>) -> Result<(), String> {
+    let snapshot: serde_json::Value =
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid snapshot: {}", e))?;
+
+    if snapshot["format"].as_u64() != Some(SNAPSHOT_FORMAT) {
+        return Err(format!(
+            "Unsupported snapshot format: {}",
+            snapshot["format"]
+        ));
+    }
+
+    if let Some(error) = snapshot["error"].as_str() {
+        return Err(format!("Failed to save the snapshot: {}", error));
+    }
+
+    let agents = snapshot["agents"]
+        .as_array()
+        .ok_or_else(|| "Invalid snapshot: missing agents".to_string())?;
+
+    for agent in agents {
+        load_agent(agent)?;
+    }
+
+    // Snapshots of older versions of this crate have none of these
+    scheduling::load_schedules(&snapshot["schedules"])?;
+    call_options::load_idempotent_results(&snapshot["idempotentResults"])?;
+    approval::load_pending_requests(&snapshot["pendingRequests"])?;
+
+    Ok(())
+}
+
+fn load_agent(agent: &serde_json::Value) -> Result<(), String> {
+    let field = |name: &str| {
+        agent
+            .get(name)
+            .ok_or_else(|| format!("Invalid snapshot: missing {} of an agent", name))
+    };
+
+    let agent_id = field("agentId")?
+        .as_str()
+        .ok_or_else(|| "Invalid snapshot: agentId is not a string".to_string())?
+        .to_string();
+    let agent_name = field("agentType")?
+        .as_str()
+        .ok_or_else(|| "Invalid snapshot: agentType is not a string".to_string())?
+        .to_string();
+    let version = field("version")?
+        .as_u64()
+        .ok_or_else(|| "Invalid snapshot: version is not a number".to_string())? as u32;
+    let params = field("constructorParams")?
+        .as_array()
+        .ok_or_else(|| "Invalid snapshot: constructorParams is not an array".to_string())?;
+
+    let agent_type = agent_registry::get_agent_def_by_name(&agent_name).ok_or_else(|| {
+        format!(
+            "Agent {} can't be restored, as agent type {} no longer exists",
+            agent_id, agent_name
+        )
+    })?;
+
+    // The parameters are decoded with the current constructor types, so changing them breaks restoring
+    let types = parameter_types(&agent_type.agent_constructor.input_schema);
+    if params.len() != types.len() {
+        return Err(format!(
+            "Agent {} can't be restored, as the constructor of {} now takes {} parameters instead of {}",
+            agent_id,
+            agent_name,
+            types.len(),
+            params.len()
+        ));
+    }
+
+    let params = params
+        .iter()
+        .zip(&types)
+        .map(|(param, typ)| json_to_value(param, typ).map(WitValue::from))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            format!(
+                "Agent {} can't be restored, as the constructor parameters of {} changed: {}",
+                agent_id, agent_name, e
+            )
+        })?;
+
+    crate::validation::validate_constructor_input(&agent_type, &params).map_err(|e| {
+        format!(
+            "Agent {} can't be restored, as the constructor parameters of {} changed: {}",
+            agent_id, agent_name, e
+        )
+    })?;
+
+    let initiator = agent_registry::get_agent_initiator(agent_name.clone())
+        .ok_or_else(|| format!("No agent implementation found for agent type {}", agent_name))?;
+
+    let remote_agents = agent
+        .get("remoteAgents")
+        .and_then(|remote_agents| remote_agents.as_array())
+        .map(|remote_agents| {
+            remote_agents
+                .iter()
+                .filter_map(|remote_agent| {
+                    Some((
+                        remote_agent["agentType"].as_str()?.to_string(),
+                        remote_agent["agentId"].as_str()?.to_string(),
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // Constructing the agent resolves its remote agents, which connects to the ones it resolved before
+    agent_instance_registry::restore_agent_id(agent_name, agent_id.clone());
+    agent_registry::restore_remote_agents(&agent_id, remote_agents);
+    let resolved_agent = initiator.initiate_with_id(agent_id.clone(), params);
+    agent_registry::finish_restoring_remote_agents(&agent_id);
+    let resolved_agent =
+        resolved_agent.map_err(|e| format!("Failed to restore {}: {}", agent_id, e))?;
+
+    if let Some(state) = field("state")?.as_str() {
+        let state = from_hex(state).map_err(|e| format!("Invalid state of {}: {}", agent_id, e))?;
+        resolved_agent
+            .agent
+            .load_snapshot(version, state)
+            .map_err(|e| format!("Failed to restore the state of {}: {}", agent_id, e))?;
+    }
+
+    if let Some(memories) = agent.get("memories") {
+        memory::load_memories(&agent_id, memories)
+            .map_err(|e| format!("Failed to restore the memories of {}: {}", agent_id, e))?;
+    }
+
+    // The sibling agents are restored as agents of the snapshot, with the same ids
+    for sibling_agent in agent
+        .get("siblingAgents")
+        .and_then(|sibling_agents| sibling_agents.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if let (Some(agent_type), Some(sibling_agent_id)) =
+            (sibling_agent["agentType"].as_str(), sibling_agent["agentId"].as_str())
+        {
+            agent_registry::register_sibling_agent(&agent_id, agent_type, sibling_agent_id.to_string());
+        }
+    }
+
+    debug_assert!(agent_registry::get_resolved_agent(&AgentId(agent_id)).is_some());
+
+    Ok(())
+}
+
+// Text parameters are passed as strings
+pub(crate) fn parameter_types(schema: &DataSchema) -> Vec<AnalysedType> {
+    match schema {
+        DataSchema::Structured(structured) => structured
+            .parameters
+            .iter()
+            .map(|parameter| match parameter {
+                ParameterType::Wit(wit_type) => AnalysedType::from(wit_type.clone()),
+                ParameterType::Text(_) => str(),
+            })
+            .collect(),
+        DataSchema::Multimodal(_) => vec![],
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 {
+        return Err("odd number of hex digits".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{self, Agent};
+    use crate::bindings::golem::agent::common::{AgentType, StatusUpdate};
+    use crate::call_options::IdempotencyKey;
+    use crate::scheduling::{self, Scheduling};
+    use crate::test_agents;
+    use crate::testing::{self, MockHost};
+    use golem_wasm_ast::analysis::analysed_type::u32;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn register_counter() {
+        test_agents::register(
+            "counter",
+            vec![str()],
+            vec![test_agents::method("count", vec![("amount", u32())], str())],
+            |_, _, input| StatusUpdate::Emit(format!("{:?}", input)),
+        );
+    }
+
+    #[test]
+    fn restores_agents_with_the_state_of_the_worker() {
+        let snapshot = {
+            let _host = MockHost::new("test-worker").install();
+            register_counter();
+
+            let agent = testing::create_agent("counter", vec![Value::String("Berlin".to_string())]).unwrap();
+            let resolved_agent = agent_registry::get_resolved_agent(&AgentId(agent.agent_id().to_string())).unwrap();
+            resolved_agent
+                .agent
+                .every("count", vec![Value::U32(1)], Duration::from_secs(60))
+                .unwrap();
+            agent.invoke_idempotent(&IdempotencyKey::new("key-1"), "count", vec![Value::U32(2)]);
+            let _request = agent::with_current_agent(agent.agent_id(), "count", || {
+                approval::publish::<String, bool>("Count twice?", "Count twice?".to_string())
+            });
+            agent_registry::register_remote_agent(
+                agent.agent_id(),
+                "weather-agent",
+                "remote-worker--weather-agent--1".to_string(),
+            );
+
+            save_snapshot().unwrap()
+        };
+
+        let saved: serde_json::Value = serde_json::from_slice(&snapshot).unwrap();
+        assert_eq!(
+            saved["agents"][0]["remoteAgents"],
+            json!([{ "agentType": "weather-agent", "agentId": "remote-worker--weather-agent--1" }])
+        );
+
+        let _host = MockHost::new("test-worker").install();
+        load_snapshot(snapshot).unwrap();
+
+        assert!(testing::is_registered("test-worker--counter--1"));
+        assert_eq!(scheduling::scheduled_invocations("test-worker--counter--1").len(), 1);
+        assert!(matches!(
+            call_options::idempotent_result("test-worker--counter--1", "count", &IdempotencyKey::new("key-1"))
+                .as_deref(),
+            Some([StatusUpdate::Emit(output)]) if output == "[U32(2)]"
+        ));
+        assert_eq!(
+            testing::pending_requests()
+                .into_iter()
+                .map(|request| (request.agent_id, request.description))
+                .collect::<Vec<_>>(),
+            vec![("test-worker--counter--1".to_string(), "Count twice?".to_string())]
+        );
+    }
+
+    // A counter kept as a u32 in version 1 of its agent type, and as a u64 from version 2 on
+    struct Counter<const WIDE: bool> {
+        agent_id: String,
+        agent_type: AgentType,
+        count: std::sync::Mutex<u64>,
+    }
+
+    type CounterV1 = Counter<false>;
+    type CounterV2 = Counter<true>;
+
+    impl<const WIDE: bool> Counter<WIDE> {
+        fn new(count: u64) -> Self {
+            Counter {
+                agent_id: "test-worker--stateful-counter--1".to_string(),
+                agent_type: agent_registry::get_agent_def_by_name("stateful-counter").unwrap(),
+                count: std::sync::Mutex::new(count),
+            }
+        }
+
+        fn count(&self) -> u64 {
+            *self.count.lock().unwrap()
+        }
+    }
+
+    impl AgentState for CounterV1 {
+        fn save_state(&self) -> Result<Vec<u8>, String> {
+            Ok((self.count() as u32).to_le_bytes().to_vec())
+        }
+
+        fn load_state(&self, state: Vec<u8>) -> Result<(), String> {
+            let bytes: [u8; 4] = state.try_into().map_err(|_| "Invalid state".to_string())?;
+            *self.count.lock().unwrap() = u32::from_le_bytes(bytes) as u64;
+            Ok(())
+        }
+    }
+
+    impl AgentState for CounterV2 {
+        fn save_state(&self) -> Result<Vec<u8>, String> {
+            Ok(self.count().to_le_bytes().to_vec())
+        }
+
+        fn load_state(&self, state: Vec<u8>) -> Result<(), String> {
+            let bytes: [u8; 8] = state.try_into().map_err(|_| "Invalid state".to_string())?;
+            *self.count.lock().unwrap() = u64::from_le_bytes(bytes);
+            Ok(())
+        }
+
+        fn migrate(from_version: u32, old_state: Vec<u8>) -> Result<Vec<u8>, String> {
+            match from_version {
+                1 => {
+                    let bytes: [u8; 4] = old_state.try_into().map_err(|_| "Invalid state".to_string())?;
+                    Ok((u32::from_le_bytes(bytes) as u64).to_le_bytes().to_vec())
+                }
+                _ => Err(format!("Unknown version {}", from_version)),
+            }
+        }
+    }
+
+    // As `#[agent_implementation(state)]` implements it
+    impl<const WIDE: bool> Agent for Counter<WIDE>
+    where
+        Counter<WIDE>: AgentState,
+    {
+        fn get_id(&self) -> String {
+            self.agent_id.clone()
+        }
+
+        fn invoke(&self, _method_name: String, _input: Vec<WitValue>) -> StatusUpdate {
+            let mut count = self.count.lock().unwrap();
+            *count += 1;
+            StatusUpdate::Emit(count.to_string())
+        }
+
+        fn get_definition(&self) -> AgentType {
+            self.agent_type.clone()
+        }
+
+        fn save_snapshot(&self) -> Result<Option<Vec<u8>>, String> {
+            AgentState::save_state(self).map(Some)
+        }
+
+        fn load_snapshot(&self, from_version: u32, state: Vec<u8>) -> Result<(), String> {
+            load_agent_state(self, self.get_definition().version, from_version, state)
+        }
+    }
+
+    fn register_stateful_counter<const WIDE: bool>(version: u32)
+    where
+        Counter<WIDE>: AgentState,
+    {
+        test_agents::register_agent(
+            "stateful-counter",
+            version,
+            vec![test_agents::method("increment", vec![], str())],
+            |agent_id, agent_type| {
+                Ok(Arc::new(Counter::<WIDE> {
+                    agent_id,
+                    agent_type,
+                    count: std::sync::Mutex::new(0),
+                }))
+            },
+        );
+    }
+
+    // An agent that never changed the encoding of its state, and so has no migration
+    struct Unmigrated;
+
+    impl AgentState for Unmigrated {
+        fn save_state(&self) -> Result<Vec<u8>, String> {
+            Ok(vec![])
+        }
+
+        fn load_state(&self, _state: Vec<u8>) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn migrates_states_saved_by_older_versions() {
+        let _host = MockHost::new("test-worker").install();
+        register_stateful_counter::<true>(2);
+
+        let state = CounterV1::new(u32::MAX as u64).save_state().unwrap();
+        let counter = CounterV2::new(0);
+        load_agent_state(&counter, 2, 1, state).unwrap();
+
+        assert_eq!(counter.count(), u32::MAX as u64);
+        assert_eq!(
+            load_agent_state(&counter, 3, 2, 7u64.to_le_bytes().to_vec()),
+            Err("Unknown version 2".to_string())
+        );
+    }
+
+    #[test]
+    fn fails_migrating_without_a_migration() {
+        assert_eq!(
+            load_agent_state(&Unmigrated, 2, 1, vec![]),
+            Err("No migration of the agent state from version 1".to_string())
+        );
+        assert_eq!(load_agent_state(&Unmigrated, 1, 1, vec![]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_states_of_newer_versions() {
+        let _host = MockHost::new("test-worker").install();
+        register_stateful_counter::<true>(2);
+
+        assert_eq!(
+            load_agent_state(&CounterV2::new(0), 1, 2, 7u64.to_le_bytes().to_vec()),
+            Err("The state is from version 2, which is newer than the current version 1".to_string())
+        );
+    }
+
+    #[test]
+    fn restores_the_state_of_agents_updated_to_a_newer_version() {
+        let snapshot = {
+            let _host = MockHost::new("test-worker").install();
+            register_stateful_counter::<false>(1);
+
+            let agent = testing::create_agent("stateful-counter", vec![]).unwrap();
+            agent.invoke("increment", vec![]);
+            agent.invoke("increment", vec![]);
+
+            save_snapshot().unwrap()
+        };
+
+        let host = MockHost::new("test-worker").install();
+        register_stateful_counter::<true>(2);
+        load_snapshot(snapshot.clone()).unwrap();
+
+        let agent = agent_registry::get_resolved_agent(&AgentId("test-worker--stateful-counter--1".to_string()))
+            .unwrap();
+        assert!(matches!(
+            agent.agent.invoke("increment".to_string(), vec![]),
+            StatusUpdate::Emit(count) if count == "3"
+        ));
+
+        // Going back to the older version of the component is not possible
+        drop(host);
+        let _host = MockHost::new("test-worker").install();
+        register_stateful_counter::<false>(0);
+        assert_eq!(
+            load_snapshot(snapshot),
+            Err("Failed to restore the state of test-worker--stateful-counter--1: \
+                 The state is from version 1, which is newer than the current version 0"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn fails_loading_snapshots_that_failed_to_save() {
+        let _host = MockHost::new("test-worker").install();
+
+        assert_eq!(
+            load_snapshot(failed_snapshot("the state of test-worker--counter--1 is too large")),
+            Err("Failed to save the snapshot: the state of test-worker--counter--1 is too large".to_string())
+        );
+    }
+}